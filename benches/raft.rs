@@ -0,0 +1,171 @@
+//! Benchmarks for the protocol on a simulated cluster, so changes that affect performance
+//! (batching, pipelining, ...) can be compared against a baseline. Everything runs on
+//! [`Cluster`], i.e. it measures the CPU time the protocol spends per tick and message,
+//! not network latency.
+//!
+//! Run with `cargo bench` (`make bench`), or `cargo bench -- <filter>` for a single group.
+
+use std::{io, time::Duration};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use miniraft::{
+    log::{App, LogEntry},
+    sim::Cluster,
+};
+
+const SEED: u64 = 7;
+const MAX_TICKS: u32 = 10_000;
+const CLUSTER_SIZES: [usize; 4] = [1, 3, 5, 7];
+
+struct SumApp(u64);
+
+impl App<u64, u64> for SumApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u64>) {
+        self.0 += entry.data;
+    }
+
+    fn get_state(&self) -> u64 {
+        self.0
+    }
+
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        writer.write_all(&self.0.to_le_bytes())
+    }
+
+    fn restore(&mut self, reader: &mut dyn io::Read) -> io::Result<()> {
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf)?;
+        self.0 = u64::from_le_bytes(buf);
+        Ok(())
+    }
+}
+
+fn cluster(n: usize) -> Cluster<u64, u64> {
+    Cluster::builder()
+        .nodes(n)
+        .seed(SEED)
+        .app(|| SumApp(0))
+        .build()
+}
+
+/// Cluster of `n` nodes that has elected a leader
+fn elected(n: usize) -> Cluster<u64, u64> {
+    let mut cluster = cluster(n);
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    cluster
+}
+
+/// Tick until every live node has committed its whole log
+fn settle(cluster: &mut Cluster<u64, u64>) {
+    assert!(cluster.run_until(MAX_TICKS, |cluster| {
+        cluster
+            .live_nodes()
+            .all(|node| node.log.committed_len == node.log.len())
+    }));
+}
+
+/// Entries committed per second when a client keeps `batch` proposals in flight
+fn proposal_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("proposal_throughput");
+    for batch in [1, 10, 100] {
+        group.throughput(Throughput::Elements(batch));
+        group.bench_with_input(BenchmarkId::from_parameter(batch), &batch, |b, &batch| {
+            let mut cluster = elected(3);
+            b.iter(|| {
+                for data in 0..batch {
+                    cluster.client_request(data).unwrap();
+                }
+                settle(&mut cluster);
+                // keep the log from growing across iterations
+                for id in 0..3 {
+                    cluster.snapshot(id).unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Time from proposing a single entry to every node committing it
+fn commit_latency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commit_latency");
+    for n in CLUSTER_SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter_batched_ref(
+                || elected(n),
+                |cluster| {
+                    cluster.client_request(1).unwrap();
+                    settle(cluster);
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// Time from a cold start to a leader every node agrees on
+fn election_convergence(c: &mut Criterion) {
+    let mut group = c.benchmark_group("election_convergence");
+    for n in CLUSTER_SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter_batched_ref(
+                || cluster(n),
+                |cluster| {
+                    assert!(cluster.run_until(MAX_TICKS, |cluster| {
+                        let leader = cluster.leader().map(|leader| leader.id);
+                        leader.is_some() && cluster.nodes().all(|node| node.leader_id() == leader)
+                    }));
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// Time for a follower that missed `behind` entries to catch up with the leader
+fn catch_up(c: &mut Criterion) {
+    let mut group = c.benchmark_group("catch_up");
+    group.measurement_time(Duration::from_secs(10));
+    for behind in [10, 100, 1_000] {
+        group.throughput(Throughput::Elements(behind));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(behind),
+            &behind,
+            |b, &behind| {
+                b.iter_batched_ref(
+                    || {
+                        let mut cluster = elected(3);
+                        let follower = (cluster.leader().unwrap().id + 1) % 3;
+                        cluster.kill(follower);
+                        for data in 0..behind {
+                            cluster.client_request(data).unwrap();
+                        }
+                        settle(&mut cluster);
+                        cluster.revive(follower);
+                        (cluster, follower)
+                    },
+                    |(cluster, follower)| {
+                        let follower = *follower;
+                        let target = behind as usize;
+                        assert!(cluster.run_until(MAX_TICKS, |cluster| {
+                            cluster.node(follower).log.committed_len == target
+                        }));
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    proposal_throughput,
+    commit_latency,
+    election_convergence,
+    catch_up
+);
+criterion_main!(benches);
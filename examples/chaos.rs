@@ -0,0 +1,243 @@
+//! Long-running chaos test for a simulated miniraft cluster.
+//!
+//! Runs a cluster under continuous random faults (partitions, skewed clocks, slow disks,
+//! corrupted messages and crash-restarts) while a client keeps proposing entries. After
+//! every tick it checks that no two nodes lead the same term and that nodes which applied
+//! the same number of entries ended up in the same state. Every node's log is compacted
+//! every few hundred ticks so it doesn't grow for hours, and stats are printed at every
+//! checkpoint.
+//!
+//! On a violation it prints the seed and what the nemeses did last, then exits with an
+//! error; rerunning with the same `--seed` replays the exact same run.
+//!
+//! Usage: `cargo run --release --example chaos -- [--seed N] [--duration SECS]
+//! [--checkpoint SECS] [--nodes N]`
+
+use std::{
+    collections::BTreeMap,
+    env,
+    hash::{DefaultHasher, Hash, Hasher},
+    io, process,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use miniraft::{
+    log::{App, LogEntry},
+    nemesis::{ClockSkewer, MessageCorrupter, Nemeses, Partitioner, SlowDisk},
+    server::{ServerId, Term},
+    sim::{Cluster, Disk},
+};
+use rand::Rng;
+
+/// Ticks between compactions of every node's log
+const COMPACT_EVERY: u32 = 500;
+
+/// Applied entries and a digest over all of them, in order
+type Ledger = (u64, u64);
+
+/// App that folds every entry into a running digest, so two nodes that applied the same
+/// number of entries have the same state exactly if they applied the same entries
+#[derive(Default)]
+struct LedgerApp {
+    applied: u64,
+    digest: u64,
+}
+
+impl App<u64, Ledger> for LedgerApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u64>) {
+        let mut hasher = DefaultHasher::new();
+        (self.digest, entry.term, entry.data).hash(&mut hasher);
+        self.digest = hasher.finish();
+        self.applied += 1;
+    }
+
+    fn get_state(&self) -> Ledger {
+        (self.applied, self.digest)
+    }
+
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        writer.write_all(&self.applied.to_le_bytes())?;
+        writer.write_all(&self.digest.to_le_bytes())
+    }
+
+    fn restore(&mut self, reader: &mut dyn io::Read) -> io::Result<()> {
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf)?;
+        self.applied = u64::from_le_bytes(buf);
+        reader.read_exact(&mut buf)?;
+        self.digest = u64::from_le_bytes(buf);
+        Ok(())
+    }
+}
+
+struct Options {
+    seed: u64,
+    duration: Duration,
+    checkpoint: Duration,
+    nodes: usize,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: chaos [--seed N] [--duration SECS] [--checkpoint SECS] [--nodes N]");
+    process::exit(2);
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        seed: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default(),
+        duration: Duration::from_secs(3600),
+        checkpoint: Duration::from_secs(10),
+        nodes: 5,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_else(|| usage())
+        };
+        match arg.as_str() {
+            "--seed" => options.seed = value(),
+            "--duration" => options.duration = Duration::from_secs(value()),
+            "--checkpoint" => options.checkpoint = Duration::from_secs(value()),
+            "--nodes" => options.nodes = value() as usize,
+            _ => usage(),
+        }
+    }
+    options
+}
+
+/// What the checks have seen so far
+#[derive(Default)]
+struct Checker {
+    /// Who led each term
+    leaders: BTreeMap<Term, ServerId>,
+    /// App state after each number of applied entries, from the slowest node's onwards
+    ledgers: BTreeMap<u64, u64>,
+}
+
+impl Checker {
+    fn check(&mut self, cluster: &Cluster<u64, Ledger>) -> Result<(), String> {
+        for node in cluster.nodes() {
+            if node.is_leader() {
+                let term = node.current_term();
+                let leader = *self.leaders.entry(term).or_insert(node.id);
+                if leader != node.id {
+                    return Err(format!(
+                        "{} and {} both lead term {}",
+                        leader, node.id, term
+                    ));
+                }
+            }
+            let (applied, digest) = node.log.app.get_state();
+            let seen = *self.ledgers.entry(applied).or_insert(digest);
+            if seen != digest {
+                return Err(format!(
+                    "node {} has a different state than others after applying {} entries",
+                    node.id, applied
+                ));
+            }
+        }
+        // nobody can apply fewer entries than the slowest node has already
+        let slowest = cluster.nodes().map(|node| node.log.app.get_state().0).min();
+        if let Some(slowest) = slowest {
+            self.ledgers = self.ledgers.split_off(&slowest);
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let options = parse_args();
+    println!(
+        "chaos: {} nodes, seed {}, running for {:?}",
+        options.nodes, options.seed, options.duration
+    );
+
+    let mut cluster = Cluster::builder()
+        .nodes(options.nodes)
+        .seed(options.seed)
+        .app(LedgerApp::default)
+        .build();
+    let mut nemeses = Nemeses::new()
+        .with(Partitioner::new(0.005, 200))
+        .with(ClockSkewer::new(0.005, 3.0, 200))
+        .with(SlowDisk::new(0.005, 10, 200))
+        .with(MessageCorrupter::new(0.005, 0.2, 100));
+    let mut checker = Checker::default();
+    let (mut proposed, mut restarts) = (0u64, 0u64);
+    let started = Instant::now();
+    let mut last_checkpoint = (Instant::now(), 0u64);
+
+    while started.elapsed() < options.duration {
+        let rng = cluster.rng();
+        let propose = rng.gen_bool(0.3);
+        let crash = rng.gen_bool(0.001).then(|| rng.gen_range(0..options.nodes));
+        if propose && cluster.client_request(cluster.now() as u64).is_ok() {
+            proposed += 1;
+        }
+        if let Some(id) = crash {
+            cluster.restart(id, Disk::Intact).expect("node exists");
+            restarts += 1;
+        }
+        nemeses.tick(&mut cluster);
+        if let Err(violation) = checker.check(&cluster) {
+            eprintln!("VIOLATION at tick {}: {}", cluster.now(), violation);
+            eprintln!("rerun with --seed {}", cluster.seed());
+            eprintln!("last things the nemeses did:");
+            for (at, nemesis, event) in nemeses.history().iter().rev().take(10).rev() {
+                eprintln!("  {:>8} {}: {}", at, nemesis, event);
+            }
+            process::exit(1);
+        }
+
+        if cluster.now() % COMPACT_EVERY == 0 {
+            for id in 0..options.nodes {
+                if let Err(err) = cluster.snapshot(id) {
+                    eprintln!("node {} failed to snapshot: {}", id, err);
+                }
+            }
+        }
+        if last_checkpoint.0.elapsed() >= options.checkpoint {
+            let (at, delivered) = last_checkpoint;
+            let rate = (cluster.delivered() - delivered) as f64 / at.elapsed().as_secs_f64();
+            let leader = cluster.leader();
+            let committed = cluster
+                .nodes()
+                .map(|node| node.log.committed_len)
+                .max()
+                .unwrap_or_default();
+            println!(
+                "[{:>6.0}s] tick {:>9} | term {:>5} leader {:<4} | {} committed of {} proposed | {} restarts, {} nemesis events | {:.0} msgs/s",
+                started.elapsed().as_secs_f64(),
+                cluster.now(),
+                cluster.nodes().map(|node| node.current_term()).max().unwrap_or_default(),
+                leader.map_or("none".into(), |leader| leader.id.to_string()),
+                committed,
+                proposed,
+                restarts,
+                nemeses.history().len(),
+                rate,
+            );
+            last_checkpoint = (Instant::now(), cluster.delivered());
+        }
+    }
+
+    nemeses.heal(&mut cluster);
+    if !cluster.run_until(10_000, |cluster| cluster.leader().is_some()) {
+        eprintln!(
+            "no leader after healing, rerun with --seed {}",
+            cluster.seed()
+        );
+        process::exit(1);
+    }
+    println!(
+        "done: {} ticks, {} proposed, {} restarts, no violations",
+        cluster.now(),
+        proposed,
+        restarts
+    );
+}
@@ -0,0 +1,119 @@
+//! Minimal in-process cluster shared by the examples. Messages are delivered instantly
+//! and never dropped.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
+
+use miniraft::{
+    log::App,
+    rpc::{SendableMessage, Target},
+    server::{RaftConfig, RaftServer, ServerId, SlowPathConfig, TieBreak},
+};
+
+/// A handful of Raft servers wired together through an in-memory message queue
+pub struct Cluster<T, S> {
+    pub nodes: BTreeMap<ServerId, RaftServer<T, S>>,
+}
+
+impl<T, S> Cluster<T, S>
+where
+    T: Clone + Debug,
+    S: PartialEq,
+{
+    /// Start `n` nodes, each with its own app from `new_app`, and wait for a leader
+    pub fn new(n: usize, new_app: impl Fn() -> Box<dyn App<T, S>>) -> Self {
+        let config = RaftConfig {
+            election_timeout: 10,
+            election_timeout_jitter: 3,
+            heartbeat_interval: 5,
+            max_apply_lag: None,
+            slow_path: SlowPathConfig::default(),
+            leaderless_alarm: None,
+            max_log_entries: None,
+            persist_in_background: false,
+            replication_batching: None,
+            broadcast_commits: false,
+            tie_break: TieBreak::Never,
+        };
+        let ids: BTreeSet<ServerId> = (0..n).collect();
+        let nodes = ids
+            .iter()
+            .map(|&id| {
+                let mut peers = ids.clone();
+                peers.remove(&id);
+                let server = RaftServer::new(id, peers, config.clone(), Some(id as u64), new_app());
+                (id, server)
+            })
+            .collect();
+        let mut cluster = Cluster { nodes };
+        cluster.tick(20);
+        cluster
+    }
+
+    /// Deliver messages between nodes until the cluster goes quiet
+    fn deliver(&mut self, mut queue: Vec<(ServerId, SendableMessage<T>)>) {
+        while !queue.is_empty() {
+            let mut next = Vec::new();
+            for (from, (target, rpc)) in queue.drain(..) {
+                for node in self.nodes.values_mut() {
+                    let addressed = match target {
+                        Target::Single(to) => to == node.id,
+                        Target::Broadcast => node.id != from,
+                    };
+                    if addressed {
+                        let id = node.id;
+                        next.extend(node.receive_rpc(&rpc).into_iter().map(|msg| (id, msg)));
+                    }
+                }
+            }
+            queue = next;
+        }
+    }
+
+    /// Advance every node by `n` ticks
+    pub fn tick(&mut self, n: u32) {
+        for _ in 0..n {
+            let mut queue = Vec::new();
+            for node in self.nodes.values_mut() {
+                let id = node.id;
+                queue.extend(node.tick().into_iter().map(|msg| (id, msg)));
+            }
+            self.deliver(queue);
+        }
+    }
+
+    /// Current leader of the cluster
+    pub fn leader(&mut self) -> &mut RaftServer<T, S> {
+        self.nodes
+            .values_mut()
+            .find(|node| node.is_leader())
+            .expect("no leader elected")
+    }
+
+    /// Submit a command to the leader and wait for it to be replicated and applied
+    pub fn submit(&mut self, cmd: T) {
+        println!("> {:?}", cmd);
+        self.leader()
+            .client_request(cmd)
+            .expect("leader rejected request");
+        // one round to replicate, another to propagate the new commit index
+        self.tick(10);
+    }
+
+    /// State of the app on the leader
+    pub fn state(&mut self) -> S {
+        self.leader().log.app.get_state()
+    }
+
+    /// Whether every replica reached the same state
+    pub fn converged(&self) -> bool {
+        let states: Vec<S> = self
+            .nodes
+            .values()
+            .map(|node| node.log.app.get_state())
+            .collect();
+        states.windows(2).all(|pair| pair[0] == pair[1])
+    }
+}
@@ -0,0 +1,296 @@
+//! Live terminal dashboard for a simulated miniraft cluster.
+//!
+//! Shows every node's role, term and log progress, the leader's view of its followers,
+//! and a scrolling feed of the messages going over the (in-process) network. Useful to
+//! watch elections and replication happen step by step.
+//!
+//! Keys:
+//! - `space` pause/resume, `n` single step while paused
+//! - `p` propose an entry to the leader
+//! - `k` kill the leader, `r` revive every dead node
+//! - `q` quit
+//!
+//! Run with `cargo run --example dashboard`
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io,
+    time::Duration,
+};
+
+use miniraft::{
+    log::{App, LogEntry},
+    rpc::{SendableMessage, Target},
+    server::{RaftConfig, RaftServer, ServerId, SlowPathConfig, TieBreak},
+    status::Role,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, List, ListItem, Paragraph, Row, Table},
+    DefaultTerminal, Frame,
+};
+
+/// Number of nodes in the cluster
+const NODES: usize = 5;
+
+/// How many messages the feed keeps around
+const FEED_LEN: usize = 200;
+
+/// Real time between two ticks while running
+const TICK_EVERY: Duration = Duration::from_millis(150);
+
+/// Sums up every entry, so there is something to look at in the state column
+struct Sum(u64);
+
+impl App<u64, u64> for Sum {
+    fn transition_fn(&mut self, entry: &LogEntry<u64>) {
+        self.0 += entry.data;
+    }
+
+    fn get_state(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A cluster on an in-memory network where nodes can be killed, plus a record of
+/// everything sent over it
+struct Sim {
+    nodes: BTreeMap<ServerId, RaftServer<u64, u64>>,
+    down: BTreeSet<ServerId>,
+    feed: VecDeque<String>,
+    ticks: u64,
+    proposed: u64,
+}
+
+impl Sim {
+    fn new() -> Self {
+        let config = RaftConfig {
+            election_timeout: 10,
+            election_timeout_jitter: 5,
+            heartbeat_interval: 5,
+            max_apply_lag: None,
+            slow_path: SlowPathConfig::default(),
+            leaderless_alarm: None,
+            max_log_entries: None,
+            persist_in_background: false,
+            replication_batching: None,
+            broadcast_commits: false,
+            tie_break: TieBreak::Never,
+        };
+        let ids: BTreeSet<ServerId> = (0..NODES).collect();
+        let nodes = ids
+            .iter()
+            .map(|&id| {
+                let mut peers = ids.clone();
+                peers.remove(&id);
+                let app = Box::new(Sum(0));
+                let server = RaftServer::new(id, peers, config.clone(), Some(id as u64), app);
+                (id, server)
+            })
+            .collect();
+        Sim {
+            nodes,
+            down: BTreeSet::new(),
+            feed: VecDeque::new(),
+            ticks: 0,
+            proposed: 0,
+        }
+    }
+
+    /// Advance every live node by one tick and deliver messages until the network goes quiet
+    fn step(&mut self) {
+        self.ticks += 1;
+        let mut queue: Vec<(ServerId, SendableMessage<u64>)> = Vec::new();
+        for node in self.nodes.values_mut() {
+            if !self.down.contains(&node.id) {
+                let id = node.id;
+                queue.extend(node.tick().into_iter().map(|msg| (id, msg)));
+            }
+        }
+        while !queue.is_empty() {
+            let mut next = Vec::new();
+            for (from, (target, rpc)) in queue.drain(..) {
+                let to = match target {
+                    Target::Single(to) => format!("{}", to),
+                    Target::Broadcast => "*".to_string(),
+                };
+                self.feed
+                    .push_front(format!("t{:<5} {} -> {}  {}", self.ticks, from, to, rpc));
+                self.feed.truncate(FEED_LEN);
+
+                for node in self.nodes.values_mut() {
+                    let addressed = match target {
+                        Target::Single(to) => to == node.id,
+                        Target::Broadcast => node.id != from,
+                    };
+                    if addressed && !self.down.contains(&node.id) {
+                        let id = node.id;
+                        next.extend(node.receive_rpc(&rpc).into_iter().map(|msg| (id, msg)));
+                    }
+                }
+            }
+            queue = next;
+        }
+    }
+
+    fn leader(&mut self) -> Option<&mut RaftServer<u64, u64>> {
+        let down = &self.down;
+        self.nodes
+            .values_mut()
+            .find(|node| node.is_leader() && !down.contains(&node.id))
+    }
+
+    fn propose(&mut self) {
+        self.proposed += 1;
+        let value = self.proposed;
+        if let Some(leader) = self.leader() {
+            let _ = leader.client_request(value);
+        }
+    }
+
+    fn kill_leader(&mut self) {
+        if let Some(id) = self.leader().map(|leader| leader.id) {
+            self.down.insert(id);
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, sim: &Sim, paused: bool) {
+    let [header, nodes, followers, feed] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(NODES as u16 + 3),
+        Constraint::Length(NODES as u16 + 2),
+        Constraint::Min(0),
+    ])
+    .areas(frame.area());
+
+    let state = if paused { "paused" } else { "running" };
+    frame.render_widget(
+        Paragraph::new(format!(
+            "tick {} ({})  [space] pause  [n] step  [p] propose  [k] kill leader  [r] revive  [q] quit",
+            sim.ticks, state
+        )),
+        header,
+    );
+
+    let rows = sim.nodes.values().map(|node| {
+        let status = node.status();
+        let (role, colour) = if sim.down.contains(&node.id) {
+            ("down".to_string(), Color::DarkGray)
+        } else {
+            let colour = match status.role {
+                Role::Leader => Color::Yellow,
+                Role::Candidate => Color::Cyan,
+                Role::Follower => Color::White,
+            };
+            (format!("{:?}", status.role), colour)
+        };
+        Row::new(vec![
+            status.id.to_string(),
+            role,
+            status.term.to_string(),
+            status
+                .voted_for
+                .map_or("-".to_string(), |id| id.to_string()),
+            status.log_len.to_string(),
+            status.committed_len.to_string(),
+            status.applied_len.to_string(),
+            node.log.app.get_state().to_string(),
+        ])
+        .style(Style::default().fg(colour))
+    });
+    let header_row = Row::new(vec![
+        "id", "role", "term", "voted", "log", "commit", "applied", "state",
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    frame.render_widget(
+        Table::new(rows, [Constraint::Length(8); 8])
+            .header(header_row)
+            .block(Block::bordered().title(" nodes ")),
+        nodes,
+    );
+
+    let leader_status = sim
+        .nodes
+        .values()
+        .find(|node| node.is_leader() && !sim.down.contains(&node.id))
+        .map(|node| node.status());
+    let rows: Vec<Row> = leader_status
+        .iter()
+        .flat_map(|status| {
+            let lag = status.follower_lag();
+            status.followers.iter().flatten().map(move |(id, state)| {
+                Row::new(vec![
+                    id.to_string(),
+                    state.sent_up_to.to_string(),
+                    state.acked_up_to.to_string(),
+                    state.applied_up_to.to_string(),
+                    lag[id].to_string(),
+                    state.inflight.to_string(),
+                ])
+            })
+        })
+        .collect();
+    let title = match &leader_status {
+        Some(status) => format!(" followers of {} ", status.id),
+        None => " no leader ".to_string(),
+    };
+    frame.render_widget(
+        Table::new(rows, [Constraint::Length(8); 6])
+            .header(
+                Row::new(vec!["id", "sent", "acked", "applied", "lag", "inflight"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::bordered().title(title)),
+        followers,
+    );
+
+    let items: Vec<ListItem> = sim
+        .feed
+        .iter()
+        .take(feed.height as usize)
+        .map(|line| ListItem::new(line.as_str()))
+        .collect();
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(" messages ")),
+        feed,
+    );
+}
+
+fn run(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    let mut sim = Sim::new();
+    let mut paused = false;
+    loop {
+        terminal.draw(|frame| draw(frame, &sim, paused))?;
+
+        if event::poll(TICK_EVERY)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char(' ') => paused = !paused,
+                    KeyCode::Char('n') if paused => sim.step(),
+                    KeyCode::Char('p') => sim.propose(),
+                    KeyCode::Char('k') => sim.kill_leader(),
+                    KeyCode::Char('r') => sim.down.clear(),
+                    _ => {}
+                }
+                continue;
+            }
+        }
+        if !paused {
+            sim.step();
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,167 @@
+//! A lease-based distributed lock service built on top of miniraft.
+//!
+//! Clients hold a session which they keep alive with [`LockCommand::KeepAlive`]. Locks are
+//! tied to the session that acquired them, so if a client crashes and stops renewing, its
+//! locks are released once the session lease expires. Every successful acquire hands out a
+//! fencing token which increases monotonically, letting downstream resources reject writes
+//! from a client that still thinks it holds a lock it has already lost.
+//!
+//! Note that time is part of the replicated command (`now`) rather than read from each
+//! replica's local clock: every replica must make the exact same decisions when replaying
+//! the log, otherwise their states would diverge.
+//!
+//! Run with `cargo run --example lock_service`
+
+mod common;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::Cluster;
+use miniraft::log::{App, LogEntry};
+
+/// Identifier for a lock client
+type ClientId = u32;
+
+/// Monotonically increasing token handed out on every successful acquire
+type FencingToken = u64;
+
+/// Logical time, supplied by the client with every command
+type Time = u64;
+
+/// How long a session stays alive without a [`LockCommand::KeepAlive`]
+const SESSION_TTL: Time = 10;
+
+/// Commands that get replicated through the Raft log
+#[derive(Clone, Debug)]
+enum LockCommand {
+    /// Open or renew a session for `client`
+    KeepAlive { client: ClientId, now: Time },
+    /// Try to take `lock` for the session of `client`
+    Acquire {
+        client: ClientId,
+        lock: &'static str,
+        now: Time,
+    },
+    /// Give up `lock`, only succeeds if `token` is still the current holder's token
+    Release {
+        client: ClientId,
+        lock: &'static str,
+        token: FencingToken,
+    },
+}
+
+/// Current holder of a lock
+#[derive(Clone, Debug, PartialEq)]
+struct Holder {
+    client: ClientId,
+    token: FencingToken,
+}
+
+/// State machine for the lock service
+#[derive(Default)]
+struct LockService {
+    /// Session expiry time for every client with a live session
+    sessions: BTreeMap<ClientId, Time>,
+    /// Who currently holds each lock
+    locks: BTreeMap<&'static str, Holder>,
+    /// Last fencing token handed out
+    last_token: FencingToken,
+}
+
+impl LockService {
+    /// Drop any sessions whose lease ran out before `now`, along with their locks
+    fn expire_sessions(&mut self, now: Time) {
+        let expired: BTreeSet<ClientId> = self
+            .sessions
+            .iter()
+            .filter(|(_, expires_at)| **expires_at < now)
+            .map(|(client, _)| *client)
+            .collect();
+        self.sessions.retain(|client, _| !expired.contains(client));
+        self.locks
+            .retain(|_, holder| !expired.contains(&holder.client));
+    }
+}
+
+impl App<LockCommand, BTreeMap<&'static str, Holder>> for LockService {
+    fn transition_fn(&mut self, entry: &LogEntry<LockCommand>) {
+        match entry.data {
+            LockCommand::KeepAlive { client, now } => {
+                self.expire_sessions(now);
+                self.sessions.insert(client, now + SESSION_TTL);
+            }
+            LockCommand::Acquire { client, lock, now } => {
+                self.expire_sessions(now);
+                // need a live session, and the lock must be free
+                if self.sessions.contains_key(&client) && !self.locks.contains_key(lock) {
+                    self.last_token += 1;
+                    self.locks.insert(
+                        lock,
+                        Holder {
+                            client,
+                            token: self.last_token,
+                        },
+                    );
+                }
+            }
+            LockCommand::Release {
+                client,
+                lock,
+                token,
+            } => {
+                let current = Holder { client, token };
+                if self.locks.get(lock) == Some(&current) {
+                    self.locks.remove(lock);
+                }
+            }
+        }
+    }
+
+    fn get_state(&self) -> BTreeMap<&'static str, Holder> {
+        self.locks.clone()
+    }
+}
+
+fn main() {
+    let mut cluster = Cluster::new(3, || Box::new(LockService::default()));
+
+    // client 1 takes the lock, client 2 is turned away
+    cluster.submit(LockCommand::KeepAlive { client: 1, now: 0 });
+    cluster.submit(LockCommand::KeepAlive { client: 2, now: 0 });
+    cluster.submit(LockCommand::Acquire {
+        client: 1,
+        lock: "db",
+        now: 1,
+    });
+    cluster.submit(LockCommand::Acquire {
+        client: 2,
+        lock: "db",
+        now: 2,
+    });
+    println!("locks: {:?}", cluster.state());
+
+    // client 1 goes silent. once its session expires, client 2 gets the lock
+    // with a larger fencing token so writes from client 1 can be fenced off
+    cluster.submit(LockCommand::KeepAlive { client: 2, now: 8 });
+    cluster.submit(LockCommand::Acquire {
+        client: 2,
+        lock: "db",
+        now: 15,
+    });
+    println!("locks: {:?}", cluster.state());
+
+    // a stale release from client 1 using its old token does nothing
+    cluster.submit(LockCommand::Release {
+        client: 1,
+        lock: "db",
+        token: 1,
+    });
+    cluster.submit(LockCommand::Release {
+        client: 2,
+        lock: "db",
+        token: 2,
+    });
+    println!("locks: {:?}", cluster.state());
+
+    assert!(cluster.converged());
+}
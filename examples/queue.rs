@@ -0,0 +1,210 @@
+//! A replicated FIFO work queue built on top of miniraft.
+//!
+//! Producers enqueue items, consumers dequeue them and later acknowledge them once the
+//! work is done. Items that were handed out but never acked can be put back at the front
+//! of the queue with [`QueueOp::Requeue`] (e.g. when a consumer crashed).
+//!
+//! Every command carries a `(client, seq)` pair. The state machine remembers the last
+//! sequence number and response of every client, which gives us two things:
+//! - dedup: if a client retries a command (say it timed out waiting on the leader and
+//!   resubmitted), the retry is not executed twice; the cached response is returned instead.
+//!   This matters a lot for a queue as a retried dequeue would otherwise swallow an item.
+//! - response routing: a client finds the outcome of its command by looking up its own
+//!   session rather than having to match up log positions.
+//!
+//! Run with `cargo run --example queue`
+
+mod common;
+
+use std::collections::{BTreeMap, VecDeque};
+
+use common::Cluster;
+use miniraft::log::{App, LogEntry};
+
+/// Identifier for a queue client (producer or consumer)
+type ClientId = u32;
+
+/// Per-client sequence number, increases with every new (non-retried) command
+type Seq = u64;
+
+/// Identifier for an item that has been handed out and not yet acked
+type DeliveryId = u64;
+
+/// Operations supported by the queue
+#[derive(Clone, Debug)]
+enum QueueOp {
+    /// Push an item onto the back of the queue
+    Enqueue(&'static str),
+    /// Take the item at the front of the queue
+    Dequeue,
+    /// Mark a delivered item as done
+    Ack(DeliveryId),
+    /// Put every unacked item handed to this client back at the front of the queue
+    Requeue,
+}
+
+/// A command as it is replicated through the log
+#[derive(Clone, Debug)]
+struct Command {
+    client: ClientId,
+    seq: Seq,
+    op: QueueOp,
+}
+
+/// Outcome of a command, routed back to the client that issued it
+#[derive(Clone, Debug, PartialEq)]
+enum Response {
+    Enqueued,
+    Delivered(DeliveryId, &'static str),
+    Empty,
+    Acked,
+    UnknownDelivery,
+    Requeued(usize),
+}
+
+/// Replicated state of the queue
+#[derive(Clone, Debug, Default, PartialEq)]
+struct QueueState {
+    /// Items waiting to be handed out
+    items: VecDeque<&'static str>,
+    /// Items handed out but not acked yet, with the client they were handed to
+    in_flight: BTreeMap<DeliveryId, (ClientId, &'static str)>,
+    /// Last delivery id handed out
+    last_delivery: DeliveryId,
+    /// Last command applied for every client along with its response
+    sessions: BTreeMap<ClientId, (Seq, Response)>,
+}
+
+impl QueueState {
+    /// Actually perform an operation, only ever called once per `(client, seq)`
+    fn execute(&mut self, client: ClientId, op: &QueueOp) -> Response {
+        match op {
+            QueueOp::Enqueue(item) => {
+                self.items.push_back(item);
+                Response::Enqueued
+            }
+            QueueOp::Dequeue => match self.items.pop_front() {
+                Some(item) => {
+                    self.last_delivery += 1;
+                    self.in_flight.insert(self.last_delivery, (client, item));
+                    Response::Delivered(self.last_delivery, item)
+                }
+                None => Response::Empty,
+            },
+            QueueOp::Ack(delivery) => match self.in_flight.get(delivery) {
+                Some((owner, _)) if *owner == client => {
+                    self.in_flight.remove(delivery);
+                    Response::Acked
+                }
+                _ => Response::UnknownDelivery,
+            },
+            QueueOp::Requeue => {
+                let mine: Vec<DeliveryId> = self
+                    .in_flight
+                    .iter()
+                    .filter(|(_, (owner, _))| *owner == client)
+                    .map(|(delivery, _)| *delivery)
+                    .collect();
+                // walk backwards so the oldest delivery ends up at the very front
+                for delivery in mine.iter().rev() {
+                    let (_, item) = self.in_flight.remove(delivery).unwrap();
+                    self.items.push_front(item);
+                }
+                Response::Requeued(mine.len())
+            }
+        }
+    }
+}
+
+/// State machine wrapper for the queue
+#[derive(Default)]
+struct ReplicatedQueue {
+    state: QueueState,
+}
+
+impl App<Command, QueueState> for ReplicatedQueue {
+    fn transition_fn(&mut self, entry: &LogEntry<Command>) {
+        let Command { client, seq, op } = &entry.data;
+        match self.state.sessions.get(client) {
+            // already applied (retry of a command that made it into the log twice),
+            // the cached response stands and nothing is executed
+            Some((last_seq, _)) if last_seq >= seq => {}
+            _ => {
+                let response = self.state.execute(*client, op);
+                self.state.sessions.insert(*client, (*seq, response));
+            }
+        }
+    }
+
+    fn get_state(&self) -> QueueState {
+        self.state.clone()
+    }
+}
+
+/// Client side helper that tags commands with sequence numbers and reads back responses
+struct Client {
+    id: ClientId,
+    seq: Seq,
+}
+
+impl Client {
+    fn new(id: ClientId) -> Self {
+        Client { id, seq: 0 }
+    }
+
+    /// Submit a new command and return its response
+    fn call(&mut self, cluster: &mut Cluster<Command, QueueState>, op: QueueOp) -> Response {
+        self.seq += 1;
+        self.resubmit(cluster, op)
+    }
+
+    /// Submit the last command again without bumping the sequence number, as a client
+    /// would after timing out on the leader
+    fn resubmit(&self, cluster: &mut Cluster<Command, QueueState>, op: QueueOp) -> Response {
+        cluster.submit(Command {
+            client: self.id,
+            seq: self.seq,
+            op,
+        });
+        match cluster.state().sessions.get(&self.id) {
+            Some((seq, response)) if *seq == self.seq => response.clone(),
+            _ => panic!("no response for client {} seq {}", self.id, self.seq),
+        }
+    }
+}
+
+fn main() {
+    let mut cluster = Cluster::new(3, || Box::new(ReplicatedQueue::default()));
+    let mut producer = Client::new(1);
+    let mut consumer = Client::new(2);
+
+    for item in ["a", "b", "c"] {
+        println!(
+            "  {:?}",
+            producer.call(&mut cluster, QueueOp::Enqueue(item))
+        );
+    }
+
+    // consumer takes "a", then retries the same dequeue as if the first response was lost.
+    // the retry gets the same delivery back instead of swallowing "b"
+    let first = consumer.call(&mut cluster, QueueOp::Dequeue);
+    let retry = consumer.resubmit(&mut cluster, QueueOp::Dequeue);
+    println!("  {:?} (retry: {:?})", first, retry);
+    assert_eq!(first, retry);
+
+    if let Response::Delivered(delivery, _) = first {
+        println!(
+            "  {:?}",
+            consumer.call(&mut cluster, QueueOp::Ack(delivery))
+        );
+    }
+
+    // consumer takes "b" but crashes before acking, its work goes back on the queue
+    println!("  {:?}", consumer.call(&mut cluster, QueueOp::Dequeue));
+    println!("  {:?}", consumer.call(&mut cluster, QueueOp::Requeue));
+
+    let state = cluster.state();
+    println!("queue: {:?}, in flight: {:?}", state.items, state.in_flight);
+    assert_eq!(state.items, ["b", "c"]);
+    assert!(cluster.converged());
+}
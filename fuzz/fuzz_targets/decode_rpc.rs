@@ -0,0 +1,16 @@
+//! Feeds arbitrary bytes to the JSON decoding of RPCs. Anything that decodes has to survive
+//! a round trip unchanged
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use miniraft::rpc::RPC;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(rpc) = serde_json::from_slice::<RPC<u32>>(data) {
+        let encoded = serde_json::to_vec(&rpc).expect("decoded RPCs can be encoded");
+        let decoded: RPC<u32> =
+            serde_json::from_slice(&encoded).expect("encoded RPCs can be decoded");
+        assert_eq!(decoded, rpc);
+    }
+});
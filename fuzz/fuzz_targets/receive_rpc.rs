@@ -0,0 +1,108 @@
+//! Drives a single node with an arbitrary mix of ticks, client requests and RPCs.
+//!
+//! RPCs are structurally valid (they claim to come from one of the node's peers) but
+//! everything else about them is up to the fuzzer: terms, log indexes, entries, snapshots.
+//! Whatever a peer sends, the node must not panic
+
+#![no_main]
+
+use std::io;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use miniraft::{
+    log::{App, LogEntry},
+    rpc::RPC,
+    server::{RaftConfig, RaftServer, ServerId, SlowPathConfig, TieBreak},
+};
+
+/// Size of the cluster the node thinks it is in, node 0 being the one under test
+const NODES: usize = 3;
+
+/// Something that happens to the node
+#[derive(Arbitrary, Debug)]
+enum Action {
+    Tick,
+    ClientRequest(u32),
+    Receive(RPC<u32>),
+    Snapshot,
+}
+
+/// Sums up every entry, snapshots are the sum as 4 bytes
+struct Counter(u32);
+
+impl App<u32, u32> for Counter {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        self.0 = self.0.wrapping_add(entry.data);
+    }
+
+    fn get_state(&self) -> u32 {
+        self.0
+    }
+
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        writer.write_all(&self.0.to_le_bytes())
+    }
+
+    fn restore(&mut self, reader: &mut dyn io::Read) -> io::Result<()> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        self.0 = u32::from_le_bytes(buf);
+        Ok(())
+    }
+}
+
+/// Map any id onto one of the node's peers
+fn peer(id: ServerId) -> ServerId {
+    1 + id % (NODES - 1)
+}
+
+/// Make the RPC come from one of the node's peers
+fn sanitize(rpc: &mut RPC<u32>) {
+    match rpc {
+        RPC::VoteRequest(req) => req.candidate_id = peer(req.candidate_id),
+        RPC::VoteResponse(res) => res.votee_id = peer(res.votee_id),
+        RPC::AppendRequest(req) => req.leader_id = peer(req.leader_id),
+        RPC::AppendResponse(res) => res.follower_id = peer(res.follower_id),
+        RPC::SnapshotRequest(req) => req.leader_id = peer(req.leader_id),
+        RPC::SnapshotResponse(res) => res.follower_id = peer(res.follower_id),
+        RPC::TimeoutNow(req) => req.leader_id = peer(req.leader_id),
+        RPC::ReadIndexRequest(req) => req.reader_id = peer(req.reader_id),
+        RPC::ReadIndexResponse(res) => res.leader_id = peer(res.leader_id),
+    }
+}
+
+fuzz_target!(|actions: Vec<Action>| {
+    let config = RaftConfig {
+        election_timeout: 4,
+        election_timeout_jitter: 1,
+        heartbeat_interval: 2,
+        max_apply_lag: None,
+        slow_path: SlowPathConfig::default(),
+        leaderless_alarm: None,
+        max_log_entries: None,
+        persist_in_background: false,
+        replication_batching: None,
+        broadcast_commits: false,
+        tie_break: TieBreak::Never,
+    };
+    let peers = (1..NODES).collect();
+    let mut server = RaftServer::new(0, peers, config, Some(0), Box::new(Counter(0)));
+    for action in actions {
+        match action {
+            Action::Tick => {
+                server.tick();
+            }
+            Action::ClientRequest(data) => {
+                let _ = server.client_request(data);
+            }
+            Action::Receive(mut rpc) => {
+                sanitize(&mut rpc);
+                server.receive_rpc(&rpc);
+            }
+            Action::Snapshot => {
+                let _ = server.snapshot_now();
+            }
+        }
+    }
+});
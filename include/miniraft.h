@@ -0,0 +1,93 @@
+/*
+ * C interface to a miniraft node, built with the `ffi` feature:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * Entries are opaque bytes, encoding them is up to the host. Messages between nodes are
+ * opaque bytes too: whatever a node hands to `send` has to reach node `to` unchanged
+ * and be passed to `miniraft_receive` there. Messages may get lost.
+ *
+ * A node is not thread safe, calls for the same node must not overlap.
+ */
+
+#ifndef MINIRAFT_H
+#define MINIRAFT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* `to` passed to `send` for messages meant for every peer */
+#define MINIRAFT_BROADCAST UINT64_MAX
+
+typedef enum {
+    MINIRAFT_OK = 0,
+    /* proposals can only go to the leader, see miniraft_leader */
+    MINIRAFT_NOT_LEADER = 1,
+    /* the node is too far behind on applying entries to take more, retry later */
+    MINIRAFT_BUSY = 2,
+    /* a null pointer, or bytes that aren't a message from another node */
+    MINIRAFT_INVALID_ARGUMENT = 3,
+    MINIRAFT_FAILED = 4,
+} miniraft_result;
+
+/* timing of a node, in ticks */
+typedef struct {
+    uint32_t election_timeout;
+    uint32_t election_timeout_jitter;
+    uint32_t heartbeat_interval;
+} miniraft_config;
+
+/*
+ * Called synchronously from inside miniraft_tick, miniraft_receive and miniraft_propose,
+ * with `ctx` as first argument. `data` is only valid until the callback returns.
+ */
+typedef struct {
+    void *ctx;
+    /* get a message to node `to`, or to every peer if `to` is MINIRAFT_BROADCAST */
+    void (*send)(void *ctx, uint64_t to, const uint8_t *data, size_t len);
+    /* apply the committed entry at `index` to the host's state machine */
+    void (*apply)(void *ctx, uint64_t index, const uint8_t *data, size_t len);
+} miniraft_callbacks;
+
+typedef struct MiniraftNode miniraft_node;
+
+/*
+ * Create node `id` of a cluster with `n_peers` other nodes listed in `peers`. `seed` makes
+ * election timeouts reproducible. Returns NULL if the config can't work or `peers` lists
+ * the node itself.
+ */
+miniraft_node *miniraft_new(uint64_t id, const uint64_t *peers, size_t n_peers,
+                            miniraft_config config, uint64_t seed,
+                            miniraft_callbacks callbacks);
+
+/* free a node created by miniraft_new, NULL is ignored */
+void miniraft_free(miniraft_node *node);
+
+/* move the node's logical clock forward by a tick */
+miniraft_result miniraft_tick(miniraft_node *node);
+
+/* hand the node a message another node sent it */
+miniraft_result miniraft_receive(miniraft_node *node, const uint8_t *data, size_t len);
+
+/* propose an entry, it gets applied on every node once committed */
+miniraft_result miniraft_propose(miniraft_node *node, const uint8_t *data, size_t len);
+
+/* write who the node believes is leader to `leader`, returns whether it knows of one */
+bool miniraft_leader(const miniraft_node *node, uint64_t *leader);
+
+/* term the node is in */
+uint64_t miniraft_term(const miniraft_node *node);
+
+/* how many entries of the node's log are committed */
+uint64_t miniraft_committed_len(const miniraft_node *node);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MINIRAFT_H */
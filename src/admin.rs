@@ -0,0 +1,194 @@
+use crate::{
+    log::LogIndex,
+    server::RaftServer,
+    status::{DebugDump, DumpedEntry},
+};
+use std::{
+    fmt::Debug,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// How many entries from the end of the log are served under `/log` by default
+pub const DEFAULT_LOG_WINDOW: usize = 1_000;
+
+/// How long a client gets to send its request, and to take the response, before we hang up
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Read-only HTTP endpoint for looking at a node with a browser or curl.
+///
+/// Serves, as JSON:
+/// - `/status`: the node's [`status`](RaftServer::status)
+/// - `/metrics`: the node's [`metrics`](RaftServer::metrics)
+/// - `/log?from=<index>`: log entries starting at `index`, only the last
+///   [`log_window`](Self::with_log_window) entries are available
+///
+/// Like [`PrometheusExporter`](crate::prometheus::PrometheusExporter) the server never
+/// touches the node directly, requests are answered from whatever was last handed to
+/// [`update`](Self::update) so call it after every `tick()`/`receive_rpc()`.
+///
+/// Every connection is served on its own thread, so a slow or idle client holds up neither
+/// other requests nor dropping the server. It is cut off after [`CLIENT_TIMEOUT`]
+pub struct AdminServer {
+    /// Address the listener is bound to
+    addr: SocketAddr,
+    /// Latest state of the node, shared with the listener thread
+    published: Arc<Mutex<Option<DebugDump>>>,
+    /// Number of log entries included in every update
+    log_window: usize,
+    /// Set on drop to get the listener thread to exit
+    stop: Arc<AtomicBool>,
+    /// Listener thread, joined on drop. Connections it handed off to threads of their own
+    /// finish by themselves
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AdminServer {
+    /// Start serving on `addr`. Bind to port 0 to get a free port, see
+    /// [`local_addr`](Self::local_addr)
+    pub fn bind(addr: impl Into<SocketAddr>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr.into())?;
+        let addr = listener.local_addr()?;
+        let published: Arc<Mutex<Option<DebugDump>>> = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_published = published.clone();
+        let thread_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                // a client hanging up on us is not our problem
+                if let Ok(stream) = stream {
+                    let published = thread_published.clone();
+                    thread::spawn(move || handle_connection(stream, &published));
+                }
+            }
+        });
+
+        Ok(AdminServer {
+            addr,
+            published,
+            log_window: DEFAULT_LOG_WINDOW,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Serve up to `log_window` entries from the end of the log instead of
+    /// [`DEFAULT_LOG_WINDOW`]
+    pub fn with_log_window(mut self, log_window: usize) -> Self {
+        self.log_window = log_window;
+        self
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Publish the current state of `server`
+    pub fn update<T, S>(&self, server: &RaftServer<T, S>)
+    where
+        T: Clone + Debug,
+    {
+        let dump = server.debug_dump(self.log_window);
+        *self.published.lock().expect("admin state lock poisoned") = Some(dump);
+    }
+}
+
+impl Drop for AdminServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake the listener up so it notices
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Answer a single request and close the connection
+fn handle_connection(stream: TcpStream, published: &Mutex<Option<DebugDump>>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // drain the headers, we don't need any of them
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let (code, body) = if method != "GET" {
+        ("405 Method Not Allowed", error("only GET is supported"))
+    } else {
+        match published
+            .lock()
+            .expect("admin state lock poisoned")
+            .as_ref()
+        {
+            None => ("503 Service Unavailable", error("no state published yet")),
+            Some(dump) => route(dump, path, query),
+        }
+    };
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Pick the response for `path`
+fn route(dump: &DebugDump, path: &str, query: &str) -> (&'static str, String) {
+    match path {
+        "/status" => ("200 OK", to_json(&dump.status)),
+        "/metrics" => ("200 OK", to_json(&dump.metrics)),
+        "/log" => {
+            let from = query
+                .split('&')
+                .filter_map(|param| param.strip_prefix("from="))
+                .next()
+                .map(str::parse::<LogIndex>);
+            match from {
+                Some(Err(_)) => ("400 Bad Request", error("`from` must be a log index")),
+                Some(Ok(from)) => ("200 OK", to_json(&entries_from(dump, from))),
+                None => ("200 OK", to_json(&dump.log_tail)),
+            }
+        }
+        _ => ("404 Not Found", error("unknown path")),
+    }
+}
+
+/// Entries at or after `from` out of the ones that were published
+fn entries_from(dump: &DebugDump, from: LogIndex) -> Vec<&DumpedEntry> {
+    dump.log_tail
+        .iter()
+        .filter(|entry| entry.index >= from)
+        .collect()
+}
+
+fn to_json(value: &impl serde::Serialize) -> String {
+    serde_json::to_string(value).expect("admin responses are always serializable")
+}
+
+fn error(message: &str) -> String {
+    to_json(&serde_json::json!({ "error": message }))
+}
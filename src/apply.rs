@@ -0,0 +1,202 @@
+use crate::log::{App, ApplyContext, LogEntry, LogIndex};
+use std::{
+    io,
+    sync::{
+        mpsc::{channel, Sender},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// How far the worker thread has gotten, shared so the Raft side can see how far behind
+/// the app is without waiting on the app itself
+type Progress = Arc<(Mutex<WorkerProgress>, Condvar)>;
+
+/// See [`Progress`]
+#[derive(Default)]
+struct WorkerProgress {
+    /// Number of entries the worker thread has finished applying
+    applied: LogIndex,
+    /// Whether the worker thread is gone, which before shutdown means the app panicked
+    stopped: bool,
+}
+
+/// Marks the worker as [stopped](WorkerProgress::stopped) when its thread exits, however
+/// it exits, so nobody waits on it forever
+struct StopGuard(Progress);
+
+impl Drop for StopGuard {
+    fn drop(&mut self) {
+        let (progress, cvar) = &*self.0;
+        lock(progress).stopped = true;
+        cvar.notify_all();
+    }
+}
+
+/// Lock the progress, which only ever holds plain counters that stay consistent even if
+/// a thread panicked while holding the lock
+fn lock(progress: &Mutex<WorkerProgress>) -> MutexGuard<'_, WorkerProgress> {
+    progress.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Error for when the app panicked on the worker thread
+fn worker_panicked() -> io::Error {
+    io::Error::other("app panicked on the apply worker thread")
+}
+
+/// An [`App`] adapter that runs the wrapped app on a dedicated worker thread.
+///
+/// Committed entries are pushed onto a channel instead of being applied inline, so a
+/// slow [`transition_fn`](App::transition_fn) can never hold up `tick()`/`receive_rpc()`
+/// (and thus heartbeats and election timers). The worker reports back how far it has
+/// gotten, which surfaces as [`Log::last_applied`](crate::log::Log::last_applied).
+///
+/// If the app panics, the worker thread stops there. Entries queued after that are dropped
+/// and stay [`pending`](App::pending), and whatever waits on the worker fails
+pub struct ApplyWorker<T, S> {
+    /// Sending half of the committed-entries channel.
+    /// Only `None` while shutting down so the worker sees the channel close
+    sender: Option<Sender<(LogEntry<T>, ApplyContext)>>,
+    /// The wrapped app, shared with the worker thread
+    app: Arc<Mutex<Box<dyn App<T, S> + Send>>>,
+    /// Number of entries handed to the worker so far
+    submitted: LogIndex,
+    /// Index in the log of the next entry, for entries queued without an [`ApplyContext`]
+    next_index: LogIndex,
+    /// Number of entries the worker has finished applying
+    progress: Progress,
+    /// Handle to the worker thread, joined on drop
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<T, S> ApplyWorker<T, S>
+where
+    T: Send + 'static,
+    S: 'static,
+{
+    /// Spawn a worker thread that applies committed entries to `app`
+    pub fn new(app: Box<dyn App<T, S> + Send>) -> Self {
+        let (sender, receiver) = channel::<(LogEntry<T>, ApplyContext)>();
+        let app = Arc::new(Mutex::new(app));
+        let progress: Progress = Arc::default();
+
+        let worker_app = app.clone();
+        let guard = StopGuard(progress.clone());
+        let handle = thread::spawn(move || {
+            // runs until the sending half is dropped, or the app panics
+            for (entry, ctx) in receiver {
+                worker_app
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .apply(&entry, &ctx);
+
+                let (progress, cvar) = &*guard.0;
+                lock(progress).applied += 1;
+                cvar.notify_all();
+            }
+        });
+
+        ApplyWorker {
+            sender: Some(sender),
+            app,
+            submitted: 0,
+            next_index: 0,
+            progress,
+            handle: Some(handle),
+        }
+    }
+
+    /// Block until every entry handed to the worker so far has been applied. Fails if the
+    /// app panicked before it got through them
+    pub fn flush(&self) -> io::Result<()> {
+        let (progress, cvar) = &*self.progress;
+        let progress = cvar
+            .wait_while(lock(progress), |progress| {
+                progress.applied < self.submitted && !progress.stopped
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+        match progress.applied < self.submitted {
+            true => Err(worker_panicked()),
+            false => Ok(()),
+        }
+    }
+
+    /// The wrapped app, once the worker is done with everything handed to it
+    fn flushed_app(&self) -> io::Result<MutexGuard<'_, Box<dyn App<T, S> + Send>>> {
+        self.flush()?;
+        self.app.lock().map_err(|_| worker_panicked())
+    }
+}
+
+impl<T, S> App<T, S> for ApplyWorker<T, S>
+where
+    T: Clone + Send + 'static,
+    S: 'static,
+{
+    /// Queue the entry for the worker thread, returns immediately.
+    /// Entries queued this way are treated as not having been proposed by this node
+    fn transition_fn(&mut self, entry: &LogEntry<T>) {
+        let ctx = ApplyContext::new(self.next_index, entry, None);
+        self.apply(entry, &ctx)
+    }
+
+    /// Queue the entry for the worker thread, returns immediately.
+    /// The context is captured at the time the entry is queued, not when the worker gets to it
+    fn apply(&mut self, entry: &LogEntry<T>, ctx: &ApplyContext) {
+        if let Some(sender) = &self.sender {
+            // a worker that is gone has already reported the entry as not applied
+            let _ = sender.send((entry.clone(), *ctx));
+        }
+        self.submitted += 1;
+        self.next_index = ctx.index + 1;
+    }
+
+    /// State of the wrapped app. Does not include entries that are still [`pending`](App::pending).
+    /// If the app panicked, this is the state it was left in
+    fn get_state(&self) -> S {
+        self.app
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_state()
+    }
+
+    fn pending(&self) -> LogIndex {
+        self.submitted - lock(&self.progress.0).applied
+    }
+
+    /// Waits for the worker to drain its queue so the snapshot covers every entry handed to it
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        self.flushed_app()?.snapshot(writer)
+    }
+
+    /// Never waits on the worker, if it is busy applying we'll get asked again next tick
+    fn wants_snapshot(&mut self) -> bool {
+        match self.app.try_lock() {
+            Ok(mut app) => app.wants_snapshot(),
+            Err(_) => false,
+        }
+    }
+
+    /// Waits for the worker to drain its queue before replacing the app state
+    fn restore(&mut self, reader: &mut dyn io::Read) -> io::Result<()> {
+        self.flushed_app()?.restore(reader)
+    }
+
+    /// Like [`restore`](Self::restore), entries queued afterwards through
+    /// [`transition_fn`](App::transition_fn) carry on from index `len`
+    fn restore_at(&mut self, reader: &mut dyn io::Read, len: LogIndex) -> io::Result<()> {
+        self.flushed_app()?.restore_at(reader, len)?;
+        self.next_index = len;
+        Ok(())
+    }
+}
+
+impl<T, S> Drop for ApplyWorker<T, S> {
+    fn drop(&mut self) {
+        // closing the channel lets the worker drain what is left and exit
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
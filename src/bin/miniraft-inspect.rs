@@ -0,0 +1,144 @@
+//! Print what a node has persisted to disk.
+//!
+//! Usage: `miniraft-inspect [--json] <path>...`
+//!
+//! Every path is either a snapshot file written by
+//! [`save_snapshot`](miniraft::storage::save_snapshot) or a data directory, in which case
+//! every file in it is inspected. Snapshots are currently the only thing a node persists,
+//! so that is all there is to show.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{bail, Context, Result};
+use miniraft::{log::LogIndex, server::Term, storage::load_snapshot};
+use serde::Serialize;
+
+/// How many bytes of app data to show in the human readable output
+const PREVIEW_LEN: usize = 32;
+
+/// What we found in a single file
+#[derive(Serialize)]
+struct FileReport {
+    path: PathBuf,
+    #[serde(flatten)]
+    contents: Contents,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Contents {
+    Snapshot {
+        /// Number of log entries covered
+        len: LogIndex,
+        /// Term of the last entry covered
+        term: Term,
+        /// Size of the app data in bytes
+        data_len: usize,
+        /// The app data, hex encoded
+        data: String,
+    },
+    /// Temporary file left behind by a save that was interrupted
+    IncompleteSave,
+    /// Something we couldn't make sense of
+    Unreadable { error: String },
+}
+
+fn inspect_file(path: &Path) -> FileReport {
+    let contents = if path.extension().is_some_and(|ext| ext == "tmp") {
+        Contents::IncompleteSave
+    } else {
+        match load_snapshot(path) {
+            Ok(Some(snapshot)) => Contents::Snapshot {
+                len: snapshot.len,
+                term: snapshot.term,
+                data_len: snapshot.data.len(),
+                data: hex(&snapshot.data),
+            },
+            Ok(None) => Contents::Unreadable {
+                error: "file disappeared".to_string(),
+            },
+            Err(err) => Contents::Unreadable {
+                error: err.to_string(),
+            },
+        }
+    };
+    FileReport {
+        path: path.to_path_buf(),
+        contents,
+    }
+}
+
+fn inspect(path: &Path) -> Result<Vec<FileReport>> {
+    if !path.is_dir() {
+        if !path.exists() {
+            bail!("{} does not exist", path.display());
+        }
+        return Ok(vec![inspect_file(path)]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path).with_context(|| format!("reading {}", path.display()))? {
+        let entry_path = entry?.path();
+        if entry_path.is_file() {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    Ok(files.iter().map(|file| inspect_file(file)).collect())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn print_human(report: &FileReport) {
+    println!("{}", report.path.display());
+    match &report.contents {
+        Contents::Snapshot {
+            len,
+            term,
+            data_len,
+            data,
+        } => {
+            println!("  snapshot covering {} entries, last term {}", len, term);
+            let preview = &data[..data.len().min(PREVIEW_LEN * 2)];
+            let ellipsis = if *data_len > PREVIEW_LEN { "..." } else { "" };
+            println!("  app data: {} bytes [{}{}]", data_len, preview, ellipsis);
+        }
+        Contents::IncompleteSave => println!("  leftover from an interrupted snapshot save"),
+        Contents::Unreadable { error } => println!("  not a snapshot: {}", error),
+    }
+}
+
+fn main() -> Result<()> {
+    let mut json = false;
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("usage: miniraft-inspect [--json] <path>...");
+                return Ok(());
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("usage: miniraft-inspect [--json] <path>...");
+        process::exit(2);
+    }
+
+    let mut reports = Vec::new();
+    for path in &paths {
+        reports.extend(inspect(path)?);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        reports.iter().for_each(print_human);
+    }
+    Ok(())
+}
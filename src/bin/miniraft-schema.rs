@@ -0,0 +1,16 @@
+//! Print the JSON Schema of the RPCs nodes send each other.
+//!
+//! Usage: `miniraft-schema`
+//!
+//! Node ids are the default [`ServerId`], and log entries may carry any JSON as what they
+//! hold is up to the app. Tools that know the app's entry type can get a tighter schema
+//! from [`rpc::schema`].
+
+use anyhow::Result;
+use miniraft::{rpc, server::ServerId};
+
+fn main() -> Result<()> {
+    let schema = rpc::schema::<serde_json::Value, ServerId>();
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
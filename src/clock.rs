@@ -0,0 +1,75 @@
+use crate::server::Ticks;
+use std::time::Duration;
+
+/// Monotonic wall time as read by the rest of the crate. `std`'s panics in the browser
+/// (`wasm32-unknown-unknown`), so there it is read from the JS clock instead
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use std::time::Instant;
+/// Monotonic wall time as read by the rest of the crate. `std`'s panics in the browser
+/// (`wasm32-unknown-unknown`), so there it is read from the JS clock instead
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use web_time::Instant;
+
+/// Where a node's logical time comes from. Whoever drives a
+/// [`RaftServer`](crate::server::RaftServer) asks its clock how many ticks went by and
+/// hands it to [`RaftServer::advance`](crate::server::RaftServer::advance), so the same
+/// driver code runs off the wall clock in a deployment and off a [`ManualClock`] in tests
+pub trait Clock {
+    /// Ticks that went by since the last call. Every tick is only ever reported once
+    fn elapsed(&mut self) -> Ticks;
+}
+
+/// A [`Clock`] that ticks every `tick` of wall time.
+/// Time that doesn't add up to a whole tick yet is carried over to the next call, so a
+/// driver that polls at odd intervals doesn't lose or gain ticks over time
+pub struct WallClock {
+    /// How much wall time a single tick stands for
+    tick: Duration,
+    /// Point in time up to which ticks have been reported
+    reported_up_to: Instant,
+}
+
+impl WallClock {
+    /// Start a clock that ticks every `tick`, counting from now
+    pub fn new(tick: Duration) -> Self {
+        assert!(!tick.is_zero(), "tick length must be positive");
+        WallClock {
+            tick,
+            reported_up_to: Instant::now(),
+        }
+    }
+}
+
+impl Clock for WallClock {
+    fn elapsed(&mut self) -> Ticks {
+        let since = self.reported_up_to.elapsed();
+        let ticks = (since.as_nanos() / self.tick.as_nanos()).min(Ticks::MAX as u128) as Ticks;
+        self.reported_up_to += self.tick * ticks;
+        ticks
+    }
+}
+
+/// A [`Clock`] that only moves when told to, for tests and simulations
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    /// Ticks added since the clock was last read
+    pending: Ticks,
+}
+
+impl ManualClock {
+    /// Create a clock that hasn't moved yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward by `n` ticks, to be reported on the next read
+    pub fn advance(&mut self, n: Ticks) {
+        self.pending = self.pending.saturating_add(n);
+    }
+}
+
+impl Clock for ManualClock {
+    fn elapsed(&mut self) -> Ticks {
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -1,14 +1,23 @@
 use crate::{
-    log::{Log, LogEntry, LogIndex},
-    rpc::{AppendRequest, AppendResponse, SendableMessage, Target, VoteRequest, VoteResponse, RPC},
-    server::{NodeReplicationState, RaftServer, ServerId, Term},
+    error::RaftError,
+    event::SlowOperation,
+    log::{Log, LogEntry, LogIndex, Snapshot},
+    rpc::{
+        AppendRequest, AppendResponse, ReadId, SendableMessage, SnapshotRequest, SnapshotResponse,
+        Target, TimeoutNow, VoteRequest, VoteResponse, RPC,
+    },
+    server::{NodeId, NodeReplicationState, RaftConfig, RaftServer, Term, Ticks},
 };
 use colored::Colorize;
 use core::fmt;
 use env_logger::TimestampPrecision;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 use random_color::{Luminosity, RandomColor};
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    hash::{DefaultHasher, Hash, Hasher},
+    time::Duration,
+};
 
 /// Level of logging
 pub enum Level {
@@ -18,6 +27,8 @@ pub enum Level {
     Requests,
     /// inner function workings
     Trace,
+    /// Something is off, always shown with the overview
+    Warning,
 }
 
 impl fmt::Display for Level {
@@ -29,6 +40,7 @@ impl fmt::Display for Level {
                 Level::Overview => "",
                 Level::Requests => "  ",
                 Level::Trace => "    ",
+                Level::Warning => "",
             }
         )
     }
@@ -36,7 +48,7 @@ impl fmt::Display for Level {
 
 /// Initialize the logger (default uses microseconds)
 pub fn init_logger() {
-    println!("");
+    println!();
     let _ = env_logger::builder()
         .is_test(true)
         .format_module_path(false)
@@ -47,11 +59,23 @@ pub fn init_logger() {
         .try_init();
 }
 
-/// Helper function to pretty print a [`ServerId`] with a unique colour
-pub fn colour_server(id: &ServerId) -> String {
+/// Helper function to pretty print a server ID with a unique colour.
+/// Numeric IDs get the same colour however they are passed in (e.g. as a
+/// [`ServerId`](crate::server::ServerId) or as the string a [`Log`] keeps),
+/// anything else is coloured by a hash of its name
+pub fn colour_server(id: &(impl fmt::Display + ?Sized)) -> String {
+    let name = id.to_string();
+    let seed = name.parse::<u32>().map_or_else(
+        |_| {
+            let mut hasher = DefaultHasher::new();
+            name.hash(&mut hasher);
+            hasher.finish() as u32
+        },
+        |n| n.wrapping_add(4),
+    );
     let [r, g, b] = RandomColor::new()
         .luminosity(Luminosity::Light)
-        .seed(*id as u32 + 4)
+        .seed(seed)
         .to_rgb_array();
     format!(" Server {} ", id)
         .black()
@@ -74,13 +98,24 @@ pub fn colour_bool(b: bool) -> String {
     .to_string()
 }
 
-/// Helper function to pretty print a message at the corresponding log [`Level`]
-pub fn log(id: &ServerId, msg: String, level: Level) {
-    let fmt_msg = format!("{} {}{}", colour_server(id), level, msg);
+/// Helper function to pretty print a message at the corresponding log [`Level`].
+/// `msg` is only called to build the message if that level is being logged
+pub fn log(id: &(impl fmt::Display + ?Sized), msg: impl FnOnce() -> String, level: Level) {
+    let filter = match level {
+        Level::Overview => log::Level::Info,
+        Level::Requests => log::Level::Debug,
+        Level::Trace => log::Level::Trace,
+        Level::Warning => log::Level::Warn,
+    };
+    if !log::log_enabled!(filter) {
+        return;
+    }
+    let fmt_msg = format!("{} {}{}", colour_server(id), level, msg());
     match level {
         Level::Overview => info!("{}", fmt_msg),
         Level::Requests => debug!("{}", fmt_msg),
         Level::Trace => trace!("{}", fmt_msg),
+        Level::Warning => warn!("{}", fmt_msg),
     }
 }
 
@@ -110,7 +145,7 @@ pub type Annotation = (AnnotationType, &'static str);
 
 /// Pretty print a set of [`Annotations`](Annotation) over a vector of [`LogEntries`](LogEntry)
 pub fn debug_log<T: fmt::Debug>(
-    entries: &Vec<LogEntry<T>>,
+    entries: &[LogEntry<T>],
     annotations: Vec<Annotation>,
     log_offset: LogIndex,
 ) -> String {
@@ -118,7 +153,7 @@ pub fn debug_log<T: fmt::Debug>(
         .iter()
         .map(|LogEntry { term, data }| format!("({}) {:?}", term, data))
         .collect();
-    let sep = if annotations.len() > 0 { "\n" } else { "" };
+    let sep = if !annotations.is_empty() { "\n" } else { "" };
     let first_line = format!("{}{}{}", " ".repeat(9 * log_offset), strs.join(" -> "), sep);
 
     let annotation_lines = annotations
@@ -141,7 +176,7 @@ pub fn debug_log<T: fmt::Debug>(
                 }
             }
             AnnotationType::Span(start, end) => {
-                if end - start <= 0 {
+                if end <= start {
                     format!("|  {msg}")
                 } else {
                     let pre_padding = " ".repeat(9 * (log_offset + start));
@@ -168,21 +203,23 @@ impl Logger {
         log_ref: &Log<T, S>,
         prefix_idx: LogIndex,
         leader_commit_len: LogIndex,
-        their_entries: &Vec<LogEntry<T>>,
-    ) {
-        let msg = if their_entries.len() > 0 {
-            format!(
-                "[append_entries] received with prefix_idx={}, leader_commit_len={}\ncurrent state: {}\nentries to append:{}",
-                prefix_idx,
-                leader_commit_len,
-                debug_log(&log_ref.entries, Vec::new(), 0),
-                debug_log(&their_entries, Vec::new(), prefix_idx)
-            )
-        } else {
-            format!(
-                "[append_entries] received heartbeat prefix_idx={}",
-                prefix_idx
-            )
+        their_entries: &[LogEntry<T>],
+    ) {
+        let msg = || {
+            if !their_entries.is_empty() {
+                format!(
+                    "[append_entries] received with prefix_idx={}, leader_commit_len={}\ncurrent state: {}\nentries to append:{}",
+                    prefix_idx,
+                    leader_commit_len,
+                    debug_log(&log_ref.entries, Vec::new(), 0),
+                    debug_log(their_entries, Vec::new(), prefix_idx)
+                )
+            } else {
+                format!(
+                    "[append_entries] received heartbeat prefix_idx={}",
+                    prefix_idx
+                )
+            }
         };
 
         log(&log_ref.parent_id, msg, Level::Requests)
@@ -191,29 +228,31 @@ impl Logger {
     /// called on potential log conflict when appending entries
     pub fn log_potential_conflict<T: Debug, S>(
         log_ref: &Log<T, S>,
-        their_entries: &Vec<LogEntry<T>>,
+        their_entries: &[LogEntry<T>],
         prefix_idx: LogIndex,
         rollback_to: LogIndex,
     ) {
         log(
             &log_ref.parent_id,
-            format!(
-                "potential log conflict! compare our terms\nour log: {}\nentries to append (attempting to insert at idx={}): {}",
-                debug_log(
-                    &log_ref.entries,
-                    vec![(AnnotationType::Index(rollback_to), "term of this entry")],
-                    0,
-                ),
-                prefix_idx,
-                debug_log(
-                    &their_entries,
-                    vec![(
-                        AnnotationType::Index(rollback_to - prefix_idx),
-                        "term of this entry leader is trying to add"
-                    )],
-                    prefix_idx
+            || {
+                format!(
+                    "potential log conflict! compare our terms\nour log: {}\nentries to append (attempting to insert at idx={}): {}",
+                    debug_log(
+                        &log_ref.entries,
+                        vec![(AnnotationType::Index(rollback_to), "term of this entry")],
+                        0,
+                    ),
+                    prefix_idx,
+                    debug_log(
+                        their_entries,
+                        vec![(
+                            AnnotationType::Index(rollback_to - prefix_idx),
+                            "term of this entry leader is trying to add"
+                        )],
+                        prefix_idx
+                    )
                 )
-            ),
+            },
             Level::Trace,
         );
     }
@@ -222,10 +261,12 @@ impl Logger {
     pub fn log_term_conflict<T: Debug, S>(log_ref: &Log<T, S>) {
         log(
             &log_ref.parent_id,
-            format!(
-                "term conflict detected! truncating our log to length={} to match leader",
-                log_ref.entries.len(),
-            ),
+            || {
+                format!(
+                    "term conflict detected! truncating our log to length={} to match leader",
+                    log_ref.entries.len(),
+                )
+            },
             Level::Trace,
         );
     }
@@ -234,36 +275,47 @@ impl Logger {
     pub fn log_append<T: Debug, S>(log_ref: &Log<T, S>, start: LogIndex) {
         log(
             &log_ref.parent_id,
-            format!(
-                "appended all request entries starting from idx={}, log now looks like: {}",
-                start,
-                debug_log(&log_ref.entries, Vec::new(), 0)
-            ),
+            || {
+                format!(
+                    "appended all request entries starting from idx={}, log now looks like: {}",
+                    start,
+                    debug_log(&log_ref.entries, Vec::new(), 0)
+                )
+            },
             Level::Trace,
         );
     }
 
     /// details about applying a number of log entries to the state machine
     pub fn log_apply<T: Debug, S>(log_ref: &Log<T, S>, leader_commit_len: LogIndex) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            id = %log_ref.parent_id,
+            from = log_ref.committed_len,
+            to = leader_commit_len,
+            "commit index advanced"
+        );
         log(
             &log_ref.parent_id,
-            format!(
-                "applied more messages to state machine: {}",
-                debug_log(
-                    &log_ref.entries,
-                    vec![
-                        (
-                            AnnotationType::Length(log_ref.committed_len),
-                            "used to be commited up to here"
-                        ),
-                        (
-                            AnnotationType::Length(leader_commit_len),
-                            "now commited up to here"
-                        ),
-                    ],
-                    0
-                ),
-            ),
+            || {
+                format!(
+                    "applied more messages to state machine: {}",
+                    debug_log(
+                        &log_ref.entries,
+                        vec![
+                            (
+                                AnnotationType::Length(log_ref.committed_len),
+                                "used to be commited up to here"
+                            ),
+                            (
+                                AnnotationType::Length(leader_commit_len),
+                                "now commited up to here"
+                            ),
+                        ],
+                        0
+                    ),
+                )
+            },
             Level::Trace,
         )
     }
@@ -272,11 +324,13 @@ impl Logger {
     pub fn log_deliver_recv<T: Debug, S>(log_ref: &Log<T, S>) {
         log(
             &log_ref.parent_id,
-            format!(
-                "[deliver_msg] at applied_idx={} out of entries.len()={}",
-                log_ref.applied_len,
-                log_ref.entries.len()
-            ),
+            || {
+                format!(
+                    "[deliver_msg] at applied_idx={} out of entries.len()={}",
+                    log_ref.applied_len,
+                    log_ref.entries.len()
+                )
+            },
             Level::Requests,
         );
     }
@@ -285,99 +339,202 @@ impl Logger {
     pub fn log_deliver_apply<T: Debug, S>(log_ref: &Log<T, S>) {
         log(
             &log_ref.parent_id,
-            debug_log(
-                &log_ref.entries,
-                vec![(
-                    AnnotationType::Length(log_ref.applied_len),
-                    "applied up to here",
-                )],
-                0,
-            ),
+            || {
+                debug_log(
+                    &log_ref.entries,
+                    vec![(
+                        AnnotationType::Length(log_ref.applied_len),
+                        "applied up to here",
+                    )],
+                    0,
+                )
+            },
+            Level::Trace,
+        );
+    }
+
+    /// log compaction replaced a prefix of the log with a snapshot
+    pub fn log_compact<T: Debug, S>(log_ref: &Log<T, S>) {
+        log(
+            &log_ref.parent_id,
+            || {
+                format!(
+                    "compacted log into a snapshot covering {} entries ({}), {} entries left in the log",
+                    log_ref.snapshot.len,
+                    colour_term(log_ref.snapshot.term),
+                    log_ref.entries.len()
+                )
+            },
+            Level::Requests,
+        );
+    }
+
+    /// ignoring a snapshot from the leader that we are already past
+    pub fn log_stale_snapshot<T: Debug, S>(log_ref: &Log<T, S>, snapshot: &Snapshot) {
+        log(
+            &log_ref.parent_id,
+            || {
+                format!(
+                    "ignoring snapshot covering {} entries, we have already committed {}",
+                    snapshot.len, log_ref.committed_len
+                )
+            },
             Level::Trace,
         );
     }
 
+    /// installed a snapshot from the leader
+    pub fn log_install_snapshot<T: Debug, S>(log_ref: &Log<T, S>) {
+        log(
+            &log_ref.parent_id,
+            || {
+                format!(
+                    "installed snapshot covering {} entries ({}), kept {} entries after it: {}",
+                    log_ref.snapshot.len,
+                    colour_term(log_ref.snapshot.term),
+                    log_ref.entries.len(),
+                    debug_log(&log_ref.entries, Vec::new(), 0)
+                )
+            },
+            Level::Requests,
+        );
+    }
+
     /// initializing a server
-    pub fn server_init<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>) {
+    pub fn server_init<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
         log(
             &raft_ref.id,
-            "initializing server".to_owned(),
+            || "initializing server".to_owned(),
             Level::Overview,
         );
         Self::state_update(raft_ref);
     }
 
     /// log a leadership state transition
-    pub fn state_update<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>) {
-        let state_str = if raft_ref.is_leader() {
-            " Leader ".on_blue()
-        } else if raft_ref.is_candidate() {
-            " Candidate ".on_yellow()
-        } else {
-            " Follower ".on_truecolor(140, 140, 140)
-        }
-        .black()
-        .to_string();
+    pub fn state_update<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            role = ?raft_ref.role(),
+            "role changed"
+        );
         log(
             &raft_ref.id,
-            format!("is now {}", state_str),
+            || {
+                let state_str = if raft_ref.is_leader() {
+                    " Leader ".on_blue()
+                } else if raft_ref.is_candidate() {
+                    " Candidate ".on_yellow()
+                } else {
+                    " Follower ".on_truecolor(140, 140, 140)
+                };
+                format!("is now {}", state_str.black())
+            },
             Level::Overview,
         );
     }
 
     /// log election states upon winning
-    pub fn won_election<T: Debug + Clone, S>(
-        raft_ref: &RaftServer<T, S>,
+    pub fn won_election<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
         num_votes: usize,
-        follower_ids: &Vec<ServerId>,
+        follower_ids: &[I],
     ) {
         Self::state_update(raft_ref);
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            votes = num_votes,
+            followers = ?follower_ids,
+            "won election"
+        );
         log(
             &raft_ref.id,
-            format!(
-                "won election with votes={} out of quorum={}, followers: {}",
-                num_votes,
-                raft_ref.quorum_size(),
-                follower_ids
-                    .iter()
-                    .map(|id| colour_server(id))
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            ),
+            || {
+                format!(
+                    "won election with votes={} out of quorum={}, followers: {}",
+                    num_votes,
+                    raft_ref.quorum_size(),
+                    follower_ids
+                        .iter()
+                        .map(colour_server)
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            },
             Level::Requests,
         );
     }
 
     /// leader sending heartbeat to followers
-    pub fn send_heartbeat<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>) {
+    pub fn send_heartbeat<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
         log(
             &raft_ref.id,
-            "sending heartbeat to all followers".to_owned(),
+            || "sending heartbeat to all followers".to_owned(),
             Level::Trace,
         );
     }
 
     /// candidate/follower election timeout reached
-    pub fn election_timer_expired<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>) {
+    pub fn election_timer_expired<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            "election timer expired, starting election"
+        );
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "election timer expired, bumped to {} and started election",
+                    colour_term(raft_ref.current_term)
+                )
+            },
+            Level::Overview,
+        );
+    }
+
+    /// follower starting an election because the leader is handing over to it
+    pub fn taking_over_leadership<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            "leader handing over, starting election"
+        );
         log(
             &raft_ref.id,
-            format!(
-                "election timer expired, bumped to {} and started election",
-                colour_term(raft_ref.current_term)
-            ),
+            || {
+                format!(
+                    "leader handing over, bumped to {} and started election",
+                    colour_term(raft_ref.current_term)
+                )
+            },
             Level::Overview,
         );
     }
 
     /// log single outgoing rpc request (including type and target)
-    pub fn outgoing_rpcs<T: Debug + Clone, S>(
-        raft_ref: &RaftServer<T, S>,
-        msgs: Vec<SendableMessage<T>>,
-    ) -> Vec<SendableMessage<T>> {
+    pub fn outgoing_rpcs<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        msgs: Vec<SendableMessage<T, I>>,
+    ) -> Vec<SendableMessage<T, I>> {
         msgs.iter().for_each(|msg| {
+            #[cfg(feature = "tracing")]
+            match &msg {
+                (Target::Single(target), rpc) => {
+                    tracing::trace!(id = %raft_ref.id, peer = %target, rpc = %rpc, "sending rpc")
+                }
+                (Target::Broadcast, rpc) => {
+                    tracing::trace!(id = %raft_ref.id, rpc = %rpc, "broadcasting rpc")
+                }
+            }
             log(
                 &raft_ref.id,
-                match &msg {
+                || match &msg {
                     (Target::Single(target), rpc) => format!("{rpc} -> {}", colour_server(target)),
                     (Target::Broadcast, rpc) => {
                         format!("{rpc} -> {}", " All servers ".bold().black().on_white())
@@ -390,268 +547,859 @@ impl Logger {
     }
 
     /// rpc request pre-req: ensure term matches before continuing
-    pub fn check_matching_term<T>(id: &ServerId, req: &AppendRequest<T>, current_term: Term) {
+    pub fn check_matching_term<T, I: NodeId>(
+        id: &I,
+        req: &AppendRequest<T, I>,
+        current_term: Term,
+    ) {
         log(
             id,
-            format!(
-                "checking pre-req, does our term match the leader's term: {}",
-                colour_bool(req.leader_term == current_term),
-            ),
+            || {
+                format!(
+                    "checking pre-req, does our term match the leader's term: {}",
+                    colour_bool(req.leader_term == current_term),
+                )
+            },
             Level::Trace,
         );
     }
 
     /// log when a term change/update has occurred
-    pub fn bumping_term<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>, new_term: Term) {
+    pub fn bumping_term<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        new_term: Term,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            id = %raft_ref.id,
+            from = raft_ref.current_term,
+            to = new_term,
+            "term changed"
+        );
         log(
             &raft_ref.id,
-            format!(
-                "bumping our term {} to match candidate/leader term {}",
-                colour_term(raft_ref.current_term),
-                colour_term(new_term)
-            ),
+            || {
+                format!(
+                    "bumping our term {} to match candidate/leader term {}",
+                    colour_term(raft_ref.current_term),
+                    colour_term(new_term)
+                )
+            },
             Level::Trace,
         );
     }
 
     /// log incoming rpc request (including type and received from)
-    pub fn receive_rpc<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>, rpc: &RPC<T>) {
-        log(&raft_ref.id, format!("<- {rpc}"), Level::Overview);
+    pub fn receive_rpc<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        rpc: &RPC<T, I>,
+    ) {
+        log(&raft_ref.id, || format!("<- {rpc}"), Level::Overview);
     }
 
     /// log client API calls
-    pub fn client_request<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>) {
+    pub fn client_request<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
+        log(
+            &raft_ref.id,
+            || "received client_request to add an entry".to_owned(),
+            Level::Overview,
+        );
+    }
+
+    /// log when a leader rejects a client request because the app is lagging behind
+    pub fn apply_lag_exceeded<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        apply_lag: LogIndex,
+        max_apply_lag: LogIndex,
+    ) {
         log(
             &raft_ref.id,
-            "received client_request to add an entry".to_owned(),
+            || {
+                format!(
+                    "rejecting client_request, app is {} entries behind commit index (max {})",
+                    apply_lag, max_apply_lag
+                )
+            },
             Level::Overview,
         );
     }
 
     /// log when leader prepares to replicate log entries to followers
-    pub fn replicate_entries<T: Debug + Clone, S>(
-        raft_ref: &RaftServer<T, S>,
-        entries: &Vec<LogEntry<T>>,
-        target: &ServerId,
+    pub fn replicate_entries<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        entries: &[LogEntry<T>],
+        target: &I,
         prefix_len: LogIndex,
     ) {
-        if entries.len() == 0 {
+        if entries.is_empty() {
             log(
                 &raft_ref.id,
-                format!("preparing heartbeat signal to {}", colour_server(target)),
+                || format!("preparing heartbeat signal to {}", colour_server(target)),
                 Level::Trace,
             );
         } else {
             log(
                 &raft_ref.id,
-                format!(
-                    "preparing RPC call to {}... replicating a portion of our log: {}",
-                    colour_server(target),
-                    debug_log(
-                        &raft_ref.log.entries,
-                        vec![(
-                            AnnotationType::Span(prefix_len, raft_ref.log.entries.len()),
-                            "these entries"
-                        )],
-                        0
-                    ),
-                ),
+                || {
+                    format!(
+                        "preparing RPC call to {}... replicating a portion of our log: {}",
+                        colour_server(target),
+                        debug_log(
+                            &raft_ref.log.entries,
+                            vec![(
+                                AnnotationType::Span(
+                                    prefix_len - raft_ref.log.snapshot.len,
+                                    raft_ref.log.entries.len()
+                                ),
+                                "these entries"
+                            )],
+                            0
+                        ),
+                    )
+                },
                 Level::Trace,
             );
         }
     }
 
+    /// log when leader falls back to sending its snapshot as the follower is too far behind
+    pub fn replicate_snapshot<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        target: &I,
+    ) {
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "{} needs entries that were compacted away, sending snapshot covering {} entries instead",
+                    colour_server(target),
+                    raft_ref.log.snapshot.len
+                )
+            },
+            Level::Trace,
+        );
+    }
+
+    /// log when leader sends a follower entries that were compacted away, read back from
+    /// where they were spilled to
+    pub fn replicate_spilled<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        target: &I,
+        prefix_len: LogIndex,
+        entries: usize,
+    ) {
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "{} needs entries that were compacted away, sending {} of them from index {} out of spill storage",
+                    colour_server(target),
+                    entries,
+                    prefix_len
+                )
+            },
+            Level::Trace,
+        );
+    }
+
+    /// log when spilled entries couldn't be read back, so followers get the snapshot
+    pub fn fetch_failed<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        err: &std::io::Error,
+    ) {
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "reading spilled entries failed, sending the snapshot: {}",
+                    err
+                )
+            },
+            Level::Overview,
+        );
+    }
+
+    /// log when follower receives a snapshot from the leader
+    pub fn rpc_snapshot_request<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        req: &SnapshotRequest<I>,
+    ) {
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "[rpc_snapshot_request] from {} covering {} entries",
+                    colour_server(&req.leader_id),
+                    req.snapshot.len
+                )
+            },
+            Level::Requests,
+        );
+    }
+
+    /// log leader receiving response from follower re: snapshot
+    pub fn snapshot_response<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        res: &SnapshotResponse<I>,
+    ) {
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "[rpc_snapshot_response] from {}, now has {} entries in common with us",
+                    colour_server(&res.follower_id),
+                    res.ack_idx
+                )
+            },
+            Level::Requests,
+        );
+    }
+
+    /// log when taking or installing a snapshot did not work out
+    pub fn snapshot_failed<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        err: &RaftError<I>,
+    ) {
+        log(
+            &raft_ref.id,
+            || format!("snapshot failed: {}", err),
+            Level::Overview,
+        );
+    }
+
     /// follower receiving a request from a candidate to vote for them
-    pub fn rpc_vote_request<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>, req: &VoteRequest) {
+    pub fn rpc_vote_request<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        req: &VoteRequest<I>,
+    ) {
         log(
             &raft_ref.id,
-            format!(
-                "[rpc_vote_request] from {}",
-                colour_server(&req.candidate_id)
-            ),
+            || {
+                format!(
+                    "[rpc_vote_request] from {}",
+                    colour_server(&req.candidate_id)
+                )
+            },
             Level::Requests,
         );
     }
 
     /// explain follower decision making for whether to vote for candidate
-    pub fn rpc_vote_result<T: Debug + Clone, S>(
-        raft_ref: &RaftServer<T, S>,
+    pub fn rpc_vote_result<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
         log_ok: bool,
         up_to_date: bool,
         havent_voted: bool,
     ) {
         log(
             &raft_ref.id,
-            format!(
-                "vote: {} because\n1) their log has a more recent term or is longer: {}\n2) their term is up to date: {}\n3) we haven't voted this election cycle or we already voted for them: {}",
-                colour_bool(log_ok && up_to_date && havent_voted),
-                colour_bool(log_ok),
-                colour_bool(up_to_date),
-                colour_bool(havent_voted)
-            ),
+            || {
+                format!(
+                    "vote: {} because\n1) their log has a more recent term or is longer: {}\n2) their term is up to date: {}\n3) we haven't voted this election cycle or we already voted for them: {}",
+                    colour_bool(log_ok && up_to_date && havent_voted),
+                    colour_bool(log_ok),
+                    colour_bool(up_to_date),
+                    colour_bool(havent_voted)
+                )
+            },
             Level::Trace,
         );
     }
 
     /// candidate receiving a vote result from a follower
-    pub fn rpc_vote_resp<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>, res: &VoteResponse) {
+    pub fn rpc_vote_resp<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        res: &VoteResponse<I>,
+    ) {
         log(
             &raft_ref.id,
-            format!(
-                "[rpc_vote_response] from {} voting {}",
-                colour_server(&res.votee_id),
-                colour_bool(res.vote_granted)
-            ),
+            || {
+                format!(
+                    "[rpc_vote_response] from {} voting {}",
+                    colour_server(&res.votee_id),
+                    colour_bool(res.vote_granted)
+                )
+            },
             Level::Requests,
         );
     }
 
     /// log decision making process for candidate deciding whether result from follower is valid
-    pub fn vote_count(id: &ServerId, res: &VoteResponse, up_to_date: bool) {
+    pub fn vote_count<I: NodeId>(id: &I, res: &VoteResponse<I>, up_to_date: bool) {
         log(
             id,
-            format!(
-                "counting vote: {} because\n1) follower is up to date: {}\n2) they granted the vote: {}",
-                colour_bool(up_to_date && res.vote_granted),
-                colour_bool(up_to_date),
-                colour_bool(res.vote_granted),
-            ),
+            || {
+                format!(
+                    "counting vote: {} because\n1) follower is up to date: {}\n2) they granted the vote: {}",
+                    colour_bool(up_to_date && res.vote_granted),
+                    colour_bool(up_to_date),
+                    colour_bool(res.vote_granted),
+                )
+            },
             Level::Trace,
         );
     }
 
     /// log total votes for candidate
-    pub fn total_vote_count(id: &ServerId, total: usize, quorum: usize) {
+    pub fn total_vote_count(id: &impl NodeId, total: usize, quorum: usize) {
         log(
             id,
-            format!("total vote count: {} out of quorum of {}", total, quorum,),
+            || format!("total vote count: {} out of quorum of {}", total, quorum,),
             Level::Trace,
         );
     }
 
     /// log adding a follower under a leader
-    pub fn added_follower<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>, votee: &ServerId) {
+    pub fn added_follower<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        votee: &I,
+    ) {
         log(
             &raft_ref.id,
-            format!("added {} to list of followers", colour_server(votee)),
+            || format!("added {} to list of followers", colour_server(votee)),
             Level::Trace,
         )
     }
 
     /// log when follower receives a request to append log entries from leader
-    pub fn rpc_append_request<T: Debug + Clone, S>(
-        raft_ref: &RaftServer<T, S>,
-        req: &AppendRequest<T>,
+    pub fn rpc_append_request<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        req: &AppendRequest<T, I>,
     ) {
         log(
             &raft_ref.id,
-            format!(
-                "[rpc_append_request] from {}",
-                colour_server(&req.leader_id),
-            ),
+            || {
+                format!(
+                    "[rpc_append_request] from {}",
+                    colour_server(&req.leader_id),
+                )
+            },
             Level::Requests,
         );
     }
 
     /// checking for potential log conflict before appending
-    pub fn append_conflict_check<T: Debug + Clone, S>(
-        raft_ref: &RaftServer<T, S>,
-        req: &AppendRequest<T>,
+    pub fn append_conflict_check<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        req: &AppendRequest<T, I>,
     ) {
         log(
             &raft_ref.id,
-            format!(
-                "comparing our term {} to supposed leader {}",
-                colour_term(raft_ref.current_term),
-                colour_term(req.leader_term)
-            ),
+            || {
+                format!(
+                    "comparing our term {} to supposed leader {}",
+                    colour_term(raft_ref.current_term),
+                    colour_term(req.leader_term)
+                )
+            },
             Level::Trace,
         );
 
         if req.leader_term == raft_ref.current_term {
             log(
                 &raft_ref.id,
-                format!("term matches, reset to follower, update term and retry"),
+                || "term matches, reset to follower, update term and retry".to_string(),
                 Level::Trace,
             );
         } else {
-            log(&raft_ref.id, format!("outdated, ignoring"), Level::Trace);
+            log(
+                &raft_ref.id,
+                || "outdated, ignoring".to_string(),
+                Level::Trace,
+            );
         }
     }
 
     /// log follower appending entries from leader
-    pub fn append_entries<T: Debug + Clone, S>(
-        raft_ref: &RaftServer<T, S>,
+    pub fn append_entries<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
         prefix_ok: bool,
         last_log_entry_matches_terms: bool,
         prefix_len: usize,
     ) {
-        log(&raft_ref.id, format!(
+        log(
+            &raft_ref.id,
+            || {
+                format!(
             "append entries: {} because\n1) the index they want to insert entries at ({}) <= our log length ({}): {}\n2) last log entry before new entries matches terms: {}",
             colour_bool(prefix_ok && last_log_entry_matches_terms),
             prefix_len,
             raft_ref.log.entries.len(),
             colour_bool(prefix_ok),
             colour_bool(last_log_entry_matches_terms)
-        ), Level::Trace);
+        )
+            },
+            Level::Trace,
+        );
     }
 
     /// log leader receiving response from follower re: append_entries
-    pub fn append_response<T: Debug + Clone, S>(raft_ref: &RaftServer<T, S>, res: &AppendResponse) {
+    pub fn append_response<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        res: &AppendResponse<I>,
+    ) {
         log(
             &raft_ref.id,
-            format!(
-                "[rpc_append_response] from {}",
-                colour_server(&res.follower_id),
-            ),
+            || {
+                format!(
+                    "[rpc_append_response] from {}",
+                    colour_server(&res.follower_id),
+                )
+            },
             Level::Requests,
         );
     }
 
     /// log decision making process for leader when updating its replication state for a follower
-    pub fn process_append_response(
-        id: &ServerId,
-        res: &AppendResponse,
+    pub fn process_append_response<I: NodeId>(
+        id: &I,
+        res: &AppendResponse<I>,
         follower_state: &NodeReplicationState,
     ) {
         let valid = res.ok && res.ack_idx >= follower_state.acked_up_to;
         log(
             id,
-            format!(
-                "valid response: {} because\n1) response indicated success: {}\n2) the index they acked up to (new={}) actually moved forward (old={}): {}",
-                colour_bool(valid),
-                colour_bool(res.ok),
-                res.ack_idx,
-                follower_state.acked_up_to,
-                res.ack_idx >= follower_state.acked_up_to,
-            ),
+            || {
+                format!(
+                    "valid response: {} because\n1) response indicated success: {}\n2) the index they acked up to (new={}) actually moved forward (old={}): {}",
+                    colour_bool(valid),
+                    colour_bool(res.ok),
+                    res.ack_idx,
+                    follower_state.acked_up_to,
+                    res.ack_idx >= follower_state.acked_up_to,
+                )
+            },
             Level::Trace,
         );
 
-        let msg = if valid {
-            format!(
-                "success! bumping sent_up_to from {} -> {} and acked_up_to from {} -> {}",
-                follower_state.sent_up_to, res.ack_idx, follower_state.acked_up_to, res.ack_idx
-            )
-        } else {
-            format!(
-                "error, decrement sent_up_to from {} -> {} and try again",
-                follower_state.sent_up_to,
-                follower_state.sent_up_to - 1,
-            )
+        let msg = || {
+            if valid {
+                format!(
+                    "success! bumping sent_up_to from {} -> {} and acked_up_to from {} -> {}",
+                    follower_state.sent_up_to, res.ack_idx, follower_state.acked_up_to, res.ack_idx
+                )
+            } else if res.ok {
+                format!(
+                    "stale ack up to {}, already know of {}. ignoring",
+                    res.ack_idx, follower_state.acked_up_to
+                )
+            } else if follower_state.sent_up_to == 0 {
+                "late rejection, nothing left to back off. ignoring".to_string()
+            } else {
+                format!(
+                    "error, decrement sent_up_to from {} -> {} and try again",
+                    follower_state.sent_up_to,
+                    follower_state.sent_up_to - 1,
+                )
+            }
         };
 
         log(id, msg, Level::Trace)
     }
 
-    /// log decision making process on a leader about whether to commit entries
-    pub fn commit_entry(id: &ServerId, commit_len: LogIndex, acks: usize, quorum_size: usize) {
-        log(id, format!(
-            "commit entry at index ({}): {} because\n1) total of {} acks for that index >= quorum size ({})",
-            commit_len,
-            colour_bool(acks >= quorum_size),
-            acks,
+    /// warn about an operation that took longer than its configured threshold
+    pub fn slow_operation<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        operation: SlowOperation,
+        elapsed: Duration,
+        threshold: Duration,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            id = %raft_ref.id,
+            operation = ?operation,
+            elapsed = ?elapsed,
+            threshold = ?threshold,
+            "slow operation"
+        );
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "{} {:?} took {:?} (threshold {:?})",
+                    " SLOW ".black().on_red(),
+                    operation,
+                    elapsed,
+                    threshold
+                )
+            },
+            Level::Warning,
+        );
+    }
+
+    /// warn about a node that has gone too long without a leader
+    pub fn leaderless_alarm<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        ticks: Ticks,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            ticks,
+            "no leader"
+        );
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "{} no leader for {} ticks, still at term {}",
+                    " LEADERLESS ".black().on_red(),
+                    ticks,
+                    colour_term(raft_ref.current_term)
+                )
+            },
+            Level::Warning,
+        );
+    }
+
+    /// warn about a response from a node we aren't replicating to, which gets dropped
+    pub fn unknown_follower<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        follower: &I,
+        rpc: &str,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            id = %raft_ref.id,
+            follower = %follower,
+            rpc,
+            "response from unknown follower"
+        );
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "dropping {} from {}, which is not one of our peers",
+                    rpc,
+                    colour_server(follower)
+                )
+            },
+            Level::Warning,
+        );
+    }
+
+    /// rejecting an append request that no correct leader would send
+    pub fn malformed_append_request<T, I: NodeId>(id: &I, req: &AppendRequest<T, I>) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            id = %id,
+            leader = %req.leader_id,
+            term = req.leader_term,
+            "malformed append request"
+        );
+        log(
+            id,
+            || {
+                format!(
+                    "rejecting malformed append request from {} for term {}, its entries' terms go down or past the leader's",
+                    colour_server(&req.leader_id),
+                    req.leader_term
+                )
+            },
+            Level::Warning,
+        );
+    }
+
+    /// vote request from a candidate outside of our configuration
+    pub fn unknown_candidate<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        req: &VoteRequest<I>,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            id = %raft_ref.id,
+            candidate = %req.candidate_id,
+            term = req.candidate_term,
+            "vote request from unknown candidate"
+        );
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "ignoring vote request for term {} from {}, which is not one of our peers",
+                    req.candidate_term,
+                    colour_server(&req.candidate_id)
+                )
+            },
+            Level::Warning,
+        );
+    }
+
+    /// turning down a vote request while leadership is handed to someone else
+    pub fn vote_withheld<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        req: &VoteRequest<I>,
+        target: &I,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            id = %raft_ref.id,
+            candidate = %req.candidate_id,
+            target = %target,
+            "vote withheld during leadership transfer"
+        );
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "not voting for {} in term {}, leadership is being handed to {}",
+                    colour_server(&req.candidate_id),
+                    req.candidate_term,
+                    colour_server(target)
+                )
+            },
+            Level::Requests,
+        );
+    }
+
+    /// leader letting its followers know about entries it just committed
+    pub fn broadcast_commit<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "broadcasting commit of {} entries",
+                    raft_ref.log.committed_len
+                )
+            },
+            Level::Trace,
+        );
+    }
+
+    /// candidate giving up its candidacy for another one of the same term
+    pub fn stepping_aside<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        candidate: &I,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            candidate = %candidate,
+            "stepping aside for another candidate"
+        );
+        log(
+            &raft_ref.id,
+            || format!("stepping aside for {}", colour_server(candidate)),
+            Level::Overview,
+        );
+    }
+
+    /// node cutting a peer off for a while
+    pub fn quarantine<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        peer: &I,
+        ticks: Ticks,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(id = %raft_ref.id, peer = %peer, ticks, "quarantining peer");
+        log(
+            &raft_ref.id,
+            || format!("quarantining {} for {} ticks", colour_server(peer), ticks),
+            Level::Warning,
+        );
+    }
+
+    /// quarantined peer being let back in
+    pub fn quarantine_over<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        peer: &I,
+    ) {
+        log(
+            &raft_ref.id,
+            || format!("{} is out of quarantine", colour_server(peer)),
+            Level::Overview,
+        );
+    }
+
+    /// RPC from a quarantined peer, which is dropped
+    pub fn quarantined_rpc_dropped<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        rpc: &RPC<T, I>,
+    ) {
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "dropping {} from quarantined {}",
+                    rpc,
+                    colour_server(rpc.sender())
+                )
+            },
+            Level::Trace,
+        );
+    }
+
+    /// leader starting to hand its leadership over to a follower
+    pub fn transfer_leadership<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        target: &I,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            target = %target,
+            "transferring leadership"
+        );
+        log(
+            &raft_ref.id,
+            || format!("transferring leadership to {}", colour_server(target)),
+            Level::Overview,
+        );
+    }
+
+    /// leader giving up on a transfer whose target never took over
+    pub fn transfer_expired<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        target: &I,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            id = %raft_ref.id,
+            term = raft_ref.current_term,
+            target = %target,
+            "leadership transfer timed out"
+        );
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "{} never took over leadership, taking client requests again",
+                    colour_server(target)
+                )
+            },
+            Level::Warning,
+        );
+    }
+
+    /// follower being told by the leader to take over
+    pub fn rpc_timeout_now<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        req: &TimeoutNow<I>,
+    ) {
+        log(
+            &raft_ref.id,
+            || format!("[rpc_timeout_now] from {}", colour_server(&req.leader_id)),
+            Level::Requests,
+        );
+    }
+
+    /// node starting a read barrier, through `leader`
+    pub fn read_barrier<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        read_id: ReadId,
+        leader: &I,
+    ) {
+        log(
+            &raft_ref.id,
+            || format!("read {} waits on {}", read_id, colour_server(leader)),
+            Level::Requests,
+        );
+    }
+
+    /// read barrier confirmed at `read_len`, or refused
+    pub fn read_answered<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        read_id: ReadId,
+        read_len: Option<LogIndex>,
+    ) {
+        log(
+            &raft_ref.id,
+            || match read_len {
+                Some(len) => format!(
+                    "read {} can go ahead once {} entries are applied",
+                    read_id, len
+                ),
+                None => format!("read {} refused", read_id),
+            },
+            Level::Requests,
+        );
+    }
+
+    /// node switching to a new config
+    pub fn reconfigured<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        config: &RaftConfig,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(id = %raft_ref.id, term = raft_ref.current_term, ?config, "reconfigured");
+        log(
+            &raft_ref.id,
+            || {
+                format!(
+                    "reconfigured: election timeout {}±{}, heartbeat interval {}",
+                    config.election_timeout,
+                    config.election_timeout_jitter,
+                    config.heartbeat_interval
+                )
+            },
+            Level::Overview,
+        );
+    }
+
+    /// node being paused
+    pub fn paused<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(id = %raft_ref.id, term = raft_ref.current_term, "paused");
+        log(&raft_ref.id, || "paused".to_owned(), Level::Overview);
+    }
+
+    /// node being resumed, with how many RPCs it held on to
+    pub fn resumed<T: Debug + Clone, S, I: NodeId>(raft_ref: &RaftServer<T, S, I>, rpcs: usize) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(id = %raft_ref.id, term = raft_ref.current_term, rpcs, "resumed");
+        log(
+            &raft_ref.id,
+            || format!("resumed, catching up on {rpcs} RPCs"),
+            Level::Overview,
+        );
+    }
+
+    /// warn about an RPC dropped because a paused node has no room left for it
+    pub fn paused_rpc_dropped<T: Debug + Clone, S, I: NodeId>(
+        raft_ref: &RaftServer<T, S, I>,
+        rpc: &RPC<T, I>,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(id = %raft_ref.id, rpc = %rpc, "paused with no room left, dropping RPC");
+        log(
+            &raft_ref.id,
+            || format!("paused with no room left, dropping {rpc}"),
+            Level::Warning,
+        );
+    }
+
+    /// log decision making process on a leader about how far to commit its log
+    pub fn commit_entries(
+        id: &impl NodeId,
+        committed_len: LogIndex,
+        quorum_len: LogIndex,
+        quorum_size: usize,
+    ) {
+        #[cfg(feature = "tracing")]
+        if quorum_len > committed_len {
+            tracing::debug!(
+                id = %id,
+                from = committed_len,
+                to = quorum_len,
+                quorum_size,
+                "commit index advanced"
+            );
+        }
+        log(
+            id,
+            || {
+                format!(
+            "commit entries up to length ({}): {} because\n1) a quorum ({}) of nodes has acked up to there, we have committed up to ({})",
+            quorum_len,
+            colour_bool(quorum_len > committed_len),
             quorum_size,
-        ), Level::Trace);
+            committed_len,
+        )
+            },
+            Level::Trace,
+        );
     }
 }
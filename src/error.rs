@@ -0,0 +1,67 @@
+use crate::{
+    log::LogIndex,
+    server::{ServerId, Term},
+};
+use std::io;
+use thiserror::Error;
+
+/// Everything that can go wrong when calling into a [`RaftServer`](crate::server::RaftServer).
+/// Odd but possible messages from the network are never an error, the node logs and drops
+/// them instead
+#[derive(Debug, Error)]
+pub enum RaftError<I = ServerId> {
+    /// Only the leader can take client requests. `leader` is who this node currently
+    /// believes is the leader, if anyone, so the client can retry there
+    #[error("not the leader")]
+    NotLeader {
+        /// Leader as far as this node knows
+        leader: Option<I>,
+    },
+    /// The app is too far behind the commit index to take more requests right now, see
+    /// [`RaftConfig::max_apply_lag`](crate::server::RaftConfig::max_apply_lag).
+    /// Clients should back off and retry
+    #[error("busy: app is {lag} entries behind the commit index (max {max})")]
+    Busy {
+        /// Committed entries the app has not applied yet
+        lag: LogIndex,
+        /// Most unapplied entries the node accepts
+        max: LogIndex,
+    },
+    /// State refers to a term that is older than one it has already seen
+    #[error("term {term} is older than term {seen}")]
+    StaleTerm {
+        /// The stale term
+        term: Term,
+        /// Newer term that was already seen
+        seen: Term,
+    },
+    /// A server id that is neither this node nor one of its peers
+    #[error("unknown peer {0}")]
+    UnknownPeer(I),
+    /// Reading or writing a snapshot or the app's state failed
+    #[error("storage: {0}")]
+    Storage(#[from] io::Error),
+    /// A [`RaftConfig`](crate::server::RaftConfig) that can't work, see
+    /// [`RaftConfig::validate`](crate::server::RaftConfig::validate)
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+    /// Arguments that can never be valid, e.g. persisted state that is inconsistent
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    /// The node is [paused](crate::server::RaftServer::pause) and takes no requests until
+    /// it is resumed
+    #[error("node paused")]
+    Paused,
+    /// A [read barrier](crate::server::RaftServer::read_barrier) the leader couldn't
+    /// confirm, because leadership changed or the leader hasn't committed an entry of its
+    /// own term yet. The read can be retried right away
+    #[error("read not confirmed by the leader")]
+    ReadUnconfirmed,
+    /// The driver running the node has stopped or is shutting down, so nothing sent to it
+    /// will be answered
+    #[error("node stopped")]
+    Stopped,
+}
+
+/// Result of calling into a [`RaftServer`](crate::server::RaftServer)
+pub type Result<T, I = ServerId> = std::result::Result<T, RaftError<I>>;
@@ -0,0 +1,110 @@
+use crate::{
+    log::LogIndex,
+    server::{ServerId, Term, Ticks},
+};
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Notable things that happen to a Raft node, published through
+/// [`Observers::subscribe`](crate::observer::Observers::subscribe) and
+/// [`Observers::on_event`](crate::observer::Observers::on_event).
+/// Meant as a single place to hook up logging, metrics, and test assertions
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum RaftEvent<I = ServerId> {
+    /// Moved to a new term
+    TermChanged {
+        /// Term before the change
+        from: Term,
+        /// Term after the change
+        to: Term,
+    },
+    /// Election timer ran out and the node started an election
+    BecameCandidate {
+        /// Term of the election
+        term: Term,
+    },
+    /// Won an election
+    BecameLeader {
+        /// Term the node is leader for
+        term: Term,
+    },
+    /// Was leader and went back to being a follower
+    SteppedDown {
+        /// Term the node is in after stepping down
+        term: Term,
+    },
+    /// Was candidate and went back to being a follower
+    BecameFollower {
+        /// Term the node is in after giving up on its election
+        term: Term,
+    },
+    /// Voted for a candidate
+    VoteGranted {
+        /// Candidate that got the vote
+        candidate: I,
+        /// Term of the election
+        term: Term,
+    },
+    /// Commit index moved forward
+    EntriesCommitted {
+        /// New length of the committed log
+        committed_len: LogIndex,
+        /// How many entries just got committed
+        count: LogIndex,
+    },
+    /// Compacted the log into a snapshot of our own app
+    SnapshotTaken {
+        /// Number of entries the snapshot covers
+        len: LogIndex,
+        /// Term of the last entry the snapshot covers
+        term: Term,
+    },
+    /// Replaced our log with a snapshot sent by the leader
+    SnapshotInstalled {
+        /// Number of entries the snapshot covers
+        len: LogIndex,
+        /// Term of the last entry the snapshot covers
+        term: Term,
+    },
+    /// The node has gone longer than
+    /// [`leaderless_alarm`](crate::server::RaftConfig::leaderless_alarm) without hearing
+    /// from a leader. Raised once, until a [`LeaderFound`](Self::LeaderFound)
+    LeaderlessAlarm {
+        /// Term the node is in now
+        term: Term,
+        /// Ticks since the node last heard from a leader
+        ticks: Ticks,
+    },
+    /// A node that raised a [`LeaderlessAlarm`](Self::LeaderlessAlarm) has a leader again
+    LeaderFound {
+        /// Term of the new leader
+        term: Term,
+    },
+    /// Some work took longer than its [`SlowPathConfig`](crate::server::SlowPathConfig)
+    /// threshold
+    SlowPath {
+        /// What was slow
+        operation: SlowOperation,
+        /// How long it took
+        elapsed: Duration,
+    },
+}
+
+/// Kinds of work that are checked against [`SlowPathConfig`](crate::server::SlowPathConfig)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SlowOperation {
+    /// Handing a batch of committed entries to the app
+    Apply {
+        /// Number of entries in the batch
+        entries: LogIndex,
+    },
+    /// Snapshotting the app or restoring it from a snapshot
+    Persist,
+    /// A single call to `tick()`
+    Tick,
+}
@@ -0,0 +1,373 @@
+use crate::{
+    log::App,
+    rpc::{Target, RPC},
+    server::{RaftConfig, RaftServer, ServerId, Term},
+    sim::{node_seed, Envelope},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::{self, Debug},
+    panic::{self, AssertUnwindSafe},
+};
+
+/// Check an [`Explorer`] runs on every state on top of its built in ones
+type Invariant<T, S> = Box<dyn Fn(&[RaftServer<T, S>]) -> bool>;
+
+/// One step of a schedule the [`Explorer`] walks through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choice {
+    /// Deliver the message at this position among the ones in flight, oldest first
+    Deliver(usize),
+    /// Tick a node until it sends something (its election or heartbeat timer runs out)
+    Timeout(ServerId),
+    /// Hand the next proposal to a node that believes it is leader
+    Propose(ServerId),
+}
+
+/// A schedule that ends in a state breaking one of Raft's safety properties (or one of
+/// the explorer's extra invariants)
+#[derive(Clone, Debug)]
+pub struct Counterexample {
+    /// Steps from the initial state to the bad one
+    pub schedule: Vec<Choice>,
+    /// What went wrong
+    pub violation: String,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}, after:", self.violation)?;
+        for (i, choice) in self.schedule.iter().enumerate() {
+            writeln!(f, "  {}. {:?}", i + 1, choice)?;
+        }
+        Ok(())
+    }
+}
+
+/// Model checker for tiny clusters: walks through every interleaving of timeouts, message
+/// deliveries and proposals up to a fixed number of steps, and checks safety at every
+/// state along the way.
+///
+/// Random testing only ever sees the schedules its seeds happen to produce, this sees all of
+/// them (up to the bound), including the unlikely ones like a vote response overtaking an
+/// append from a newer leader. Messages can be delivered in any order or never, nodes time
+/// out whenever, but nothing is duplicated. States are deduplicated, but the number of
+/// them still grows exponentially with the depth, so keep clusters at 3 nodes and depths
+/// below 10. To look deeper into one part of the protocol, start from a state further
+/// along with [`after`](Self::after), e.g. one where a leader is already elected.
+///
+/// Checked at every state:
+/// - Election Safety: at most one leader per term along a schedule
+/// - Log Matching: logs that agree on an entry's term agree on everything before it
+/// - State Machine Safety: nodes never commit different entries at the same index
+/// - in debug builds, each node's own invariants (a panic becomes a counterexample)
+/// - anything added with [`invariant`](Self::invariant)
+pub struct Explorer<T, S> {
+    /// Number of nodes, ids `0..nodes`
+    nodes: usize,
+    /// Config every node runs with
+    config: RaftConfig,
+    /// Seed the nodes' seeds are derived from, as in a [`Cluster`](crate::sim::Cluster)
+    seed: u64,
+    /// Creates the app of every node
+    new_app: Box<dyn FnMut(ServerId) -> Box<dyn App<T, S>>>,
+    /// Values clients propose, in order
+    proposals: Vec<T>,
+    /// Schedule leading to the state exploration starts from
+    prefix: Vec<Choice>,
+    /// Number of steps to explore past the prefix
+    depth: usize,
+    /// Extra checks along with a name for error messages
+    invariants: Vec<(&'static str, Invariant<T, S>)>,
+}
+
+/// A state of the cluster being explored
+struct World<T, S> {
+    nodes: Vec<RaftServer<T, S>>,
+    /// Messages sent but not delivered yet, oldest first
+    in_flight: Vec<Envelope<T>>,
+    /// Number of proposals handed out so far
+    proposed: usize,
+    /// Who led each term so far along the schedule
+    leaders: BTreeMap<Term, ServerId>,
+}
+
+impl<T, S> Explorer<T, S>
+where
+    T: Clone + Debug + PartialEq,
+{
+    /// Explore a cluster of `nodes` nodes, each with its own app from `new_app`, up to
+    /// `depth` steps deep
+    pub fn new(
+        nodes: usize,
+        config: RaftConfig,
+        depth: usize,
+        new_app: impl FnMut(ServerId) -> Box<dyn App<T, S>> + 'static,
+    ) -> Self {
+        Explorer {
+            nodes,
+            config,
+            seed: 0,
+            new_app: Box::new(new_app),
+            proposals: Vec::new(),
+            prefix: Vec::new(),
+            depth,
+            invariants: Vec::new(),
+        }
+    }
+
+    /// Seed the nodes' seeds (and so their election timeouts) are derived from, 0 unless set
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Let clients propose `values`, one at a time and in order, whenever there is a leader
+    pub fn proposals(mut self, values: Vec<T>) -> Self {
+        self.proposals = values;
+        self
+    }
+
+    /// Start exploring from the state `prefix` leads to rather than from scratch, e.g. the
+    /// [`schedule`](Counterexample::schedule) of an earlier run
+    pub fn after(mut self, prefix: Vec<Choice>) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Check `holds` on every state too, `name` shows up in the counterexample if it doesn't
+    pub fn invariant(
+        mut self,
+        name: &'static str,
+        holds: impl Fn(&[RaftServer<T, S>]) -> bool + 'static,
+    ) -> Self {
+        self.invariants.push((name, Box::new(holds)));
+        self
+    }
+
+    /// Walk through every schedule up to the depth bound, returning the number of distinct
+    /// states seen, or the first schedule that breaks safety
+    pub fn run(&mut self) -> Result<usize, Counterexample> {
+        let mut seen = HashSet::new();
+        let mut schedule = self.prefix.clone();
+        self.visit(&mut schedule, &mut seen)?;
+        Ok(seen.len())
+    }
+
+    /// Check the state `schedule` leads to and everything reachable from it
+    fn visit(
+        &mut self,
+        schedule: &mut Vec<Choice>,
+        seen: &mut HashSet<String>,
+    ) -> Result<(), Counterexample> {
+        let world = match self.replay(schedule) {
+            Ok(world) => world,
+            Err(violation) => {
+                return Err(Counterexample {
+                    schedule: schedule.clone(),
+                    violation,
+                })
+            }
+        };
+        if !seen.insert(world.fingerprint()) || schedule.len() == self.prefix.len() + self.depth {
+            return Ok(());
+        }
+        for choice in world.choices(self.proposals.len()) {
+            schedule.push(choice);
+            self.visit(schedule, seen)?;
+            schedule.pop();
+        }
+        Ok(())
+    }
+
+    /// Build the state `schedule` leads to from scratch, checking every state on the way.
+    /// Nodes can't be cloned, so this is how the search backtracks
+    fn replay(&mut self, schedule: &[Choice]) -> Result<World<T, S>, String> {
+        let ids: BTreeSet<ServerId> = (0..self.nodes).collect();
+        let nodes = ids
+            .iter()
+            .map(|&id| {
+                let mut peers = ids.clone();
+                peers.remove(&id);
+                let seed = Some(node_seed(self.seed, id, 0));
+                RaftServer::new(id, peers, self.config.clone(), seed, (self.new_app)(id))
+            })
+            .collect();
+        let mut world = World {
+            nodes,
+            in_flight: Vec::new(),
+            proposed: 0,
+            leaders: BTreeMap::new(),
+        };
+        for &choice in schedule {
+            let limit = self.config.election_timeout + self.config.election_timeout_jitter + 1;
+            let proposals = &self.proposals;
+            panic::catch_unwind(AssertUnwindSafe(|| world.step(choice, limit, proposals)))
+                .map_err(|err| {
+                    let msg = err
+                        .downcast_ref::<String>()
+                        .cloned()
+                        .or_else(|| err.downcast_ref::<&str>().map(|msg| msg.to_string()))
+                        .unwrap_or_default();
+                    format!("node panicked: {}", msg)
+                })?;
+            world.check()?;
+            for (name, holds) in &self.invariants {
+                if !holds(&world.nodes) {
+                    return Err(format!("invariant '{}' violated", name));
+                }
+            }
+        }
+        Ok(world)
+    }
+}
+
+impl<T, S> World<T, S>
+where
+    T: Clone + Debug + PartialEq,
+{
+    /// Everything that can happen next
+    fn choices(&self, proposals: usize) -> Vec<Choice> {
+        let mut choices: Vec<Choice> = (0..self.in_flight.len()).map(Choice::Deliver).collect();
+        choices.extend((0..self.nodes.len()).map(Choice::Timeout));
+        if self.proposed < proposals {
+            choices.extend(
+                self.nodes
+                    .iter()
+                    .filter(|node| node.is_leader())
+                    .map(|node| Choice::Propose(node.id)),
+            );
+        }
+        choices
+    }
+
+    fn step(&mut self, choice: Choice, limit: u32, proposals: &[T]) {
+        match choice {
+            Choice::Deliver(i) => {
+                let Envelope { to, rpc, .. } = self.in_flight.remove(i);
+                let replies = self.nodes[to].receive_rpc(&rpc);
+                self.send(to, replies);
+            }
+            Choice::Timeout(id) => {
+                for _ in 0..limit {
+                    let msgs = self.nodes[id].tick();
+                    if !msgs.is_empty() {
+                        self.send(id, msgs);
+                        break;
+                    }
+                }
+            }
+            Choice::Propose(id) => {
+                if self.nodes[id]
+                    .client_request(proposals[self.proposed].clone())
+                    .is_ok()
+                {
+                    self.proposed += 1;
+                }
+            }
+        }
+    }
+
+    /// Put messages a node sent in flight
+    fn send(&mut self, from: ServerId, msgs: Vec<(Target, RPC<T>)>) {
+        for (target, rpc) in msgs {
+            match target {
+                Target::Single(to) => self.in_flight.push(Envelope { from, to, rpc }),
+                Target::Broadcast => {
+                    for to in (0..self.nodes.len()).filter(|&to| to != from) {
+                        self.in_flight.push(Envelope {
+                            from,
+                            to,
+                            rpc: rpc.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check Raft's safety properties, see [`Explorer`]
+    fn check(&mut self) -> Result<(), String> {
+        for node in self.nodes.iter().filter(|node| node.is_leader()) {
+            let leader = *self.leaders.entry(node.current_term()).or_insert(node.id);
+            if leader != node.id {
+                return Err(format!(
+                    "{} and {} both led term {}",
+                    leader,
+                    node.id,
+                    node.current_term()
+                ));
+            }
+        }
+        for a in &self.nodes {
+            for b in self.nodes.iter().filter(|b| b.id > a.id) {
+                let (log_a, log_b) = (&a.log.entries, &b.log.entries);
+                let last_match = (0..log_a.len().min(log_b.len()))
+                    .rev()
+                    .find(|&i| log_a[i].term == log_b[i].term);
+                if let Some(i) = last_match {
+                    if log_a[..=i] != log_b[..=i] {
+                        return Err(format!("logs of {} and {} diverge", a.id, b.id));
+                    }
+                }
+                let committed = a.log.committed_len.min(b.log.committed_len);
+                if log_a[..committed] != log_b[..committed] {
+                    return Err(format!("{} and {} committed different entries", a.id, b.id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Everything about the state that matters for what can happen next
+    fn fingerprint(&self) -> String {
+        let nodes: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let dump = node.debug_dump(0);
+                let followers: Vec<_> = dump
+                    .status
+                    .followers
+                    .iter()
+                    .flatten()
+                    .map(|(id, state)| (*id, state.sent_up_to, state.acked_up_to))
+                    .collect();
+                format!(
+                    "{:?} {} {:?} {:?} {} {:?} {:?} {}",
+                    dump.status.role,
+                    dump.status.term,
+                    dump.status.voted_for,
+                    dump.status.votes_received,
+                    dump.status.committed_len,
+                    node.log.entries,
+                    followers,
+                    dump.timer,
+                )
+            })
+            .collect();
+        let mut in_flight: Vec<String> = self
+            .in_flight
+            .iter()
+            .map(|envelope| {
+                // request ids only count messages sent, the same message sent at a
+                // different point is the same message
+                let mut rpc = envelope.rpc.clone();
+                match &mut rpc {
+                    RPC::VoteRequest(req) => req.request_id = 0,
+                    RPC::VoteResponse(res) => res.request_id = 0,
+                    RPC::AppendRequest(req) => req.request_id = 0,
+                    RPC::AppendResponse(res) => res.request_id = 0,
+                    RPC::SnapshotRequest(_)
+                    | RPC::SnapshotResponse(_)
+                    | RPC::TimeoutNow(_)
+                    | RPC::ReadIndexRequest(_)
+                    | RPC::ReadIndexResponse(_) => {}
+                }
+                format!("{} {} {:?}", envelope.from, envelope.to, rpc)
+            })
+            .collect();
+        in_flight.sort();
+        format!("{:?} {:?} {}", nodes, in_flight, self.proposed)
+    }
+}
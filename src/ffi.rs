@@ -0,0 +1,276 @@
+use crate::{
+    error::RaftError,
+    log::{App, ApplyContext, LogEntry},
+    rpc::{Target, RPC},
+    server::{RaftConfig, RaftServer, ServerId},
+};
+use std::{ffi::c_void, ptr, slice};
+
+/// `to` passed to [`MiniraftCallbacks::send`] for messages meant for every peer
+pub const MINIRAFT_BROADCAST: u64 = u64::MAX;
+
+/// Outcome of a call into the library
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MiniraftResult {
+    /// The call succeeded
+    Ok = 0,
+    /// Proposals can only go to the leader, see [`miniraft_leader`]
+    NotLeader = 1,
+    /// The node is too far behind on applying entries to take more, retry later
+    Busy = 2,
+    /// A null pointer, or bytes that aren't a message from another node
+    InvalidArgument = 3,
+    /// Anything else went wrong
+    Failed = 4,
+}
+
+/// Timing of a node, in ticks, see [`RaftConfig`]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MiniraftConfig {
+    /// See [`RaftConfig::election_timeout`]
+    pub election_timeout: u32,
+    /// See [`RaftConfig::election_timeout_jitter`]
+    pub election_timeout_jitter: u32,
+    /// See [`RaftConfig::heartbeat_interval`]
+    pub heartbeat_interval: u32,
+}
+
+/// How the node talks back to the host. Both callbacks are called synchronously from
+/// inside [`miniraft_tick`], [`miniraft_receive`] and [`miniraft_propose`], with `ctx` as
+/// their first argument. The bytes they get are only valid until they return
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MiniraftCallbacks {
+    /// Passed back to every callback untouched
+    pub ctx: *mut c_void,
+    /// Get a message to node `to`, or to every peer if `to` is [`MINIRAFT_BROADCAST`].
+    /// Messages may get lost, but have to arrive unchanged if they do arrive
+    pub send: extern "C" fn(ctx: *mut c_void, to: u64, data: *const u8, len: usize),
+    /// Apply the committed entry at `index` to the host's state machine
+    pub apply: extern "C" fn(ctx: *mut c_void, index: u64, data: *const u8, len: usize),
+}
+
+/// A node driven through the C ABI, see `include/miniraft.h` for the C side.
+///
+/// Entries are opaque bytes, how they are encoded is up to the host. RPCs are handed to the
+/// host as bytes too, which it has to get to the right peer and feed into
+/// [`miniraft_receive`] there without looking inside.
+/// A node is not thread safe, every call for a node has to come from the same thread or be
+/// serialized by the host
+pub struct MiniraftNode {
+    /// The node itself
+    server: RaftServer<Vec<u8>, ()>,
+    /// Where messages go
+    callbacks: MiniraftCallbacks,
+}
+
+/// Hands committed entries to the host
+struct HostApp {
+    /// The host's callbacks
+    callbacks: MiniraftCallbacks,
+}
+
+impl App<Vec<u8>, ()> for HostApp {
+    fn transition_fn(&mut self, _entry: &LogEntry<Vec<u8>>) {
+        unreachable!("entries are always applied with a context")
+    }
+
+    fn apply(&mut self, entry: &LogEntry<Vec<u8>>, ctx: &ApplyContext) {
+        let data = &entry.data;
+        (self.callbacks.apply)(
+            self.callbacks.ctx,
+            ctx.index as u64,
+            data.as_ptr(),
+            data.len(),
+        );
+    }
+
+    fn get_state(&self) {}
+}
+
+impl MiniraftNode {
+    /// Hand messages the node sent to the host
+    fn send(&self, msgs: Vec<(Target, RPC<Vec<u8>>)>) {
+        for (target, rpc) in msgs {
+            let to = match target {
+                Target::Single(id) => id as u64,
+                Target::Broadcast => MINIRAFT_BROADCAST,
+            };
+            let data = serde_json::to_vec(&rpc).expect("RPCs are always serializable");
+            (self.callbacks.send)(self.callbacks.ctx, to, data.as_ptr(), data.len());
+        }
+    }
+}
+
+impl From<RaftError> for MiniraftResult {
+    fn from(err: RaftError) -> Self {
+        match err {
+            RaftError::NotLeader { .. } => MiniraftResult::NotLeader,
+            RaftError::Busy { .. } => MiniraftResult::Busy,
+            RaftError::InvalidConfig(_) | RaftError::InvalidRequest(_) => {
+                MiniraftResult::InvalidArgument
+            }
+            _ => MiniraftResult::Failed,
+        }
+    }
+}
+
+/// Bytes at `data`, treating a null pointer as no bytes
+///
+/// # Safety
+/// `data` must be null or point to `len` readable bytes
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if data.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// Create node `id` of a cluster with `n_peers` other nodes listed in `peers`. `seed` makes
+/// election timeouts reproducible. Returns null if the config can't work, see
+/// [`RaftConfig::validate`], or `peers` lists the node itself. Free the node with
+/// [`miniraft_free`]
+///
+/// # Safety
+/// `peers` must point to `n_peers` ids, or be null if `n_peers` is 0. The callbacks must
+/// stay valid until the node is freed
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_new(
+    id: u64,
+    peers: *const u64,
+    n_peers: usize,
+    config: MiniraftConfig,
+    seed: u64,
+    callbacks: MiniraftCallbacks,
+) -> *mut MiniraftNode {
+    let config = RaftConfig::builder()
+        .election_timeout(config.election_timeout)
+        .election_timeout_jitter(config.election_timeout_jitter)
+        .heartbeat_interval(config.heartbeat_interval)
+        .build();
+    let Ok(config) = config else {
+        return ptr::null_mut();
+    };
+    let peers = if peers.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(peers, n_peers)
+    };
+    let server = RaftServer::builder()
+        .id(id as ServerId)
+        .peers(peers.iter().map(|peer| *peer as ServerId))
+        .config(config)
+        .seed(seed)
+        .app(HostApp { callbacks })
+        .build();
+    let Ok(server) = server else {
+        return ptr::null_mut();
+    };
+    Box::into_raw(Box::new(MiniraftNode { server, callbacks }))
+}
+
+/// Free a node created by [`miniraft_new`]. Does nothing for null
+///
+/// # Safety
+/// `node` must come from [`miniraft_new`] and not be used again afterwards
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_free(node: *mut MiniraftNode) {
+    if !node.is_null() {
+        drop(Box::from_raw(node));
+    }
+}
+
+/// Move the node's logical clock forward by a tick
+///
+/// # Safety
+/// `node` must be a live node from [`miniraft_new`]
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_tick(node: *mut MiniraftNode) -> MiniraftResult {
+    let Some(node) = node.as_mut() else {
+        return MiniraftResult::InvalidArgument;
+    };
+    let msgs = node.server.tick();
+    node.send(msgs);
+    MiniraftResult::Ok
+}
+
+/// Hand the node a message another node [`send`](MiniraftCallbacks::send)s it
+///
+/// # Safety
+/// `node` must be a live node from [`miniraft_new`], `data` must point to `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_receive(
+    node: *mut MiniraftNode,
+    data: *const u8,
+    len: usize,
+) -> MiniraftResult {
+    let Some(node) = node.as_mut() else {
+        return MiniraftResult::InvalidArgument;
+    };
+    let Ok(rpc) = serde_json::from_slice::<RPC<Vec<u8>>>(bytes(data, len)) else {
+        return MiniraftResult::InvalidArgument;
+    };
+    let msgs = node.server.receive_rpc(&rpc);
+    node.send(msgs);
+    MiniraftResult::Ok
+}
+
+/// Propose an entry holding the `len` bytes at `data`. It gets
+/// [`apply`](MiniraftCallbacks::apply)'d on every node once committed
+///
+/// # Safety
+/// `node` must be a live node from [`miniraft_new`], `data` must point to `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_propose(
+    node: *mut MiniraftNode,
+    data: *const u8,
+    len: usize,
+) -> MiniraftResult {
+    let Some(node) = node.as_mut() else {
+        return MiniraftResult::InvalidArgument;
+    };
+    match node.server.client_request(bytes(data, len).to_vec()) {
+        Ok(()) => MiniraftResult::Ok,
+        Err(err) => err.into(),
+    }
+}
+
+/// Write who the node believes is leader to `leader`, returning whether it knows of one
+///
+/// # Safety
+/// `node` must be a live node from [`miniraft_new`], `leader` must be writable
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_leader(node: *const MiniraftNode, leader: *mut u64) -> bool {
+    let (Some(node), Some(leader_out)) = (node.as_ref(), leader.as_mut()) else {
+        return false;
+    };
+    match node.server.leader_id() {
+        Some(id) => {
+            *leader_out = id as u64;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Term the node is in, 0 for null
+///
+/// # Safety
+/// `node` must be null or a live node from [`miniraft_new`]
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_term(node: *const MiniraftNode) -> u64 {
+    node.as_ref().map_or(0, |node| node.server.current_term())
+}
+
+/// How many entries of the node's log are committed, 0 for null
+///
+/// # Safety
+/// `node` must be null or a live node from [`miniraft_new`]
+#[no_mangle]
+pub unsafe extern "C" fn miniraft_committed_len(node: *const MiniraftNode) -> u64 {
+    node.as_ref()
+        .map_or(0, |node| node.server.log.committed_len as u64)
+}
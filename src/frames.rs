@@ -0,0 +1,210 @@
+use std::{collections::BTreeMap, num::NonZeroUsize};
+use thiserror::Error;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Which message a [`Frame`] is part of, counted up by each [`Framer`]
+pub type MessageId = u64;
+
+/// Part of a message that was too big for the transport to send in one go
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Frame {
+    /// Message this is part of
+    pub message: MessageId,
+    /// Where this goes in the message, counting from 0
+    pub index: u32,
+    /// How many frames the message was split into
+    pub count: u32,
+    /// This frame's part of the message
+    pub data: Vec<u8>,
+}
+
+/// Splits encoded messages, e.g. RPCs carrying large entries, into frames a transport with a
+/// maximum message size can send. The other end puts them back together with a
+/// [`Reassembler`]. A sender needs a single framer for all of its messages to a peer, so that
+/// every message gets its own [`MessageId`]
+pub struct Framer {
+    /// Most bytes of a message that go into a frame
+    max_frame_len: NonZeroUsize,
+    /// Id of the next message to split
+    next_message: MessageId,
+}
+
+impl Framer {
+    /// Split messages into frames carrying at most `max_frame_len` bytes of them each. The
+    /// transport has to leave room for the rest of the [`Frame`] on top of that
+    pub fn new(max_frame_len: NonZeroUsize) -> Self {
+        Framer {
+            max_frame_len,
+            next_message: 0,
+        }
+    }
+
+    /// Frames to send `message` in, in order. An empty message still takes a frame
+    pub fn split(&mut self, message: &[u8]) -> Vec<Frame> {
+        let id = self.next_message;
+        self.next_message += 1;
+        let chunks: Vec<&[u8]> = if message.is_empty() {
+            vec![message]
+        } else {
+            message.chunks(self.max_frame_len.get()).collect()
+        };
+        let count = u32::try_from(chunks.len()).expect("message split into over u32::MAX frames");
+        chunks
+            .into_iter()
+            .zip(0..)
+            .map(|(chunk, index)| Frame {
+                message: id,
+                index,
+                count,
+                data: chunk.to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// A frame a [`Reassembler`] turned away. Frames come off the wire unchecked, so these are
+/// what a broken or malicious sender gets
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum FrameError {
+    /// The frame claims a place past the end of its message
+    #[error("frame {index} of a message split into {count}")]
+    IndexOutOfRange {
+        /// Where the frame says it goes
+        index: u32,
+        /// How many frames the frame says there are
+        count: u32,
+    },
+    /// The frame doesn't agree with the message's other frames on how many there are
+    #[error("frame says its message has {count} frames, earlier ones said {expected}")]
+    CountMismatch {
+        /// How many frames the frame says there are
+        count: u32,
+        /// How many the first frame of the message said there are
+        expected: u32,
+    },
+    /// The message would be longer than the reassembler takes, whether going by the bytes
+    /// received for it or by how many frames it claims to have
+    #[error("message of at least {len} bytes, at most {max} are allowed")]
+    TooLong {
+        /// Bytes (or frames, none of which can be empty) the message has at least
+        len: usize,
+        /// Longest message the reassembler takes
+        max: usize,
+    },
+}
+
+/// Message whose frames are still coming in
+struct Partial {
+    /// Frames received so far, by index. Kept sparse so nothing is allocated for frames
+    /// that haven't arrived
+    frames: BTreeMap<u32, Vec<u8>>,
+    /// How many frames the message has
+    count: u32,
+    /// Bytes received so far
+    len: usize,
+}
+
+/// Puts messages split up by a [`Framer`] back together on the receiving end. Frames can
+/// come in any order and more than once. Since the network can lose a frame for good, only
+/// the most recent messages still missing frames are held on to. Raft retries anything that
+/// didn't make it anyway. A receiver needs a reassembler per peer, as message ids from
+/// different senders overlap.
+///
+/// Memory is only ever taken for data that actually arrived, and never for more than
+/// `max_partial` messages of `max_message_len` bytes
+pub struct Reassembler {
+    /// Messages still missing frames, oldest first
+    partial: BTreeMap<MessageId, Partial>,
+    /// Most messages to hold on to at once
+    max_partial: NonZeroUsize,
+    /// Longest message to put back together
+    max_message_len: usize,
+}
+
+impl Reassembler {
+    /// Reassemble messages of up to `max_message_len` bytes, holding on to at most
+    /// `max_partial` incomplete ones at a time
+    pub fn new(max_partial: NonZeroUsize, max_message_len: usize) -> Self {
+        Reassembler {
+            partial: BTreeMap::new(),
+            max_partial,
+            max_message_len,
+        }
+    }
+
+    /// Take in a frame, returning the message it completes if it does. Frames that can't
+    /// belong to a message we'd take are turned away, along with the rest of their message
+    pub fn receive(&mut self, frame: Frame) -> Result<Option<Vec<u8>>, FrameError> {
+        if frame.index >= frame.count {
+            return Err(FrameError::IndexOutOfRange {
+                index: frame.index,
+                count: frame.count,
+            });
+        }
+        // every frame but that of an empty message carries at least a byte
+        if frame.count as usize > self.max_message_len.max(1) {
+            return Err(FrameError::TooLong {
+                len: frame.count as usize,
+                max: self.max_message_len,
+            });
+        }
+        if frame.count == 1 {
+            if frame.data.len() > self.max_message_len {
+                return Err(self.too_long(frame.message, frame.data.len()));
+            }
+            return Ok(Some(frame.data));
+        }
+
+        let partial = self
+            .partial
+            .entry(frame.message)
+            .or_insert_with(|| Partial {
+                frames: BTreeMap::new(),
+                count: frame.count,
+                len: 0,
+            });
+        if partial.count != frame.count {
+            let expected = partial.count;
+            self.partial.remove(&frame.message);
+            return Err(FrameError::CountMismatch {
+                count: frame.count,
+                expected,
+            });
+        }
+        if !partial.frames.contains_key(&frame.index) {
+            let len = partial.len + frame.data.len();
+            if len > self.max_message_len {
+                return Err(self.too_long(frame.message, len));
+            }
+            partial.len = len;
+            partial.frames.insert(frame.index, frame.data);
+        }
+
+        if partial.frames.len() == partial.count as usize {
+            let partial = self.partial.remove(&frame.message);
+            return Ok(partial.map(|partial| partial.frames.into_values().flatten().collect()));
+        }
+        // give up on the oldest messages, their missing frames are likely lost
+        while self.partial.len() > self.max_partial.get() {
+            self.partial.pop_first();
+        }
+        Ok(None)
+    }
+
+    /// Give up on `message`, which would be at least `len` bytes long
+    fn too_long(&mut self, message: MessageId, len: usize) -> FrameError {
+        self.partial.remove(&message);
+        FrameError::TooLong {
+            len,
+            max: self.max_message_len,
+        }
+    }
+
+    /// Number of messages still missing frames
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    error::{RaftError, Result},
+    log::LogIndex,
+    rpc::{SendableMessage, RPC},
+    server::{NodeId, RaftServer, ServerId},
+    status::RaftStatus,
+};
+use std::{
+    fmt::Debug,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// Something a [`RaftHandle`] asks the node to do, along with where the answer goes
+enum Request<T, I> {
+    /// [Tick](RaftServer::tick) the node
+    Tick(Sender<Vec<SendableMessage<T, I>>>),
+    /// Hand an RPC from a peer to the node
+    Receive(RPC<T, I>, Sender<Vec<SendableMessage<T, I>>>),
+    /// Append an entry to the leader's log
+    Propose(T, Sender<Result<LogIndex, I>>),
+    /// Report the node's status
+    Status(Sender<RaftStatus<I>>),
+}
+
+/// A single [`RaftServer`] shared between threads. Every call goes through to the node in
+/// turn, so callers never see it halfway through another call and don't need any locking
+/// of their own.
+///
+/// Unlike the drivers in [`threaded`](crate::threaded) and `node`, the handle does
+/// nothing by itself: callers [`tick`](Self::tick) the node on their own clock and deliver
+/// the messages it returns themselves. The node lives on a thread of its own (neither apps
+/// nor observer callbacks have to be `Send`) which stops once every handle is dropped
+pub struct RaftHandle<T, I = ServerId> {
+    /// Requests for the node
+    requests: Sender<Request<T, I>>,
+}
+
+impl<T, I> Clone for RaftHandle<T, I> {
+    fn clone(&self) -> Self {
+        RaftHandle {
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+impl<T, I> RaftHandle<T, I>
+where
+    T: Clone + Debug + Send + Sync + 'static,
+    I: NodeId + Send + 'static,
+{
+    /// Build a node with `build` on a new thread and return the first handle to it
+    pub fn spawn<S: 'static>(
+        build: impl FnOnce() -> RaftServer<T, S, I> + Send + 'static,
+    ) -> RaftHandle<T, I> {
+        let (requests, incoming) = mpsc::channel();
+        thread::spawn(move || serve(build(), incoming));
+        RaftHandle { requests }
+    }
+
+    /// Move the node's clock forward by a tick, returning the messages it sent
+    pub fn tick(&self) -> Result<Vec<SendableMessage<T, I>>, I> {
+        let (reply, msgs) = mpsc::channel();
+        self.request(Request::Tick(reply))?;
+        msgs.recv().map_err(|_| RaftError::Stopped)
+    }
+
+    /// Hand the node an RPC that came in from a peer, returning the messages it sent back
+    pub fn receive(&self, rpc: RPC<T, I>) -> Result<Vec<SendableMessage<T, I>>, I> {
+        let (reply, msgs) = mpsc::channel();
+        self.request(Request::Receive(rpc, reply))?;
+        msgs.recv().map_err(|_| RaftError::Stopped)
+    }
+
+    /// Propose `data` to the cluster, returning the index it was appended at. This only
+    /// gets it into the leader's log: it goes out to followers on the next tick, and it is
+    /// committed once [`status`](Self::status) says so
+    pub fn propose(&self, data: T) -> Result<LogIndex, I> {
+        let (reply, outcome) = mpsc::channel();
+        self.request(Request::Propose(data, reply))?;
+        outcome.recv().map_err(|_| RaftError::Stopped)?
+    }
+
+    /// Current status of the node
+    pub fn status(&self) -> Result<RaftStatus<I>, I> {
+        let (reply, status) = mpsc::channel();
+        self.request(Request::Status(reply))?;
+        status.recv().map_err(|_| RaftError::Stopped)
+    }
+
+    /// Queue a request for the node, failing if its thread is gone
+    fn request(&self, request: Request<T, I>) -> Result<(), I> {
+        self.requests.send(request).map_err(|_| RaftError::Stopped)
+    }
+}
+
+/// Carry out requests on `server` one at a time, until every handle is dropped
+fn serve<T, S, I>(mut server: RaftServer<T, S, I>, requests: Receiver<Request<T, I>>)
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    for request in requests {
+        match request {
+            Request::Tick(reply) => {
+                let _ = reply.send(server.tick());
+            }
+            Request::Receive(rpc, reply) => {
+                let _ = reply.send(server.receive_rpc(&rpc));
+            }
+            Request::Propose(data, reply) => {
+                let outcome = server.client_request(data).map(|()| server.log.last_idx());
+                let _ = reply.send(outcome);
+            }
+            Request::Status(reply) => {
+                let _ = reply.send(server.status());
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::server::{ServerId, Term, Ticks};
+use std::collections::VecDeque;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How many elections [`ElectionHistory`] remembers before it starts forgetting the oldest
+pub const ELECTION_HISTORY_LEN: usize = 16;
+
+/// How an election run by this node ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ElectionOutcome {
+    /// Got votes from a quorum and became leader
+    Won,
+    /// Stepped down to follower, because another node won or showed up with a higher term
+    SteppedDown,
+    /// Didn't get a quorum before the election timer ran out, so started another election
+    TimedOut,
+}
+
+/// A single finished election run by this node
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ElectionRecord<I = ServerId> {
+    /// Term the election was held in
+    pub term: Term,
+    /// Node that stood for election
+    pub candidate: I,
+    /// How the election ended
+    pub outcome: ElectionOutcome,
+    /// Tick (counted from when the node started) the election started at
+    pub started_at: Ticks,
+    /// How many ticks the election took to come to an outcome
+    pub duration: Ticks,
+    /// Votes received (including our own) by the time the election ended
+    pub votes_received: usize,
+}
+
+/// Bounded history of the most recent elections run by a node, oldest first
+#[derive(Clone, Debug)]
+pub struct ElectionHistory<I = ServerId> {
+    /// At most [`ELECTION_HISTORY_LEN`] records
+    records: VecDeque<ElectionRecord<I>>,
+}
+
+impl<I> Default for ElectionHistory<I> {
+    fn default() -> Self {
+        ElectionHistory {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl<I> ElectionHistory<I> {
+    /// Remember an election, forgetting the oldest one if the history is full
+    pub(crate) fn record(&mut self, record: ElectionRecord<I>) {
+        if self.records.len() == ELECTION_HISTORY_LEN {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// Iterate over remembered elections, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &ElectionRecord<I>> {
+        self.records.iter()
+    }
+
+    /// Most recent election, if any
+    pub fn last(&self) -> Option<&ElectionRecord<I>> {
+        self.records.back()
+    }
+
+    /// Number of remembered elections
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no election has been remembered yet
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
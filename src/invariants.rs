@@ -0,0 +1,126 @@
+use crate::{
+    log::{Log, LogIndex},
+    server::{NodeId, ServerId, Term},
+    status::RaftStatus,
+};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug},
+};
+
+/// Safety properties of a single node that must hold after every step, checked in debug
+/// builds after every `tick()`/`receive_rpc()`:
+/// - the current term never decreases
+/// - a node never votes for two different candidates in the same term
+/// - the committed part of the log never shrinks
+/// - committed entries are never replaced
+///
+/// A violation means a bug in the Raft logic (or someone poking at the node's public
+/// fields), so it panics right away instead of letting the cluster carry on with diverged
+/// state
+pub(crate) struct InvariantChecker<I = ServerId> {
+    /// Highest term seen so far
+    term: Term,
+    /// Vote cast in `term`, if any
+    vote: Option<(Term, I)>,
+    /// Index in the full log of the first entry in `committed_terms`
+    first_committed: LogIndex,
+    /// Terms of the committed entries that are still in the log, i.e. not in the snapshot
+    committed_terms: VecDeque<Term>,
+}
+
+impl<I> Default for InvariantChecker<I> {
+    fn default() -> Self {
+        InvariantChecker {
+            term: 0,
+            vote: None,
+            first_committed: 0,
+            committed_terms: VecDeque::new(),
+        }
+    }
+}
+
+impl<I: NodeId> InvariantChecker<I> {
+    /// Check the node's state after `step`, panicking with `status` and the details of what
+    /// went wrong if an invariant no longer holds
+    pub(crate) fn check<T: Clone + Debug, S>(
+        &mut self,
+        step: fmt::Arguments,
+        term: Term,
+        voted_for: Option<I>,
+        log: &Log<T, S>,
+        status: impl Fn() -> RaftStatus<I>,
+    ) {
+        let violated = |what: String| -> ! {
+            panic!(
+                "raft invariant violated after {}: {}\nnode status: {:#?}",
+                step,
+                what,
+                status()
+            )
+        };
+
+        if term < self.term {
+            violated(format!(
+                "term went backwards from {} to {}",
+                self.term, term
+            ));
+        }
+        if term > self.term {
+            self.term = term;
+            self.vote = None;
+        }
+
+        if let Some(candidate) = voted_for {
+            match self.vote {
+                Some((vote_term, ref earlier)) if vote_term == term && *earlier != candidate => {
+                    violated(format!(
+                        "voted for both {} and {} in term {}",
+                        earlier, candidate, term
+                    ))
+                }
+                _ => self.vote = Some((term, candidate)),
+            }
+        }
+
+        let known_committed = self.first_committed + self.committed_terms.len();
+        if log.committed_len < known_committed {
+            violated(format!(
+                "committed length went backwards from {} to {}",
+                known_committed, log.committed_len
+            ));
+        }
+
+        // entries compacted into the snapshot can't change anymore
+        while self.first_committed < log.snapshot.len && !self.committed_terms.is_empty() {
+            self.committed_terms.pop_front();
+            self.first_committed += 1;
+        }
+        if self.committed_terms.is_empty() {
+            self.first_committed = self.first_committed.max(log.snapshot.len);
+        }
+
+        for (offset, expected) in self.committed_terms.iter().enumerate() {
+            let index = self.first_committed + offset;
+            let actual = log.term_at(index + 1);
+            if actual != Some(*expected) {
+                violated(format!(
+                    "committed entry {} changed from term {} to {:?}",
+                    index, expected, actual
+                ));
+            }
+        }
+
+        let known_committed = self.first_committed + self.committed_terms.len();
+        for index in known_committed..log.committed_len {
+            match log.term_at(index + 1) {
+                Some(term) => self.committed_terms.push_back(term),
+                None => violated(format!(
+                    "committed length {} is past the end of the log ({})",
+                    log.committed_len,
+                    log.len()
+                )),
+            }
+        }
+    }
+}
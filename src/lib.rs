@@ -3,18 +3,118 @@
 //! Do NOT use this in production.
 #![warn(missing_docs)]
 
+/// Module serving a node's status, metrics and log over HTTP
+#[cfg(feature = "admin")]
+pub mod admin;
+
+/// Module containing the apply pipeline that runs an [`App`](log::App) on its own thread
+pub mod apply;
+
+/// Module containing the sources of logical time that drive a node
+pub mod clock;
+
 /// Module for pretty printing state transitions, log updates, etc.
 /// No actual Raft-specific logic.
 pub mod debug;
 
+/// Module containing the errors returned by a node's public API
+pub mod error;
+
+/// Module containing the events a node publishes as it changes state
+pub mod event;
+
+/// Module for model checking tiny clusters by walking through every message interleaving
+pub mod explore;
+
+/// Module exposing a node through a C ABI
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+/// Module for sending messages too big for a transport in several frames
+pub mod frames;
+
+/// Module containing a handle for sharing a single node between threads
+pub mod handle;
+
+/// Module containing the history of recent elections a node took part in
+pub mod history;
+
+/// Module checking Raft's safety properties after every step, in debug builds only
+#[cfg(debug_assertions)]
+mod invariants;
+
+/// Module for checking that histories of client operations are linearizable
+pub mod linearizability;
+
 /// Module containing implementation for an event log. This is the basis
 /// for the replicated log at the core of Raft
 pub mod log;
 
+/// Module containing counters that a node keeps about itself for monitoring
+pub mod metrics;
+
+/// Module containing a tokio driver that runs a node off a timer and a transport
+#[cfg(feature = "tokio")]
+pub mod node;
+
+/// Module containing randomized fault injectors for soak testing a simulated cluster
+pub mod nemesis;
+
+/// Module containing callbacks embedders can register to react to state changes
+pub mod observer;
+
+/// Module for keeping a node's persistent state in an openraft storage backend
+#[cfg(feature = "openraft")]
+pub mod openraft;
+
+/// Module for propagating OpenTelemetry trace context across RPCs
+#[cfg(feature = "opentelemetry")]
+mod otel;
+
+/// Module for exporting node metrics to prometheus
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+/// Module exposing a node and the simulated cluster to Python
+#[cfg(feature = "python")]
+pub mod python;
+
+/// Module for recording everything a node sees and sends, so it can be replayed offline
+#[cfg(feature = "serde")]
+pub mod record;
+
 /// Module containing definitions for all of the RPCs that Raft nodes use to
 /// communicate with each other.
 pub mod rpc;
 
+/// Module tracking proposals made through a node driver until they commit
+mod proposals;
+
+/// Module for scripting failure scenarios on a simulated cluster
+pub mod scenario;
+
 /// Module containing majority of the logic for handling RPCs, managing state
 /// transitions, and the API
 pub mod server;
+
+/// Module for routing the keys of a sharded store to the Raft groups that own them
+pub mod shard;
+
+/// Module containing a deterministic in-process cluster for tests and experiments
+pub mod sim;
+
+/// Module containing types for introspecting a running node
+pub mod status;
+
+/// Module for persisting Raft state (snapshots and node state) to disk
+pub mod storage;
+
+/// Module containing a driver that runs a node on a plain thread, without an async runtime
+pub mod threaded;
+
+/// Module for rendering a cluster's topology as a GraphViz graph
+pub mod topology;
+
+/// Module for running nodes over turmoil's simulated network
+#[cfg(feature = "turmoil")]
+pub mod turmoil;
@@ -0,0 +1,89 @@
+use std::{collections::HashSet, hash::Hash};
+
+/// Sequential specification of the object a history is checked against, e.g. a register
+/// or a key-value store
+pub trait Model: Clone + Eq + Hash {
+    /// Operations clients perform on the object
+    type Op;
+    /// What an operation returns
+    type Output: PartialEq;
+
+    /// Perform `op` on the object, returning the object after it along with the result
+    fn step(&self, op: &Self::Op) -> (Self, Self::Output);
+}
+
+/// A single operation in a history, as seen by the client that issued it.
+/// Times only have to be comparable with each other, e.g. ticks of a simulation
+#[derive(Clone, Debug)]
+pub struct Operation<Op, Output> {
+    /// What the client asked for
+    pub op: Op,
+    /// When the client sent the request
+    pub invoked_at: u64,
+    /// When the client got a response and what it was. `None` if it never got one
+    /// (e.g. it timed out), in which case the operation may or may not have taken effect
+    pub returned: Option<(u64, Output)>,
+}
+
+/// Whether `history` is linearizable with respect to `model` starting out as `init`:
+/// whether every operation can be given a single point in time between its invocation and
+/// its response at which it took effect, such that applying the operations in that order to
+/// `model` gives every client the response it got.
+///
+/// This is the Wing & Gong search with memoization of visited states (as in Lowe's
+/// refinement), which is exponential in the worst case. Split the history up per key (or
+/// per independent object) before checking it.
+///
+/// Panics on histories of more than 128 operations
+pub fn is_linearizable<M: Model>(init: M, history: &[Operation<M::Op, M::Output>]) -> bool {
+    assert!(
+        history.len() <= 128,
+        "history too long to check, split it up per key"
+    );
+    let completed: u128 = history
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| op.returned.is_some())
+        .fold(0, |mask, (i, _)| mask | 1 << i);
+    let mut seen = HashSet::new();
+    search(&init, history, 0, completed, &mut seen)
+}
+
+/// Try to linearize everything not in `done` from `state` on
+fn search<M: Model>(
+    state: &M,
+    history: &[Operation<M::Op, M::Output>],
+    done: u128,
+    completed: u128,
+    seen: &mut HashSet<(u128, M)>,
+) -> bool {
+    // pending operations don't have to take effect at all
+    if done & completed == completed {
+        return true;
+    }
+    if !seen.insert((done, state.clone())) {
+        return false;
+    }
+
+    // an operation can only go next if it was invoked before every remaining operation
+    // returned, otherwise one that already finished would have to come after it
+    let first_return = history
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| done & (1 << i) == 0)
+        .filter_map(|(_, op)| op.returned.as_ref().map(|(at, _)| *at))
+        .min()
+        .unwrap_or(u64::MAX);
+
+    history.iter().enumerate().any(|(i, op)| {
+        if done & (1 << i) != 0 || op.invoked_at > first_return {
+            return false;
+        }
+        let (next, output) = state.step(&op.op);
+        let matches = match &op.returned {
+            Some((_, expected)) => *expected == output,
+            None => true,
+        };
+        matches && search(&next, history, done | 1 << i, completed, seen)
+    })
+}
@@ -1,17 +1,23 @@
-use crate::{
-    debug::Logger,
-    server::{ServerId, Term},
-};
+use crate::{debug::Logger, server::Term};
 use std::{
     cmp::min,
     fmt::{self, Debug},
+    io,
+    ops::Deref,
+    sync::Arc,
 };
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// Type alias for indexing into the [`Log`]
 pub type LogIndex = usize;
 
 /// A single log entry
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct LogEntry<T> {
     /// What term it was submitted
     pub term: Term,
@@ -20,11 +26,134 @@ pub struct LogEntry<T> {
     pub data: T,
 }
 
+/// Consecutive log entries that are cheap to clone: clones share the same entries, so a
+/// leader sending overlapping parts of its log to many followers only copies it out once.
+/// Derefs to a slice of the entries
+pub struct SharedEntries<T> {
+    /// Entries, of which this only covers the ones from `start` on
+    entries: Arc<[LogEntry<T>]>,
+    /// Where in `entries` the ones this covers start
+    start: usize,
+}
+
+impl<T> SharedEntries<T> {
+    /// The same entries without the first `n`, sharing them rather than copying
+    pub fn skip(&self, n: usize) -> Self {
+        SharedEntries {
+            entries: self.entries.clone(),
+            start: min(self.start + n, self.entries.len()),
+        }
+    }
+}
+
+impl<T> Deref for SharedEntries<T> {
+    type Target = [LogEntry<T>];
+
+    fn deref(&self) -> &[LogEntry<T>] {
+        &self.entries[self.start..]
+    }
+}
+
+impl<T> Clone for SharedEntries<T> {
+    fn clone(&self) -> Self {
+        self.skip(0)
+    }
+}
+
+impl<T> Default for SharedEntries<T> {
+    fn default() -> Self {
+        Vec::new().into()
+    }
+}
+
+impl<T> From<Vec<LogEntry<T>>> for SharedEntries<T> {
+    fn from(entries: Vec<LogEntry<T>>) -> Self {
+        SharedEntries {
+            entries: entries.into(),
+            start: 0,
+        }
+    }
+}
+
+impl<T: Debug> Debug for SharedEntries<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for SharedEntries<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize> Serialize for SharedEntries<T> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SharedEntries<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Into::into)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl<T: schemars::JsonSchema> schemars::JsonSchema for SharedEntries<T> {
+    fn inline_schema() -> bool {
+        Vec::<LogEntry<T>>::inline_schema()
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        Vec::<LogEntry<T>>::schema_name()
+    }
+
+    fn schema_id() -> std::borrow::Cow<'static, str> {
+        Vec::<LogEntry<T>>::schema_id()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        Vec::<LogEntry<T>>::json_schema(generator)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a, T: arbitrary::Arbitrary<'a>> arbitrary::Arbitrary<'a> for SharedEntries<T> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Vec::arbitrary(u).map(Into::into)
+    }
+}
+
+/// A snapshot of the [`App`] state which replaces a prefix of the log
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Snapshot {
+    /// Number of log entries the snapshot covers
+    pub len: LogIndex,
+    /// Term of the last log entry the snapshot covers
+    pub term: Term,
+    /// App state as written by [`App::snapshot`]. Kept in memory in full, as it is sent to
+    /// followers in a single [`SnapshotRequest`](crate::rpc::SnapshotRequest) and this is
+    /// what the app is restored from, so snapshots are meant for states that fit in memory
+    /// a second time
+    pub data: Vec<u8>,
+}
+
 /// A collection of LogEntries
 pub struct Log<T, S> {
-    /// Log entries
+    /// Log entries that come after the [`snapshot`](Self::snapshot).
+    /// `entries[0]` sits at index `snapshot.len` of the full log
     pub entries: Vec<LogEntry<T>>,
 
+    /// Snapshot of the app that replaced the start of the log.
+    /// Empty (covering 0 entries) until the log is first compacted
+    pub snapshot: Snapshot,
+
     /// How much of the log has been considered committed.
     /// A log entry is considered 'safely replicated' or committed once it is replicated on a majority of servers.
     /// Only meaningful on servers which are leaders.
@@ -32,15 +161,53 @@ pub struct Log<T, S> {
     /// Increases monotonically.
     pub committed_len: LogIndex,
 
-    /// How much of the log has been applied to the state machine.
+    /// How much of the log is known to be on disk, see
+    /// [`RaftConfig::persist_in_background`](crate::server::RaftConfig::persist_in_background).
+    /// Only looked at then, and only on leaders
+    pub persisted_len: LogIndex,
+
+    /// How much of the log has been handed to the state machine.
+    /// For apps that apply asynchronously, see [`last_applied`](Self::last_applied)
+    /// for how much has actually been applied.
     /// Initialized to 0, increases monotonically.
     pub applied_len: LogIndex,
 
     /// State machine
     pub app: Box<dyn App<T, S>>,
 
-    /// [`ServerId`] of our parent for pretty printing documentation
-    pub parent_id: ServerId,
+    /// ID of our parent, rendered through `Display`, for pretty printing documentation
+    pub parent_id: String,
+
+    /// Term our parent is leader for, `None` when it isn't leader.
+    /// Used to tell the app which entries it is applying as the leader that proposed them
+    pub leader_term: Option<Term>,
+}
+
+/// Circumstances under which an entry is being applied, handed to [`App::apply`]
+#[derive(Clone, Copy, Debug)]
+pub struct ApplyContext {
+    /// Index of the entry in the log
+    pub index: LogIndex,
+
+    /// Whether this node is the leader that proposed the entry, and is still leader now.
+    /// At most one node in the cluster applies a given entry with this set, which makes it
+    /// the place to emit external side effects (sending an email, charging a card, ...)
+    /// that should not be repeated by every replica.
+    /// Note the leader may crash before it gets to apply the entry, so effects are emitted
+    /// at most once rather than exactly once; pair this with idempotent effects.
+    pub is_leader: bool,
+}
+
+impl ApplyContext {
+    /// Context for applying `entry` at `index` while leader for `leader_term` (if any).
+    /// A leader only ever proposes entries in its own term, so an entry from our
+    /// leadership term must have been proposed by us
+    pub fn new<T>(index: LogIndex, entry: &LogEntry<T>, leader_term: Option<Term>) -> Self {
+        ApplyContext {
+            index,
+            is_leader: leader_term == Some(entry.term),
+        }
+    }
 }
 
 impl<T, S> Log<T, S>
@@ -48,30 +215,66 @@ where
     T: fmt::Debug,
 {
     /// Instantiate a new empty event log
-    pub fn new(parent_id: ServerId, app: Box<dyn App<T, S>>) -> Self {
+    pub fn new(parent_id: impl fmt::Display, app: Box<dyn App<T, S>>) -> Self {
         Log {
             entries: Vec::new(),
+            snapshot: Snapshot::default(),
             committed_len: 0,
+            persisted_len: 0,
             applied_len: 0,
             app,
-            parent_id,
+            parent_id: parent_id.to_string(),
+            leader_term: None,
         }
     }
 
-    /// Fetch the most recent term we have recorded in the log
+    /// Fetch the most recent term we have recorded in the log, which is the snapshot's
+    /// when every entry has been compacted into it
     pub fn last_term(&self) -> Term {
-        self.entries.last().map(|x| x.term).unwrap_or(0)
+        self.entries
+            .last()
+            .map(|x| x.term)
+            .unwrap_or(self.snapshot.term)
     }
 
-    /// Get index of the last element
+    /// Get index of the last element of the full log, which may be in the snapshot
     pub fn last_idx(&self) -> LogIndex {
-        if self.entries.len() > 0 {
-            self.entries.len() - 1
+        self.len().saturating_sub(1)
+    }
+
+    /// Length of the full log, including entries that have been compacted into the snapshot
+    pub fn len(&self) -> LogIndex {
+        self.snapshot.len + self.entries.len()
+    }
+
+    /// Whether the log has never had anything added to it
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Term of the last entry in the first `len` entries of the log.
+    /// The prefix that ends at the snapshot (`len` 0 for a log that was never compacted)
+    /// has the snapshot's term, i.e. term 0 for a fresh log, so replication can always
+    /// start from the very beginning.
+    /// `None` if that entry has been compacted away or does not exist yet
+    pub fn term_at(&self, len: LogIndex) -> Option<Term> {
+        if len == self.snapshot.len {
+            Some(self.snapshot.term)
+        } else if len > self.snapshot.len {
+            self.entries
+                .get(len - self.snapshot.len - 1)
+                .map(|entry| entry.term)
         } else {
-            0
+            None
         }
     }
 
+    /// All entries from index `start` of the full log onwards.
+    /// `start` must not be inside the snapshot
+    pub fn entries_from(&self, start: LogIndex) -> &[LogEntry<T>] {
+        &self.entries[start - self.snapshot.len..]
+    }
+
     /// Append additional entries to the log.
     /// `prefix_idx` is what index caller expects entries to be inserted at,
     /// `leader_commit_len` is the index of last log that leader has commited.
@@ -81,61 +284,119 @@ where
         leader_commit_len: LogIndex,
         mut entries: Vec<LogEntry<T>>,
     ) {
-        Logger::append_entries_recv(&self, prefix_idx, leader_commit_len, &entries);
-        // check to see if we need to truncate our existing log
-        // this happens when we have conflicts between our log and leader's log
-        if entries.len() > 0 && self.entries.len() > prefix_idx {
-            // we pick the last log index we can compare between leader and follower
-            // either the last entry in the follower's log or last entry in the
-            // new logs, whichever comes first
-            let rollback_to = min(self.entries.len(), prefix_idx + entries.len()) - 1;
-            let our_last_term = self.entries.get(rollback_to).unwrap().term;
-            let leader_last_term = entries.get(rollback_to - prefix_idx).unwrap().term;
-            Logger::log_potential_conflict(&self, &entries, prefix_idx, rollback_to);
-
-            // truncate from start to rollback_to
-            if our_last_term != leader_last_term {
-                self.entries.truncate(prefix_idx);
-                Logger::log_term_conflict(&self);
-            }
+        // entries that are already covered by our snapshot are committed, so they are
+        // guaranteed to match the leader's. skip past them
+        let covered = min(self.snapshot.len.saturating_sub(prefix_idx), entries.len());
+        entries.drain(..covered);
+        // from here on, work relative to the start of `self.entries`. a request that ends
+        // inside our snapshot has nothing left to add
+        let prefix_idx = (prefix_idx + covered).saturating_sub(self.snapshot.len);
+
+        Logger::append_entries_recv(self, prefix_idx, leader_commit_len, &entries);
+        // skip the entries we already have. retransmitted and reordered requests overlap
+        // what we hold, and only a real term conflict may truncate our log
+        let overlap = min(self.entries.len().saturating_sub(prefix_idx), entries.len());
+        let matching = (0..overlap)
+            .find(|&i| self.entries[prefix_idx + i].term != entries[i].term)
+            .unwrap_or(overlap);
+        if matching < overlap {
+            // everything from the first conflicting entry on is from a stale leader
+            Logger::log_potential_conflict(self, &entries, prefix_idx, prefix_idx + matching);
+            self.entries.truncate(prefix_idx + matching);
+            self.persisted_len = min(self.persisted_len, self.len());
+            Logger::log_term_conflict(self);
         }
 
-        // add all entries we don't have
-        if prefix_idx + entries.len() > self.entries.len() {
-            let start = self.entries.len() - prefix_idx;
-            let new_entries_range = start..;
-            self.entries.extend(entries.drain(new_entries_range));
-            Logger::log_append(&self, start);
+        // add all entries we don't have, as long as they follow on from ours
+        if matching < entries.len() && prefix_idx + matching == self.entries.len() {
+            self.entries.extend(entries.drain(matching..));
+            Logger::log_append(self, matching);
         }
 
+        // never commit past what we hold, whatever the leader claims
+        let leader_commit_len = min(leader_commit_len, self.len());
         // leader has commited more messages than us, we can move forward and commit some of our messages
         if leader_commit_len > self.committed_len {
             // apply each element we haven't committed
-            self.entries[self.committed_len..leader_commit_len]
-                .iter()
-                .for_each(|entry| {
-                    self.app.transition_fn(entry);
-                });
+            let offset = self.snapshot.len;
+            for index in self.committed_len..leader_commit_len {
+                let entry = &self.entries[index - offset];
+                let ctx = ApplyContext::new(index, entry, self.leader_term);
+                self.app.apply(entry, &ctx);
+            }
 
-            Logger::log_apply(&self, leader_commit_len);
+            Logger::log_apply(self, leader_commit_len);
             // update commit index to reflect changes
             self.applied_len = leader_commit_len;
             self.committed_len = leader_commit_len;
         }
     }
 
+    /// How much of the log the state machine has actually finished applying.
+    /// Lags behind [`applied_len`](Self::applied_len) while an asynchronous app is still working
+    pub fn last_applied(&self) -> LogIndex {
+        self.applied_len - self.app.pending()
+    }
+
+    /// Snapshot the app into [`snapshot`](Self::snapshot) and drop every entry it has
+    /// applied from the log
+    pub fn compact(&mut self) -> io::Result<()> {
+        if self.applied_len == self.snapshot.len {
+            // nothing new to compact
+            return Ok(());
+        }
+
+        let mut data = Vec::new();
+        self.app.snapshot(&mut data)?;
+        let term = self
+            .term_at(self.applied_len)
+            .expect("applied entries should be in the log");
+        self.entries.drain(..self.applied_len - self.snapshot.len);
+        self.snapshot = Snapshot {
+            len: self.applied_len,
+            term,
+            data,
+        };
+        Logger::log_compact(self);
+        Ok(())
+    }
+
+    /// Replace our state with a snapshot sent by the leader.
+    /// Entries after the snapshot are kept if our log agrees with the snapshot, otherwise the
+    /// whole log is discarded. Snapshots older than what we have already committed are ignored.
+    pub fn install_snapshot(&mut self, snapshot: Snapshot) -> io::Result<()> {
+        if snapshot.len <= self.committed_len {
+            Logger::log_stale_snapshot(self, &snapshot);
+            return Ok(());
+        }
+
+        self.app
+            .restore_at(&mut snapshot.data.as_slice(), snapshot.len)?;
+        if self.term_at(snapshot.len) == Some(snapshot.term) {
+            self.entries.drain(..snapshot.len - self.snapshot.len);
+        } else {
+            self.entries.clear();
+            self.persisted_len = min(self.persisted_len, self.snapshot.len);
+        }
+        self.committed_len = snapshot.len;
+        self.applied_len = snapshot.len;
+        self.snapshot = snapshot;
+        Logger::log_install_snapshot(self);
+        Ok(())
+    }
+
     /// Deliver a single message from the message log to the application
     pub fn deliver_msg(&mut self) {
-        Logger::log_deliver_recv(&self);
+        Logger::log_deliver_recv(self);
 
-        let applied_idx = self.applied_len;
-        self.app.transition_fn(
-            self.entries
-                .get(applied_idx)
-                .expect("msg_idx of msg to be delivered was out of bounds"),
-        );
+        let entry = self
+            .entries
+            .get(self.applied_len - self.snapshot.len)
+            .expect("msg_idx of msg to be delivered was out of bounds");
+        let ctx = ApplyContext::new(self.applied_len, entry, self.leader_term);
+        self.app.apply(entry, &ctx);
         self.applied_len += 1;
-        Logger::log_deliver_apply(&self);
+        Logger::log_deliver_apply(self);
     }
 }
 
@@ -146,6 +407,59 @@ pub trait App<T, S> {
     /// considered applied (meaning it won't be re-run or removed).
     fn transition_fn(&mut self, entry: &LogEntry<T>);
 
+    /// Entry point Raft uses to apply a committed entry. Defaults to
+    /// [`transition_fn`](Self::transition_fn); implement this instead when the app needs to
+    /// know more about the circumstances, e.g. to only emit external side effects on the
+    /// leader (see [`ApplyContext::is_leader`]). State changes must still be identical on
+    /// every node regardless of the context.
+    fn apply(&mut self, entry: &LogEntry<T>, _ctx: &ApplyContext) {
+        self.transition_fn(entry)
+    }
+
     /// Return the current state of the application
     fn get_state(&self) -> S;
+
+    /// How many entries handed to [`transition_fn`](Self::transition_fn) have not finished
+    /// applying yet, for apps that apply entries asynchronously (e.g.
+    /// [`ApplyWorker`](crate::apply::ApplyWorker)). Synchronous apps can keep the default
+    /// of 0, an entry then counts as applied as soon as `transition_fn` returns.
+    fn pending(&self) -> LogIndex {
+        0
+    }
+
+    /// Write a snapshot of the current application state into `writer`.
+    /// The app picks its own encoding and writes it out as it goes, so it doesn't have to
+    /// build an encoded copy of its state first. The node still ends up holding the whole
+    /// snapshot in memory, see [`Snapshot::data`].
+    /// The default implementation reports that snapshots are not supported.
+    fn snapshot(&self, _writer: &mut dyn io::Write) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "app does not support snapshots",
+        ))
+    }
+
+    /// Polled by the server on every tick. Return `true` to have the server snapshot the
+    /// app and compact the log right away, e.g. after a bulk load made the log grow a lot.
+    /// Apps should only return `true` once per request.
+    fn wants_snapshot(&mut self) -> bool {
+        false
+    }
+
+    /// Replace the current application state with one previously written by
+    /// [`snapshot`](Self::snapshot)
+    fn restore(&mut self, _reader: &mut dyn io::Read) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "app does not support snapshots",
+        ))
+    }
+
+    /// Entry point Raft uses to restore a snapshot covering the first `len` entries of the
+    /// log, so the next entry applied is the one at index `len`. Defaults to
+    /// [`restore`](Self::restore); implement this instead when the app keeps track of where
+    /// it is in the log
+    fn restore_at(&mut self, reader: &mut dyn io::Read, _len: LogIndex) -> io::Result<()> {
+        self.restore(reader)
+    }
 }
@@ -0,0 +1,121 @@
+use crate::server::ServerId;
+use std::{collections::BTreeMap, time::Duration};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Counters a node keeps about what it has been doing since it started, as returned by
+/// [`RaftServer::metrics`](crate::server::RaftServer::metrics).
+/// Counters only ever go up, so rates can be derived by scraping them periodically
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RaftMetrics {
+    /// Elections this node started after its election timer ran out
+    pub elections_started: u64,
+    /// Elections this node won
+    pub elections_won: u64,
+    /// Times this node saw leadership move to a different node (including itself).
+    /// A steadily increasing count means leadership is flapping
+    pub leader_changes: u64,
+    /// Vote requests from other candidates that this node granted
+    pub votes_granted: u64,
+    /// Vote requests from other candidates that this node denied
+    pub votes_denied: u64,
+    /// Append requests sent to followers, including heartbeats
+    pub append_requests_sent: u64,
+    /// Append requests received from leaders
+    pub append_requests_received: u64,
+    /// Append requests this node rejected because of a term or log mismatch, or because
+    /// they were malformed
+    pub append_requests_rejected: u64,
+    /// Entries this node saw become committed.
+    /// Entries that arrive already committed inside a snapshot are not counted
+    pub entries_committed: u64,
+    /// Entries this node handed to its [`App`](crate::log::App)
+    pub entries_applied: u64,
+    /// Append requests sent because the heartbeat timer ran out
+    pub heartbeats_sent: u64,
+    /// Operations that took longer than their [`SlowPathConfig`](crate::server::SlowPathConfig)
+    /// threshold
+    pub slow_operations: u64,
+    /// Snapshots of the app, or of the leader's app sent to this node, that could not be
+    /// taken or installed
+    pub snapshots_failed: u64,
+    /// Append, snapshot and vote responses from nodes that aren't among this node's peers,
+    /// e.g. a node that was never part of the cluster. They are dropped
+    pub responses_from_unknown_followers: u64,
+}
+
+/// Upper bounds of the buckets in a [`LatencyHistogram`].
+/// Anything slower than the last one lands in an overflow bucket
+pub const LATENCY_BUCKETS: [Duration; 12] = [
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_micros(2_500),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_secs(1),
+];
+
+/// Distribution of round trip times, bucketed by [`LATENCY_BUCKETS`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LatencyHistogram {
+    /// Number of samples per bucket (not cumulative), with one more bucket at the end for
+    /// samples slower than every bound
+    pub buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    /// Number of samples
+    pub count: u64,
+    /// Sum of all samples
+    pub sum: Duration,
+}
+
+impl LatencyHistogram {
+    /// Add a sample
+    pub fn observe(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum += latency;
+    }
+
+    /// Average of all samples, `None` if there are none
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.sum / self.count as u32)
+    }
+}
+
+/// Round trip latency of requests to every peer, matched up with their responses through
+/// the request's correlation id, as returned by
+/// [`RaftServer::rpc_latencies`](crate::server::RaftServer::rpc_latencies).
+/// Requests that never get a response don't show up
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "I: Deserialize<'de> + Ord"))
+)]
+pub struct RpcLatencies<I = ServerId> {
+    /// [`AppendRequest`](crate::rpc::AppendRequest) round trips per follower
+    pub append: BTreeMap<I, LatencyHistogram>,
+    /// [`VoteRequest`](crate::rpc::VoteRequest) round trips per peer
+    pub vote: BTreeMap<I, LatencyHistogram>,
+}
+
+impl<I> Default for RpcLatencies<I> {
+    fn default() -> Self {
+        RpcLatencies {
+            append: BTreeMap::new(),
+            vote: BTreeMap::new(),
+        }
+    }
+}
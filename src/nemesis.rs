@@ -0,0 +1,389 @@
+use crate::{
+    server::{ServerId, Ticks},
+    sim::{Cluster, Faults},
+};
+use anyhow::{bail, Result};
+use log::info;
+use rand::{seq::SliceRandom, Rng};
+use std::fmt::Debug;
+
+/// Something that makes a simulated [`Cluster`]'s life hard on a randomized schedule, in
+/// the spirit of Jepsen's nemesis. Nemeses are combined in a [`Nemeses`] and take turns
+/// every tick
+pub trait Nemesis<T, S> {
+    /// Short name for logs and the history
+    fn name(&self) -> &'static str;
+
+    /// Called once per tick before the cluster ticks. Draws from
+    /// [`Cluster::rng`] to decide whether to strike, so schedules are reproducible from the
+    /// cluster's seed. Returns what it did, if anything
+    fn step(&mut self, cluster: &mut Cluster<T, S>) -> Option<String>;
+
+    /// Undo everything the nemesis did, so the cluster can recover
+    fn heal(&mut self, cluster: &mut Cluster<T, S>);
+}
+
+/// Splits the cluster in two at random, heals it again after a while
+pub struct Partitioner {
+    /// Chance per tick of a partition, while there is none
+    pub chance: f64,
+    /// Most ticks a partition lasts
+    pub max_duration: Ticks,
+    /// Tick the current partition heals at
+    until: Option<Ticks>,
+}
+
+impl Partitioner {
+    /// Partition the cluster with `chance` per tick, for up to `max_duration` ticks
+    pub fn new(chance: f64, max_duration: Ticks) -> Self {
+        Partitioner {
+            chance,
+            max_duration,
+            until: None,
+        }
+    }
+}
+
+impl<T: Clone + Debug, S> Nemesis<T, S> for Partitioner {
+    fn name(&self) -> &'static str {
+        "partitioner"
+    }
+
+    fn step(&mut self, cluster: &mut Cluster<T, S>) -> Option<String> {
+        let now = cluster.now();
+        match self.until {
+            Some(until) if until <= now => {
+                Nemesis::<T, S>::heal(self, cluster);
+                Some("healed".into())
+            }
+            Some(_) => None,
+            None if cluster.rng().gen_bool(self.chance) => {
+                let mut ids: Vec<ServerId> = cluster.nodes().map(|node| node.id).collect();
+                let rng = cluster.rng();
+                ids.shuffle(rng);
+                let side = rng.gen_range(1..ids.len().max(2));
+                ids.truncate(side);
+                ids.sort_unstable();
+                let duration = rng.gen_range(1..=self.max_duration.max(1));
+                cluster.partition(&ids);
+                self.until = Some(now + duration);
+                Some(format!("cut off {:?} for {} ticks", ids, duration))
+            }
+            None => None,
+        }
+    }
+
+    fn heal(&mut self, cluster: &mut Cluster<T, S>) {
+        cluster.heal();
+        self.until = None;
+    }
+}
+
+/// Makes a random node's clock run fast or slow for a while, so its timers fire early or
+/// late compared to everyone else's
+pub struct ClockSkewer {
+    /// Chance per tick of skewing a clock, while none is skewed
+    pub chance: f64,
+    /// Most a clock is sped up or slowed down by, e.g. 2.0 for anything between half and
+    /// double speed
+    pub max_skew: f64,
+    /// Most ticks a clock stays skewed
+    pub max_duration: Ticks,
+    /// Node whose clock is skewed, with the tick it is put back in sync at
+    skewed: Option<(ServerId, Ticks)>,
+}
+
+impl ClockSkewer {
+    /// Skew a clock with `chance` per tick by up to `max_skew`, for up to `max_duration`
+    /// ticks
+    pub fn new(chance: f64, max_skew: f64, max_duration: Ticks) -> Self {
+        assert!(max_skew >= 1.0, "skew is a factor, at least 1.0");
+        ClockSkewer {
+            chance,
+            max_skew,
+            max_duration,
+            skewed: None,
+        }
+    }
+}
+
+impl<T: Clone + Debug, S> Nemesis<T, S> for ClockSkewer {
+    fn name(&self) -> &'static str {
+        "clock-skewer"
+    }
+
+    fn step(&mut self, cluster: &mut Cluster<T, S>) -> Option<String> {
+        let now = cluster.now();
+        match self.skewed {
+            Some((id, until)) if until <= now => {
+                cluster.set_clock_rate(id, 1.0);
+                self.skewed = None;
+                Some(format!("clock of {} back in sync", id))
+            }
+            Some(_) => None,
+            None if cluster.rng().gen_bool(self.chance) => {
+                let ids: Vec<ServerId> = cluster.nodes().map(|node| node.id).collect();
+                let rng = cluster.rng();
+                let id = *ids.choose(rng)?;
+                let factor = rng.gen_range(1.0..=self.max_skew);
+                let rate = if rng.gen_bool(0.5) {
+                    factor
+                } else {
+                    1.0 / factor
+                };
+                let duration = rng.gen_range(1..=self.max_duration.max(1));
+                cluster.set_clock_rate(id, rate);
+                self.skewed = Some((id, now + duration));
+                Some(format!(
+                    "clock of {} runs at {:.2}x for {} ticks",
+                    id, rate, duration
+                ))
+            }
+            None => None,
+        }
+    }
+
+    fn heal(&mut self, cluster: &mut Cluster<T, S>) {
+        if let Some((id, _)) = self.skewed.take() {
+            cluster.set_clock_rate(id, 1.0);
+        }
+    }
+}
+
+/// Gives a random node a slow disk for a while, delaying everything it sends
+pub struct SlowDisk {
+    /// Chance per tick of slowing a disk down, while none is slow
+    pub chance: f64,
+    /// Most ticks a single write takes on the slow disk
+    pub max_latency: Ticks,
+    /// Most ticks a disk stays slow
+    pub max_duration: Ticks,
+    /// Node whose disk is slow, with the tick it is fast again at
+    slow: Option<(ServerId, Ticks)>,
+}
+
+impl SlowDisk {
+    /// Slow a disk down with `chance` per tick to up to `max_latency` ticks per write, for
+    /// up to `max_duration` ticks
+    pub fn new(chance: f64, max_latency: Ticks, max_duration: Ticks) -> Self {
+        SlowDisk {
+            chance,
+            max_latency,
+            max_duration,
+            slow: None,
+        }
+    }
+}
+
+impl<T: Clone + Debug, S> Nemesis<T, S> for SlowDisk {
+    fn name(&self) -> &'static str {
+        "slow-disk"
+    }
+
+    fn step(&mut self, cluster: &mut Cluster<T, S>) -> Option<String> {
+        let now = cluster.now();
+        match self.slow {
+            Some((id, until)) if until <= now => {
+                cluster.set_slow_disk(id, 0);
+                self.slow = None;
+                Some(format!("disk of {} is fast again", id))
+            }
+            Some(_) => None,
+            None if cluster.rng().gen_bool(self.chance) => {
+                let ids: Vec<ServerId> = cluster.nodes().map(|node| node.id).collect();
+                let rng = cluster.rng();
+                let id = *ids.choose(rng)?;
+                let latency = rng.gen_range(1..=self.max_latency.max(1));
+                let duration = rng.gen_range(1..=self.max_duration.max(1));
+                cluster.set_slow_disk(id, latency);
+                self.slow = Some((id, now + duration));
+                Some(format!(
+                    "disk of {} takes {} ticks per write for {} ticks",
+                    id, latency, duration
+                ))
+            }
+            None => None,
+        }
+    }
+
+    fn heal(&mut self, cluster: &mut Cluster<T, S>) {
+        if let Some((id, _)) = self.slow.take() {
+            cluster.set_slow_disk(id, 0);
+        }
+    }
+}
+
+/// Corrupts a fraction of all messages for a while. Corrupted messages fail their
+/// checksum and are dropped, see [`Faults::corrupt`]
+pub struct MessageCorrupter {
+    /// Chance per tick of a burst of corruption, while there is none
+    pub chance: f64,
+    /// Fraction of messages corrupted during a burst
+    pub rate: f64,
+    /// Most ticks a burst lasts
+    pub max_duration: Ticks,
+    /// Tick the current burst ends at
+    until: Option<Ticks>,
+}
+
+impl MessageCorrupter {
+    /// Corrupt `rate` of all messages with `chance` per tick, for up to `max_duration`
+    /// ticks
+    pub fn new(chance: f64, rate: f64, max_duration: Ticks) -> Self {
+        MessageCorrupter {
+            chance,
+            rate,
+            max_duration,
+            until: None,
+        }
+    }
+
+    /// Turn corruption to `corrupt`, leaving the cluster's other faults alone
+    fn set_rate<T: Clone + Debug, S>(cluster: &mut Cluster<T, S>, corrupt: f64) {
+        let faults = Faults {
+            corrupt,
+            ..cluster.faults().clone()
+        };
+        cluster.set_faults(faults);
+    }
+}
+
+impl<T: Clone + Debug, S> Nemesis<T, S> for MessageCorrupter {
+    fn name(&self) -> &'static str {
+        "message-corrupter"
+    }
+
+    fn step(&mut self, cluster: &mut Cluster<T, S>) -> Option<String> {
+        let now = cluster.now();
+        match self.until {
+            Some(until) if until <= now => {
+                Nemesis::<T, S>::heal(self, cluster);
+                Some("corruption over".into())
+            }
+            Some(_) => None,
+            None if cluster.rng().gen_bool(self.chance) => {
+                let duration = cluster.rng().gen_range(1..=self.max_duration.max(1));
+                Self::set_rate(cluster, self.rate);
+                self.until = Some(now + duration);
+                Some(format!(
+                    "corrupting {:.0}% of messages for {} ticks",
+                    self.rate * 100.0,
+                    duration
+                ))
+            }
+            None => None,
+        }
+    }
+
+    fn heal(&mut self, cluster: &mut Cluster<T, S>) {
+        Self::set_rate(cluster, 0.0);
+        self.until = None;
+    }
+}
+
+/// Several [`Nemesis`]es working on the same cluster, e.g. for a soak test:
+///
+/// ```
+/// # use miniraft::{log::{App, LogEntry}, nemesis::*, server::{RaftConfig, SlowPathConfig, TieBreak}, sim::Cluster};
+/// # struct Counter(u32);
+/// # impl App<u32, u32> for Counter {
+/// #     fn transition_fn(&mut self, entry: &LogEntry<u32>) { self.0 += entry.data }
+/// #     fn get_state(&self) -> u32 { self.0 }
+/// # }
+/// # let config = RaftConfig {
+/// #     election_timeout: 10, election_timeout_jitter: 3, heartbeat_interval: 5,
+/// #     max_apply_lag: None, slow_path: SlowPathConfig::default(), leaderless_alarm: None,
+/// #     max_log_entries: None, persist_in_background: false, replication_batching: None,
+/// #     broadcast_commits: false, tie_break: TieBreak::Never,
+/// # };
+/// let mut cluster = Cluster::new(5, 7, config, |_| Box::new(Counter(0)));
+/// let mut nemeses = Nemeses::new()
+///     .with(Partitioner::new(0.01, 50))
+///     .with(ClockSkewer::new(0.01, 2.0, 50))
+///     .with(SlowDisk::new(0.01, 5, 50))
+///     .with(MessageCorrupter::new(0.01, 0.2, 50));
+/// nemeses
+///     .run(&mut cluster, 1_000, |cluster| {
+///         let _ = cluster.client_request(1);
+///         Ok(())
+///     })
+///     .unwrap();
+/// nemeses.heal(&mut cluster);
+/// ```
+///
+/// Everything the nemeses did is kept in the [`history`](Self::history), which together
+/// with the cluster's seed is all it takes to replay a failing run
+pub struct Nemeses<T, S> {
+    /// Nemeses taking turns, in order
+    nemeses: Vec<Box<dyn Nemesis<T, S>>>,
+    /// Tick, nemesis and what it did
+    history: Vec<(Ticks, &'static str, String)>,
+}
+
+impl<T, S> Default for Nemeses<T, S> {
+    fn default() -> Self {
+        Nemeses {
+            nemeses: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone + Debug, S> Nemeses<T, S> {
+    /// No nemeses yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a nemesis, it takes its turn after the ones added before it
+    pub fn with(mut self, nemesis: impl Nemesis<T, S> + 'static) -> Self {
+        self.nemeses.push(Box::new(nemesis));
+        self
+    }
+
+    /// Let every nemesis take its turn, then tick the cluster
+    pub fn tick(&mut self, cluster: &mut Cluster<T, S>) {
+        for nemesis in &mut self.nemeses {
+            if let Some(event) = nemesis.step(cluster) {
+                info!("nemesis {}: {}", nemesis.name(), event);
+                self.history.push((cluster.now(), nemesis.name(), event));
+            }
+        }
+        cluster.tick();
+    }
+
+    /// Tick for `ticks` ticks under attack, calling `check` after every tick. Stops at the
+    /// first error from `check`, adding the tick and seed to reproduce it with
+    pub fn run(
+        &mut self,
+        cluster: &mut Cluster<T, S>,
+        ticks: Ticks,
+        mut check: impl FnMut(&mut Cluster<T, S>) -> Result<()>,
+    ) -> Result<()> {
+        for _ in 0..ticks {
+            self.tick(cluster);
+            if let Err(err) = check(cluster) {
+                bail!(
+                    "check failed at tick {} with seed {}: {}",
+                    cluster.now(),
+                    cluster.seed(),
+                    err
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo everything every nemesis did, so the cluster can recover
+    pub fn heal(&mut self, cluster: &mut Cluster<T, S>) {
+        for nemesis in &mut self.nemeses {
+            nemesis.heal(cluster);
+        }
+        self.history.push((cluster.now(), "all", "healed".into()));
+    }
+
+    /// Everything the nemeses did so far: tick, nemesis and what it did
+    pub fn history(&self) -> &[(Ticks, &'static str, String)] {
+        &self.history
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    clock::Clock,
+    error::{RaftError, Result},
+    log::LogIndex,
+    proposals::Proposals,
+    rpc::{SendableMessage, Transport, RPC},
+    server::{NodeId, RaftConfig, RaftServer, ServerId, Term, Ticks},
+    status::RaftStatus,
+};
+use std::{fmt::Debug, time::Duration};
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    time::{self, Instant, MissedTickBehavior},
+};
+
+/// Requests from clients that can be queued up before [`RaftClient::propose`] has to wait
+/// and [`RaftClient::receive`] starts dropping messages
+const COMMAND_QUEUE_LEN: usize = 1024;
+
+/// Something a [`RaftClient`] asks the node to do
+enum Command<T, I> {
+    /// Hand an RPC from a peer to the node
+    Receive(RPC<T, I>),
+    /// Propose an entry, answered once it is committed
+    Propose(T, oneshot::Sender<Result<LogIndex, I>>),
+    /// Report the node's status
+    Status(oneshot::Sender<RaftStatus<I>>),
+    /// Hand out a [leadership watch](RaftServer::watch_leadership)
+    WatchLeadership(oneshot::Sender<watch::Receiver<(Term, Option<I>)>>),
+    /// [Switch](RaftServer::reconfigure) the node to a new config
+    Reconfigure(RaftConfig, oneshot::Sender<Result<(), I>>),
+    /// [Pause](RaftServer::pause) the node, answered once it is
+    Pause(oneshot::Sender<()>),
+    /// [Resume](RaftServer::resume) the node, answered once it is
+    Resume(oneshot::Sender<()>),
+    /// Stop the node, handing over leadership first if asked to. Answered once it stopped
+    Shutdown(bool, oneshot::Sender<()>),
+}
+
+/// Drives a [`RaftServer`] from a tokio task: ticks it off a timer, feeds it the RPCs and
+/// proposals that come in through its [`RaftClient`]s, and hands everything it sends to a
+/// [`Transport`].
+///
+/// Neither apps nor observer callbacks have to be `Send`, so the node isn't either.
+/// [`run`](Self::run) it on the thread that created it, e.g. with
+/// `tokio::task::spawn_local`, and talk to it from anywhere through clients
+pub struct RaftNode<T, S, I = ServerId> {
+    /// The node being driven
+    server: RaftServer<T, S, I>,
+    /// Time a single tick stands for, on tokio's clock
+    tick: Duration,
+    /// Where outgoing messages go
+    transport: Box<dyn Transport<T, I>>,
+    /// Requests from clients
+    commands: mpsc::Receiver<Command<T, I>>,
+    /// Proposals that haven't been committed yet
+    proposals: Proposals<oneshot::Sender<Result<LogIndex, I>>>,
+    /// Clients waiting for the node to stop, once one asked it to
+    stopping: Vec<oneshot::Sender<()>>,
+}
+
+/// Counts ticks like a [`WallClock`](crate::clock::WallClock), but off tokio's clock rather
+/// than the system's. A node then keeps time with its timers when tokio's time is paused
+/// (`tokio::time::pause`) or simulated, e.g. by turmoil or madsim
+struct TokioClock {
+    /// How much time a single tick stands for
+    tick: Duration,
+    /// Point in time up to which ticks have been reported
+    reported_up_to: Instant,
+}
+
+impl Clock for TokioClock {
+    fn elapsed(&mut self) -> Ticks {
+        let since = self.reported_up_to.elapsed();
+        let ticks = (since.as_nanos() / self.tick.as_nanos()).min(Ticks::MAX as u128) as Ticks;
+        self.reported_up_to += self.tick * ticks;
+        ticks
+    }
+}
+
+/// Handle for talking to a running [`RaftNode`]. Cheap to clone and `Send` as long as the
+/// entries and node ids are
+pub struct RaftClient<T, I = ServerId> {
+    /// Requests for the node
+    commands: mpsc::Sender<Command<T, I>>,
+}
+
+impl<T, I> Clone for RaftClient<T, I> {
+    fn clone(&self) -> Self {
+        RaftClient {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<T, S, I> RaftNode<T, S, I>
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    /// Drive `server`, ticking it every `tick` of tokio's time and sending its messages through
+    /// `transport`. Returns the node, which does nothing until it is [`run`](Self::run),
+    /// along with the first client for it.
+    /// Panics if `tick` is zero
+    pub fn new(
+        server: RaftServer<T, S, I>,
+        tick: Duration,
+        transport: impl Transport<T, I> + 'static,
+    ) -> (Self, RaftClient<T, I>) {
+        assert!(!tick.is_zero(), "tick length must be positive");
+        let (sender, commands) = mpsc::channel(COMMAND_QUEUE_LEN);
+        let node = RaftNode {
+            server,
+            tick,
+            transport: Box::new(transport),
+            commands,
+            proposals: Proposals::new(),
+            stopping: Vec::new(),
+        };
+        (node, RaftClient { commands: sender })
+    }
+
+    /// Run the node until it is [shut down](RaftClient::shutdown) or every [`RaftClient`]
+    /// for it has been dropped, then hand back the server, e.g. to save its state
+    pub async fn run(mut self) -> RaftServer<T, S, I> {
+        let mut clock = TokioClock {
+            tick: self.tick,
+            reported_up_to: Instant::now(),
+        };
+        let mut timer = time::interval(self.tick);
+        // the clock counts every tick that went by, however late the timer fires
+        timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            let msgs = tokio::select! {
+                _ = timer.tick() => self.server.advance(&mut clock),
+                command = self.commands.recv() => match command {
+                    Some(command) => self.handle(command),
+                    None => break,
+                },
+            };
+            for (target, rpc) in msgs {
+                self.transport.send(target, rpc);
+            }
+            for (reply, outcome) in self.proposals.settle(&self.server) {
+                let _ = reply.send(outcome);
+            }
+            // a node asked to stop only keeps going while it hands over leadership
+            if !self.stopping.is_empty() && self.server.leadership_transfer().is_none() {
+                break;
+            }
+        }
+        for reply in self.proposals.abandon() {
+            let _ = reply.send(Err(RaftError::Stopped));
+        }
+        for done in self.stopping.drain(..) {
+            let _ = done.send(());
+        }
+        self.server
+    }
+
+    /// Carry out a single request from a client
+    fn handle(&mut self, command: Command<T, I>) -> Vec<SendableMessage<T, I>> {
+        match command {
+            Command::Receive(rpc) => return self.server.receive_rpc(&rpc),
+            Command::Propose(_, reply) if !self.stopping.is_empty() => {
+                let _ = reply.send(Err(RaftError::Stopped));
+            }
+            Command::Propose(data, reply) => {
+                if let Some((reply, rejected)) =
+                    self.proposals.propose(&mut self.server, data, reply)
+                {
+                    let _ = reply.send(rejected);
+                }
+            }
+            Command::Status(reply) => {
+                let _ = reply.send(self.server.status());
+            }
+            Command::WatchLeadership(reply) => {
+                let _ = reply.send(self.server.watch_leadership());
+            }
+            Command::Reconfigure(config, reply) => {
+                let _ = reply.send(self.server.reconfigure(config));
+            }
+            Command::Pause(done) => {
+                self.server.pause();
+                let _ = done.send(());
+            }
+            Command::Resume(done) => {
+                let _ = done.send(());
+                return self.server.resume();
+            }
+            Command::Shutdown(transfer_leadership, done) => {
+                self.stopping.push(done);
+                if transfer_leadership
+                    && self.server.is_leader()
+                    && self.server.leadership_transfer().is_none()
+                {
+                    // a single node cluster has nobody to hand over to, and just stops
+                    return self.server.transfer_leadership(None).unwrap_or_default();
+                }
+            }
+        }
+        vec![]
+    }
+}
+
+impl<T, I> RaftClient<T, I> {
+    /// Hand an RPC that came in from a peer to the node. Like the network, this drops the
+    /// message if the node is too backed up to take it or no longer running
+    pub fn receive(&self, rpc: RPC<T, I>) {
+        let _ = self.commands.try_send(Command::Receive(rpc));
+    }
+
+    /// Propose `data` to the cluster, resolving to its index in the log once it is committed.
+    /// Fails right away on a node that isn't leader, and later if another leader overwrote
+    /// the entry before it got committed. Either way the error says who the leader is, if
+    /// the node knows
+    pub async fn propose(&self, data: T) -> Result<LogIndex, I> {
+        let (reply, outcome) = oneshot::channel();
+        self.request(Command::Propose(data, reply)).await?;
+        outcome.await.map_err(|_| RaftError::Stopped)?
+    }
+
+    /// Current status of the node
+    pub async fn status(&self) -> Result<RaftStatus<I>, I> {
+        let (reply, status) = oneshot::channel();
+        self.request(Command::Status(reply)).await?;
+        status.await.map_err(|_| RaftError::Stopped)
+    }
+
+    /// Handle that always holds the node's term and the leader it knows of, see
+    /// [`RaftServer::watch_leadership`]. It stops changing once the node stops
+    pub async fn watch_leadership(&self) -> Result<watch::Receiver<(Term, Option<I>)>, I> {
+        let (reply, watch) = oneshot::channel();
+        self.request(Command::WatchLeadership(reply)).await?;
+        watch.await.map_err(|_| RaftError::Stopped)
+    }
+
+    /// [Switch](RaftServer::reconfigure) the node to `config` without restarting it, e.g.
+    /// after a [`ConfigWatcher`](crate::storage::ConfigWatcher) saw the config file change
+    pub async fn reconfigure(&self, config: RaftConfig) -> Result<(), I> {
+        let (reply, outcome) = oneshot::channel();
+        self.request(Command::Reconfigure(config, reply)).await?;
+        outcome.await.map_err(|_| RaftError::Stopped)?
+    }
+
+    /// [Pause](RaftServer::pause) the node, e.g. to back up its state. Its timers stop,
+    /// RPCs from peers are held back until it is resumed, and proposals fail with
+    /// [`RaftError::Paused`]
+    pub async fn pause(&self) -> Result<(), I> {
+        let (done, paused) = oneshot::channel();
+        self.request(Command::Pause(done)).await?;
+        paused.await.map_err(|_| RaftError::Stopped)
+    }
+
+    /// [Resume](RaftServer::resume) a paused node, which catches up on the RPCs it held back
+    pub async fn resume(&self) -> Result<(), I> {
+        let (done, resumed) = oneshot::channel();
+        self.request(Command::Resume(done)).await?;
+        resumed.await.map_err(|_| RaftError::Stopped)
+    }
+
+    /// Stop the node, resolving once it has. With `transfer_leadership` a leader first hands
+    /// its leadership to the follower that is furthest along, which takes at most an
+    /// election timeout, so the cluster doesn't go without a leader until its next election.
+    /// Proposals that haven't been committed by then fail with [`RaftError::Stopped`], as
+    /// does anything sent to the node from then on. Resolves right away if the node had
+    /// already stopped
+    pub async fn shutdown(&self, transfer_leadership: bool) {
+        let (done, stopped) = oneshot::channel();
+        if self
+            .request(Command::Shutdown(transfer_leadership, done))
+            .await
+            .is_ok()
+        {
+            let _ = stopped.await;
+        }
+    }
+
+    /// Queue a request for the node, waiting for room if need be
+    async fn request(&self, command: Command<T, I>) -> Result<(), I> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| RaftError::Stopped)
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{
+    event::RaftEvent,
+    log::LogIndex,
+    server::{ServerId, Term},
+    status::Role,
+};
+use std::sync::mpsc::{channel, Receiver, Sender};
+#[cfg(feature = "tokio")]
+use tokio::sync::watch;
+
+/// Called with the old role, the new role, and the term the node is in after the change
+pub type RoleCallback = Box<dyn FnMut(Role, Role, Term)>;
+
+/// Called with the old term and the new term
+pub type TermCallback = Box<dyn FnMut(Term, Term)>;
+
+/// Called with the new committed length of the log and how many entries just got committed
+pub type CommitCallback = Box<dyn FnMut(LogIndex, LogIndex)>;
+
+/// Called with every [`RaftEvent`] the node publishes
+pub type EventCallback<I = ServerId> = Box<dyn FnMut(&RaftEvent<I>)>;
+
+/// Callbacks registered on a [`RaftServer`](crate::server::RaftServer) that get fired
+/// synchronously from inside `tick()`/`receive_rpc()` as the node changes state.
+/// Callbacks should be quick, the node can't make progress until they return
+pub struct Observers<I = ServerId> {
+    /// Fired on every change of role
+    role: Vec<RoleCallback>,
+    /// Fired every time the node moves to a new term
+    term: Vec<TermCallback>,
+    /// Fired every time the commit index moves forward
+    commit: Vec<CommitCallback>,
+    /// Fired for every event
+    event: Vec<EventCallback<I>>,
+    /// Channels every event gets published into, dropped once the receiving end hangs up
+    subscribers: Vec<Sender<RaftEvent<I>>>,
+    /// Term and leader the node knows of, once anyone is watching them
+    #[cfg(feature = "tokio")]
+    leadership: Option<watch::Sender<(Term, Option<I>)>>,
+}
+
+impl<I> Default for Observers<I> {
+    fn default() -> Self {
+        Observers {
+            role: Vec::new(),
+            term: Vec::new(),
+            commit: Vec::new(),
+            event: Vec::new(),
+            subscribers: Vec::new(),
+            #[cfg(feature = "tokio")]
+            leadership: None,
+        }
+    }
+}
+
+impl<I: Clone> Observers<I> {
+    /// Register a callback for role changes
+    pub fn on_role_change(&mut self, callback: impl FnMut(Role, Role, Term) + 'static) {
+        self.role.push(Box::new(callback));
+    }
+
+    /// Register a callback for term changes
+    pub fn on_term_change(&mut self, callback: impl FnMut(Term, Term) + 'static) {
+        self.term.push(Box::new(callback));
+    }
+
+    /// Register a callback for commit index advancement
+    pub fn on_commit(&mut self, callback: impl FnMut(LogIndex, LogIndex) + 'static) {
+        self.commit.push(Box::new(callback));
+    }
+
+    /// Register a callback for every [`RaftEvent`]
+    pub fn on_event(&mut self, callback: impl FnMut(&RaftEvent<I>) + 'static) {
+        self.event.push(Box::new(callback));
+    }
+
+    /// Get a channel that every [`RaftEvent`] from now on gets published into.
+    /// Dropping the receiver unsubscribes
+    pub fn subscribe(&mut self) -> Receiver<RaftEvent<I>> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Get a handle that always holds the term the node is in and the leader it knows of
+    /// for that term, starting at `current`. Dropping every handle stops the updates
+    #[cfg(feature = "tokio")]
+    pub(crate) fn watch_leadership(
+        &mut self,
+        current: (Term, Option<I>),
+    ) -> watch::Receiver<(Term, Option<I>)> {
+        match &self.leadership {
+            Some(sender) if !sender.is_closed() => sender.subscribe(),
+            _ => {
+                let (sender, receiver) = watch::channel(current);
+                self.leadership = Some(sender);
+                receiver
+            }
+        }
+    }
+
+    /// Whether anyone is [watching](Self::watch_leadership) the term and leader
+    #[cfg(feature = "tokio")]
+    pub(crate) fn watching_leadership(&self) -> bool {
+        self.leadership
+            .as_ref()
+            .is_some_and(|sender| !sender.is_closed())
+    }
+
+    /// Let leadership watchers know the node is in `term` and follows `leader`, if either
+    /// changed
+    #[cfg(feature = "tokio")]
+    pub(crate) fn leadership_changed(&mut self, term: Term, leader: Option<I>)
+    where
+        I: PartialEq,
+    {
+        if let Some(sender) = &self.leadership {
+            sender.send_if_modified(|current| {
+                let changed = current.0 != term || current.1 != leader;
+                if changed {
+                    *current = (term, leader);
+                }
+                changed
+            });
+        }
+    }
+
+    /// Publish an event to every event callback and subscriber
+    pub(crate) fn emit(&mut self, event: RaftEvent<I>) {
+        self.event.iter_mut().for_each(|callback| callback(&event));
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Let every role observer know the node went from `from` to `to`
+    pub(crate) fn role_changed(&mut self, from: Role, to: Role, term: Term) {
+        self.role
+            .iter_mut()
+            .for_each(|callback| callback(from, to, term));
+        let event = match (from, to) {
+            (_, Role::Candidate) => RaftEvent::BecameCandidate { term },
+            (_, Role::Leader) => RaftEvent::BecameLeader { term },
+            (Role::Leader, Role::Follower) => RaftEvent::SteppedDown { term },
+            (_, Role::Follower) => RaftEvent::BecameFollower { term },
+        };
+        self.emit(event);
+    }
+
+    /// Let every term observer know the node went from `from` to `to`
+    pub(crate) fn term_changed(&mut self, from: Term, to: Term) {
+        self.term.iter_mut().for_each(|callback| callback(from, to));
+        self.emit(RaftEvent::TermChanged { from, to });
+    }
+
+    /// Let every commit observer know the log is now committed up to `committed_len`,
+    /// if it moved forward since `old_committed_len`
+    pub(crate) fn committed(&mut self, old_committed_len: LogIndex, committed_len: LogIndex) {
+        if committed_len > old_committed_len {
+            let newly_committed = committed_len - old_committed_len;
+            self.commit
+                .iter_mut()
+                .for_each(|callback| callback(committed_len, newly_committed));
+            self.emit(RaftEvent::EntriesCommitted {
+                committed_len,
+                count: newly_committed,
+            });
+        }
+    }
+}
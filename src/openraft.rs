@@ -0,0 +1,241 @@
+use crate::{
+    log::{LogEntry, Snapshot},
+    server::PersistentState,
+    storage::Storage,
+};
+use ::openraft::{
+    storage::{LogState, RaftStorage, SnapshotMeta},
+    CommittedLeaderId, Entry, EntryPayload, LogId, RaftTypeConfig, StoredMembership, Vote,
+};
+use futures::executor::block_on;
+use std::{
+    io::{self, SeekFrom},
+    marker::PhantomData,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Keeps a node's [`PersistentState`] in an openraft [`RaftStorage`], so storage backends
+/// written for openraft can be reused.
+///
+/// The state maps onto the backend like this:
+/// - term and vote are saved as openraft's [`Vote`]. openraft has no way to say a node saw a
+///   term without voting in it, so not having voted is saved as a vote for ourselves, which
+///   only means we refuse to vote again in that term after a restart
+/// - an entry covering the first `n` entries of the log gets log index `n`, so the snapshot's
+///   `len` is the index of its last log id. Log ids carry the term of the entry and our own
+///   id as leader, as the log doesn't remember who proposed what
+/// - the snapshot's data goes into the backend as is, through
+///   [`begin_receiving_snapshot`](RaftStorage::begin_receiving_snapshot) and
+///   [`install_snapshot`](RaftStorage::install_snapshot). A backend whose state machine
+///   decodes snapshots has to be able to take what the [`App`](crate::log::App) writes
+///
+/// Entries are never applied to the backend's state machine, the node's app does that.
+/// The backend's futures are run to completion on the calling thread, so a backend that
+/// needs a tokio runtime for its I/O can't be used from inside one
+pub struct OpenraftStorage<C: RaftTypeConfig, S> {
+    /// Backend everything is saved to
+    store: S,
+    /// Our own id, the stand-in for votes and leaders openraft needs but we don't have
+    id: C::NodeId,
+    _config: PhantomData<C>,
+}
+
+impl<C: RaftTypeConfig, S: RaftStorage<C>> OpenraftStorage<C, S> {
+    /// Save the state of node `id` to `store`
+    pub fn new(id: C::NodeId, store: S) -> Self {
+        OpenraftStorage {
+            store,
+            id,
+            _config: PhantomData,
+        }
+    }
+
+    /// The backend, e.g. to shut it down cleanly
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    /// Log id of the entry `len` entries into the log, which is from `term`
+    fn log_id(&self, term: u64, len: usize) -> LogId<C::NodeId> {
+        LogId::new(CommittedLeaderId::new(term, self.id.clone()), len as u64)
+    }
+
+    /// Hand `snapshot` to the backend, unless it already has it
+    async fn save_snapshot(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        if snapshot.len == 0 {
+            return Ok(());
+        }
+        let last_log_id = self.log_id(snapshot.term, snapshot.len);
+        let current = self
+            .store
+            .get_current_snapshot()
+            .await
+            .map_err(io::Error::other)?;
+        if current.is_some_and(|current| current.meta.last_log_id.as_ref() == Some(&last_log_id)) {
+            return Ok(());
+        }
+
+        let mut data = self
+            .store
+            .begin_receiving_snapshot()
+            .await
+            .map_err(io::Error::other)?;
+        data.write_all(&snapshot.data).await?;
+        data.flush().await?;
+        data.seek(SeekFrom::Start(0)).await?;
+        let meta = SnapshotMeta {
+            last_log_id: Some(last_log_id.clone()),
+            last_membership: StoredMembership::default(),
+            snapshot_id: format!("{}-{}", snapshot.term, snapshot.len),
+        };
+        self.store
+            .install_snapshot(&meta, data)
+            .await
+            .map_err(io::Error::other)?;
+        self.store
+            .purge_logs_upto(last_log_id)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// Make the backend's log after the snapshot match `entries`, leaving alone whatever
+    /// it already has of them
+    async fn save_entries(&mut self, start: usize, entries: &[LogEntry<C::D>]) -> io::Result<()>
+    where
+        C::D: Clone,
+        C: RaftTypeConfig<Entry = Entry<C>>,
+    {
+        let LogState { last_log_id, .. } =
+            self.store.get_log_state().await.map_err(io::Error::other)?;
+        let end = last_log_id.map_or(0, |id| id.index);
+        let saved = match end > start as u64 {
+            true => self
+                .store
+                .try_get_log_entries(start as u64 + 1..=end)
+                .await
+                .map_err(io::Error::other)?,
+            false => Vec::new(),
+        };
+
+        // an entry with the same index and term holds the same data, see the log matching
+        // property in the raft paper
+        let kept = saved
+            .iter()
+            .zip(entries)
+            .take_while(|(saved, entry)| saved.log_id.leader_id.term == entry.term)
+            .count();
+        if let Some(conflict) = saved.get(kept) {
+            self.store
+                .delete_conflict_logs_since(conflict.log_id.clone())
+                .await
+                .map_err(io::Error::other)?;
+        }
+        let new: Vec<_> = entries[kept..]
+            .iter()
+            .zip(start + kept + 1..)
+            .map(|(entry, len)| Entry {
+                log_id: self.log_id(entry.term, len),
+                payload: EntryPayload::Normal(entry.data.clone()),
+            })
+            .collect();
+        if new.is_empty() {
+            return Ok(());
+        }
+        self.store
+            .append_to_log(new)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// Read the backend's current snapshot back, an empty one if it has none
+    async fn load_snapshot(&mut self) -> io::Result<Snapshot> {
+        let Some(mut current) = self
+            .store
+            .get_current_snapshot()
+            .await
+            .map_err(io::Error::other)?
+        else {
+            return Ok(Snapshot::default());
+        };
+        let Some(last_log_id) = current.meta.last_log_id else {
+            return Ok(Snapshot::default());
+        };
+        let mut data = Vec::new();
+        current.snapshot.seek(SeekFrom::Start(0)).await?;
+        current.snapshot.read_to_end(&mut data).await?;
+        Ok(Snapshot {
+            len: last_log_id.index as usize,
+            term: last_log_id.leader_id.term,
+            data,
+        })
+    }
+}
+
+impl<C, S> Storage<C::D, C::NodeId> for OpenraftStorage<C, S>
+where
+    C: RaftTypeConfig<Entry = Entry<C>>,
+    C::D: Clone,
+    S: RaftStorage<C>,
+{
+    fn save(&mut self, state: &PersistentState<C::D, C::NodeId>) -> io::Result<()> {
+        block_on(async {
+            let voted_for = state.voted_for.clone().unwrap_or_else(|| self.id.clone());
+            self.store
+                .save_vote(&Vote::new(state.current_term, voted_for))
+                .await
+                .map_err(io::Error::other)?;
+            self.save_snapshot(&state.snapshot).await?;
+            self.save_entries(state.snapshot.len, &state.entries).await
+        })
+    }
+
+    fn load(&mut self) -> io::Result<Option<PersistentState<C::D, C::NodeId>>> {
+        block_on(async {
+            let Some(vote) = self.store.read_vote().await.map_err(io::Error::other)? else {
+                return Ok(None);
+            };
+            let snapshot = self.load_snapshot().await?;
+            let LogState { last_log_id, .. } =
+                self.store.get_log_state().await.map_err(io::Error::other)?;
+            let end = last_log_id.map_or(0, |id| id.index);
+            let saved = match end > snapshot.len as u64 {
+                true => self
+                    .store
+                    .try_get_log_entries(snapshot.len as u64 + 1..=end)
+                    .await
+                    .map_err(io::Error::other)?,
+                false => Vec::new(),
+            };
+
+            let mut entries = Vec::with_capacity(saved.len());
+            for (entry, len) in saved.into_iter().zip(snapshot.len as u64 + 1..) {
+                let data = match entry.payload {
+                    EntryPayload::Normal(data) if entry.log_id.index == len => data,
+                    EntryPayload::Normal(_) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("log has a hole before index {}", entry.log_id.index),
+                        ))
+                    }
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("entry {} was not written by a miniraft node", entry.log_id),
+                        ))
+                    }
+                };
+                entries.push(LogEntry {
+                    term: entry.log_id.leader_id.term,
+                    data,
+                });
+            }
+
+            Ok(Some(PersistentState {
+                current_term: vote.leader_id().get_term(),
+                voted_for: vote.leader_id().voted_for(),
+                snapshot,
+                entries,
+            }))
+        })
+    }
+}
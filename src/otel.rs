@@ -0,0 +1,63 @@
+use crate::rpc::TraceContext;
+use opentelemetry::{
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Version of the W3C `traceparent` format we speak
+const VERSION: &str = "00";
+
+/// Trace context of the current span, `None` if it isn't being recorded by OpenTelemetry
+pub(crate) fn current() -> Option<TraceContext> {
+    let cx = tracing::Span::current().context();
+    let span_context = cx.span().span_context().clone();
+    if !span_context.is_valid() {
+        return None;
+    }
+    Some(TraceContext {
+        traceparent: format!(
+            "{}-{}-{}-{:02x}",
+            VERSION,
+            span_context.trace_id(),
+            span_context.span_id(),
+            span_context.trace_flags().to_u8()
+        ),
+    })
+}
+
+/// Parse a trace context sent by another node, `None` if it is malformed
+pub(crate) fn extract(trace: &TraceContext) -> Option<Context> {
+    let mut parts = trace.traceparent.split('-');
+    if parts.next()? != VERSION {
+        return None;
+    }
+    let trace_id = TraceId::from_hex(parts.next()?).ok()?;
+    let span_id = SpanId::from_hex(parts.next()?).ok()?;
+    let flags = u8::from_str_radix(parts.next()?, 16).ok()?;
+    let span_context = SpanContext::new(
+        trace_id,
+        span_id,
+        TraceFlags::new(flags),
+        true,
+        TraceState::default(),
+    );
+    span_context
+        .is_valid()
+        .then(|| Context::new().with_remote_span_context(span_context))
+}
+
+/// Make `span` a child of the span that sent us `trace`. Has to happen before `span` is entered
+pub(crate) fn follow(span: &tracing::Span, trace: Option<&TraceContext>) {
+    if let Some(cx) = trace.and_then(extract) {
+        // fails if no OpenTelemetry layer is installed, nothing to link up then
+        let _ = span.set_parent(cx);
+    }
+}
+
+/// Span to apply a committed entry under, as a child of the client request that proposed it
+pub(crate) fn apply_span(index: usize, trace: &TraceContext) -> tracing::Span {
+    let span = tracing::info_span!("apply", index);
+    follow(&span, Some(trace));
+    span
+}
@@ -0,0 +1,201 @@
+use crate::{
+    clock::Instant,
+    log::LogIndex,
+    metrics::RaftMetrics,
+    server::{NodeId, RaftServer},
+    status::Role,
+};
+use ::prometheus::{Histogram, HistogramOpts, IntCounter, IntGauge, Opts, Registry};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug},
+};
+
+/// Counters registered with prometheus, paired with the getter for the matching
+/// [`RaftMetrics`] field
+type CounterSource = (IntCounter, fn(&RaftMetrics) -> u64);
+
+/// Mirrors a [`RaftServer`]'s [`status`](RaftServer::status) and
+/// [`metrics`](RaftServer::metrics) into a prometheus [`Registry`].
+///
+/// The server itself knows nothing about prometheus, call [`update`](Self::update) after
+/// every `tick()`/`receive_rpc()` (or at least before every scrape) to bring the registered
+/// metrics up to date. Every metric carries a `server_id` label so several nodes can share
+/// one registry.
+pub struct PrometheusExporter {
+    /// Current term of the node
+    term: IntGauge,
+    /// 1 while the node is leader, 0 otherwise
+    is_leader: IntGauge,
+    /// Length of the committed prefix of the log
+    commit_index: IntGauge,
+    /// Number of entries the app has finished applying
+    applied_index: IntGauge,
+    /// Length of the full log
+    log_len: IntGauge,
+    /// Ticks since the node last heard from a leader, -1 if leader or never heard from one
+    ticks_since_leader_contact: IntGauge,
+    /// Ticks the node has been leader for, -1 if not leader
+    ticks_as_leader: IntGauge,
+    /// Every counter in [`RaftMetrics`]
+    counters: Vec<CounterSource>,
+    /// Values of the counters at the last update, prometheus counters can only be incremented
+    last_metrics: RaftMetrics,
+    /// Time from an entry first showing up in the log to it being committed
+    commit_latency: Histogram,
+    /// Entries seen in the log but not yet committed, along with when we first saw them
+    uncommitted: VecDeque<(LogIndex, Instant)>,
+}
+
+impl PrometheusExporter {
+    /// Register every metric for node `id` with `registry`
+    pub fn new(id: impl fmt::Display, registry: &Registry) -> ::prometheus::Result<Self> {
+        let opts =
+            |name: &str, help: &str| Opts::new(name, help).const_label("server_id", id.to_string());
+        let gauge = |name: &str, help: &str| -> ::prometheus::Result<IntGauge> {
+            let gauge = IntGauge::with_opts(opts(name, help))?;
+            registry.register(Box::new(gauge.clone()))?;
+            Ok(gauge)
+        };
+        let counter = |name: &str,
+                       help: &str,
+                       get: fn(&RaftMetrics) -> u64|
+         -> ::prometheus::Result<CounterSource> {
+            let counter = IntCounter::with_opts(opts(name, help))?;
+            registry.register(Box::new(counter.clone()))?;
+            Ok((counter, get))
+        };
+
+        let commit_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "raft_commit_latency_seconds",
+                "Time from an entry being appended to the log to it being committed",
+            )
+            .const_label("server_id", id.to_string()),
+        )?;
+        registry.register(Box::new(commit_latency.clone()))?;
+
+        Ok(PrometheusExporter {
+            term: gauge("raft_term", "Current term")?,
+            is_leader: gauge("raft_is_leader", "Whether this node is leader")?,
+            commit_index: gauge("raft_commit_index", "Length of the committed log")?,
+            applied_index: gauge("raft_applied_index", "Entries applied by the app")?,
+            log_len: gauge("raft_log_length", "Length of the log")?,
+            ticks_since_leader_contact: gauge(
+                "raft_ticks_since_leader_contact",
+                "Ticks since the last message from a leader, -1 if leader or none yet",
+            )?,
+            ticks_as_leader: gauge(
+                "raft_ticks_as_leader",
+                "Ticks since this node became leader, -1 if not leader",
+            )?,
+            counters: vec![
+                counter("raft_elections_started_total", "Elections started", |m| {
+                    m.elections_started
+                })?,
+                counter("raft_elections_won_total", "Elections won", |m| {
+                    m.elections_won
+                })?,
+                counter(
+                    "raft_leader_changes_total",
+                    "Times leadership moved to a different node",
+                    |m| m.leader_changes,
+                )?,
+                counter("raft_votes_granted_total", "Votes granted", |m| {
+                    m.votes_granted
+                })?,
+                counter("raft_votes_denied_total", "Votes denied", |m| {
+                    m.votes_denied
+                })?,
+                counter(
+                    "raft_append_requests_sent_total",
+                    "Append requests sent",
+                    |m| m.append_requests_sent,
+                )?,
+                counter(
+                    "raft_append_requests_received_total",
+                    "Append requests received",
+                    |m| m.append_requests_received,
+                )?,
+                counter(
+                    "raft_append_requests_rejected_total",
+                    "Append requests rejected",
+                    |m| m.append_requests_rejected,
+                )?,
+                counter("raft_heartbeats_sent_total", "Heartbeats sent", |m| {
+                    m.heartbeats_sent
+                })?,
+                counter("raft_entries_committed_total", "Entries committed", |m| {
+                    m.entries_committed
+                })?,
+                counter("raft_entries_applied_total", "Entries applied", |m| {
+                    m.entries_applied
+                })?,
+                counter(
+                    "raft_slow_operations_total",
+                    "Operations slower than their configured threshold",
+                    |m| m.slow_operations,
+                )?,
+                counter(
+                    "raft_snapshots_failed_total",
+                    "Snapshots that could not be taken or installed",
+                    |m| m.snapshots_failed,
+                )?,
+                counter(
+                    "raft_responses_from_unknown_followers_total",
+                    "Responses dropped because they came from a node that is not a peer",
+                    |m| m.responses_from_unknown_followers,
+                )?,
+            ],
+            last_metrics: RaftMetrics::default(),
+            commit_latency,
+            uncommitted: VecDeque::new(),
+        })
+    }
+
+    /// Bring every registered metric up to date with the current state of `server`.
+    /// Commit latency is only as precise as how often this gets called
+    pub fn update<T, S, I>(&mut self, server: &RaftServer<T, S, I>)
+    where
+        T: Clone + Debug,
+        I: NodeId,
+    {
+        let status = server.status();
+        let now = Instant::now();
+
+        self.term.set(status.term as i64);
+        self.is_leader.set((status.role == Role::Leader) as i64);
+        self.commit_index.set(status.committed_len as i64);
+        self.applied_index.set(status.last_applied as i64);
+        self.log_len.set(status.log_len as i64);
+        self.ticks_since_leader_contact
+            .set(status.ticks_since_leader_contact.map_or(-1, i64::from));
+        self.ticks_as_leader
+            .set(status.ticks_as_leader.map_or(-1, i64::from));
+
+        let metrics = server.metrics();
+        for (counter, get) in &self.counters {
+            counter.inc_by(get(&metrics).saturating_sub(get(&self.last_metrics)));
+        }
+        self.last_metrics = metrics;
+
+        // uncommitted entries can be overwritten by a new leader, forget about anything
+        // past the end of the log so the new entries at that index start a fresh clock
+        self.uncommitted.retain(|(len, _)| *len <= status.log_len);
+        let seen = self
+            .uncommitted
+            .back()
+            .map_or(status.committed_len, |(len, _)| *len);
+        for len in seen.max(status.committed_len) + 1..=status.log_len {
+            self.uncommitted.push_back((len, now));
+        }
+        while let Some((len, appended)) = self.uncommitted.front() {
+            if *len > status.committed_len {
+                break;
+            }
+            self.commit_latency
+                .observe(now.duration_since(*appended).as_secs_f64());
+            self.uncommitted.pop_front();
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    error::{RaftError, Result},
+    log::LogIndex,
+    server::{NodeId, RaftServer, Term},
+};
+use std::fmt::Debug;
+
+/// Proposal waiting to get committed
+struct Proposal<R> {
+    /// Index the entry was appended at
+    index: LogIndex,
+    /// Term the entry was appended in
+    term: Term,
+    /// Where to send the outcome
+    reply: R,
+}
+
+/// Proposals a driver made on behalf of its clients that haven't been committed yet, along
+/// with however the driver answers the client (`R`)
+pub(crate) struct Proposals<R> {
+    /// Oldest first
+    pending: Vec<Proposal<R>>,
+}
+
+impl<R> Proposals<R> {
+    pub(crate) fn new() -> Self {
+        Proposals {
+            pending: Vec::new(),
+        }
+    }
+
+    /// Propose `data` to `server`. Returns the reply along with the error if the server
+    /// turned it down, otherwise the reply is kept until [`settle`](Self::settle) has an outcome
+    pub(crate) fn propose<T, S, I>(
+        &mut self,
+        server: &mut RaftServer<T, S, I>,
+        data: T,
+        reply: R,
+    ) -> Option<(R, Result<LogIndex, I>)>
+    where
+        T: Clone + Debug,
+        I: NodeId,
+    {
+        match server.client_request(data) {
+            Ok(()) => {
+                self.pending.push(Proposal {
+                    index: server.log.last_idx(),
+                    term: server.current_term(),
+                    reply,
+                });
+                None
+            }
+            Err(err) => Some((reply, Err(err))),
+        }
+    }
+
+    /// Take out the proposals that got committed, or that another leader overwrote before
+    /// they could, along with their outcome. A proposal whose entry ended up inside a
+    /// snapshot from a later term counts as overwritten, as there is no telling anymore
+    /// whether it made it
+    pub(crate) fn settle<T, S, I>(
+        &mut self,
+        server: &RaftServer<T, S, I>,
+    ) -> Vec<(R, Result<LogIndex, I>)>
+    where
+        T: Clone + Debug,
+        I: NodeId,
+    {
+        let log = &server.log;
+        let mut settled = Vec::new();
+        for proposal in std::mem::take(&mut self.pending) {
+            // the entry at an index is only ever replaced by one from another term. Once it
+            // is compacted its term is gone, but a snapshot that ends in the same term must
+            // still hold it, as only we appended entries in that term and only after it
+            let len = proposal.index + 1;
+            let kept = match log.term_at(len) {
+                Some(term) => term == proposal.term,
+                None => len < log.snapshot.len && log.snapshot.term == proposal.term,
+            };
+            if !kept {
+                let leader = server.leader_id();
+                settled.push((proposal.reply, Err(RaftError::NotLeader { leader })));
+            } else if log.committed_len >= len {
+                settled.push((proposal.reply, Ok(proposal.index)));
+            } else {
+                self.pending.push(proposal);
+            }
+        }
+        settled
+    }
+
+    /// Take out every proposal still waiting, for when the driver stops before they settle
+    pub(crate) fn abandon(&mut self) -> Vec<R> {
+        self.pending
+            .drain(..)
+            .map(|proposal| proposal.reply)
+            .collect()
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{
+    error::RaftError,
+    log::{App, LogEntry},
+    rpc::{SendableMessage, Target, RPC},
+    server::{RaftConfig, RaftServer, ServerId, Ticks},
+    sim,
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::PyList,
+};
+use serde::Serialize;
+
+/// Entries and state as seen from Python: entries are strings, and the state of a node is
+/// every entry it applied, in order
+type Entry = String;
+
+/// Remembers every entry applied, which is all a script needs to see nodes agree
+#[derive(Default)]
+struct Applied(Vec<Entry>);
+
+impl App<Entry, Vec<Entry>> for Applied {
+    fn transition_fn(&mut self, entry: &LogEntry<Entry>) {
+        self.0.push(entry.data.clone());
+    }
+
+    fn get_state(&self) -> Vec<Entry> {
+        self.0.clone()
+    }
+}
+
+/// Turn anything serializable into plain Python dicts and lists, by way of JSON
+fn to_python<'py>(py: Python<'py>, value: &impl Serialize) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).expect("status is always serializable");
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+/// Errors from a node become `ValueError` for bad arguments, `RuntimeError` otherwise
+fn to_py_err(err: RaftError) -> PyErr {
+    match err {
+        RaftError::InvalidConfig(_) | RaftError::InvalidRequest(_) => {
+            PyValueError::new_err(err.to_string())
+        }
+        _ => PyRuntimeError::new_err(err.to_string()),
+    }
+}
+
+/// Messages as `(to, rpc)` tuples, `to` being `None` for a broadcast and `rpc` the JSON the
+/// peer's [`Server.receive`](PyServer::receive) takes
+fn to_messages(msgs: Vec<SendableMessage<Entry>>) -> Vec<(Option<ServerId>, String)> {
+    msgs.into_iter()
+        .map(|(target, rpc)| {
+            let to = match target {
+                Target::Single(id) => Some(id),
+                Target::Broadcast => None,
+            };
+            let rpc = serde_json::to_string(&rpc).expect("RPCs are always serializable");
+            (to, rpc)
+        })
+        .collect()
+}
+
+/// A single node, `miniraft.Server` in Python. The script plays the network: it ticks the
+/// node, and hands messages between nodes itself
+#[pyclass(name = "Server", module = "miniraft", unsendable)]
+pub struct PyServer {
+    /// The node itself
+    server: RaftServer<Entry, Vec<Entry>>,
+}
+
+#[pymethods]
+impl PyServer {
+    /// Node `id` of a cluster with the other nodes in `peers`
+    #[new]
+    #[pyo3(signature = (
+        id, peers, seed=None, election_timeout=10, election_timeout_jitter=3, heartbeat_interval=5
+    ))]
+    fn new(
+        id: ServerId,
+        peers: Vec<ServerId>,
+        seed: Option<u64>,
+        election_timeout: Ticks,
+        election_timeout_jitter: Ticks,
+        heartbeat_interval: Ticks,
+    ) -> PyResult<Self> {
+        let config = RaftConfig::builder()
+            .election_timeout(election_timeout)
+            .election_timeout_jitter(election_timeout_jitter)
+            .heartbeat_interval(heartbeat_interval)
+            .build()
+            .map_err(to_py_err)?;
+        let mut server = RaftServer::builder()
+            .id(id)
+            .peers(peers)
+            .config(config)
+            .app(Applied::default());
+        if let Some(seed) = seed {
+            server = server.seed(seed);
+        }
+        let server = server.build().map_err(to_py_err)?;
+        Ok(PyServer { server })
+    }
+
+    /// Move the node's clock forward by a tick, returning the messages it sent
+    fn tick(&mut self) -> Vec<(Option<ServerId>, String)> {
+        to_messages(self.server.tick())
+    }
+
+    /// Hand the node a message another node sent it, returning the messages it sent back
+    fn receive(&mut self, rpc: &str) -> PyResult<Vec<(Option<ServerId>, String)>> {
+        let rpc: RPC<Entry> =
+            serde_json::from_str(rpc).map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(to_messages(self.server.receive_rpc(&rpc)))
+    }
+
+    /// Propose an entry, only the leader takes it
+    fn propose(&mut self, data: Entry) -> PyResult<()> {
+        self.server.client_request(data).map_err(to_py_err)
+    }
+
+    /// ID of the node
+    #[getter]
+    fn id(&self) -> ServerId {
+        self.server.id
+    }
+
+    /// `"Follower"`, `"Candidate"` or `"Leader"`
+    #[getter]
+    fn role(&self) -> String {
+        format!("{:?}", self.server.role())
+    }
+
+    /// Current term of the node
+    #[getter]
+    fn term(&self) -> u64 {
+        self.server.current_term()
+    }
+
+    /// Who the node believes is leader, if anyone
+    #[getter]
+    fn leader(&self) -> Option<ServerId> {
+        self.server.leader_id()
+    }
+
+    /// Every entry the node applied, in order
+    #[getter]
+    fn applied(&self) -> Vec<Entry> {
+        self.server.log.app.get_state()
+    }
+
+    /// The node's [status](crate::status::RaftStatus) as a dict
+    fn status<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_python(py, &self.server.status())
+    }
+}
+
+/// A deterministic simulated cluster, `miniraft.Cluster` in Python, see [`sim::Cluster`]
+#[pyclass(name = "Cluster", module = "miniraft", unsendable)]
+pub struct PyCluster {
+    /// The cluster itself
+    cluster: sim::Cluster<Entry, Vec<Entry>>,
+}
+
+#[pymethods]
+impl PyCluster {
+    /// A cluster of `nodes` nodes with ids `0..nodes`. The same seed always plays out the
+    /// same way
+    #[new]
+    #[pyo3(signature = (nodes=3, seed=0))]
+    fn new(nodes: usize, seed: u64) -> Self {
+        let cluster = sim::Cluster::builder()
+            .nodes(nodes)
+            .seed(seed)
+            .app(Applied::default)
+            .build();
+        PyCluster { cluster }
+    }
+
+    /// Move the whole cluster forward by `n` ticks
+    #[pyo3(signature = (n=1))]
+    fn tick(&mut self, n: Ticks) {
+        self.cluster.tick_by(n);
+    }
+
+    /// Ticks since the cluster was created
+    #[getter]
+    fn now(&self) -> Ticks {
+        self.cluster.now()
+    }
+
+    /// The live leader in the highest term, if there is one
+    #[getter]
+    fn leader(&self) -> Option<ServerId> {
+        self.cluster.leader().map(|leader| leader.id)
+    }
+
+    /// Propose an entry to the leader, returning who took it
+    fn propose(&mut self, data: Entry) -> PyResult<ServerId> {
+        self.cluster
+            .client_request(data)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// Take node `id` down, it keeps its state for when it is revived
+    fn kill(&mut self, id: ServerId) {
+        self.cluster.kill(id);
+    }
+
+    /// Bring node `id` back up
+    fn revive(&mut self, id: ServerId) {
+        self.cluster.revive(id);
+    }
+
+    /// Cut the nodes in `side` off from the rest of the cluster
+    fn partition(&mut self, side: Vec<ServerId>) {
+        self.cluster.partition(&side);
+    }
+
+    /// Undo every partition and cut link
+    fn heal(&mut self) {
+        self.cluster.heal();
+    }
+
+    /// Every entry node `id` applied, in order
+    fn applied(&self, id: ServerId) -> PyResult<Vec<Entry>> {
+        let node = self.cluster.nodes().find(|node| node.id == id);
+        let node = node.ok_or_else(|| PyValueError::new_err(format!("no node {id}")))?;
+        Ok(node.log.app.get_state())
+    }
+
+    /// Status of every node as a list of dicts, see [`PyServer::status`]
+    fn status<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let statuses = self
+            .cluster
+            .nodes()
+            .map(|node| to_python(py, &node.status()))
+            .collect::<PyResult<Vec<_>>>()?;
+        PyList::new(py, statuses)
+    }
+}
+
+/// The `miniraft` Python module
+#[pymodule]
+pub fn miniraft(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyServer>()?;
+    m.add_class::<PyCluster>()?;
+    Ok(())
+}
@@ -0,0 +1,280 @@
+use crate::{
+    error::RaftError,
+    log::App,
+    rpc::{SendableMessage, RPC},
+    server::{RaftConfig, RaftServer, ServerId, Ticks},
+};
+use anyhow::{bail, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fmt::Debug,
+    io::{self, Write},
+};
+
+/// A single line of a recording. Inputs are recorded together with the messages the node
+/// sent in response, so a replay can check it still behaves the same way
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RecordedEvent<T> {
+    /// Everything needed to create an identical node, always the first line
+    Start {
+        /// ID of the node
+        id: ServerId,
+        /// IDs of every other node in the cluster
+        peers: BTreeSet<ServerId>,
+        /// Config the node was created with
+        config: RaftConfig,
+        /// Seed for the node's RNG, which decides its election timeouts
+        seed: u64,
+    },
+    /// [`RaftServer::tick`] was called
+    Tick {
+        /// Number of ticks so far, including this one
+        tick: Ticks,
+        /// What the node sent
+        outgoing: Vec<SendableMessage<T>>,
+    },
+    /// [`RaftServer::receive_rpc`] was called
+    Receive {
+        /// Number of ticks before the message arrived
+        tick: Ticks,
+        /// The message that arrived
+        rpc: RPC<T>,
+        /// What the node sent in response
+        outgoing: Vec<SendableMessage<T>>,
+    },
+    /// [`RaftServer::client_request`] was called
+    ClientRequest {
+        /// Number of ticks before the request arrived
+        tick: Ticks,
+        /// The proposed entry
+        data: T,
+        /// Whether the node took the request
+        accepted: bool,
+    },
+}
+
+/// Wraps a [`RaftServer`] and writes every message it receives and sends, every tick and
+/// every client request to a writer as JSON lines, so an incident can be [`replay`]ed offline.
+///
+/// Drive the node through the recorder instead of directly. Recording is best effort: the
+/// first write error stops the recording (the node keeps running) and is returned by
+/// [`flush`](Self::flush).
+pub struct Recorder<T, S> {
+    /// The node being recorded
+    server: RaftServer<T, S>,
+    /// Where the recording goes
+    writer: Box<dyn Write>,
+    /// Number of times the node was ticked
+    ticks: Ticks,
+    /// First error we hit writing the recording
+    error: Option<io::Error>,
+}
+
+impl<T, S> Recorder<T, S>
+where
+    T: Clone + Debug + Serialize,
+{
+    /// Create a node the same way [`RaftServer::new`] does and start recording it.
+    /// The seed is not optional here, a recording without one couldn't be replayed
+    pub fn new(
+        id: ServerId,
+        peers: BTreeSet<ServerId>,
+        config: RaftConfig,
+        seed: u64,
+        app: Box<dyn App<T, S>>,
+        writer: Box<dyn Write>,
+    ) -> Self {
+        let mut recorder = Recorder {
+            server: RaftServer::new(id, peers.clone(), config.clone(), Some(seed), app),
+            writer,
+            ticks: 0,
+            error: None,
+        };
+        recorder.record(RecordedEvent::Start {
+            id,
+            peers,
+            config,
+            seed,
+        });
+        recorder
+    }
+
+    /// The node being recorded
+    pub fn server(&self) -> &RaftServer<T, S> {
+        &self.server
+    }
+
+    /// [`RaftServer::tick`], recorded
+    pub fn tick(&mut self) -> Vec<SendableMessage<T>> {
+        let outgoing = self.server.tick();
+        self.ticks += 1;
+        self.record(RecordedEvent::Tick {
+            tick: self.ticks,
+            outgoing,
+        })
+    }
+
+    /// [`RaftServer::receive_rpc`], recorded
+    pub fn receive_rpc(&mut self, rpc: &RPC<T>) -> Vec<SendableMessage<T>> {
+        let outgoing = self.server.receive_rpc(rpc);
+        self.record(RecordedEvent::Receive {
+            tick: self.ticks,
+            rpc: rpc.clone(),
+            outgoing,
+        })
+    }
+
+    /// [`RaftServer::client_request`], recorded
+    pub fn client_request(&mut self, data: T) -> Result<(), RaftError> {
+        let result = self.server.client_request(data.clone());
+        self.record(RecordedEvent::ClientRequest {
+            tick: self.ticks,
+            data,
+            accepted: result.is_ok(),
+        });
+        result
+    }
+
+    /// Flush the writer, returns the error that stopped the recording if there was one
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => self.writer.flush(),
+        }
+    }
+
+    /// Write out an event and hand back the messages it carries
+    fn record(&mut self, event: RecordedEvent<T>) -> Vec<SendableMessage<T>> {
+        if self.error.is_none() {
+            if let Err(err) = write_event(&mut self.writer, &event) {
+                self.error = Some(err);
+            }
+        }
+        match event {
+            RecordedEvent::Tick { outgoing, .. } | RecordedEvent::Receive { outgoing, .. } => {
+                outgoing
+            }
+            _ => vec![],
+        }
+    }
+}
+
+fn write_event<T: Serialize>(writer: &mut dyn Write, event: &RecordedEvent<T>) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, event)?;
+    writer.write_all(b"\n")
+}
+
+/// Feed a recording back into a fresh node and check it sends exactly what the recorded
+/// node sent, and takes the same client requests. Returns the node as it is at the end of
+/// the recording, or an error pointing at the first event where the two diverge.
+///
+/// `app` should be in the state the recorded node's app started in. Trace contexts are
+/// left out of the comparison as they differ from run to run
+pub fn replay<T, S>(
+    events: impl IntoIterator<Item = RecordedEvent<T>>,
+    app: Box<dyn App<T, S>>,
+) -> Result<RaftServer<T, S>>
+where
+    T: Clone + Debug + PartialEq,
+{
+    let mut events = events.into_iter().enumerate();
+    let mut server = match events.next() {
+        Some((
+            _,
+            RecordedEvent::Start {
+                id,
+                peers,
+                config,
+                seed,
+            },
+        )) => RaftServer::new(id, peers, config, Some(seed), app),
+        _ => bail!("recording does not start with a start event"),
+    };
+
+    let mut ticks = 0;
+    for (line, event) in events {
+        // lines are numbered from 1, and the start event was line 1
+        let line = line + 1;
+        let (tick, expected, actual) = match event {
+            RecordedEvent::Start { .. } => bail!("line {}: second start event", line),
+            RecordedEvent::Tick { tick, outgoing } => {
+                ticks += 1;
+                (tick, outgoing, server.tick())
+            }
+            RecordedEvent::Receive {
+                tick,
+                rpc,
+                outgoing,
+            } => (tick, outgoing, server.receive_rpc(&rpc)),
+            RecordedEvent::ClientRequest {
+                tick,
+                data,
+                accepted,
+            } => {
+                let result = server.client_request(data);
+                if result.is_ok() != accepted {
+                    bail!(
+                        "line {} (tick {}): client request was {} when recorded but {} on replay",
+                        line,
+                        tick,
+                        verdict(accepted),
+                        verdict(result.is_ok())
+                    );
+                }
+                (tick, vec![], vec![])
+            }
+        };
+        if tick != ticks {
+            bail!(
+                "line {}: recorded at tick {} but replay is at tick {}",
+                line,
+                tick,
+                ticks
+            );
+        }
+        let (expected, actual) = (without_traces(expected), without_traces(actual));
+        if expected != actual {
+            bail!(
+                "line {} (tick {}): replay diverged\n  recorded: {:?}\n  replayed: {:?}",
+                line,
+                tick,
+                expected,
+                actual
+            );
+        }
+    }
+    Ok(server)
+}
+
+fn verdict(accepted: bool) -> &'static str {
+    if accepted {
+        "accepted"
+    } else {
+        "rejected"
+    }
+}
+
+/// Clear the trace context of every message so runs can be compared
+fn without_traces<T>(mut msgs: Vec<SendableMessage<T>>) -> Vec<SendableMessage<T>> {
+    for (_, rpc) in msgs.iter_mut() {
+        match rpc {
+            RPC::AppendRequest(req) => req.trace = None,
+            RPC::AppendResponse(res) => res.trace = None,
+            _ => {}
+        }
+    }
+    msgs
+}
+
+/// Read back a recording written by a [`Recorder`]
+pub fn read_recording<T: DeserializeOwned>(
+    reader: impl io::BufRead,
+) -> io::Result<Vec<RecordedEvent<T>>> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
@@ -3,83 +3,300 @@ use std::fmt::{Display, Formatter, Result};
 use crate::log::*;
 use crate::server::*;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Correlation id stamped on requests and echoed back in their responses,
+/// so a response can be matched up with the request it answers
+pub type RequestId = u64;
+
+/// Id a node gives every [read barrier](RaftServer::read_barrier) it starts
+pub type ReadId = u64;
+
 /// A message can be either targeted at a single server or to everyone
-pub type SendableMessage<T> = (Target, RPC<T>);
+pub type SendableMessage<T, I = ServerId> = (Target<I>, RPC<T, I>);
 
 /// Whether to send a message to everyone or just a single node
-#[derive(Eq, PartialEq, PartialOrd, Ord, Clone, Copy)]
-pub enum Target {
+#[derive(Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Target<I = ServerId> {
     /// A single server
-    Single(ServerId),
+    Single(I),
     /// To everyone
     Broadcast,
 }
 
+/// The network as seen by a node driver, e.g. [`ThreadedNode`](crate::threaded::ThreadedNode).
+/// Raft copes with messages getting lost, duplicated or reordered, so sending is fire and
+/// forget: implementations should queue the message and return rather than wait on the
+/// peer. Messages for the node itself come back in through the driver's handle.
+/// Any `FnMut(Target, RPC)` closure is a transport
+pub trait Transport<T, I = ServerId> {
+    /// Send `rpc` to `target`. A broadcast goes to every peer but not back to the sender
+    fn send(&mut self, target: Target<I>, rpc: RPC<T, I>);
+}
+
+impl<T, I, F: FnMut(Target<I>, RPC<T, I>)> Transport<T, I> for F {
+    fn send(&mut self, target: Target<I>, rpc: RPC<T, I>) {
+        self(target, rpc)
+    }
+}
+
 /// A Raft RPC request
-pub enum RPC<T> {
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum RPC<T, I = ServerId> {
     /// Candidate requesting to become leader
-    VoteRequest(VoteRequest),
+    VoteRequest(VoteRequest<I>),
     /// Response to [`VoteRequest`]
-    VoteResponse(VoteResponse),
+    VoteResponse(VoteResponse<I>),
     /// Leader heartbeat/appending entries to followers
-    AppendRequest(AppendRequest<T>),
+    AppendRequest(AppendRequest<T, I>),
     /// Response to [`AppendRequest`]
-    AppendResponse(AppendResponse),
+    AppendResponse(AppendResponse<I>),
+    /// Leader sending its snapshot to a follower that is too far behind to catch up from the log
+    SnapshotRequest(SnapshotRequest<I>),
+    /// Response to [`SnapshotRequest`]
+    SnapshotResponse(SnapshotResponse<I>),
+    /// Leader handing its leadership to a follower, see
+    /// [`RaftServer::transfer_leadership`]
+    TimeoutNow(TimeoutNow<I>),
+    /// Follower asking the leader for a commit index to read at, see
+    /// [`RaftServer::read_barrier`]
+    ReadIndexRequest(ReadIndexRequest<I>),
+    /// Response to [`ReadIndexRequest`]
+    ReadIndexResponse(ReadIndexResponse<I>),
 }
 
 /// Request by a candidate to become a Raft leader
-pub struct VoteRequest {
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct VoteRequest<I = ServerId> {
     /// Current term of candidate
     pub candidate_term: Term,
     /// ID of candidate requesting a vote
-    pub candidate_id: ServerId,
+    pub candidate_id: I,
     /// Index of candidate's last log entry
     pub candidate_last_log_idx: LogIndex,
     /// Term of candidate's last log entry
     pub candidate_last_log_term: Term,
+    /// Correlation id, stamped when the request is sent out
+    pub request_id: RequestId,
 }
 
 /// Response to a [`VoteRequest`]
-pub struct VoteResponse {
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct VoteResponse<I = ServerId> {
     /// [`current_term`](RaftServer::current_term) of server for candidate to update itself
     pub term: Term,
     /// Whether the [`VoteRequest`] was granted or not
     pub vote_granted: bool,
     /// Who sent the vote
-    pub votee_id: ServerId,
+    pub votee_id: I,
+    /// [`request_id`](VoteRequest::request_id) of the request this answers
+    pub request_id: RequestId,
 }
 
 /// Request from leader to append entries to follower's log
-#[derive(Clone)]
-pub struct AppendRequest<T> {
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct AppendRequest<T, I = ServerId> {
     /// Term of leader requesting log append
     pub leader_term: Term,
     /// ID of leader (used so follower can redirect clients)
-    pub leader_id: ServerId,
+    pub leader_id: I,
     /// Log index immediately preceding index of next element in [`entries`](Self::entries)
     pub leader_last_log_idx: LogIndex,
     /// Term of [`leader_last_log_idx`](Self::leader_last_log_idx)
     pub leader_last_log_term: Term,
     /// Leader's [`committed_len`](Log::committed_len)
     pub leader_commit: LogIndex,
-    /// A list of consecutive log entries to append to follower
-    pub entries: Vec<LogEntry<T>>,
+    /// Follower the leader is [handing leadership to](RaftServer::transfer_leadership), if
+    /// it is. Until the transfer is over, followers only vote for it
+    pub transfer_target: Option<I>,
+    /// Ticks left until the transfer times out, 0 without one. Followers go back to voting
+    /// for anyone then, even if they never hear from the leader again
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transfer_ticks_left: Ticks,
+    /// A list of consecutive log entries to append to follower, shared with other requests
+    /// carrying the same entries
+    pub entries: SharedEntries<T>,
+    /// Correlation id, stamped when the request is sent out
+    pub request_id: RequestId,
+    /// Trace context of the client request that proposed the first entry, or of the span that
+    /// sent a heartbeat. Only ever set with the `opentelemetry` feature
+    pub trace: Option<TraceContext>,
 }
 
 /// Response to an [`AppendRequest`]
-pub struct AppendResponse {
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct AppendResponse<I = ServerId> {
     /// Whether the follower added it to their log or not
     pub ok: bool,
+    /// Why the follower didn't, if it didn't
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rejection: Option<AppendRejection>,
     /// [`current_term`](RaftServer::current_term) of server for candidate to update itself
     pub term: Term,
     /// Index of the last log entry we appended to the log
     pub ack_idx: LogIndex,
+    /// How much of its log the follower's app has finished applying, see
+    /// [`Log::last_applied`](crate::log::Log::last_applied)
+    pub last_applied: LogIndex,
     /// Follower ID
-    pub follower_id: ServerId,
+    pub follower_id: I,
+    /// [`request_id`](AppendRequest::request_id) of the request this answers
+    pub request_id: RequestId,
+    /// Trace context of the span that handled the [`AppendRequest`] on the follower.
+    /// Only ever set with the `opentelemetry` feature
+    pub trace: Option<TraceContext>,
+}
+
+/// Why a follower turned down an [`AppendRequest`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum AppendRejection {
+    /// The request is from an older term than the follower's
+    StaleTerm,
+    /// The follower's log doesn't hold the entry the request's entries follow on from, so
+    /// the leader has to back off and send earlier entries
+    LogMismatch,
+    /// No correct leader sends a request like this, e.g. one whose entries' terms go down
+    /// or are newer than the leader's own term. Sending it again won't help
+    Malformed,
+}
+
+/// W3C trace context identifying the span an RPC was sent from, so the receiving node can
+/// continue the same trace
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TraceContext {
+    /// `traceparent` header value, i.e. `00-<trace id>-<span id>-<flags>`
+    pub traceparent: String,
+}
+
+/// Request from leader to replace a follower's state with the leader's snapshot.
+/// Sent instead of an [`AppendRequest`] when the entries a follower needs next
+/// have already been compacted away on the leader
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct SnapshotRequest<I = ServerId> {
+    /// Term of leader sending the snapshot
+    pub leader_term: Term,
+    /// ID of leader (used so follower can redirect clients)
+    pub leader_id: I,
+    /// The leader's snapshot
+    pub snapshot: Snapshot,
+}
+
+/// Response to a [`SnapshotRequest`]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct SnapshotResponse<I = ServerId> {
+    /// [`current_term`](RaftServer::current_term) of server for leader to update itself
+    pub term: Term,
+    /// Length of the log the follower now has in common with the leader
+    pub ack_idx: LogIndex,
+    /// Follower ID
+    pub follower_id: I,
+}
+
+/// Request from a leader for a follower to start an election right away, without waiting
+/// for its election timer. Only sent once the follower's log has caught up with the
+/// leader's, so it is sure to win
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TimeoutNow<I = ServerId> {
+    /// Term of leader handing over leadership
+    pub leader_term: Term,
+    /// ID of leader handing over leadership
+    pub leader_id: I,
+}
+
+/// Request from a node for the leader's commit index. Once the node has applied its log up
+/// to there, reads from its app are linearizable
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ReadIndexRequest<I = ServerId> {
+    /// [`current_term`](RaftServer::current_term) of the node asking
+    pub term: Term,
+    /// ID of the node asking
+    pub reader_id: I,
+    /// Which of the node's reads this is for
+    pub read_id: ReadId,
+}
+
+/// Response to a [`ReadIndexRequest`]. Only sent once the leader has heard from a quorum
+/// since the request came in, so it knows it was still leader at the time
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ReadIndexResponse<I = ServerId> {
+    /// [`current_term`](RaftServer::current_term) of the node answering
+    pub term: Term,
+    /// ID of the node answering
+    pub leader_id: I,
+    /// [`read_id`](ReadIndexRequest::read_id) of the request this answers
+    pub read_id: ReadId,
+    /// Length of the log the reader has to apply before reading. `None` if the node
+    /// couldn't vouch for its commit index: it isn't leader, or it hasn't committed an
+    /// entry of its own term yet
+    pub read_len: Option<LogIndex>,
+}
+
+impl<T, I> RPC<T, I> {
+    /// ID of the node that sent the RPC
+    pub fn sender(&self) -> &I {
+        match self {
+            RPC::VoteRequest(req) => &req.candidate_id,
+            RPC::VoteResponse(res) => &res.votee_id,
+            RPC::AppendRequest(req) => &req.leader_id,
+            RPC::AppendResponse(res) => &res.follower_id,
+            RPC::SnapshotRequest(req) => &req.leader_id,
+            RPC::SnapshotResponse(res) => &res.follower_id,
+            RPC::TimeoutNow(req) => &req.leader_id,
+            RPC::ReadIndexRequest(req) => &req.reader_id,
+            RPC::ReadIndexResponse(res) => &res.leader_id,
+        }
+    }
+}
+
+/// JSON Schema of an [`RPC`] carrying log entries of type `T` between nodes with ids of type
+/// `I`, as they are serialized with the `serde` feature. See the `miniraft-schema` binary
+#[cfg(feature = "schema")]
+pub fn schema<T: schemars::JsonSchema, I: schemars::JsonSchema>() -> schemars::Schema {
+    schemars::schema_for!(RPC<T, I>)
 }
 
 /// Display trait implementations
-impl<T> Display for RPC<T> {
+impl<T, I> Display for RPC<T, I> {
     fn fmt(&self, f: &mut Formatter) -> Result {
         write!(
             f,
@@ -89,6 +306,11 @@ impl<T> Display for RPC<T> {
                 RPC::AppendRequest(_) => "AppendRequest",
                 RPC::VoteResponse(_) => "VoteResponse",
                 RPC::AppendResponse(_) => "AppendResponse",
+                RPC::SnapshotRequest(_) => "SnapshotRequest",
+                RPC::SnapshotResponse(_) => "SnapshotResponse",
+                RPC::TimeoutNow(_) => "TimeoutNow",
+                RPC::ReadIndexRequest(_) => "ReadIndexRequest",
+                RPC::ReadIndexResponse(_) => "ReadIndexResponse",
             }
         )
     }
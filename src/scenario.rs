@@ -0,0 +1,415 @@
+use crate::{
+    log::{App, Log},
+    server::{RaftConfig, ServerId, Term, Ticks},
+    sim::{Cluster, Disk},
+};
+use anyhow::{anyhow, bail, Result};
+use std::fmt::{self, Debug};
+
+/// How many election timeouts a `wait_for_*` step waits before giving up
+const WAIT_ELECTIONS: Ticks = 10;
+
+/// Condition a [`Scenario`] waits for
+type Condition<T, S> = Box<dyn Fn(&Cluster<T, S>) -> bool>;
+
+/// Something a [`Scenario`] does to its cluster, or waits for, or checks
+enum Step<T, S> {
+    Tick(Ticks),
+    Propose(T),
+    Kill(ServerId),
+    KillLeader,
+    Revive(ServerId),
+    Restart(ServerId, Disk),
+    Snapshot(ServerId),
+    SnapshotLeader,
+    Partition(Vec<ServerId>),
+    IsolateLeader,
+    Heal,
+    WaitForLeader,
+    WaitForNewLeader,
+    AssertLogsConverge,
+    AssertRebuiltFromSnapshot(ServerId),
+    Check(&'static str, Condition<T, S>),
+}
+
+impl<T: Debug, S> fmt::Display for Step<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Tick(n) => write!(f, "tick {}", n),
+            Step::Propose(data) => write!(f, "propose {:?}", data),
+            Step::Kill(id) => write!(f, "kill {}", id),
+            Step::KillLeader => write!(f, "kill leader"),
+            Step::Revive(id) => write!(f, "revive {}", id),
+            Step::Restart(id, disk) => write!(f, "restart {} with {:?} disk", id, disk),
+            Step::Snapshot(id) => write!(f, "snapshot {}", id),
+            Step::SnapshotLeader => write!(f, "snapshot leader"),
+            Step::Partition(side) => write!(f, "partition {:?}", side),
+            Step::IsolateLeader => write!(f, "isolate leader"),
+            Step::Heal => write!(f, "heal"),
+            Step::WaitForLeader => write!(f, "wait for leader"),
+            Step::WaitForNewLeader => write!(f, "wait for new leader"),
+            Step::AssertLogsConverge => write!(f, "assert logs converge"),
+            Step::AssertRebuiltFromSnapshot(id) => {
+                write!(f, "assert {} is rebuilt from a snapshot", id)
+            }
+            Step::Check(name, _) => write!(f, "check {}", name),
+        }
+    }
+}
+
+/// A scripted failure story played out on a simulated [`Cluster`], e.g.
+///
+/// ```
+/// # use miniraft::{log::{App, LogEntry}, scenario::Scenario, server::{RaftConfig, SlowPathConfig, TieBreak}};
+/// # struct Counter(u32);
+/// # impl App<u32, u32> for Counter {
+/// #     fn transition_fn(&mut self, entry: &LogEntry<u32>) { self.0 += entry.data }
+/// #     fn get_state(&self) -> u32 { self.0 }
+/// # }
+/// # let config = RaftConfig {
+/// #     election_timeout: 10, election_timeout_jitter: 3, heartbeat_interval: 5,
+/// #     max_apply_lag: None, slow_path: SlowPathConfig::default(), leaderless_alarm: None,
+/// #     max_log_entries: None, persist_in_background: false, replication_batching: None,
+/// #     broadcast_commits: false, tie_break: TieBreak::Never,
+/// # };
+/// Scenario::new(5, config, |_| Box::new(Counter(0)))
+///     .wait_for_leader()
+///     .isolate_leader()
+///     .wait_for_new_leader()
+///     .propose(1)
+///     .heal()
+///     .assert_logs_converge()
+///     .run()
+///     .unwrap();
+/// ```
+///
+/// Steps run in order. Waits and assertions give up after a few election timeouts, and the
+/// error names the step that failed along with the seed to reproduce it with
+pub struct Scenario<T, S> {
+    /// Number of nodes in the cluster
+    nodes: usize,
+    /// Master seed of the cluster
+    seed: u64,
+    /// Config every node runs with
+    config: RaftConfig,
+    /// Creates the app of every node
+    new_app: Box<dyn FnMut(ServerId) -> Box<dyn App<T, S>>>,
+    /// What to do, in order
+    steps: Vec<Step<T, S>>,
+}
+
+impl<T, S> Scenario<T, S>
+where
+    T: Clone + Debug + 'static,
+    S: PartialEq + Debug + 'static,
+{
+    /// Start describing a scenario on a cluster of `nodes` nodes with ids `0..nodes`
+    pub fn new(
+        nodes: usize,
+        config: RaftConfig,
+        new_app: impl FnMut(ServerId) -> Box<dyn App<T, S>> + 'static,
+    ) -> Self {
+        Scenario {
+            nodes,
+            seed: 0,
+            config,
+            new_app: Box::new(new_app),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Master seed of the cluster, 0 unless set
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn step(mut self, step: Step<T, S>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Let the cluster run for `n` ticks
+    pub fn tick(self, n: Ticks) -> Self {
+        self.step(Step::Tick(n))
+    }
+
+    /// Propose `data` to the leader. Fails if there is no leader
+    pub fn propose(self, data: T) -> Self {
+        self.step(Step::Propose(data))
+    }
+
+    /// Take node `id` down
+    pub fn kill(self, id: ServerId) -> Self {
+        self.step(Step::Kill(id))
+    }
+
+    /// Take the leader down. Fails if there is no leader
+    pub fn kill_leader(self) -> Self {
+        self.step(Step::KillLeader)
+    }
+
+    /// Bring node `id` back up
+    pub fn revive(self, id: ServerId) -> Self {
+        self.step(Step::Revive(id))
+    }
+
+    /// Crash node `id` and start it again with what `disk` leaves of its state
+    pub fn restart(self, id: ServerId, disk: Disk) -> Self {
+        self.step(Step::Restart(id, disk))
+    }
+
+    /// Make node `id` snapshot its app and compact its log
+    pub fn snapshot(self, id: ServerId) -> Self {
+        self.step(Step::Snapshot(id))
+    }
+
+    /// Make the leader snapshot its app and compact its log. Fails if there is no leader
+    pub fn snapshot_leader(self) -> Self {
+        self.step(Step::SnapshotLeader)
+    }
+
+    /// Cut the nodes in `side` off from everyone else
+    pub fn partition(self, side: &[ServerId]) -> Self {
+        self.step(Step::Partition(side.to_vec()))
+    }
+
+    /// Cut the leader off from everyone else. Fails if there is no leader
+    pub fn isolate_leader(self) -> Self {
+        self.step(Step::IsolateLeader)
+    }
+
+    /// Restore every cut link
+    pub fn heal(self) -> Self {
+        self.step(Step::Heal)
+    }
+
+    /// Wait until there is a leader
+    pub fn wait_for_leader(self) -> Self {
+        self.step(Step::WaitForLeader)
+    }
+
+    /// Wait until there is a leader in a later term than the last leader the scenario saw
+    pub fn wait_for_new_leader(self) -> Self {
+        self.step(Step::WaitForNewLeader)
+    }
+
+    /// Wait until every live node has the same log, commit index and app state
+    pub fn assert_logs_converge(self) -> Self {
+        self.step(Step::AssertLogsConverge)
+    }
+
+    /// Wait until node `id` has installed a snapshot and ended up with exactly the leader's
+    /// snapshot, log entries after it, commit index and app state. Meant for after
+    /// [`restart`](Self::restart)ing a follower with a [`Disk::Wiped`], so all it has is
+    /// what InstallSnapshot and the entries trailing it gave it
+    pub fn assert_rebuilt_from_snapshot(self, id: ServerId) -> Self {
+        self.step(Step::AssertRebuiltFromSnapshot(id))
+    }
+
+    /// Wait until `cond` holds, `name` shows up in the error if it never does
+    pub fn check(
+        self,
+        name: &'static str,
+        cond: impl Fn(&Cluster<T, S>) -> bool + 'static,
+    ) -> Self {
+        self.step(Step::Check(name, Box::new(cond)))
+    }
+
+    /// Play the scenario out, returning the cluster as it was left
+    pub fn run(self) -> Result<Cluster<T, S>> {
+        let Scenario {
+            nodes,
+            seed,
+            config,
+            new_app,
+            steps,
+        } = self;
+        let wait = WAIT_ELECTIONS * (config.election_timeout + config.election_timeout_jitter);
+        let mut runner = Runner {
+            cluster: Cluster::new(nodes, seed, config, new_app),
+            last_leader_term: 0,
+            wait,
+        };
+        for (i, step) in steps.into_iter().enumerate() {
+            if let Err(err) = runner.run(&step) {
+                bail!(
+                    "step {} ({}) failed with seed {}: {}",
+                    i + 1,
+                    step,
+                    seed,
+                    err
+                );
+            }
+        }
+        Ok(runner.cluster)
+    }
+}
+
+/// A [`Scenario`] being played out
+struct Runner<T, S> {
+    /// The cluster the scenario plays out on
+    cluster: Cluster<T, S>,
+    /// Term of the last leader seen by a step
+    last_leader_term: Term,
+    /// Ticks to wait for things to happen
+    wait: Ticks,
+}
+
+impl<T, S> Runner<T, S>
+where
+    T: Clone + Debug,
+    S: PartialEq + Debug,
+{
+    fn run(&mut self, step: &Step<T, S>) -> Result<()> {
+        match step {
+            Step::Tick(n) => self.cluster.tick_by(*n),
+            Step::Propose(data) => {
+                self.cluster.client_request(data.clone())?;
+            }
+            Step::Kill(id) => self.cluster.kill(*id),
+            Step::KillLeader => {
+                let leader = self.leader()?;
+                self.cluster.kill(leader);
+            }
+            Step::Revive(id) => self.cluster.revive(*id),
+            Step::Restart(id, disk) => self.cluster.restart(*id, *disk)?,
+            Step::Snapshot(id) => self.cluster.snapshot(*id)?,
+            Step::SnapshotLeader => {
+                let leader = self.leader()?;
+                self.cluster.snapshot(leader)?;
+            }
+            Step::Partition(side) => self.cluster.partition(side),
+            Step::IsolateLeader => {
+                let leader = self.leader()?;
+                self.cluster.partition(&[leader]);
+            }
+            Step::Heal => self.cluster.heal(),
+            Step::WaitForLeader => {
+                self.wait_until(|cluster| cluster.leader().is_some())?;
+                self.leader()?;
+            }
+            Step::WaitForNewLeader => {
+                let old_term = self.last_leader_term;
+                self.wait_until(|cluster| {
+                    cluster
+                        .leader()
+                        .is_some_and(|leader| leader.current_term() > old_term)
+                })?;
+                self.leader()?;
+            }
+            Step::AssertLogsConverge => {
+                if !self.cluster.run_until(self.wait, logs_converged) {
+                    let logs: Vec<String> = self
+                        .cluster
+                        .live_nodes()
+                        .map(|node| {
+                            format!(
+                                "node {}: {} entries, {} committed, state {:?}",
+                                node.id,
+                                node.log.len(),
+                                node.log.committed_len,
+                                node.log.app.get_state()
+                            )
+                        })
+                        .collect();
+                    bail!("logs never converged: {}", logs.join("; "));
+                }
+            }
+            Step::AssertRebuiltFromSnapshot(id) => {
+                let id = *id;
+                if !self.cluster.run_until(self.wait, |cluster| {
+                    snapshot_mismatch(cluster, id).is_none()
+                }) {
+                    let mismatch = snapshot_mismatch(&self.cluster, id).unwrap_or_default();
+                    bail!("node {} never matched the leader: {}", id, mismatch);
+                }
+            }
+            Step::Check(_, cond) => self.wait_until(|cluster| cond(cluster))?,
+        }
+        Ok(())
+    }
+
+    /// The current leader, remembered for [`Step::WaitForNewLeader`]
+    fn leader(&mut self) -> Result<ServerId> {
+        let leader = self.cluster.leader().ok_or_else(|| anyhow!("no leader"))?;
+        self.last_leader_term = leader.current_term();
+        Ok(leader.id)
+    }
+
+    /// Tick until `cond` holds, failing if it doesn't within the wait
+    fn wait_until(&mut self, cond: impl Fn(&Cluster<T, S>) -> bool) -> Result<()> {
+        if !self.cluster.run_until(self.wait, cond) {
+            bail!("gave up after {} ticks", self.wait);
+        }
+        Ok(())
+    }
+}
+
+/// Whether every live node has the same log, commit index and app state
+fn logs_converged<T, S: PartialEq>(cluster: &Cluster<T, S>) -> bool
+where
+    T: Clone + Debug,
+{
+    let views: Vec<_> = cluster
+        .live_nodes()
+        .map(|node| {
+            let terms: Vec<Term> = node.log.entries.iter().map(|entry| entry.term).collect();
+            (
+                node.log.len(),
+                node.log.committed_len,
+                terms,
+                node.log.app.get_state(),
+            )
+        })
+        .collect();
+    views.windows(2).all(|pair| pair[0] == pair[1])
+}
+
+/// How node `id` differs from the leader, `None` if it installed a snapshot and has the
+/// same snapshot, log, commit index and app state as the leader
+fn snapshot_mismatch<T, S: PartialEq + Debug>(
+    cluster: &Cluster<T, S>,
+    id: ServerId,
+) -> Option<String>
+where
+    T: Clone + Debug,
+{
+    let Some(leader) = cluster.leader() else {
+        return Some("no leader".into());
+    };
+    let node = cluster.node(id);
+    let (theirs, ours) = (&node.log, &leader.log);
+    if theirs.snapshot.len == 0 {
+        return Some("no snapshot installed".into());
+    }
+    if theirs.snapshot != ours.snapshot {
+        return Some(format!(
+            "snapshot covers {} entries up to term {}, the leader's {} up to term {}",
+            theirs.snapshot.len, theirs.snapshot.term, ours.snapshot.len, ours.snapshot.term
+        ));
+    }
+    let terms =
+        |log: &Log<T, S>| -> Vec<Term> { log.entries.iter().map(|entry| entry.term).collect() };
+    if terms(theirs) != terms(ours) {
+        return Some(format!(
+            "entry terms after the snapshot are {:?}, the leader's {:?}",
+            terms(theirs),
+            terms(ours)
+        ));
+    }
+    if theirs.committed_len != ours.committed_len {
+        return Some(format!(
+            "{} entries committed, the leader {}",
+            theirs.committed_len, ours.committed_len
+        ));
+    }
+    if theirs.app.get_state() != ours.app.get_state() {
+        return Some(format!(
+            "app state {:?}, the leader's {:?}",
+            theirs.app.get_state(),
+            ours.app.get_state()
+        ));
+    }
+    None
+}
@@ -1,30 +1,83 @@
+#[cfg(debug_assertions)]
+use crate::invariants::InvariantChecker;
+#[cfg(feature = "opentelemetry")]
+use crate::otel;
 use crate::{
+    clock::{Clock, Instant},
     debug::Logger,
-    log::{App, Log, LogEntry, LogIndex},
-    rpc::{AppendRequest, AppendResponse, SendableMessage, Target, VoteRequest, VoteResponse, RPC},
+    error::{RaftError, Result},
+    event::{RaftEvent, SlowOperation},
+    history::{ElectionHistory, ElectionOutcome, ElectionRecord},
+    log::{App, Log, LogEntry, LogIndex, Snapshot},
+    metrics::{RaftMetrics, RpcLatencies},
+    observer::Observers,
+    rpc::{
+        AppendRequest, AppendResponse, ReadId, ReadIndexRequest, ReadIndexResponse, RequestId,
+        SendableMessage, SnapshotRequest, SnapshotResponse, Target, TimeoutNow, TraceContext,
+        VoteRequest, VoteResponse, RPC,
+    },
+    status::{CatchUpProgress, DebugDump, DumpedEntry, RaftStatus, Role, SnapshotTransfer},
+    storage::Storage,
 };
-use anyhow::{bail, Result};
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use rand_core::SeedableRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::{
+    cmp::{max, min},
     collections::{BTreeMap, BTreeSet},
-    fmt::Debug,
-    ops::Div,
+    fmt::{Debug, Display},
+    hash::Hash,
+    time::Duration,
     vec,
 };
 
+/// Module containing what a follower does and the state it keeps
+mod follower;
+
+/// Module containing what a candidate does and the state it keeps
+mod candidate;
+
+/// Module containing what a leader does and the state it keeps
+mod leader;
+
+use candidate::Candidate;
+pub use candidate::CandidateState;
+use follower::Follower;
+pub use follower::FollowerState;
+use leader::Leader;
+pub use leader::LeaderState;
+
 /// Type alias for Raft leadership term
 pub type Term = u64;
 
-/// Type alias for the ID of a single Raft server
+/// Type alias for the ID of a single Raft server, the [`NodeId`] used unless told otherwise
 pub type ServerId = usize;
 
+/// Anything Raft servers can be identified by. Dense [`ServerId`] indexes by default, but
+/// deployments can use e.g. UUIDs or hostnames instead by picking a different type for the
+/// `I` parameter of [`RaftServer`], its RPCs and its status
+pub trait NodeId: Clone + Ord + Hash + Debug + Display {}
+
+impl<I: Clone + Ord + Hash + Debug + Display> NodeId for I {}
+
+/// How many unanswered requests we keep timing before forgetting the oldest.
+/// Requests lost by the network are never answered so this has to be bounded
+const MAX_PENDING_REQUESTS: usize = 1024;
+
+/// How many RPCs a [paused](RaftServer::pause) node holds on to. Any more are dropped, as if
+/// the network had lost them
+pub const MAX_PAUSED_RPCS: usize = 1024;
+
 /// Type alias for a unit of logical time
-type Ticks = u32;
+pub type Ticks = u32;
 
-/// Configuration options for a Raft server
-#[derive(Clone)]
+/// Configuration options for a Raft server.
+/// With the `serde` feature it can be kept in a file, see
+/// [`storage::load_config`](crate::storage::load_config)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RaftConfig {
     /// How long a server should wait for a message from
     /// current leader before giving up and starting an election
@@ -36,47 +89,405 @@ pub struct RaftConfig {
     /// How often a leader should send empty 'heartbeat' AppendEntry RPC
     /// calls to maintain power. Generally one magnitude smaller than [`election_timeout`](Self::election_timeout)
     pub heartbeat_interval: Ticks,
+
+    /// How many committed entries the [`App`] is allowed to fall behind by before a leader
+    /// stops accepting new client requests. `None` means proposals are never rejected for
+    /// being too far ahead of the app
+    pub max_apply_lag: Option<LogIndex>,
+
+    /// Thresholds past which work is reported as slow
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub slow_path: SlowPathConfig,
+
+    /// Number of [`election_timeout`](Self::election_timeout)s a node may go without
+    /// hearing from a leader before it raises a [`RaftEvent::LeaderlessAlarm`]. Elections
+    /// failing over and over point at split votes, a partition or bad timeouts.
+    /// `None` disables the alarm
+    pub leaderless_alarm: Option<u32>,
+
+    /// How many entries the log may hold in memory before the node snapshots the app and
+    /// drops the applied ones, as if the app had asked through [`App::wants_snapshot`].
+    /// Followers that fall behind the snapshot are sent it instead of the entries, unless
+    /// the node [spills](RaftServer::spill_to) dropped entries to storage, so a leader
+    /// doesn't have to keep its whole log around through a long follower outage.
+    /// Entries that aren't applied yet can't be dropped and always stay. Needs an app that
+    /// supports snapshots; if snapshotting fails, it is only tried again once the log has
+    /// grown by another `max_log_entries`. `None` leaves compacting the log to the app
+    pub max_log_entries: Option<LogIndex>,
+
+    /// Whether the embedder writes new entries to disk in the background rather than
+    /// before anything the node sent goes out. A leader then sends entries to followers
+    /// while its own write is still going on, and only counts itself towards a quorum for
+    /// what [`log_persisted`](RaftServer::log_persisted) says is on disk. Followers still
+    /// have to persist entries before their response goes out
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub persist_in_background: bool,
+
+    /// When a leader sends newly proposed entries to its followers. `None` leaves them for
+    /// the next heartbeat
+    pub replication_batching: Option<ReplicationBatching>,
+
+    /// Whether a leader tells its followers about entries it commits right away, instead of
+    /// with the next heartbeat or append. Followers then apply them, and can serve
+    /// [reads](RaftServer::read_barrier) of them, sooner, at the cost of an extra round of
+    /// requests for every commit
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub broadcast_commits: bool,
+
+    /// What a candidate does when another candidate of its own term asks for its vote.
+    /// Stepping aside for one of them lets it win the next election instead of the two
+    /// splitting the vote again, which small clusters are prone to
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tie_break: TieBreak,
+}
+
+/// Sends proposals to followers sooner than the next heartbeat, in batches of whatever came
+/// in over a short delay. A longer delay means fewer and bigger requests at the cost of
+/// latency. The leader's heartbeat timer restarts after every batch
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReplicationBatching {
+    /// Ticks to wait after a proposal for more to batch with it
+    pub delay: Ticks,
+    /// Proposals to batch at most, once there are this many they go out on the next tick
+    pub max_entries: LogIndex,
+}
+
+/// Which of two candidates of the same term steps aside, see [`RaftConfig::tie_break`].
+/// Votes already given are kept either way, the candidate stepping aside only stays out of
+/// the next election so the other one can win it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TieBreak {
+    /// Neither, the election timers sort it out
+    #[default]
+    Never,
+    /// The one with the higher id
+    LowerId,
+    /// The one whose log is behind, or the one with the higher id if the logs are as far
+    /// along
+    LongerLog,
+}
+
+/// How long work is allowed to take before it gets reported through a warning and a
+/// [`RaftEvent::SlowPath`]. Slow apply/persist/ticks are the usual cause of missed heartbeats
+/// and the election storms that follow. `None` disables the check
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SlowPathConfig {
+    /// Per entry handed to the app
+    pub apply: Option<Duration>,
+    /// For snapshotting the app or restoring it from a snapshot
+    pub persist: Option<Duration>,
+    /// For a single call to [`tick`](RaftServer::tick)
+    pub tick: Option<Duration>,
+}
+
+impl RaftConfig {
+    /// Set up a config step by step, see [`RaftConfigBuilder`]
+    pub fn builder() -> RaftConfigBuilder {
+        RaftConfigBuilder {
+            config: RaftConfig {
+                election_timeout: 10,
+                election_timeout_jitter: 3,
+                heartbeat_interval: 5,
+                max_apply_lag: None,
+                slow_path: SlowPathConfig::default(),
+                leaderless_alarm: None,
+                max_log_entries: None,
+                persist_in_background: false,
+                replication_batching: None,
+                broadcast_commits: false,
+                tie_break: TieBreak::Never,
+            },
+        }
+    }
+
+    /// Config for a node driven by a [`WallClock`](crate::clock::WallClock) ticking every
+    /// `tick`, with timings in wall time instead of ticks. They are rounded to whole ticks,
+    /// the election timeout up and the heartbeat interval down, and the election timeout gets
+    /// a third of itself as jitter either way. Everything else is as in
+    /// [`builder`](Self::builder), whose checks this goes through too
+    pub fn from_durations(
+        election_timeout: Duration,
+        heartbeat_interval: Duration,
+        tick: Duration,
+    ) -> Result<RaftConfig> {
+        if tick.is_zero() {
+            return Err(RaftError::InvalidConfig(
+                "tick must be longer than 0".into(),
+            ));
+        }
+        let to_ticks = |duration: Duration, round_up: bool| {
+            let (nanos, tick_nanos) = (duration.as_nanos(), tick.as_nanos());
+            let ticks = if round_up {
+                nanos.div_ceil(tick_nanos)
+            } else {
+                nanos / tick_nanos
+            };
+            Ticks::try_from(ticks).map_err(|_| {
+                RaftError::InvalidConfig(format!(
+                    "{:?} is more than {} ticks of {:?}",
+                    duration,
+                    Ticks::MAX,
+                    tick
+                ))
+            })
+        };
+        let election_timeout = to_ticks(election_timeout, true)?;
+        RaftConfig::builder()
+            .election_timeout(election_timeout)
+            .election_timeout_jitter(election_timeout / 3)
+            .heartbeat_interval(to_ticks(heartbeat_interval, false)?)
+            .build()
+    }
+
+    /// Check the timing makes sense: timeouts are non-zero, the jitter never makes the
+    /// election timeout drop to 0 and a leader heartbeats before any follower can time out
+    pub fn validate(&self) -> Result<()> {
+        self.check()
+    }
+
+    /// [`validate`](Self::validate) for nodes with any kind of id. Config errors don't
+    /// mention node ids, so they are the same whatever `I` is
+    pub(crate) fn check<I>(&self) -> Result<(), I> {
+        let invalid = |msg: String| Err(RaftError::InvalidConfig(msg));
+        if self.election_timeout == 0 {
+            return invalid("election_timeout must be at least 1 tick".into());
+        }
+        if self.heartbeat_interval == 0 {
+            return invalid("heartbeat_interval must be at least 1 tick".into());
+        }
+        if self.election_timeout_jitter >= self.election_timeout {
+            return invalid(format!(
+                "election_timeout_jitter ({}) must be smaller than election_timeout ({})",
+                self.election_timeout_jitter, self.election_timeout
+            ));
+        }
+        let shortest_timeout = self.election_timeout - self.election_timeout_jitter;
+        if self.heartbeat_interval >= shortest_timeout {
+            return invalid(format!(
+                "heartbeat_interval ({}) must be smaller than the shortest election timeout ({}), \
+                 or followers start elections while the leader is fine",
+                self.heartbeat_interval, shortest_timeout
+            ));
+        }
+        if self.max_apply_lag == Some(0) {
+            return invalid("max_apply_lag of 0 rejects every client request".into());
+        }
+        if self.leaderless_alarm == Some(0) {
+            return invalid("leaderless_alarm must be at least 1 election timeout".into());
+        }
+        if let Some(batching) = &self.replication_batching {
+            if batching.delay == 0 {
+                return invalid("replication_batching delay must be at least 1 tick".into());
+            }
+            if batching.max_entries == 0 {
+                return invalid("replication_batching max_entries must be at least 1".into());
+            }
+        }
+        if self.max_log_entries == Some(0) {
+            return invalid("max_log_entries of 0 snapshots after every entry".into());
+        }
+        Ok(())
+    }
+}
+
+/// Sets up a [`RaftConfig`] that is checked to make sense, see [`RaftConfig::builder`].
+/// Starts out with a 10 tick election timeout with 3 ticks of jitter, a 5 tick heartbeat
+/// and everything optional turned off
+#[derive(Clone, Debug)]
+pub struct RaftConfigBuilder {
+    config: RaftConfig,
+}
+
+impl RaftConfigBuilder {
+    /// See [`RaftConfig::election_timeout`]
+    pub fn election_timeout(mut self, ticks: Ticks) -> Self {
+        self.config.election_timeout = ticks;
+        self
+    }
+
+    /// See [`RaftConfig::election_timeout_jitter`]
+    pub fn election_timeout_jitter(mut self, ticks: Ticks) -> Self {
+        self.config.election_timeout_jitter = ticks;
+        self
+    }
+
+    /// See [`RaftConfig::heartbeat_interval`]
+    pub fn heartbeat_interval(mut self, ticks: Ticks) -> Self {
+        self.config.heartbeat_interval = ticks;
+        self
+    }
+
+    /// See [`RaftConfig::max_apply_lag`]
+    pub fn max_apply_lag(mut self, entries: LogIndex) -> Self {
+        self.config.max_apply_lag = Some(entries);
+        self
+    }
+
+    /// See [`RaftConfig::slow_path`]
+    pub fn slow_path(mut self, slow_path: SlowPathConfig) -> Self {
+        self.config.slow_path = slow_path;
+        self
+    }
+
+    /// See [`RaftConfig::leaderless_alarm`]
+    pub fn leaderless_alarm(mut self, election_timeouts: u32) -> Self {
+        self.config.leaderless_alarm = Some(election_timeouts);
+        self
+    }
+
+    /// See [`RaftConfig::max_log_entries`]
+    pub fn max_log_entries(mut self, entries: LogIndex) -> Self {
+        self.config.max_log_entries = Some(entries);
+        self
+    }
+
+    /// See [`RaftConfig::persist_in_background`]
+    pub fn persist_in_background(mut self, background: bool) -> Self {
+        self.config.persist_in_background = background;
+        self
+    }
+
+    /// See [`RaftConfig::replication_batching`]
+    pub fn replication_batching(mut self, delay: Ticks, max_entries: LogIndex) -> Self {
+        self.config.replication_batching = Some(ReplicationBatching { delay, max_entries });
+        self
+    }
+
+    /// See [`RaftConfig::broadcast_commits`]
+    pub fn broadcast_commits(mut self, broadcast: bool) -> Self {
+        self.config.broadcast_commits = broadcast;
+        self
+    }
+
+    /// See [`RaftConfig::tie_break`]
+    pub fn tie_break(mut self, policy: TieBreak) -> Self {
+        self.config.tie_break = policy;
+        self
+    }
+
+    /// Create the config, or explain what is wrong with it, see [`RaftConfig::validate`]
+    pub fn build(self) -> Result<RaftConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Everything a Raft node has to keep on stable storage to survive a restart.
+/// The rest of its state (role, commit index, follower progress) is volatile and rebuilt
+/// from talking to the cluster, see [`RaftServer::recover`]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PersistentState<T, I = ServerId> {
+    /// Latest term the node has seen
+    pub current_term: Term,
+    /// Who the node voted for in [`current_term`](Self::current_term)
+    pub voted_for: Option<I>,
+    /// Latest snapshot, the baseline of the log
+    pub snapshot: Snapshot,
+    /// Log entries after the snapshot
+    pub entries: Vec<LogEntry<T>>,
 }
 
 /// Possible states a Raft Node can be in
-pub enum RaftLeadershipState {
+pub enum RaftLeadershipState<I = ServerId> {
     /// Issues no requests but responds to requests from leaders and candidates.
     /// All Raft Nodes start in Follower state
-    Follower(FollowerState),
+    Follower(FollowerState<I>),
 
     /// Used to elect a new leader.
-    Candidate(CandidateState),
+    Candidate(CandidateState<I>),
 
     /// Handles all client requests.
-    Leader(LeaderState),
+    Leader(LeaderState<I>),
 }
 
-/// [`Follower`](RaftLeadershipState::Follower) specific volatile state
-pub struct FollowerState {
-    /// Ticks left to start an election if not reset by activity/heartbeat
-    election_time: Ticks,
-    /// Current leader node is following
-    leader: Option<ServerId>,
-}
+/// What a node does that depends on the role it is in. Each role implements this in a module
+/// of its own, next to the state it keeps. The node deals with whatever all roles have in
+/// common (e.g. catching up on a newer term) and then hands over to the role it is in
+trait RoleBehavior<T, S, I>
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    /// The role's timer ran out: the election timer for followers and candidates, the
+    /// heartbeat timer for leaders
+    fn timer_ran_out(&self, server: &mut RaftServer<T, S, I>) -> Vec<SendableMessage<T, I>>;
+
+    /// An append request from a leader of our term or an older one. `election_time` is a
+    /// freshly picked election timeout, for if we end up following the leader.
+    /// Anyone but a follower steps down for a leader of their own term and tries again
+    fn append_request(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        req: &AppendRequest<T, I>,
+        _election_time: Ticks,
+    ) -> Vec<SendableMessage<T, I>> {
+        Logger::append_conflict_check(server, req);
+        if req.leader_term == server.current_term {
+            // if leader is in same term as us, they have recovered from
+            // failure and we can go back to follower and try the request again
+            server.reset_to_follower(req.leader_term);
+            server.rpc_append_request(req)
+        } else {
+            // otherwise just do nothing, only followers should respond to
+            // append_request RPCs
+            vec![]
+        }
+    }
 
-/// [`Candidate`](RaftLeadershipState::Candidate) specific volatile state
-pub struct CandidateState {
-    /// Ticks left to start an election if quorum is not reached
-    election_time: Ticks,
-    /// Set of all nodes this node has received votes for
-    votes_received: BTreeSet<ServerId>,
-}
+    /// A vote from a peer in our term or an older one, only candidates count them
+    fn vote_response(
+        &self,
+        _server: &mut RaftServer<T, S, I>,
+        _res: &VoteResponse<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        vec![]
+    }
 
-/// [`Leader`](RaftLeadershipState::Leader) specific volatile state
-pub struct LeaderState {
-    /// Track state about followers to figure out what to send them next
-    followers: BTreeMap<ServerId, NodeReplicationState>,
-    /// Ticks left till when to send the next heartbeat
-    heartbeat_timeout: Ticks,
+    /// A follower's answer to an append request, only leaders send those
+    fn append_response(
+        &self,
+        _server: &mut RaftServer<T, S, I>,
+        _res: &AppendResponse<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        vec![]
+    }
+
+    /// A follower's answer to a snapshot request, only leaders send those
+    fn snapshot_response(
+        &self,
+        _server: &mut RaftServer<T, S, I>,
+        _res: &SnapshotResponse<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        vec![]
+    }
+
+    /// The leader asking us to take over, only followers do
+    fn timeout_now(
+        &self,
+        _server: &mut RaftServer<T, S, I>,
+        _req: &TimeoutNow<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        vec![]
+    }
+
+    /// A node asking for our commit index to read at. Only a leader can vouch for its own,
+    /// anyone else refuses
+    fn read_index_request(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        req: &ReadIndexRequest<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        server.answer_read(req.reader_id.clone(), req.read_id, None)
+    }
 }
 
 /// State of a single Node as tracked by a leader
-#[derive(Default)]
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodeReplicationState {
     /// Index of next log entry to send to that server.
     /// Initialized to leader's last log index + 1
@@ -85,15 +496,67 @@ pub struct NodeReplicationState {
     /// Index of highest log entry known to be replicated on server.
     /// Initialized to 0, increases monotonically
     pub acked_up_to: LogIndex,
+
+    /// How much of its log the server has applied, as of its last response.
+    /// Trails `acked_up_to` when the server's app is slow rather than the network
+    pub applied_up_to: LogIndex,
+
+    /// Tick (counted from when this node started) at which the server last responded
+    /// to us, `None` if it hasn't responded since we became leader
+    pub last_response_tick: Option<Ticks>,
+
+    /// Number of append/snapshot requests sent to the server that haven't been answered yet.
+    /// Requests lost by the network are never answered, so this keeps growing for a
+    /// follower that is unreachable
+    pub inflight: usize,
+
+    /// Tick the server was first seen behind our log, and how much it had acked at that
+    /// point. `None` once it has caught up
+    pub catching_up_since: Option<(Ticks, LogIndex)>,
+
+    /// Snapshot we sent the server that it hasn't confirmed yet
+    pub snapshot_in_flight: Option<SnapshotTransfer>,
+}
+
+impl NodeReplicationState {
+    /// Start or stop tracking catch-up after sending to or hearing back from the server
+    fn update_catch_up(&mut self, log_len: LogIndex, now: Ticks) {
+        if self.acked_up_to >= log_len {
+            self.catching_up_since = None;
+        } else if self.catching_up_since.is_none() {
+            self.catching_up_since = Some((now, self.acked_up_to));
+        }
+    }
+
+    /// How the server is doing catching up, `None` if it isn't behind
+    fn catch_up_progress(&self, log_len: LogIndex, now: Ticks) -> Option<CatchUpProgress> {
+        let (since, acked_then) = match self.catching_up_since {
+            Some(started) => started,
+            None if self.snapshot_in_flight.is_some() => (now, self.acked_up_to),
+            None => return None,
+        };
+        let remaining = log_len.saturating_sub(self.acked_up_to);
+        let transferred = self.acked_up_to.saturating_sub(acked_then);
+        let elapsed = now - since;
+        let estimated_ticks_remaining = (transferred > 0 && elapsed > 0)
+            .then(|| (remaining as u64 * elapsed as u64 / transferred as u64) as Ticks);
+        Some(CatchUpProgress {
+            remaining,
+            transferred,
+            elapsed,
+            estimated_ticks_remaining,
+            snapshot: self.snapshot_in_flight.clone(),
+        })
+    }
 }
 
 /// A Raft server that replicates Logs of type `T`
-pub struct RaftServer<T, S> {
+pub struct RaftServer<T, S, I = ServerId> {
     // Static State
     /// ID of this node
-    pub id: ServerId,
+    pub id: I,
     /// All other servers in this Raft cluster
-    peers: BTreeSet<ServerId>,
+    peers: BTreeSet<I>,
     /// Config of this node
     config: RaftConfig,
 
@@ -103,30 +566,207 @@ pub struct RaftServer<T, S> {
     /// Current term of this node
     pub current_term: Term,
     /// Candidate node that we voted for this election
-    voted_for: Option<ServerId>,
+    voted_for: Option<I>,
     /// List of log entries for this node.
     /// This is the data that is being replicated
     pub log: Log<T, S>,
 
     /// State of the node that depends on its leadership status
     /// (one of [`FollowerState`], [`CandidateState`], or [`LeaderState`])
-    leadership_state: RaftLeadershipState,
+    leadership_state: RaftLeadershipState<I>,
 
     /// Internal seeded random number generator
     rng: ChaCha8Rng,
+
+    /// Counters exposed through [`RaftServer::metrics`]
+    metrics: RaftMetrics,
+
+    /// Number of times [`tick`](Self::tick) has been called
+    ticks: Ticks,
+
+    /// Recent elections this node stood in
+    election_history: ElectionHistory<I>,
+
+    /// Last leader we heard from (or ourselves if we were leader), to spot leadership changes
+    last_known_leader: Option<I>,
+    /// Tick we last heard from a leader other than ourselves
+    last_leader_contact: Option<Ticks>,
+    /// Tick we became leader at, while we are leader
+    leader_since: Option<Ticks>,
+    /// Whether the [`leaderless_alarm`](RaftConfig::leaderless_alarm) is currently raised
+    leaderless: bool,
+    /// Whether the app asked for a snapshot that hasn't been taken yet. Asking only
+    /// returns `true` once, so it is kept here when it comes up outside of a tick
+    snapshot_wanted: bool,
+    /// Length of the log when snapshotting it for being
+    /// [too long](RaftConfig::max_log_entries) last failed. There is no point trying
+    /// again every tick, so we wait for the log to grow by another `max_log_entries`
+    snapshot_failed_at: Option<LogIndex>,
+
+    /// Correlation id for the next request we send
+    next_request_id: RequestId,
+    /// When every request still waiting on a response was sent, and who to
+    pending_requests: BTreeMap<(RequestId, I), Instant>,
+    /// Round trip times of answered requests
+    rpc_latencies: RpcLatencies<I>,
+
+    /// Read barriers we started that haven't been picked up with
+    /// [`read_ready`](Self::read_ready) yet
+    reads: BTreeMap<ReadId, ReadProgress>,
+    /// Id for the next read barrier we start
+    next_read_id: ReadId,
+
+    /// Trace context of the client request behind every entry we proposed as leader
+    /// that hasn't been committed yet
+    #[cfg(feature = "opentelemetry")]
+    proposal_traces: BTreeMap<LogIndex, TraceContext>,
+
+    /// RPCs that came in while [paused](Self::pause), oldest first. `None` unless paused
+    paused: Option<Vec<RPC<T, I>>>,
+    /// [Quarantined](Self::quarantine) peers, with the tick each is let back in at
+    quarantined: BTreeMap<I, Ticks>,
+    /// Where entries go when they are compacted out of memory, see [`spill_to`](Self::spill_to)
+    spill: Option<Box<dyn Storage<T, I>>>,
+
+    /// Callbacks to fire on role/term changes
+    pub observers: Observers<I>,
+
+    /// What we've seen of our own state so far, to catch safety violations
+    #[cfg(debug_assertions)]
+    invariants: InvariantChecker<I>,
+}
+
+/// How far along a [read barrier](RaftServer::read_barrier) is
+enum ReadProgress {
+    /// Waiting for the leader to confirm its commit index
+    Unconfirmed,
+    /// Confirmed, the app can be read once the log is applied up to this length
+    At(LogIndex),
+    /// The leader couldn't confirm it
+    Refused,
+}
+
+/// Sets up a [`RaftServer`], see [`RaftServer::builder`]. Only the [`id`](Self::id) and the
+/// [`app`](Self::app) have to be given, everything else has a default: no peers, so a
+/// single-node cluster, the same config as [`RaftConfig::builder`], a seed drawn from
+/// system entropy, and a fresh node with nothing on stable storage
+pub struct RaftServerBuilder<T, S, I = ServerId> {
+    /// ID of the node
+    id: Option<I>,
+    /// Every other node in the cluster
+    peers: BTreeSet<I>,
+    /// Config of the node
+    config: RaftConfig,
+    /// Seed for the node's randomness
+    seed: Option<u64>,
+    /// App the node runs over its log
+    app: Option<Box<dyn App<T, S>>>,
+    /// What an earlier incarnation of the node left on stable storage
+    storage: Option<PersistentState<T, I>>,
+    /// Where the node spills compacted entries to
+    spill: Option<Box<dyn Storage<T, I>>>,
+}
+
+impl<T, S, I> RaftServerBuilder<T, S, I>
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    /// ID of the node. The caller is responsible for it being unique in the cluster
+    pub fn id(mut self, id: I) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Every other node in the cluster
+    pub fn peers(mut self, peers: impl IntoIterator<Item = I>) -> Self {
+        self.peers = peers.into_iter().collect();
+        self
+    }
+
+    /// Config of the node, checked when the node is [built](Self::build)
+    pub fn config(mut self, config: RaftConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Seed for the node's randomness, so elections play out the same every run
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// [`App`] the node runs over the log to arrive at a state
+    pub fn app(mut self, app: impl App<T, S> + 'static) -> Self {
+        self.app = Some(Box::new(app));
+        self
+    }
+
+    /// Start from what an earlier incarnation of the node left on stable storage, e.g.
+    /// loaded with [`load_state`](crate::storage::load_state), see [`RaftServer::recover`]
+    pub fn storage(mut self, state: PersistentState<T, I>) -> Self {
+        self.storage = Some(state);
+        self
+    }
+
+    /// See [`RaftServer::spill_to`]
+    pub fn spill_to(mut self, storage: impl Storage<T, I> + 'static) -> Self {
+        self.spill = Some(Box::new(storage));
+        self
+    }
+
+    /// Create the node. Fails without an id or an app, if the peers include the node
+    /// itself, if the config can't work, or on storage [`recover`](RaftServer::recover)
+    /// rejects
+    pub fn build(self) -> Result<RaftServer<T, S, I>, I> {
+        let invalid = |what: &str| Err(RaftError::InvalidRequest(what.to_owned()));
+        let Some(id) = self.id else {
+            return invalid("a node needs an id, see RaftServerBuilder::id");
+        };
+        let Some(app) = self.app else {
+            return invalid("a node needs an app, see RaftServerBuilder::app");
+        };
+        if self.peers.contains(&id) {
+            return invalid("a node can't be its own peer");
+        }
+        self.config.check()?;
+        let mut server = match self.storage {
+            Some(state) => RaftServer::recover(id, self.peers, self.config, self.seed, app, state)?,
+            None => RaftServer::new(id, self.peers, self.config, self.seed, app),
+        };
+        server.spill = self.spill;
+        Ok(server)
+    }
 }
 
-impl<T, S> RaftServer<T, S>
+impl<T, S, I> RaftServer<T, S, I>
 where
     T: Clone + Debug,
+    I: NodeId,
 {
+    /// Set up a node step by step, see [`RaftServerBuilder`]. Unlike [`new`](Self::new),
+    /// checks that the config makes sense
+    pub fn builder() -> RaftServerBuilder<T, S, I> {
+        RaftServerBuilder {
+            id: None,
+            peers: BTreeSet::new(),
+            config: RaftConfig::builder().config,
+            seed: None,
+            app: None,
+            storage: None,
+            spill: None,
+        }
+    }
+
     /// Create a new Raft node with a given ID. Caller is responsible for
     /// ensuring it is unique.
     /// Initialize with all peers in the cluster along with an [`App`] that runs over
     /// the event log to arrive at a state.
+    /// Empty `peers` make a single-node cluster, which elects itself on its first tick
+    /// and commits entries as soon as they are proposed.
     pub fn new(
-        id: ServerId,
-        peers: BTreeSet<ServerId>,
+        id: I,
+        peers: BTreeSet<I>,
         config: RaftConfig,
         seed: Option<u64>,
         app: Box<dyn App<T, S>>,
@@ -141,23 +781,131 @@ where
             config.election_timeout,
             config.election_timeout_jitter,
         );
+        // a node without peers has nobody to hear from and nobody to split the vote with,
+        // so it elects itself on its first tick instead of waiting out a timeout
+        let election_time = if peers.is_empty() {
+            1
+        } else {
+            random_election_time
+        };
+        let log = Log::new(&id, app);
         let server = RaftServer {
             id,
             peers,
             config,
             current_term: 0,
             voted_for: None,
-            log: Log::new(id, app),
+            log,
             rng,
+            metrics: RaftMetrics::default(),
+            ticks: 0,
+            election_history: ElectionHistory::default(),
+            last_known_leader: None,
+            last_leader_contact: None,
+            leader_since: None,
+            leaderless: false,
+            snapshot_wanted: false,
+            snapshot_failed_at: None,
+            next_request_id: 0,
+            pending_requests: BTreeMap::new(),
+            rpc_latencies: RpcLatencies::default(),
+            reads: BTreeMap::new(),
+            next_read_id: 0,
+            #[cfg(feature = "opentelemetry")]
+            proposal_traces: BTreeMap::new(),
+            paused: None,
+            quarantined: BTreeMap::new(),
+            spill: None,
+            observers: Observers::default(),
+            #[cfg(debug_assertions)]
+            invariants: InvariantChecker::default(),
             leadership_state: RaftLeadershipState::Follower(FollowerState {
                 leader: None,
-                election_time: random_election_time,
+                election_time,
+                transfer_target: None,
+                transfer_deadline: 0,
             }),
         };
         Logger::server_init(&server);
         server
     }
 
+    /// Create a Raft node that starts from a snapshot, e.g. one loaded with
+    /// [`load_snapshot`](crate::storage::load_snapshot) after a restart.
+    /// The snapshot is restored into the app before the node ever sees an RPC, and acts as
+    /// the baseline of the log for elections and replication.
+    pub fn from_snapshot(
+        id: I,
+        peers: BTreeSet<I>,
+        config: RaftConfig,
+        seed: Option<u64>,
+        app: Box<dyn App<T, S>>,
+        snapshot: Snapshot,
+    ) -> Result<Self, I> {
+        let mut server = Self::new(id, peers, config, seed, app);
+        // a node should never be in a term older than the last entry in its log
+        server.current_term = snapshot.term;
+        server.log.install_snapshot(snapshot)?;
+        Ok(server)
+    }
+
+    /// Create a Raft node from the state an earlier incarnation of it left on stable storage.
+    /// Starts out as a follower with nothing committed past the snapshot, the leader will
+    /// tell it how much of the log is committed.
+    /// Fails on state no node could have persisted, e.g. a vote for an unknown peer or
+    /// entries from a term newer than the persisted one
+    pub fn recover(
+        id: I,
+        peers: BTreeSet<I>,
+        config: RaftConfig,
+        seed: Option<u64>,
+        app: Box<dyn App<T, S>>,
+        state: PersistentState<T, I>,
+    ) -> Result<Self, I> {
+        if let Some(candidate) = &state.voted_for {
+            if *candidate != id && !peers.contains(candidate) {
+                return Err(RaftError::UnknownPeer(candidate.clone()));
+            }
+        }
+        let mut last_term = state.snapshot.term;
+        for (i, entry) in state.entries.iter().enumerate() {
+            if entry.term < last_term {
+                return Err(RaftError::InvalidRequest(format!(
+                    "entry {} is from term {}, after an entry from term {}",
+                    state.snapshot.len + i,
+                    entry.term,
+                    last_term
+                )));
+            }
+            last_term = entry.term;
+        }
+        let mut server = Self::from_snapshot(id, peers, config, seed, app, state.snapshot)?;
+        server.current_term = max(server.current_term, state.current_term);
+        // terms only ever go up, so we must have seen the term of every entry in our log
+        if last_term > server.current_term {
+            return Err(RaftError::StaleTerm {
+                term: server.current_term,
+                seen: last_term,
+            });
+        }
+        server.voted_for = state.voted_for;
+        server.log.entries = state.entries;
+        // it was read back from disk
+        server.log.persisted_len = server.log.len();
+        Ok(server)
+    }
+
+    /// State the node would have to write to stable storage before answering any RPC,
+    /// to be handed to [`recover`](Self::recover) after a restart
+    pub fn persistent_state(&self) -> PersistentState<T, I> {
+        PersistentState {
+            current_term: self.current_term,
+            voted_for: self.voted_for.clone(),
+            snapshot: self.log.snapshot.clone(),
+            entries: self.log.entries.clone(),
+        }
+    }
+
     /// Helper function to generate a random election time given current configuration
     fn random_election_time(&mut self) -> Ticks {
         rng_jitter(
@@ -168,56 +916,157 @@ where
     }
 
     /// Tick state and perform necessary state transitions/RPC calls
-    pub fn tick(&mut self) -> Vec<SendableMessage<T>> {
-        use RaftLeadershipState::*;
-        match &mut self.leadership_state {
-            Follower(FollowerState { election_time, .. })
-            | Candidate(CandidateState { election_time, .. }) => {
-                *election_time = election_time.saturating_sub(1);
-
-                // suspect leader has failed, election timeout reached
-                // attempt to become candidate
-                if *election_time == 0 {
-                    self.current_term += 1;
-                    Logger::election_timer_expired(&self);
-
-                    // vote for self
-                    self.voted_for = Some(self.id);
-                    let mut vote_list = BTreeSet::new();
-                    vote_list.insert(self.id);
-
-                    // see if we can instantly become leader
-                    // (if cluster size is 1)
-                    if 1 == self.quorum_size() {
-                        return self.promote_to_leader(BTreeMap::new());
-                    }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(id = %self.id, term = self.current_term))
+    )]
+    pub fn tick(&mut self) -> Vec<SendableMessage<T, I>> {
+        if self.paused.is_some() {
+            return vec![];
+        }
+        let started = Instant::now();
+        let mut msgs = self.tick_timers();
+        self.track_outgoing(&mut msgs);
+        #[cfg(feature = "tokio")]
+        self.publish_leadership();
+        self.check_slow(SlowOperation::Tick, started.elapsed());
+        self.check_leaderless();
+        #[cfg(debug_assertions)]
+        self.check_invariants(format_args!("tick"));
+        msgs
+    }
 
-                    // otherwise, become candidate as normal
-                    self.leadership_state = Candidate(CandidateState {
-                        election_time: self.random_election_time(),
-                        votes_received: vote_list,
-                    });
-                    Logger::state_update(&self);
-
-                    // broadcast message to all nodes asking for a vote
-                    let rpc = RPC::VoteRequest(VoteRequest {
-                        candidate_term: self.current_term,
-                        candidate_id: self.id,
-                        candidate_last_log_idx: self.log.last_idx(),
-                        candidate_last_log_term: self.log.last_term(),
-                    });
-                    return Logger::outgoing_rpcs(&self, vec![(Target::Broadcast, rpc)]);
+    /// Move the clock forward by `n` ticks at once, for drivers that wake up late. Ends up
+    /// exactly where `n` calls to [`tick`](Self::tick) would, sending the same messages,
+    /// but only does any work for the ticks on which a timer runs out
+    pub fn tick_n(&mut self, n: Ticks) -> Vec<SendableMessage<T, I>> {
+        let mut msgs = vec![];
+        let mut left = n;
+        while left > 0 && self.paused.is_none() {
+            let quiet = self.quiet_ticks().min(left - 1);
+            self.skip_ticks(quiet);
+            msgs.extend(self.tick());
+            left -= quiet + 1;
+        }
+        msgs
+    }
+
+    /// Catch up on every tick `clock` says went by since it was last read, returning the
+    /// messages sent along the way
+    pub fn advance(&mut self, clock: &mut impl Clock) -> Vec<SendableMessage<T, I>> {
+        self.tick_n(clock.elapsed())
+    }
+
+    /// How many ticks can go by before one does anything but count down timers
+    fn quiet_ticks(&mut self) -> Ticks {
+        self.snapshot_wanted |= self.log.app.wants_snapshot();
+        if self.snapshot_wanted || self.log_too_long() {
+            return 0;
+        }
+        let mut due = self.timer();
+        if let RaftLeadershipState::Leader(LeaderState {
+            transfer: Some(transfer),
+            ..
+        }) = &self.leadership_state
+        {
+            due = due.min(transfer.deadline.saturating_sub(self.ticks));
+        }
+        if let Some(until) = self.quarantined.values().min() {
+            due = due.min(until.saturating_sub(self.ticks));
+        }
+        // the leaderless alarm goes off one tick past the limit, and clears on the first
+        // tick back under it, see `check_leaderless`
+        if let Some(timeouts) = self.config.leaderless_alarm {
+            let limit = timeouts.saturating_mul(self.config.election_timeout);
+            let alarm = match (self.leader_since, self.leaderless) {
+                (Some(_), false) => Ticks::MAX,
+                (Some(_), true) => 1,
+                (None, leaderless) => {
+                    let without_leader = self.ticks - self.last_leader_contact.unwrap_or(0);
+                    match leaderless {
+                        false => limit.saturating_add(1).saturating_sub(without_leader),
+                        true if without_leader <= limit => 1,
+                        true => Ticks::MAX,
+                    }
                 }
-            }
-            Leader(state) => {
-                state.heartbeat_timeout = state.heartbeat_timeout.saturating_sub(1);
-                if state.heartbeat_timeout == 0 {
-                    Logger::send_heartbeat(&self);
-                    let msgs = self.replicate_log(Target::Broadcast);
-                    return Logger::outgoing_rpcs(&self, msgs);
+            };
+            due = due.min(alarm);
+        }
+        due.saturating_sub(1)
+    }
+
+    /// Whether the log holds more entries than [`max_log_entries`](RaftConfig::max_log_entries)
+    /// allows, and compacting it would drop some. After a failed snapshot it has to grow
+    /// by another `max_log_entries` first
+    fn log_too_long(&self) -> bool {
+        self.config.max_log_entries.is_some_and(|max| {
+            self.log.entries.len() > max
+                && self.log.applied_len > self.log.snapshot.len
+                && self
+                    .snapshot_failed_at
+                    .is_none_or(|len| self.log.len() >= len.saturating_add(max))
+        })
+    }
+
+    /// Count down timers by `n` ticks in which nothing else happens
+    fn skip_ticks(&mut self, n: Ticks) {
+        self.ticks += n;
+        *self.timer_mut() -= n;
+    }
+
+    /// Ticks left on the timer of the role we are in: the election timer of followers and
+    /// candidates, the heartbeat timer of leaders
+    fn timer(&self) -> Ticks {
+        match &self.leadership_state {
+            RaftLeadershipState::Follower(state) => state.election_time,
+            RaftLeadershipState::Candidate(state) => state.election_time,
+            RaftLeadershipState::Leader(state) => state.heartbeat_timeout,
+        }
+    }
+
+    /// See [`timer`](Self::timer)
+    fn timer_mut(&mut self) -> &mut Ticks {
+        match &mut self.leadership_state {
+            RaftLeadershipState::Follower(state) => &mut state.election_time,
+            RaftLeadershipState::Candidate(state) => &mut state.election_time,
+            RaftLeadershipState::Leader(state) => &mut state.heartbeat_timeout,
+        }
+    }
+
+    /// What the role we are in does, see [`RoleBehavior`]
+    fn behavior<'a>(&self) -> &'a dyn RoleBehavior<T, S, I> {
+        match &self.leadership_state {
+            RaftLeadershipState::Follower(_) => &Follower,
+            RaftLeadershipState::Candidate(_) => &Candidate,
+            RaftLeadershipState::Leader(_) => &Leader,
+        }
+    }
+
+    /// Advance election/heartbeat timers by a tick and act on any that ran out
+    fn tick_timers(&mut self) -> Vec<SendableMessage<T, I>> {
+        self.ticks += 1;
+
+        // the app gets to decide when it is a good time to snapshot, unless the log gets
+        // too long first
+        let wanted = std::mem::take(&mut self.snapshot_wanted) || self.log.app.wants_snapshot();
+        let too_long = self.log_too_long();
+        if wanted || too_long {
+            if let Err(err) = self.snapshot_now() {
+                Logger::snapshot_failed(self, &err);
+                self.metrics.snapshots_failed += 1;
+                if too_long {
+                    self.snapshot_failed_at = Some(self.log.len());
                 }
             }
         }
+        self.expire_transfer();
+        self.expire_quarantines();
+
+        let timer = self.timer_mut();
+        *timer = timer.saturating_sub(1);
+        if *timer == 0 {
+            return self.behavior().timer_ran_out(self);
+        }
 
         // fallthrough, no notable events, don't send anything
         vec![]
@@ -226,54 +1075,327 @@ where
     /// Helper function to reset current state back to follower if we are behind
     fn reset_to_follower(&mut self, new_term: Term) {
         if new_term > self.current_term {
-            Logger::bumping_term(&self, new_term);
-            self.current_term = new_term;
+            Logger::bumping_term(self, new_term);
+            self.set_term(new_term);
+            // a vote is only ever given up along with its term, stepping down within the
+            // same term must not let us vote a second time
+            self.voted_for = None;
         }
-        self.voted_for = None;
-        self.leadership_state = RaftLeadershipState::Follower(FollowerState {
+        self.log.leader_term = None;
+        #[cfg(feature = "opentelemetry")]
+        self.proposal_traces.clear();
+        let election_time = self.random_election_time();
+        self.set_leadership_state(RaftLeadershipState::Follower(FollowerState {
             leader: None, // as we are in an election
-            election_time: self.random_election_time(),
-        });
-        Logger::state_update(&self);
+            election_time,
+            transfer_target: None,
+            transfer_deadline: 0,
+        }));
+        Logger::state_update(self);
     }
 
-    /// Calculate quorum of current set of peers.
-    /// quorum = ceil((peers.length + 1)/2)
+    /// Move to a new term, letting observers know
+    fn set_term(&mut self, term: Term) {
+        let old_term = self.current_term;
+        self.current_term = term;
+        // whoever we asked might not be leader anymore, and can't answer for the new one
+        for progress in self.reads.values_mut() {
+            if let ReadProgress::Unconfirmed = progress {
+                *progress = ReadProgress::Refused;
+            }
+        }
+        self.observers.term_changed(old_term, term);
+    }
+
+    /// Switch to a new leadership state, letting observers know if our role changed
+    fn set_leadership_state(&mut self, state: RaftLeadershipState<I>) {
+        let old_role = self.role();
+        let old_state = std::mem::replace(&mut self.leadership_state, state);
+        let new_role = self.role();
+
+        if old_role == Role::Leader && new_role != Role::Leader {
+            self.leader_since = None;
+        }
+
+        // leaving candidate state, one way or another, means the election is over
+        if let RaftLeadershipState::Candidate(election) = old_state {
+            let outcome = match new_role {
+                Role::Leader => ElectionOutcome::Won,
+                Role::Follower => ElectionOutcome::SteppedDown,
+                Role::Candidate => ElectionOutcome::TimedOut,
+            };
+            self.election_history.record(ElectionRecord {
+                term: election.term,
+                candidate: self.id.clone(),
+                outcome,
+                started_at: election.started_at,
+                duration: self.ticks - election.started_at,
+                votes_received: election.votes_received.len(),
+            });
+        }
+
+        if old_role != new_role {
+            self.observers
+                .role_changed(old_role, new_role, self.current_term);
+        }
+    }
+
+    /// Calculate quorum of current set of peers, a strict majority of the cluster.
+    /// quorum = floor((peers.length + 1)/2) + 1
     pub fn quorum_size(&self) -> usize {
         // add an extra because self.peers doesn't include self
-        self.peers.len().saturating_add(2).div(2)
+        let voters = self.peers.len() + 1;
+        voters / 2 + 1
     }
 
     /// Demultiplex incoming RPC to its correct receiver function
-    pub fn receive_rpc(&mut self, rpc: &RPC<T>) -> Vec<SendableMessage<T>> {
-        Logger::receive_rpc(&self, &rpc);
-        let msgs = match rpc {
+    pub fn receive_rpc(&mut self, rpc: &RPC<T, I>) -> Vec<SendableMessage<T, I>> {
+        #[cfg(feature = "tracing")]
+        let _span = {
+            let span = tracing::debug_span!(
+                "receive_rpc",
+                id = %self.id,
+                term = self.current_term,
+                rpc = %rpc
+            );
+            // the parent has to be set before the span is entered
+            #[cfg(feature = "opentelemetry")]
+            match rpc {
+                RPC::AppendRequest(AppendRequest { trace, .. })
+                | RPC::AppendResponse(AppendResponse { trace, .. }) => {
+                    otel::follow(&span, trace.as_ref())
+                }
+                _ => {}
+            }
+            span.entered()
+        };
+        Logger::receive_rpc(self, rpc);
+        if let Some(buffered) = &mut self.paused {
+            if buffered.len() < MAX_PAUSED_RPCS {
+                buffered.push(rpc.clone());
+            } else {
+                Logger::paused_rpc_dropped(self, rpc);
+            }
+            return vec![];
+        }
+        if self.quarantined.contains_key(rpc.sender()) {
+            Logger::quarantined_rpc_dropped(self, rpc);
+            return vec![];
+        }
+        self.track_response(rpc);
+        let mut msgs = match rpc {
             RPC::VoteRequest(req) => self.rpc_vote_request(req),
             RPC::VoteResponse(res) => self.rpc_vote_response(res),
-            RPC::AppendRequest(req) => self.rpc_append_request(req),
+            RPC::AppendRequest(req) => {
+                self.metrics.append_requests_received += 1;
+                self.rpc_append_request(req)
+            }
             RPC::AppendResponse(res) => self.rpc_append_response(res),
+            RPC::SnapshotRequest(req) => self.rpc_snapshot_request(req),
+            RPC::SnapshotResponse(res) => self.rpc_snapshot_response(res),
+            RPC::TimeoutNow(req) => self.rpc_timeout_now(req),
+            RPC::ReadIndexRequest(req) => self.rpc_read_index_request(req),
+            RPC::ReadIndexResponse(res) => self.rpc_read_index_response(res),
+        };
+        self.track_outgoing(&mut msgs);
+        #[cfg(feature = "tokio")]
+        self.publish_leadership();
+        #[cfg(debug_assertions)]
+        self.check_invariants(format_args!("receiving {}", rpc));
+        Logger::outgoing_rpcs(self, msgs)
+    }
+
+    /// Bookkeeping for requests about to go out: stamp correlation ids and note when they
+    /// were sent, bump the sent counter for every append request, and the inflight count of
+    /// every follower we're sending to
+    fn track_outgoing(&mut self, msgs: &mut [SendableMessage<T, I>]) {
+        self.metrics.append_requests_sent += msgs
+            .iter()
+            .filter(|(_, rpc)| matches!(rpc, RPC::AppendRequest(_)))
+            .count() as u64;
+
+        let now = Instant::now();
+        for (target, rpc) in msgs.iter_mut() {
+            let request_id = match rpc {
+                RPC::AppendRequest(AppendRequest { request_id, .. })
+                | RPC::VoteRequest(VoteRequest { request_id, .. }) => request_id,
+                _ => continue,
+            };
+            *request_id = self.next_request_id;
+            self.next_request_id += 1;
+            let recipients: Vec<I> = match target {
+                Target::Single(id) => vec![id.clone()],
+                Target::Broadcast => self.peers.iter().cloned().collect(),
+            };
+            for id in recipients {
+                self.pending_requests.insert((*request_id, id), now);
+            }
+        }
+        while self.pending_requests.len() > MAX_PENDING_REQUESTS {
+            self.pending_requests.pop_first();
+        }
+
+        if let RaftLeadershipState::Leader(state) = &mut self.leadership_state {
+            for (target, rpc) in msgs {
+                if let (Target::Single(id), RPC::AppendRequest(_) | RPC::SnapshotRequest(_)) =
+                    (target, &rpc)
+                {
+                    if let Some(follower_state) = state.followers.get_mut(id) {
+                        follower_state.inflight += 1;
+                        follower_state.update_catch_up(self.log.len(), self.ticks);
+                        if let RPC::SnapshotRequest(req) = rpc {
+                            // resends don't restart the clock
+                            follower_state
+                                .snapshot_in_flight
+                                .get_or_insert(SnapshotTransfer {
+                                    entries: req.snapshot.len,
+                                    bytes: req.snapshot.data.len(),
+                                    sent_at: self.ticks,
+                                    last_sent_at: self.ticks,
+                                })
+                                .last_sent_at = self.ticks;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Panic if we just broke one of Raft's safety properties
+    #[cfg(debug_assertions)]
+    fn check_invariants(&mut self, step: std::fmt::Arguments) {
+        let mut invariants = std::mem::take(&mut self.invariants);
+        invariants.check(
+            step,
+            self.current_term,
+            self.voted_for.clone(),
+            &self.log,
+            || self.status(),
+        );
+        self.invariants = invariants;
+    }
+
+    /// Match a response up with the request it answers and record the round trip time
+    fn track_response(&mut self, rpc: &RPC<T, I>) {
+        let (histograms, peer, request_id) = match rpc {
+            RPC::AppendResponse(res) => (
+                &mut self.rpc_latencies.append,
+                res.follower_id.clone(),
+                res.request_id,
+            ),
+            RPC::VoteResponse(res) => (
+                &mut self.rpc_latencies.vote,
+                res.votee_id.clone(),
+                res.request_id,
+            ),
+            _ => return,
+        };
+        if let Some(sent) = self.pending_requests.remove(&(request_id, peer.clone())) {
+            histograms.entry(peer).or_default().observe(sent.elapsed());
+        }
+    }
+
+    /// Start a linearizable read that doesn't go through the log: ask the leader for its
+    /// commit index, or confirm our own if we are leader, so reads can be served by any
+    /// node. Returns the read's id and the messages to send for it. Once
+    /// [`read_ready`](Self::read_ready) says so, the app reflects every write committed
+    /// before now.
+    /// Fails if paused, or with [`RaftError::NotLeader`] if we don't know of a leader
+    pub fn read_barrier(&mut self) -> Result<(ReadId, Vec<SendableMessage<T, I>>), I> {
+        if self.paused.is_some() {
+            return Err(RaftError::Paused);
+        }
+        let Some(leader) = self.leader_id() else {
+            return Err(RaftError::NotLeader { leader: None });
+        };
+        let read_id = self.next_read_id;
+        self.next_read_id += 1;
+        self.reads.insert(read_id, ReadProgress::Unconfirmed);
+        Logger::read_barrier(self, read_id, &leader);
+
+        let mut msgs = if leader == self.id {
+            self.start_read(self.id.clone(), read_id)
+        } else {
+            let rpc = RPC::ReadIndexRequest(ReadIndexRequest {
+                term: self.current_term,
+                reader_id: self.id.clone(),
+                read_id,
+            });
+            vec![(Target::Single(leader), rpc)]
         };
-        Logger::outgoing_rpcs(&self, msgs)
+        self.track_outgoing(&mut msgs);
+        Ok((read_id, Logger::outgoing_rpcs(self, msgs)))
+    }
+
+    /// Whether the app can be read for `read`, started with
+    /// [`read_barrier`](Self::read_barrier): the leader confirmed its commit index and we
+    /// have applied everything up to there. The read is forgotten once this returns `true`
+    /// or an error. Fails with [`RaftError::ReadUnconfirmed`] if the leader refused the read
+    /// or leadership changed before it was confirmed, in which case a new read can be
+    /// started right away
+    pub fn read_ready(&mut self, read: ReadId) -> Result<bool, I> {
+        match self.reads.get(&read) {
+            Some(ReadProgress::Unconfirmed) => Ok(false),
+            Some(ReadProgress::At(len)) if self.log.last_applied() < *len => Ok(false),
+            Some(ReadProgress::At(_)) => {
+                self.reads.remove(&read);
+                Ok(true)
+            }
+            Some(ReadProgress::Refused) | None => {
+                self.reads.remove(&read);
+                Err(RaftError::ReadUnconfirmed)
+            }
+        }
     }
 
     /// Public interface for clients to request adding log entries to the cluster.
     /// Will fail if the node it is called on a non-[`Leader`](RaftLeadershipState::Leader) node
-    pub fn client_request(&mut self, msg: T) -> Result<()> {
-        Logger::client_request(&self);
+    pub fn client_request(&mut self, msg: T) -> Result<(), I> {
+        Logger::client_request(self);
+        if self.paused.is_some() {
+            return Err(RaftError::Paused);
+        }
         match &mut self.leadership_state {
+            RaftLeadershipState::Leader(LeaderState {
+                transfer: Some(transfer),
+                ..
+            }) => {
+                // entries proposed now might not make it to the new leader in time,
+                // send the client there right away
+                Err(RaftError::NotLeader {
+                    leader: Some(transfer.target.clone()),
+                })
+            }
             RaftLeadershipState::Leader(_) => {
+                // if the app is too far behind, push back on the client instead of letting
+                // unapplied entries pile up. client is responsible for retrying later
+                let apply_lag = self.log.committed_len - self.log.last_applied();
+                if let Some(max_apply_lag) = self.config.max_apply_lag {
+                    if apply_lag >= max_apply_lag {
+                        Logger::apply_lag_exceeded(self, apply_lag, max_apply_lag);
+                        return Err(RaftError::Busy {
+                            lag: apply_lag,
+                            max: max_apply_lag,
+                        });
+                    }
+                }
+
                 // append log entry
                 self.log.entries.push(LogEntry {
                     term: self.current_term,
                     data: msg,
                 });
+                #[cfg(feature = "opentelemetry")]
+                if let Some(trace) = otel::current() {
+                    self.proposal_traces.insert(self.log.last_idx(), trace);
+                }
 
-                if self.peers.len() == 0 {
+                if self.peers.is_empty() {
                     // single cluster, we can just try to commit these
                     self.commit_log_entries();
                 } else {
-                    // replicate our log to followers
-                    self.replicate_log(Target::Broadcast);
+                    // it goes out to followers with the next heartbeat, or batch
+                    self.batch_proposal();
                 }
                 Ok(())
             }
@@ -281,7 +1403,9 @@ where
                 // we aren't a leader so not authorized to add to the replicated log
                 // respond to client by saying we are not the leader. client is responsible
                 // for trying again with a different server
-                bail!("cannot add a log entry to a non-leader!")
+                Err(RaftError::NotLeader {
+                    leader: self.leader_id(),
+                })
 
                 // in a more robust implementation, client requests would generate a unique
                 // serial number of each request (client id, request number) and 'retry' with
@@ -292,56 +1416,39 @@ where
         }
     }
 
-    /// Replicate some section of our log entries to followers.
-    /// Intended to only be called when we are a Leader, do nothing otherwise
-    fn replicate_log(&mut self, target: Target) -> Vec<SendableMessage<T>> {
-        if let RaftLeadershipState::Leader(state) = &self.leadership_state {
-            // construct closure for the sending logic so we don't need
-            // to duplicate logic
-
-            let sending_logic = |target| {
-                // prefix len is the index of all the entries we have sent up to
-                let prefix_len = state
-                    .followers
-                    .get(target)
-                    .unwrap_or_else(|| panic!("target={} is not a follower", target))
-                    .sent_up_to;
-                let prefix_term = if prefix_len > 0 {
-                    self.log.entries.get(prefix_len - 1).unwrap().term
-                } else {
-                    0
-                };
-                let entries = self.log.entries[prefix_len..self.log.entries.len()].to_vec();
-                Logger::replicate_entries(&self, &entries, &target, prefix_len);
-
-                let rpc = RPC::AppendRequest(AppendRequest {
-                    entries,
-                    leader_id: self.id,
-                    leader_term: self.current_term,
-                    leader_commit: self.log.committed_len,
-                    leader_last_log_idx: prefix_len,
-                    leader_last_log_term: prefix_term,
+    /// Process an RPC Request to vote for requesting candidate
+    fn rpc_vote_request(&mut self, req: &VoteRequest<I>) -> Vec<SendableMessage<T, I>> {
+        Logger::rpc_vote_request(self, req);
+        if !self.peers.contains(&req.candidate_id) {
+            // e.g. a node that was taken out of the cluster and keeps campaigning. It must
+            // neither get our vote nor bump our term and depose the leader
+            Logger::unknown_candidate(self, req);
+            self.metrics.votes_denied += 1;
+            return vec![];
+        }
+        // while leadership is being handed over, anyone but the chosen successor would only
+        // start a competing election. Turn them down without moving to their term
+        if let Some(target) = self.transfer_in_progress() {
+            if *target != req.candidate_id {
+                Logger::vote_withheld(self, req, target);
+                self.metrics.votes_denied += 1;
+                let rpc = RPC::VoteResponse(VoteResponse {
+                    votee_id: self.id.clone(),
+                    term: self.current_term,
+                    vote_granted: false,
+                    request_id: req.request_id,
                 });
-                (Target::Single(*target), rpc)
-            };
-
-            match target {
-                Target::Single(target) => vec![sending_logic(&target)],
-                Target::Broadcast => state.followers.keys().map(sending_logic).collect(),
+                return vec![(Target::Single(req.candidate_id.clone()), rpc)];
             }
-        } else {
-            vec![]
         }
-    }
-
-    /// Process an RPC Request to vote for requesting candidate
-    fn rpc_vote_request(&mut self, req: &VoteRequest) -> Vec<SendableMessage<T>> {
-        Logger::rpc_vote_request(&self, req);
 
         if req.candidate_term > self.current_term {
             // if we are behind the other candidate, just reset to follower
             self.reset_to_follower(req.candidate_term);
         }
+        if self.yields_to(req) {
+            self.step_aside(&req.candidate_id);
+        }
 
         // check if candidate's log is up to date with ours
         // if they are outdated, don't vote for them (we don't want an outdated leader)
@@ -354,249 +1461,597 @@ where
         let up_to_date = req.candidate_term == self.current_term;
 
         // check to make sure we haven't voted yet (or we've already voted for them to make this idempotent)
-        let havent_voted = match self.voted_for {
-            Some(voted_candidate_id) => voted_candidate_id == req.candidate_id,
+        let havent_voted = match &self.voted_for {
+            Some(voted_candidate_id) => *voted_candidate_id == req.candidate_id,
             None => true,
         };
 
         // construct a response depending on conditions
         let vote_granted = if log_ok && up_to_date && havent_voted {
             // all conditions met! vote for them
-            self.voted_for = Some(req.candidate_id);
+            self.voted_for = Some(req.candidate_id.clone());
+            self.metrics.votes_granted += 1;
+            self.observers.emit(RaftEvent::VoteGranted {
+                candidate: req.candidate_id.clone(),
+                term: self.current_term,
+            });
             true
         } else {
+            self.metrics.votes_denied += 1;
             false
         };
-        Logger::rpc_vote_result(&self, log_ok, up_to_date, havent_voted);
+        Logger::rpc_vote_result(self, log_ok, up_to_date, havent_voted);
         let rpc = RPC::VoteResponse(VoteResponse {
-            votee_id: self.id,
+            votee_id: self.id.clone(),
             term: self.current_term,
             vote_granted,
+            request_id: req.request_id,
         });
-        vec![(Target::Single(req.candidate_id), rpc)]
+        vec![(Target::Single(req.candidate_id.clone()), rpc)]
     }
 
     /// Process an RPC response to [`rpc_vote_request`]
-    fn rpc_vote_response(&mut self, res: &VoteResponse) -> Vec<SendableMessage<T>> {
-        Logger::rpc_vote_resp(&self, res);
+    fn rpc_vote_response(&mut self, res: &VoteResponse<I>) -> Vec<SendableMessage<T, I>> {
+        Logger::rpc_vote_resp(self, res);
         if res.term > self.current_term {
             // if votee is ahead, we are out of date, reset to follower
             self.reset_to_follower(res.term);
         }
+        self.behavior().vote_response(self, res)
+    }
 
-        let quorum = self.quorum_size();
-        if let RaftLeadershipState::Candidate(state) = &mut self.leadership_state {
-            let up_to_date = res.term == self.current_term;
-            // only process the vote if we are a candidate, the votee is voting for
-            // our current term, and the vote was positive
-            Logger::vote_count(&self.id, res, up_to_date);
-            if up_to_date && res.vote_granted {
-                // add this to votes received
-                state.votes_received.insert(res.votee_id);
-                Logger::total_vote_count(&self.id, state.votes_received.len(), quorum);
-                if state.votes_received.len() < quorum {
-                    // if less than quorum, do nothing
-                    return vec![];
-                }
+    /// Process an RPC request to append a message to the replicated event log
+    fn rpc_append_request(&mut self, req: &AppendRequest<T, I>) -> Vec<SendableMessage<T, I>> {
+        Logger::rpc_append_request(self, req);
 
-                // otherwise, we won election! promote self to leader
-                // initialize followers to all nodes except for ourselves
-                let mut followers = BTreeMap::new();
-                self.peers
-                    .iter()
-                    .filter(|votee| **votee != self.id)
-                    .for_each(|votee| {
-                        // add that votee to our list of followers
-                        match followers.insert(
-                            *votee,
-                            NodeReplicationState {
-                                sent_up_to: self.log.last_idx(),
-                                acked_up_to: 0,
-                            },
-                        ) {
-                            None => Logger::added_follower(&self, &votee),
-                            _ => {}
-                        };
-                    });
-                return self.promote_to_leader(followers);
-            }
+        // check to see if we are out of date
+        if req.leader_term > self.current_term {
+            self.reset_to_follower(req.leader_term);
+        }
+        if req.leader_term == self.current_term {
+            self.note_leader(req.leader_id.clone());
         }
 
-        // fallthrough case, do nothing
-        vec![]
+        // pre-pick a new election time for if we revert to follower
+        let random_election_time = self.random_election_time();
+        self.behavior()
+            .append_request(self, req, random_election_time)
     }
 
-    /// Manually promote node to leader. Do not call during normal operation.
-    pub fn promote_to_leader(
+    /// Process an RPC response to [`rpc_append_request`]
+    fn rpc_append_response(&mut self, res: &AppendResponse<I>) -> Vec<SendableMessage<T, I>> {
+        Logger::append_response(self, res);
+
+        // check to see if we are out of date
+        if res.term > self.current_term {
+            self.reset_to_follower(res.term);
+        }
+        let old_committed_len = self.log.committed_len;
+        let mut msgs = self.behavior().append_response(self, res);
+        let commit = self.broadcast_commit(old_committed_len, &msgs);
+        msgs.extend(commit);
+        if res.term == self.current_term {
+            msgs.extend(self.confirm_reads(Some((&res.follower_id, res.request_id))));
+        }
+        msgs
+    }
+
+    /// Record that `leader` is the leader of our current term
+    fn note_leader(&mut self, leader: I) {
+        if self.last_known_leader.as_ref() != Some(&leader) {
+            self.last_known_leader = Some(leader.clone());
+            self.metrics.leader_changes += 1;
+        }
+        if leader != self.id {
+            self.last_leader_contact = Some(self.ticks);
+        }
+    }
+
+    /// Process a request from the leader to take over its leadership
+    fn rpc_timeout_now(&mut self, req: &TimeoutNow<I>) -> Vec<SendableMessage<T, I>> {
+        Logger::rpc_timeout_now(self, req);
+        self.behavior().timeout_now(self, req)
+    }
+
+    /// Process a request for our commit index from a node that wants to read
+    fn rpc_read_index_request(&mut self, req: &ReadIndexRequest<I>) -> Vec<SendableMessage<T, I>> {
+        if req.term > self.current_term {
+            self.reset_to_follower(req.term);
+        }
+        self.behavior().read_index_request(self, req)
+    }
+
+    /// Process the leader's answer to one of our read barriers
+    fn rpc_read_index_response(
         &mut self,
-        followers: BTreeMap<ServerId, NodeReplicationState>,
-    ) -> Vec<SendableMessage<T>> {
-        let num_votes = followers.len() + 1;
-        let follower_ids: Vec<ServerId> = followers.keys().cloned().collect();
+        res: &ReadIndexResponse<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        // a confirmed read stays good even if the leader has moved on since
+        self.read_answered(res.read_id, res.read_len);
+        if res.term > self.current_term {
+            self.reset_to_follower(res.term);
+        }
+        vec![]
+    }
 
-        // set state to leader
-        self.leadership_state = RaftLeadershipState::Leader(LeaderState {
-            followers,
-            heartbeat_timeout: self.config.heartbeat_interval,
+    /// Answer `reader`'s read `read_id` with the length of the log it can read at, or
+    /// `None` if we can't vouch for it
+    fn answer_read(
+        &mut self,
+        reader: I,
+        read_id: ReadId,
+        read_len: Option<LogIndex>,
+    ) -> Vec<SendableMessage<T, I>> {
+        if reader == self.id {
+            self.read_answered(read_id, read_len);
+            return vec![];
+        }
+        let rpc = RPC::ReadIndexResponse(ReadIndexResponse {
+            term: self.current_term,
+            leader_id: self.id.clone(),
+            read_id,
+            read_len,
         });
-        Logger::won_election(&self, num_votes, &follower_ids);
+        vec![(Target::Single(reader), rpc)]
+    }
 
-        // then replicate our logs to all our followers
-        self.replicate_log(Target::Broadcast)
+    /// Note the answer to one of our read barriers, unless it already has one
+    fn read_answered(&mut self, read_id: ReadId, read_len: Option<LogIndex>) {
+        if !matches!(self.reads.get(&read_id), Some(ReadProgress::Unconfirmed)) {
+            return;
+        }
+        Logger::read_answered(self, read_id, read_len);
+        let progress = match read_len {
+            Some(len) => ReadProgress::At(len),
+            None => ReadProgress::Refused,
+        };
+        self.reads.insert(read_id, progress);
     }
 
-    /// Process an RPC request to append a message to the replicated event log
-    fn rpc_append_request(&mut self, req: &AppendRequest<T>) -> Vec<SendableMessage<T>> {
-        Logger::rpc_append_request(&self, req);
+    /// Process an RPC request to replace our log with the leader's snapshot
+    fn rpc_snapshot_request(&mut self, req: &SnapshotRequest<I>) -> Vec<SendableMessage<T, I>> {
+        Logger::rpc_snapshot_request(self, req);
 
         // check to see if we are out of date
         if req.leader_term > self.current_term {
             self.reset_to_follower(req.leader_term);
         }
 
-        // pre-pick a new election time for if we revert to follower
-        let random_election_time = self.random_election_time();
-        match &mut self.leadership_state {
-            RaftLeadershipState::Candidate(_) | RaftLeadershipState::Leader(_) => {
-                // if leader is in same term as us, they have recovered from
-                // failure and we can go back to follower and try the request again
-                Logger::append_conflict_check(&self, req);
-                if req.leader_term == self.current_term {
-                    self.reset_to_follower(req.leader_term);
-                    self.rpc_append_request(req)
-                } else {
-                    // otherwise just do nothing, only followers should respond to
-                    // append_request RPCs
-                    vec![]
-                }
+        if req.leader_term == self.current_term {
+            self.note_leader(req.leader_id.clone());
+            // there is a leader for our term, so we can't be candidate/leader ourselves
+            if !self.is_follower() {
+                self.reset_to_follower(req.leader_term);
+            }
+            let random_election_time = self.random_election_time();
+            if let RaftLeadershipState::Follower(state) = &mut self.leadership_state {
+                state.election_time = random_election_time;
+                state.leader = Some(req.leader_id.clone());
             }
-            RaftLeadershipState::Follower(state) => {
-                // if leader is same term as us, we accept requester as current leader
-                Logger::check_matching_term(&self.id, req, self.current_term);
-                let success = if req.leader_term == self.current_term {
-                    state.election_time = random_election_time;
-                    state.leader = Some(req.leader_id);
-
-                    // check if we have the messages that the leader is claiming we have
-                    let prefix_len = req.leader_last_log_idx;
-                    let prefix_ok = self.log.entries.len() >= prefix_len;
-                    let last_entry_matches_terms = prefix_len == 0
-                        || (self
-                            .log
-                            .entries
-                            .get(prefix_len - 1)
-                            .expect("invalid leader_last_log_idx")
-                            .term
-                            == req.leader_last_log_term);
-
-                    Logger::append_entries(&self, prefix_ok, last_entry_matches_terms, prefix_len);
-                    if prefix_ok && last_entry_matches_terms {
-                        // assumptions match, append it to our local log
-                        self.log
-                            .append_entries(prefix_len, req.leader_commit, req.entries.clone());
-                        true // success
-                    } else {
-                        false // bad request if we have mismatched assumptions about where the log is
-                    }
-                } else {
-                    false // bad request if we have mismatched terms
-                };
 
-                // send response
-                let ack_idx = if success {
-                    req.leader_last_log_idx + req.entries.len()
-                } else {
-                    0
-                };
-                let rpc = RPC::AppendResponse(AppendResponse {
-                    ok: success,
-                    term: self.current_term,
-                    ack_idx,
-                    follower_id: self.id,
-                });
-                vec![(Target::Single(req.leader_id), rpc)]
+            let old_committed_len = self.log.committed_len;
+            let old_snapshot_len = self.log.snapshot.len;
+            let started = Instant::now();
+            let installed = self.log.install_snapshot(req.snapshot.clone());
+            self.check_slow(SlowOperation::Persist, started.elapsed());
+            match installed {
+                Ok(()) if self.log.snapshot.len > old_snapshot_len => {
+                    self.observers.emit(RaftEvent::SnapshotInstalled {
+                        len: self.log.snapshot.len,
+                        term: self.log.snapshot.term,
+                    })
+                }
+                Ok(()) => {}
+                // leader will notice we didn't move forward and send it again
+                Err(err) => {
+                    Logger::snapshot_failed(self, &err.into());
+                    self.metrics.snapshots_failed += 1;
+                }
             }
+            self.observers
+                .committed(old_committed_len, self.log.committed_len);
         }
+
+        // everything we have committed is known to match the leader's log
+        let rpc = RPC::SnapshotResponse(SnapshotResponse {
+            term: self.current_term,
+            ack_idx: self.log.committed_len,
+            follower_id: self.id.clone(),
+        });
+        vec![(Target::Single(req.leader_id.clone()), rpc)]
     }
 
-    /// Process an RPC response to [`rpc_append_request`]
-    fn rpc_append_response(&mut self, res: &AppendResponse) -> Vec<SendableMessage<T>> {
-        Logger::append_response(&self, res);
+    /// Process an RPC response to [`rpc_snapshot_request`]
+    fn rpc_snapshot_response(&mut self, res: &SnapshotResponse<I>) -> Vec<SendableMessage<T, I>> {
+        Logger::snapshot_response(self, res);
 
         // check to see if we are out of date
         if res.term > self.current_term {
             self.reset_to_follower(res.term);
         }
+        let old_committed_len = self.log.committed_len;
+        let mut msgs = self.behavior().snapshot_response(self, res);
+        let commit = self.broadcast_commit(old_committed_len, &msgs);
+        msgs.extend(commit);
+        msgs
+    }
 
-        if let RaftLeadershipState::Leader(state) = &mut self.leadership_state {
-            if res.term == self.current_term {
-                // make sure that the response was ok and the length that the follower is
-                // at is greater than what we have recorded for them before
-                let follower_state = state
-                    .followers
-                    .get_mut(&res.follower_id)
-                    .expect("unknown/invalid follower id");
-
-                Logger::process_append_response(&self.id, res, follower_state);
-                if res.ok && res.ack_idx >= follower_state.acked_up_to {
-                    // update replication state, we know follower has sent + acked up
-                    // to `replication_state.ack_idx`
-
-                    follower_state.sent_up_to = res.ack_idx;
-                    follower_state.acked_up_to = res.ack_idx;
-                    // try to formally commit these entries, no need to respond
-                    self.commit_log_entries();
-                    return vec![];
-                } else if follower_state.sent_up_to > 0 {
-                    // if there's a gap in the log, res.ok is not true!
-                    // reduce what we assume the client has received by one and try again
+    /// Let the node know its log has been written to disk up to `len`, when the embedder
+    /// [persists in the background](RaftConfig::persist_in_background). A leader may
+    /// commit entries it was only waiting on its own write for
+    pub fn log_persisted(&mut self, len: LogIndex) {
+        self.log.persisted_len = max(self.log.persisted_len, min(len, self.log.len()));
+        self.commit_log_entries();
+    }
 
-                    follower_state.sent_up_to = follower_state.sent_up_to.saturating_sub(1);
-                    return self.replicate_log(Target::Single(res.follower_id));
-                } else {
-                    // something is critically wrong
-                    panic!("invalid append_response received: already tried resending whole log and response still fails");
-                }
-            } else {
-                // this should never be reached, client should have updated their term when we sent the first response
-                panic!("invalid append_response received: client term should never be behind at this point");
+    /// Snapshot the app and compact the log, dropping every entry the app has applied.
+    /// This is meant for when the embedder knows now is a good time (e.g. right after a bulk
+    /// load), apps can ask for the same through [`App::wants_snapshot`].
+    /// With an asynchronous app, this waits for the app to catch up first.
+    pub fn snapshot_now(&mut self) -> Result<(), I> {
+        let old_snapshot_len = self.log.snapshot.len;
+        let started = Instant::now();
+        if let Some(storage) = &mut self.spill {
+            let applied = &self.log.entries[..self.log.applied_len - old_snapshot_len];
+            if !applied.is_empty() {
+                storage.spill(old_snapshot_len, applied)?;
             }
-        } else {
-            vec![]
         }
+        self.log.compact()?;
+        self.check_slow(SlowOperation::Persist, started.elapsed());
+        self.snapshot_failed_at = None;
+        if self.log.snapshot.len > old_snapshot_len {
+            self.observers.emit(RaftEvent::SnapshotTaken {
+                len: self.log.snapshot.len,
+                term: self.log.snapshot.term,
+            });
+        }
+        Ok(())
     }
 
-    /// Commit any log entries that have been acknowledged by a quorum of nodes.
-    /// When a log entry is committed, its message is delivered to the application.
-    fn commit_log_entries(&mut self) {
-        let quorum_size = self.quorum_size();
-        if let RaftLeadershipState::Leader(state) = &mut self.leadership_state {
-            // construct a collection of all nodes in system
-            let mut all_nodes: Vec<&ServerId> = self.peers.iter().collect();
-            all_nodes.push(&self.id);
-
-            // repeat until we have committed all entries
-            while self.log.committed_len < self.log.entries.len() {
-                // count all nodes which have acked past what our current commit_len is
-                // +1 is to include ourselves!
-                let acks = state
-                    .followers
-                    .values()
-                    .filter(|follower_state| follower_state.acked_up_to > self.log.committed_len)
-                    .count()
-                    + 1;
-
-                Logger::commit_entry(&self.id, self.log.committed_len, acks, quorum_size);
-                if acks >= quorum_size {
-                    // hit quorum! deliver last log to application and bump commit_len
-                    self.log.deliver_msg();
-                    self.log.committed_len += 1;
-                } else {
-                    // exit early, nothing we can do except wait for more nodes to acknowledge
-                    // the entries we told them to add
-                    break;
-                }
+    /// Hand every entry the log is compacted by from now on to `storage`, rather than only
+    /// dropping it. As leader, followers that need entries from before our snapshot are then
+    /// sent them out of `storage`, in batches of up to
+    /// [`max_log_entries`](RaftConfig::max_log_entries), and only get the snapshot if
+    /// `storage` doesn't have them. Together with `max_log_entries` this keeps a leader's
+    /// memory bounded however far behind a follower falls, without making the follower
+    /// install a snapshot whenever it was down for a while
+    pub fn spill_to(&mut self, storage: impl Storage<T, I> + 'static) {
+        self.spill = Some(Box::new(storage));
+    }
+
+    /// Trace context to send along with entries from `start` onwards: that of the first
+    /// proposal in there if it was traced, otherwise that of the current span
+    #[cfg(feature = "opentelemetry")]
+    fn append_trace(&self, start: LogIndex) -> Option<TraceContext> {
+        self.proposal_traces
+            .range(start..)
+            .next()
+            .map(|(_, trace)| trace.clone())
+            .or_else(otel::current)
+    }
+
+    /// Trace context is only propagated with the `opentelemetry` feature
+    #[cfg(not(feature = "opentelemetry"))]
+    fn append_trace(&self, _start: LogIndex) -> Option<TraceContext> {
+        None
+    }
+
+    /// Warn about `operation` if it took longer than its configured threshold
+    fn check_slow(&mut self, operation: SlowOperation, elapsed: Duration) {
+        let slow_path = &self.config.slow_path;
+        let threshold = match operation {
+            SlowOperation::Apply { entries } => slow_path
+                .apply
+                .map(|per_entry| per_entry.saturating_mul(entries as u32)),
+            SlowOperation::Persist => slow_path.persist,
+            SlowOperation::Tick => slow_path.tick,
+        };
+        if let Some(threshold) = threshold.filter(|threshold| elapsed > *threshold) {
+            self.metrics.slow_operations += 1;
+            Logger::slow_operation(self, operation, elapsed, threshold);
+            self.observers
+                .emit(RaftEvent::SlowPath { operation, elapsed });
+        }
+    }
+
+    /// Raise the leaderless alarm once we've gone too long without a leader, and lower it
+    /// again as soon as we hear from one (or become one)
+    fn check_leaderless(&mut self) {
+        let Some(timeouts) = self.config.leaderless_alarm else {
+            return;
+        };
+        let limit = timeouts.saturating_mul(self.config.election_timeout);
+        let ticks = match self.leader_since {
+            Some(_) => 0,
+            None => self.ticks - self.last_leader_contact.unwrap_or(0),
+        };
+        if ticks > limit && !self.leaderless {
+            self.leaderless = true;
+            Logger::leaderless_alarm(self, ticks);
+            self.observers.emit(RaftEvent::LeaderlessAlarm {
+                term: self.current_term,
+                ticks,
+            });
+        } else if ticks <= limit && self.leaderless {
+            self.leaderless = false;
+            self.observers.emit(RaftEvent::LeaderFound {
+                term: self.current_term,
+            });
+        }
+    }
+
+    /// Most recent elections this node stood in, oldest first.
+    /// Only the last [`ELECTION_HISTORY_LEN`](crate::history::ELECTION_HISTORY_LEN) are kept
+    pub fn election_history(&self) -> &ElectionHistory<I> {
+        &self.election_history
+    }
+
+    /// Round trip latency of append and vote requests to every peer
+    pub fn rpc_latencies(&self) -> &RpcLatencies<I> {
+        &self.rpc_latencies
+    }
+
+    /// Counters this node has kept since it started, cheap enough to scrape every tick
+    pub fn metrics(&self) -> RaftMetrics {
+        self.metrics
+    }
+
+    /// Point in time view of this node's role, term, log progress and (if leader)
+    /// replication progress of its followers
+    pub fn status(&self) -> RaftStatus<I> {
+        let (votes_received, followers) = match &self.leadership_state {
+            RaftLeadershipState::Follower(_) => (None, None),
+            RaftLeadershipState::Candidate(state) => (Some(state.votes_received.clone()), None),
+            RaftLeadershipState::Leader(state) => (None, Some(state.followers.clone())),
+        };
+        let catch_up = followers
+            .iter()
+            .flatten()
+            .filter_map(|(id, state)| {
+                state
+                    .catch_up_progress(self.log.len(), self.ticks)
+                    .map(|progress| (id.clone(), progress))
+            })
+            .collect();
+        RaftStatus {
+            id: self.id.clone(),
+            role: self.role(),
+            term: self.current_term,
+            leader_hint: self.leader_id(),
+            voted_for: self.voted_for.clone(),
+            votes_received,
+            committed_len: self.log.committed_len,
+            applied_len: self.log.applied_len,
+            last_applied: self.log.last_applied(),
+            log_len: self.log.len(),
+            snapshot_len: self.log.snapshot.len,
+            followers,
+            ticks_since_leader_contact: match self.leader_since {
+                Some(_) => None,
+                None => self.last_leader_contact.map(|tick| self.ticks - tick),
+            },
+            ticks_as_leader: self.leader_since.map(|tick| self.ticks - tick),
+            leaderless_alarm: self.leaderless,
+            catch_up,
+            paused: self.is_paused(),
+        }
+    }
+
+    /// Full state of this node for bug reports, including up to `max_entries` entries from the
+    /// end of the log. With the `serde` feature this can be turned into JSON with
+    /// [`DebugDump::to_json`]
+    pub fn debug_dump(&self, max_entries: usize) -> DebugDump<I> {
+        let timer = self.timer();
+        let start = self
+            .log
+            .len()
+            .saturating_sub(max_entries)
+            .max(self.log.snapshot.len);
+        let log_tail = self
+            .log
+            .entries_from(start)
+            .iter()
+            .enumerate()
+            .map(|(offset, entry)| DumpedEntry {
+                index: start + offset,
+                term: entry.term,
+                data: format!("{:?}", entry.data),
+            })
+            .collect();
+        DebugDump {
+            status: self.status(),
+            timer,
+            config: self.config.clone(),
+            metrics: self.metrics,
+            snapshot_term: self.log.snapshot.term,
+            log_tail,
+        }
+    }
+
+    /// Current role of this node
+    pub fn role(&self) -> Role {
+        match &self.leadership_state {
+            RaftLeadershipState::Follower(_) => Role::Follower,
+            RaftLeadershipState::Candidate(_) => Role::Candidate,
+            RaftLeadershipState::Leader(_) => Role::Leader,
+        }
+    }
+
+    /// Current term of this node
+    pub fn current_term(&self) -> Term {
+        self.current_term
+    }
+
+    /// Who this node voted for in the current term, if anyone
+    pub fn voted_for(&self) -> Option<I> {
+        self.voted_for.clone()
+    }
+
+    /// Who this node believes is leader of the current term: itself while leader, the node
+    /// it last heard from while follower, nobody while it runs an election.
+    /// This is where clients should be redirected to
+    pub fn leader_id(&self) -> Option<I> {
+        match &self.leadership_state {
+            RaftLeadershipState::Follower(state) => state.leader.clone(),
+            RaftLeadershipState::Candidate(_) => None,
+            RaftLeadershipState::Leader(_) => Some(self.id.clone()),
+        }
+    }
+
+    /// Config the node runs with
+    pub fn config(&self) -> &RaftConfig {
+        &self.config
+    }
+
+    /// Switch to `config` without restarting the node, e.g. after
+    /// [`ConfigWatcher`](crate::storage::ConfigWatcher) saw the config file change.
+    /// Timeouts apply from the next time a timer is reset, except that a running timer is cut
+    /// short to fit the new config. Everything else only ever changes what the node does
+    /// next: the apply lag, slow operation thresholds, leaderless alarm and log limit are
+    /// checked against the new values, batching and commit broadcasts apply from the next
+    /// proposal and commit, and the tie break from the next vote request.
+    /// [`persist_in_background`](RaftConfig::persist_in_background) is the exception, as the
+    /// embedder's writes have to change along with it, so it can't change on a running node.
+    /// Fails with [`RaftError::InvalidConfig`], leaving the node as it was, if `config`
+    /// doesn't [validate](RaftConfig::validate) or tries to change it
+    pub fn reconfigure(&mut self, config: RaftConfig) -> Result<(), I> {
+        config.check()?;
+        if config.persist_in_background != self.config.persist_in_background {
+            return Err(RaftError::InvalidConfig(
+                "persist_in_background can't change while the node is running".into(),
+            ));
+        }
+        Logger::reconfigured(self, &config);
+        self.config = config;
+        let longest = match self.role() {
+            Role::Follower | Role::Candidate => {
+                self.config.election_timeout + self.config.election_timeout_jitter
             }
+            Role::Leader => self.config.heartbeat_interval,
+        };
+        let timer = self.timer_mut();
+        *timer = (*timer).min(longest);
+        Ok(())
+    }
+
+    /// Cut `peer` off for the next `ticks` ticks without taking it out of the cluster, e.g.
+    /// while it is known to be flapping or to have a corrupt disk: every RPC from it is
+    /// dropped and, as leader, we stop replicating to it. It still counts towards the
+    /// quorum, so quarantining too many peers stalls the cluster as if they were down.
+    /// Quarantining a peer again replaces its expiry. Fails if `peer` isn't one of our peers
+    pub fn quarantine(&mut self, peer: I, ticks: Ticks) -> Result<(), I> {
+        if !self.peers.contains(&peer) {
+            return Err(RaftError::UnknownPeer(peer));
+        }
+        Logger::quarantine(self, &peer, ticks);
+        self.quarantined
+            .insert(peer, self.ticks.saturating_add(ticks));
+        Ok(())
+    }
+
+    /// Let a [quarantined](Self::quarantine) peer back in before its quarantine is over.
+    /// Does nothing if it isn't quarantined
+    pub fn release(&mut self, peer: &I) {
+        if self.quarantined.remove(peer).is_some() {
+            Logger::quarantine_over(self, peer);
+        }
+    }
+
+    /// Peers currently [quarantined](Self::quarantine), with the tick each one is let back
+    /// in at
+    pub fn quarantined(&self) -> &BTreeMap<I, Ticks> {
+        &self.quarantined
+    }
+
+    /// Let back in the peers whose quarantine is over
+    fn expire_quarantines(&mut self) {
+        while let Some(peer) = self
+            .quarantined
+            .iter()
+            .find(|(_, until)| **until <= self.ticks)
+            .map(|(peer, _)| peer.clone())
+        {
+            self.release(&peer);
+        }
+    }
+
+    /// Freeze the node: [`tick`](Self::tick) leaves its timers alone, RPCs are held on to
+    /// (up to [`MAX_PAUSED_RPCS`]) instead of handled, and proposals fail with
+    /// [`RaftError::Paused`]. Its state stays exactly as it is until it is
+    /// [resumed](Self::resume), e.g. to take a consistent backup or to stop it in a debugger
+    /// without it missing anything. To the rest of the cluster a paused node looks like one
+    /// behind a slow network: a paused leader gets replaced once followers time out.
+    /// Does nothing if the node is already paused
+    pub fn pause(&mut self) {
+        if self.paused.is_none() {
+            Logger::paused(self);
+            self.paused = Some(Vec::new());
+        }
+    }
+
+    /// Pick up where [`pause`](Self::pause) left off, handling every RPC that came in
+    /// meanwhile in the order it arrived, and returning the messages sent in response.
+    /// Timers carry on from where they were frozen. Does nothing if the node isn't paused
+    pub fn resume(&mut self) -> Vec<SendableMessage<T, I>> {
+        let Some(buffered) = self.paused.take() else {
+            return vec![];
+        };
+        Logger::resumed(self, buffered.len());
+        buffered
+            .iter()
+            .flat_map(|rpc| self.receive_rpc(rpc))
+            .collect()
+    }
+
+    /// Get a handle that always holds the [term](Self::current_term) the node is in and
+    /// the [leader](Self::leader_id) it knows of for that term, e.g. for a proxy or lock
+    /// holder that has to fence off requests from a deposed leader. Whoever holds it can
+    /// wait for the next change with `changed().await`
+    #[cfg(feature = "tokio")]
+    pub fn watch_leadership(&mut self) -> tokio::sync::watch::Receiver<(Term, Option<I>)> {
+        let current = (self.current_term, self.leader_id());
+        self.observers.watch_leadership(current)
+    }
+
+    /// Update the [leadership watch](Self::watch_leadership), if anyone is watching
+    #[cfg(feature = "tokio")]
+    fn publish_leadership(&mut self) {
+        if self.observers.watching_leadership() {
+            let leader = self.leader_id();
+            self.observers.leadership_changed(self.current_term, leader);
+        }
+    }
+
+    /// Whether the node is [paused](Self::pause)
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Follower we are handing leadership to, while we are, see
+    /// [`transfer_leadership`](Self::transfer_leadership)
+    pub fn leadership_transfer(&self) -> Option<I> {
+        match &self.leadership_state {
+            RaftLeadershipState::Leader(LeaderState {
+                transfer: Some(transfer),
+                ..
+            }) => Some(transfer.target.clone()),
+            _ => None,
+        }
+    }
+
+    /// Follower that leadership is being handed to, as far as we know: by us if we are
+    /// leader and the transfer hasn't timed out, by our leader if we follow one
+    fn transfer_in_progress(&self) -> Option<&I> {
+        match &self.leadership_state {
+            RaftLeadershipState::Leader(LeaderState {
+                transfer: Some(transfer),
+                ..
+            }) if self.ticks < transfer.deadline => Some(&transfer.target),
+            RaftLeadershipState::Follower(state) if self.ticks < state.transfer_deadline => {
+                state.transfer_target.as_ref()
+            }
+            _ => None,
         }
     }
 
@@ -617,9 +2072,17 @@ where
     }
 }
 
+/// Trace context of the current span, only ever set with the `opentelemetry` feature
+fn current_trace() -> Option<TraceContext> {
+    #[cfg(feature = "opentelemetry")]
+    return otel::current();
+    #[cfg(not(feature = "opentelemetry"))]
+    None
+}
+
 /// Returns a random u32 uniformly from (expected)
 fn rng_jitter(rng: &mut ChaCha8Rng, expected: u32, jitter: u32) -> u32 {
-    let low = expected - jitter;
+    let low = expected.saturating_sub(jitter);
     let hi = expected + jitter;
     rng.gen_range(low..=hi)
 }
@@ -0,0 +1,181 @@
+use super::{
+    FollowerState, NodeId, NodeReplicationState, RaftLeadershipState, RaftServer, RoleBehavior,
+    ServerId, Term, Ticks, TieBreak,
+};
+use crate::{
+    debug::Logger,
+    rpc::{SendableMessage, Target, VoteRequest, VoteResponse, RPC},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
+
+/// [`Candidate`](RaftLeadershipState::Candidate) specific volatile state
+pub struct CandidateState<I = ServerId> {
+    /// Ticks left to start an election if quorum is not reached
+    pub(super) election_time: Ticks,
+    /// Set of all nodes this node has received votes for
+    pub(super) votes_received: BTreeSet<I>,
+    /// Term this election is being held in
+    pub(super) term: Term,
+    /// Tick the election started at
+    pub(super) started_at: Ticks,
+}
+
+/// Asks everyone for their vote, until it has a quorum or hears from a leader
+pub(super) struct Candidate;
+
+impl<T, S, I> RoleBehavior<T, S, I> for Candidate
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    fn timer_ran_out(&self, server: &mut RaftServer<T, S, I>) -> Vec<SendableMessage<T, I>> {
+        // nobody won the election in time, try again in the next term
+        server.start_election(true)
+    }
+
+    fn vote_response(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        res: &VoteResponse<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        let quorum = server.quorum_size();
+        let RaftLeadershipState::Candidate(state) = &mut server.leadership_state else {
+            return vec![];
+        };
+        if !server.peers.contains(&res.votee_id) {
+            // a stray node's vote must not count towards our quorum
+            Logger::unknown_follower(server, &res.votee_id, "VoteResponse");
+            server.metrics.responses_from_unknown_followers += 1;
+            return vec![];
+        }
+        let up_to_date = res.term == server.current_term;
+        // only process the vote if the votee is voting for our current term, and the vote
+        // was positive
+        Logger::vote_count(&server.id, res, up_to_date);
+        if !up_to_date || !res.vote_granted {
+            return vec![];
+        }
+
+        // add this to votes received
+        state.votes_received.insert(res.votee_id.clone());
+        Logger::total_vote_count(&server.id, state.votes_received.len(), quorum);
+        if state.votes_received.len() < quorum {
+            // if less than quorum, do nothing
+            return vec![];
+        }
+
+        // otherwise, we won election! promote self to leader
+        // initialize followers to all nodes except for ourselves
+        let mut followers = BTreeMap::new();
+        server
+            .peers
+            .iter()
+            .filter(|votee| **votee != server.id)
+            .for_each(|votee| {
+                // add that votee to our list of followers
+                if followers
+                    .insert(
+                        votee.clone(),
+                        NodeReplicationState {
+                            sent_up_to: server.log.last_idx(),
+                            ..Default::default()
+                        },
+                    )
+                    .is_none()
+                {
+                    Logger::added_follower(server, votee)
+                };
+            });
+        server.promote_to_leader(followers)
+    }
+}
+
+impl<T, S, I> RaftServer<T, S, I>
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    /// Become candidate in the next term, vote for ourselves and ask everyone else for
+    /// their vote. `timed_out` is false when the leader asked us to take over
+    pub(super) fn start_election(&mut self, timed_out: bool) -> Vec<SendableMessage<T, I>> {
+        self.set_term(self.current_term + 1);
+        self.metrics.elections_started += 1;
+        if timed_out {
+            Logger::election_timer_expired(self);
+        } else {
+            Logger::taking_over_leadership(self);
+        }
+
+        // vote for self
+        self.voted_for = Some(self.id.clone());
+        let mut vote_list = BTreeSet::new();
+        vote_list.insert(self.id.clone());
+
+        let election_time = self.random_election_time();
+        self.set_leadership_state(RaftLeadershipState::Candidate(CandidateState {
+            election_time,
+            votes_received: vote_list,
+            term: self.current_term,
+            started_at: self.ticks,
+        }));
+
+        // see if we can instantly become leader
+        // (if cluster size is 1)
+        if 1 == self.quorum_size() {
+            return self.promote_to_leader(BTreeMap::new());
+        }
+
+        // otherwise, stay candidate as normal
+        Logger::state_update(self);
+
+        // broadcast message to all nodes asking for a vote
+        let rpc = RPC::VoteRequest(VoteRequest {
+            candidate_term: self.current_term,
+            candidate_id: self.id.clone(),
+            candidate_last_log_idx: self.log.last_idx(),
+            candidate_last_log_term: self.log.last_term(),
+            request_id: 0, // stamped on the way out
+        });
+        Logger::outgoing_rpcs(self, vec![(Target::Broadcast, rpc)])
+    }
+
+    /// Whether the [tie break](super::RaftConfig::tie_break) has us step aside for `req`,
+    /// from another candidate of the election we are standing in
+    pub(super) fn yields_to(&self, req: &VoteRequest<I>) -> bool {
+        let RaftLeadershipState::Candidate(state) = &self.leadership_state else {
+            return false;
+        };
+        if req.candidate_term != state.term {
+            return false;
+        }
+        let lower_id = req.candidate_id < self.id;
+        match self.config.tie_break {
+            TieBreak::Never => false,
+            TieBreak::LowerId => lower_id,
+            TieBreak::LongerLog => {
+                let theirs = (req.candidate_last_log_term, req.candidate_last_log_idx);
+                let ours = (self.log.last_term(), self.log.last_idx());
+                theirs > ours || (theirs == ours && lower_id)
+            }
+        }
+    }
+
+    /// Give up our candidacy in favour of `candidate`. We keep our vote for ourselves, but
+    /// sit out until the candidate's election has run out, so it gets our vote in the next
+    /// term rather than having to compete with us again
+    pub(super) fn step_aside(&mut self, candidate: &I) {
+        Logger::stepping_aside(self, candidate);
+        // longer than whatever is left of the candidate's election timer
+        let election_time = self.config.election_timeout + self.config.election_timeout_jitter + 1;
+        self.set_leadership_state(RaftLeadershipState::Follower(FollowerState {
+            leader: None,
+            election_time,
+            transfer_target: None,
+            transfer_deadline: 0,
+        }));
+        Logger::state_update(self);
+    }
+}
@@ -0,0 +1,157 @@
+use super::{NodeId, RaftLeadershipState, RaftServer, RoleBehavior, ServerId, Ticks};
+use crate::{
+    clock::Instant,
+    debug::Logger,
+    event::SlowOperation,
+    rpc::{
+        AppendRejection, AppendRequest, AppendResponse, SendableMessage, Target, TimeoutNow, RPC,
+    },
+};
+use std::{
+    cmp::{max, min},
+    fmt::Debug,
+    iter,
+};
+
+/// [`Follower`](RaftLeadershipState::Follower) specific volatile state
+pub struct FollowerState<I = ServerId> {
+    /// Ticks left to start an election if not reset by activity/heartbeat
+    pub(super) election_time: Ticks,
+    /// Current leader node is following
+    pub(super) leader: Option<I>,
+    /// Follower the leader said it is handing leadership to, the only one we vote for
+    /// until [`transfer_deadline`](Self::transfer_deadline)
+    pub(super) transfer_target: Option<I>,
+    /// Tick the leader's transfer times out at
+    pub(super) transfer_deadline: Ticks,
+}
+
+/// Issues no requests of its own, it appends whatever the leader sends it
+pub(super) struct Follower;
+
+impl<T, S, I> RoleBehavior<T, S, I> for Follower
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    fn timer_ran_out(&self, server: &mut RaftServer<T, S, I>) -> Vec<SendableMessage<T, I>> {
+        // suspect leader has failed, election timeout reached
+        // attempt to become candidate
+        server.start_election(true)
+    }
+
+    fn append_request(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        req: &AppendRequest<T, I>,
+        election_time: Ticks,
+    ) -> Vec<SendableMessage<T, I>> {
+        let RaftLeadershipState::Follower(state) = &mut server.leadership_state else {
+            return vec![];
+        };
+        // if leader is same term as us, we accept requester as current leader
+        Logger::check_matching_term(&server.id, req, server.current_term);
+        let rejection = if req.leader_term != server.current_term {
+            Some(AppendRejection::StaleTerm) // bad request if we have mismatched terms
+        } else if !well_formed(req) {
+            // don't let it count as a sign of life from the leader either
+            Logger::malformed_append_request(&server.id, req);
+            Some(AppendRejection::Malformed)
+        } else {
+            state.election_time = election_time;
+            state.leader = Some(req.leader_id.clone());
+            state.transfer_target = req.transfer_target.clone();
+            state.transfer_deadline = server.ticks + req.transfer_ticks_left;
+
+            // check if we have the messages that the leader is claiming we have
+            let prefix_len = req.leader_last_log_idx;
+            let prefix_ok = server.log.len() >= prefix_len;
+            // anything inside our snapshot is committed and so matches the leader
+            let last_entry_matches_terms = prefix_len < server.log.snapshot.len
+                || server.log.term_at(prefix_len) == Some(req.leader_last_log_term);
+
+            Logger::append_entries(server, prefix_ok, last_entry_matches_terms, prefix_len);
+            if prefix_ok && last_entry_matches_terms {
+                // assumptions match, append it to our local log. only what the request
+                // carries is known to match the leader, anything we hold past that might not
+                let leader_commit = min(req.leader_commit, prefix_len + req.entries.len());
+                let (committed_len, applied_len) =
+                    (server.log.committed_len, server.log.applied_len);
+                let started = Instant::now();
+                server
+                    .log
+                    .append_entries(prefix_len, leader_commit, req.entries.to_vec());
+                let elapsed = started.elapsed();
+                let entries = server.log.applied_len - applied_len;
+                if entries > 0 {
+                    server.check_slow(SlowOperation::Apply { entries }, elapsed);
+                }
+                server.metrics.entries_committed +=
+                    (server.log.committed_len - committed_len) as u64;
+                server.metrics.entries_applied += (server.log.applied_len - applied_len) as u64;
+                server
+                    .observers
+                    .committed(committed_len, server.log.committed_len);
+                None // success
+            } else {
+                // bad request if we have mismatched assumptions about where the log is
+                Some(AppendRejection::LogMismatch)
+            }
+        };
+
+        // send response
+        let success = rejection.is_none();
+        if !success {
+            server.metrics.append_requests_rejected += 1;
+        }
+        let ack_idx = if success {
+            // our snapshot is committed, so it matches the leader's log even when the
+            // request ended before it
+            max(
+                req.leader_last_log_idx + req.entries.len(),
+                server.log.snapshot.len,
+            )
+        } else {
+            0
+        };
+        let rpc = RPC::AppendResponse(AppendResponse {
+            ok: success,
+            rejection,
+            term: server.current_term,
+            ack_idx,
+            follower_id: server.id.clone(),
+            last_applied: server.log.last_applied(),
+            request_id: req.request_id,
+            trace: super::current_trace(),
+        });
+        vec![(Target::Single(req.leader_id.clone()), rpc)]
+    }
+
+    fn timeout_now(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        req: &TimeoutNow<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        // only the leader we are following can hand over, anything else is a stale message
+        let from_leader = req.leader_term == server.current_term
+            && server.leader_id().as_ref() == Some(&req.leader_id);
+        if from_leader {
+            server.start_election(false)
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Whether `req` could have come from a correct leader: the terms of its entries never go
+/// down, starting from the entry they follow on from and ending at the leader's own term
+fn well_formed<T, I>(req: &AppendRequest<T, I>) -> bool {
+    let terms = || {
+        iter::once(req.leader_last_log_term)
+            .chain(req.entries.iter().map(|entry| entry.term))
+            .chain(iter::once(req.leader_term))
+    };
+    terms()
+        .zip(terms().skip(1))
+        .all(|(term, next)| term <= next)
+}
@@ -0,0 +1,682 @@
+use super::{
+    NodeId, NodeReplicationState, RaftLeadershipState, RaftServer, RoleBehavior, ServerId, Term,
+    Ticks,
+};
+#[cfg(feature = "opentelemetry")]
+use crate::otel;
+use crate::{
+    clock::Instant,
+    debug::Logger,
+    error::{RaftError, Result},
+    event::SlowOperation,
+    log::{LogIndex, SharedEntries},
+    rpc::{
+        AppendRejection, AppendRequest, AppendResponse, ReadId, ReadIndexRequest, RequestId,
+        SendableMessage, SnapshotRequest, SnapshotResponse, Target, TimeoutNow, RPC,
+    },
+};
+use std::{
+    cell::OnceCell,
+    cmp::{max, min},
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    mem,
+    time::Duration,
+};
+
+/// [`Leader`](RaftLeadershipState::Leader) specific volatile state
+pub struct LeaderState<I = ServerId> {
+    /// Track state about followers to figure out what to send them next
+    pub(super) followers: BTreeMap<I, NodeReplicationState>,
+    /// Ticks left till when to send the next heartbeat
+    pub(super) heartbeat_timeout: Ticks,
+    /// Follower we are handing leadership to, if we are
+    pub(super) transfer: Option<LeadershipTransfer<I>>,
+    /// Proposals since entries last went out, with
+    /// [`replication_batching`](super::RaftConfig::replication_batching)
+    pub(super) batched: LogIndex,
+    /// Reads waiting on a quorum to confirm we are still leader, oldest first
+    pub(super) reads: Vec<PendingRead<I>>,
+}
+
+/// Leadership handover a leader is in the middle of, see [`RaftServer::transfer_leadership`]
+pub(super) struct LeadershipTransfer<I> {
+    /// Follower taking over
+    pub(super) target: I,
+    /// Tick at which we give up on the target and take client requests again
+    pub(super) deadline: Ticks,
+}
+
+/// Read a leader vouches for once it has heard from a quorum, see
+/// [`RaftServer::read_barrier`]
+pub(super) struct PendingRead<I> {
+    /// Node that started the read, possibly us
+    pub(super) reader: I,
+    /// Which of the reader's reads it is
+    pub(super) read_id: ReadId,
+    /// Our committed length when the read came in
+    pub(super) read_len: LogIndex,
+    /// First request we sent after the read came in, only answers to it or later ones show
+    /// we were still leader by then
+    pub(super) first_request: RequestId,
+    /// Followers that answered such a request
+    pub(super) confirmed_by: BTreeSet<I>,
+}
+
+/// Takes proposals and replicates them to everyone else, until it hears of a newer term
+pub(super) struct Leader;
+
+impl<T, S, I> RoleBehavior<T, S, I> for Leader
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    fn timer_ran_out(&self, server: &mut RaftServer<T, S, I>) -> Vec<SendableMessage<T, I>> {
+        Logger::send_heartbeat(server);
+        let msgs = server.replicate_log(Target::Broadcast);
+        server.metrics.heartbeats_sent += msgs.len() as u64;
+        // with batching the next batch starts here, and the heartbeat is only due again
+        // after a full interval
+        let interval = server.config.heartbeat_interval;
+        let batching = server.config.replication_batching.is_some();
+        if let RaftLeadershipState::Leader(state) = &mut server.leadership_state {
+            if batching {
+                state.heartbeat_timeout = interval;
+                state.batched = 0;
+            }
+        }
+        Logger::outgoing_rpcs(server, msgs)
+    }
+
+    fn append_response(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        res: &AppendResponse<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        let RaftLeadershipState::Leader(state) = &mut server.leadership_state else {
+            return vec![];
+        };
+        if res.term != server.current_term {
+            // a response from an older term answers a request sent by an earlier leader
+            // (possibly us), delayed by the network. It says nothing about the follower now
+            return vec![];
+        }
+        // make sure that the response was ok and the length that the follower is
+        // at is greater than what we have recorded for them before
+        let Some(follower_state) = state.followers.get_mut(&res.follower_id) else {
+            Logger::unknown_follower(server, &res.follower_id, "AppendResponse");
+            server.metrics.responses_from_unknown_followers += 1;
+            return vec![];
+        };
+        follower_state.last_response_tick = Some(server.ticks);
+        follower_state.applied_up_to = res.last_applied;
+        follower_state.inflight = follower_state.inflight.saturating_sub(1);
+
+        Logger::process_append_response(&server.id, res, follower_state);
+        if res.ok {
+            // a duplicated or late ack can be behind what we already know about the
+            // follower, in which case there is nothing to learn from it
+            if res.ack_idx >= follower_state.acked_up_to {
+                // update replication state, we know follower has sent + acked up
+                // to `replication_state.ack_idx`
+                follower_state.sent_up_to = res.ack_idx;
+                follower_state.acked_up_to = res.ack_idx;
+                // a heartbeat can beat the answer to our snapshot to showing it got there
+                if follower_state
+                    .snapshot_in_flight
+                    .as_ref()
+                    .is_some_and(|transfer| res.ack_idx >= transfer.entries)
+                {
+                    follower_state.snapshot_in_flight = None;
+                }
+                follower_state.update_catch_up(server.log.len(), server.ticks);
+                // try to formally commit these entries, no need to respond
+                server.commit_log_entries();
+            }
+            // unless this is the follower we are handing leadership to
+            server.continue_transfer(&res.follower_id)
+        } else if res.rejection == Some(AppendRejection::Malformed) {
+            // backing off won't change what the follower thinks is wrong with our requests
+            vec![]
+        } else if follower_state.snapshot_in_flight.is_some() {
+            // the heartbeats we send while it installs our snapshot don't match its log,
+            // the snapshot is what catches it up
+            vec![]
+        } else if follower_state.sent_up_to > 0 {
+            // if there's a gap in the log, res.ok is not true!
+            // reduce what we assume the client has received by one and try again
+
+            follower_state.sent_up_to = follower_state.sent_up_to.saturating_sub(1);
+            server.replicate_log(Target::Single(res.follower_id.clone()))
+        } else {
+            // a late rejection of a request we have already backed off past
+            vec![]
+        }
+    }
+
+    fn read_index_request(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        req: &ReadIndexRequest<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        server.start_read(req.reader_id.clone(), req.read_id)
+    }
+
+    fn snapshot_response(
+        &self,
+        server: &mut RaftServer<T, S, I>,
+        res: &SnapshotResponse<I>,
+    ) -> Vec<SendableMessage<T, I>> {
+        let RaftLeadershipState::Leader(state) = &mut server.leadership_state else {
+            return vec![];
+        };
+        if res.term != server.current_term {
+            return vec![];
+        }
+        let Some(follower_state) = state.followers.get_mut(&res.follower_id) else {
+            Logger::unknown_follower(server, &res.follower_id, "SnapshotResponse");
+            server.metrics.responses_from_unknown_followers += 1;
+            return vec![];
+        };
+        follower_state.last_response_tick = Some(server.ticks);
+        follower_state.inflight = follower_state.inflight.saturating_sub(1);
+
+        // pick up replicating from wherever the follower is now,
+        // entries after the snapshot go out with the next heartbeat
+        follower_state.sent_up_to = res.ack_idx;
+        follower_state.acked_up_to = max(follower_state.acked_up_to, res.ack_idx);
+        follower_state.snapshot_in_flight = None;
+        follower_state.update_catch_up(server.log.len(), server.ticks);
+        server.commit_log_entries();
+        vec![]
+    }
+}
+
+impl<T, S, I> RaftServer<T, S, I>
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    /// Manually promote node to leader. Do not call during normal operation.
+    pub fn promote_to_leader(
+        &mut self,
+        followers: BTreeMap<I, NodeReplicationState>,
+    ) -> Vec<SendableMessage<T, I>> {
+        let num_votes = followers.len() + 1;
+        let follower_ids: Vec<I> = followers.keys().cloned().collect();
+
+        // set state to leader
+        self.metrics.elections_won += 1;
+        self.note_leader(self.id.clone());
+        self.leader_since = Some(self.ticks);
+        self.log.leader_term = Some(self.current_term);
+        self.set_leadership_state(RaftLeadershipState::Leader(LeaderState {
+            followers,
+            heartbeat_timeout: self.config.heartbeat_interval,
+            transfer: None,
+            batched: 0,
+            reads: Vec::new(),
+        }));
+        Logger::won_election(self, num_votes, &follower_ids);
+
+        // then replicate our logs to all our followers
+        self.replicate_log(Target::Broadcast)
+    }
+
+    /// Replicate some section of our log entries to followers.
+    /// Intended to only be called when we are a Leader, do nothing otherwise
+    pub(super) fn replicate_log(&mut self, target: Target<I>) -> Vec<SendableMessage<T, I>> {
+        let spilled = self.fetch_spilled(&target);
+        if let RaftLeadershipState::Leader(state) = &self.leadership_state {
+            // prefix len is the index of all the entries we have sent up to. it comes
+            // from what followers acked, so never trust it to be inside our log
+            let sent_up_to =
+                |follower: &NodeReplicationState| min(follower.sent_up_to, self.log.len());
+            // the entries followers are missing are copied out of our log once, from where
+            // the one furthest behind needs them, and shared between all the requests
+            let shared_from = state
+                .followers
+                .iter()
+                .filter(|(id, _)| match &target {
+                    Target::Single(target) => *id == target,
+                    Target::Broadcast => true,
+                })
+                .filter(|(id, _)| !self.quarantined.contains_key(id))
+                .map(|(_, follower)| sent_up_to(follower))
+                .filter(|prefix_len| *prefix_len >= self.log.snapshot.len)
+                .min()
+                .unwrap_or(self.log.len());
+            let shared = OnceCell::new();
+            // followers that already have our whole log only need a heartbeat, which is the
+            // same for all of them, so it is built once and copied. That touches neither the
+            // log nor any entries, so heartbeats cost the same however long the log gets
+            let heartbeat = OnceCell::new();
+            // construct closure for the sending logic so we don't need
+            // to duplicate logic
+
+            let send_heartbeat = |target: &I| {
+                Logger::replicate_entries(self, &[], target, self.log.len());
+                let rpc = heartbeat.get_or_init(|| {
+                    self.append_request(
+                        self.log.len(),
+                        self.log.last_term(),
+                        SharedEntries::default(),
+                    )
+                });
+                Some((Target::Single(target.clone()), rpc.clone()))
+            };
+
+            let sending_logic = |(target, follower): (&I, &NodeReplicationState)| {
+                if self.quarantined.contains_key(target) {
+                    return None;
+                }
+                let prefix_len = sent_up_to(follower);
+                if prefix_len == self.log.len() {
+                    return send_heartbeat(target);
+                }
+
+                // the entries this follower needs next were compacted away. Unless
+                // they can be read back from where they were spilled to, the only way to
+                // catch it up is to send over our snapshot
+                if let Some((prefix_term, entries)) = spilled.get(target) {
+                    Logger::replicate_spilled(self, target, prefix_len, entries.len());
+                    let rpc = self.append_request(prefix_len, *prefix_term, entries.clone());
+                    return Some((Target::Single(target.clone()), rpc));
+                }
+                if prefix_len < self.log.snapshot.len {
+                    // it's big, so unless the one we sent got lost, let the follower get
+                    // on with installing it rather than piling up copies. A heartbeat
+                    // still keeps it from calling an election meanwhile
+                    let retry_at = follower
+                        .snapshot_in_flight
+                        .as_ref()
+                        .map(|transfer| transfer.last_sent_at + self.config.election_timeout);
+                    if retry_at.is_some_and(|retry_at| self.ticks < retry_at) {
+                        return send_heartbeat(target);
+                    }
+                    Logger::replicate_snapshot(self, target);
+                    let rpc = RPC::SnapshotRequest(SnapshotRequest {
+                        leader_id: self.id.clone(),
+                        leader_term: self.current_term,
+                        snapshot: self.log.snapshot.clone(),
+                    });
+                    return Some((Target::Single(target.clone()), rpc));
+                }
+
+                // anything from the snapshot to the end of our log has a known term
+                let prefix_term = self.log.term_at(prefix_len)?;
+                let shared = shared.get_or_init(|| {
+                    SharedEntries::from(self.log.entries_from(shared_from).to_vec())
+                });
+                let entries = shared.skip(prefix_len - shared_from);
+                Logger::replicate_entries(self, &entries, target, prefix_len);
+
+                let rpc = self.append_request(prefix_len, prefix_term, entries);
+                Some((Target::Single(target.clone()), rpc))
+            };
+
+            match target {
+                Target::Single(target) => state
+                    .followers
+                    .get_key_value(&target)
+                    .and_then(sending_logic)
+                    .into_iter()
+                    .collect(),
+                Target::Broadcast => state.followers.iter().filter_map(sending_logic).collect(),
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Entries from before our snapshot for the followers `target` covers that need them,
+    /// read back from [spill storage](RaftServer::spill_to), each along with the term of the
+    /// entry they follow on from. Followers the storage has nothing for are left out
+    fn fetch_spilled(&mut self, target: &Target<I>) -> BTreeMap<I, (Term, SharedEntries<T>)> {
+        let mut fetched = BTreeMap::new();
+        let (Some(storage), RaftLeadershipState::Leader(state)) =
+            (&mut self.spill, &self.leadership_state)
+        else {
+            return fetched;
+        };
+        let snapshot_len = self.log.snapshot.len;
+        let mut failed = None;
+        for (id, follower) in &state.followers {
+            let prefix_len = min(follower.sent_up_to, self.log.len());
+            let targeted = match target {
+                Target::Single(target) => target == id,
+                Target::Broadcast => true,
+            };
+            if !targeted || prefix_len >= snapshot_len || self.quarantined.contains_key(id) {
+                continue;
+            }
+            // the entry before the first one to send is only read for its term
+            let from = prefix_len.saturating_sub(1);
+            let batch = self
+                .config
+                .max_log_entries
+                .unwrap_or(LogIndex::MAX)
+                .min(snapshot_len - prefix_len);
+            let entries = match storage.fetch(from, batch + (prefix_len - from)) {
+                Ok(entries) => SharedEntries::from(entries),
+                Err(err) => {
+                    failed = Some(err);
+                    continue;
+                }
+            };
+            let (prefix_term, entries) = match prefix_len {
+                0 => (0, entries),
+                _ => match entries.first() {
+                    Some(prefix) => (prefix.term, entries.skip(1)),
+                    None => continue,
+                },
+            };
+            if !entries.is_empty() {
+                fetched.insert(id.clone(), (prefix_term, entries));
+            }
+        }
+        if let Some(err) = failed {
+            Logger::fetch_failed(self, &err);
+        }
+        fetched
+    }
+
+    /// Bring the next replication forward for a proposal that was just appended, with
+    /// [`replication_batching`](super::RaftConfig::replication_batching). Without it, the
+    /// proposal waits for the next heartbeat
+    pub(super) fn batch_proposal(&mut self) {
+        let (Some(batching), RaftLeadershipState::Leader(state)) = (
+            &self.config.replication_batching,
+            &mut self.leadership_state,
+        ) else {
+            return;
+        };
+        state.batched += 1;
+        let due = if state.batched >= batching.max_entries {
+            1
+        } else {
+            batching.delay
+        };
+        state.heartbeat_timeout = min(state.heartbeat_timeout, due);
+    }
+
+    /// Tell every follower right away that our commit index moved on from
+    /// `old_committed_len`, with [`broadcast_commits`](super::RaftConfig::broadcast_commits).
+    /// Without it, they find out with the next heartbeat or append. Followers that `sent`
+    /// already has a request for are left out, that request tells them just as well
+    pub(super) fn broadcast_commit(
+        &mut self,
+        old_committed_len: LogIndex,
+        sent: &[SendableMessage<T, I>],
+    ) -> Vec<SendableMessage<T, I>> {
+        if !self.config.broadcast_commits || self.log.committed_len == old_committed_len {
+            return vec![];
+        }
+        let RaftLeadershipState::Leader(state) = &self.leadership_state else {
+            return vec![];
+        };
+        let already_sent = |follower: &I| {
+            sent.iter().any(|(target, rpc)| {
+                *target == Target::Single(follower.clone())
+                    && matches!(rpc, RPC::AppendRequest(_) | RPC::SnapshotRequest(_))
+            })
+        };
+        let rest: Vec<I> = state
+            .followers
+            .keys()
+            .filter(|follower| !already_sent(follower))
+            .cloned()
+            .collect();
+        Logger::broadcast_commit(self);
+        if rest.len() == state.followers.len() {
+            return self.replicate_log(Target::Broadcast);
+        }
+        rest.into_iter()
+            .flat_map(|follower| self.replicate_log(Target::Single(follower)))
+            .collect()
+    }
+
+    /// AppendRequest carrying `entries` to go after the first `prefix_len` entries of our log,
+    /// the last of which has term `prefix_term`
+    fn append_request(
+        &self,
+        prefix_len: LogIndex,
+        prefix_term: Term,
+        entries: SharedEntries<T>,
+    ) -> RPC<T, I> {
+        let (transfer_target, transfer_ticks_left) = match &self.leadership_state {
+            RaftLeadershipState::Leader(LeaderState {
+                transfer: Some(transfer),
+                ..
+            }) => (
+                Some(transfer.target.clone()),
+                transfer.deadline.saturating_sub(self.ticks),
+            ),
+            _ => (None, 0),
+        };
+        RPC::AppendRequest(AppendRequest {
+            entries,
+            leader_id: self.id.clone(),
+            leader_term: self.current_term,
+            leader_commit: self.log.committed_len,
+            transfer_target,
+            transfer_ticks_left,
+            leader_last_log_idx: prefix_len,
+            leader_last_log_term: prefix_term,
+            trace: self.append_trace(prefix_len),
+            request_id: 0, // stamped on the way out
+        })
+    }
+
+    /// Commit any log entries that have been acknowledged by a quorum of nodes.
+    /// When a log entry is committed, its message is delivered to the application.
+    pub(super) fn commit_log_entries(&mut self) {
+        // acks for heartbeats, with everything already committed, change nothing
+        if self.log.committed_len == self.log.len() {
+            return;
+        }
+        let quorum_size = self.quorum_size();
+        let old_committed_len = self.log.committed_len;
+        let mut apply_time = Duration::ZERO;
+        // we only count ourselves for what we know we have on disk
+        let own_len = if self.config.persist_in_background {
+            min(self.log.persisted_len, self.log.len())
+        } else {
+            self.log.len()
+        };
+        if let RaftLeadershipState::Leader(state) = &mut self.leadership_state {
+            // how far each node has acked our log, including ourselves. Peers we have
+            // not heard from yet count as having nothing
+            let mut acked: Vec<LogIndex> = state
+                .followers
+                .values()
+                .map(|follower_state| follower_state.acked_up_to)
+                .chain([own_len])
+                .collect();
+            acked.resize(max(acked.len(), self.peers.len() + 1), 0);
+            // the longest prefix of the log that a quorum of nodes have is the quorum-th
+            // highest of those
+            acked.sort_unstable_by(|a, b| b.cmp(a));
+            let quorum_len = acked[quorum_size - 1];
+            Logger::commit_entries(&self.id, self.log.committed_len, quorum_len, quorum_size);
+
+            // only entries from our own term are committed by counting replicas, older ones
+            // get committed along with them: an older entry on a majority can still be
+            // overwritten by a leader of a later term (figure 8 of the Raft paper). Terms
+            // only grow along the log, so if the last entry isn't ours, none before it are
+            let commit_len = if quorum_len > self.log.committed_len
+                && self.log.term_at(quorum_len) == Some(self.current_term)
+            {
+                quorum_len
+            } else {
+                self.log.committed_len
+            };
+
+            // deliver everything up to there to the application
+            while self.log.committed_len < commit_len {
+                #[cfg(feature = "opentelemetry")]
+                let _apply = self
+                    .proposal_traces
+                    .remove(&self.log.committed_len)
+                    .map(|trace| otel::apply_span(self.log.committed_len, &trace).entered());
+                let started = Instant::now();
+                self.log.deliver_msg();
+                apply_time += started.elapsed();
+                self.log.committed_len += 1;
+                self.metrics.entries_committed += 1;
+                self.metrics.entries_applied += 1;
+            }
+        }
+        let entries = self.log.committed_len - old_committed_len;
+        if entries > 0 {
+            self.check_slow(SlowOperation::Apply { entries }, apply_time);
+        }
+        self.observers
+            .committed(old_committed_len, self.log.committed_len);
+    }
+
+    /// Hand leadership to `target`, or to the follower that is furthest along if `None`, e.g.
+    /// before taking this node down for maintenance. The target is first sent whatever it
+    /// is missing of our log and then told to start an election, which it is sure to win
+    /// since nobody has a more up to date log. Until then proposals are turned away with
+    /// [`RaftError::NotLeader`] pointing at the target.
+    /// The transfer is given up on if the target hasn't taken over within an election
+    /// timeout. Fails if we aren't leader, or `target` isn't one of our followers
+    pub fn transfer_leadership(
+        &mut self,
+        target: Option<I>,
+    ) -> Result<Vec<SendableMessage<T, I>>, I> {
+        let RaftLeadershipState::Leader(state) = &mut self.leadership_state else {
+            return Err(RaftError::NotLeader {
+                leader: self.leader_id(),
+            });
+        };
+        let target = match target {
+            Some(target) if state.followers.contains_key(&target) => target,
+            Some(target) => return Err(RaftError::UnknownPeer(target)),
+            None => state
+                .followers
+                .iter()
+                .max_by_key(|(_, follower)| follower.acked_up_to)
+                .map(|(id, _)| id.clone())
+                .ok_or_else(|| {
+                    RaftError::InvalidRequest("no follower to hand leadership to".to_owned())
+                })?,
+        };
+        state.transfer = Some(LeadershipTransfer {
+            target: target.clone(),
+            deadline: self.ticks + self.config.election_timeout,
+        });
+        Logger::transfer_leadership(self, &target);
+
+        let mut msgs = self.continue_transfer(&target);
+        self.track_outgoing(&mut msgs);
+        Ok(Logger::outgoing_rpcs(self, msgs))
+    }
+
+    /// Move a leadership transfer to `follower` along: tell it to start its election once it
+    /// has every entry we have, otherwise send it what it is missing. Does nothing unless we
+    /// are transferring leadership to `follower`
+    pub(super) fn continue_transfer(&mut self, follower: &I) -> Vec<SendableMessage<T, I>> {
+        let RaftLeadershipState::Leader(LeaderState {
+            followers,
+            transfer: Some(transfer),
+            ..
+        }) = &self.leadership_state
+        else {
+            return vec![];
+        };
+        if transfer.target != *follower {
+            return vec![];
+        }
+        let caught_up = followers
+            .get(follower)
+            .is_some_and(|state| state.acked_up_to >= self.log.len());
+        if !caught_up {
+            return self.replicate_log(Target::Single(follower.clone()));
+        }
+        let rpc = RPC::TimeoutNow(TimeoutNow {
+            leader_term: self.current_term,
+            leader_id: self.id.clone(),
+        });
+        vec![(Target::Single(follower.clone()), rpc)]
+    }
+
+    /// Give up on a leadership transfer whose target didn't take over in time, and go back
+    /// to taking proposals. Followers forget about the transfer at the same time, in case
+    /// the leader is no longer around to tell them
+    pub(super) fn expire_transfer(&mut self) {
+        let state = match &mut self.leadership_state {
+            RaftLeadershipState::Leader(state) => state,
+            RaftLeadershipState::Follower(state) if self.ticks >= state.transfer_deadline => {
+                state.transfer_target = None;
+                return;
+            }
+            _ => return,
+        };
+        if let Some(transfer) = state
+            .transfer
+            .take_if(|transfer| self.ticks >= transfer.deadline)
+        {
+            Logger::transfer_expired(self, &transfer.target);
+        }
+    }
+
+    /// Vouch for our commit index to `reader` for its read `read_id`, once a quorum has
+    /// answered a request sent from now on. Refused right away unless we have committed an
+    /// entry of our own term, as until then our commit index can be behind the last
+    /// leader's
+    pub(super) fn start_read(&mut self, reader: I, read_id: ReadId) -> Vec<SendableMessage<T, I>> {
+        let read_len = self.log.committed_len;
+        let committed_in_term = self.log.term_at(read_len) == Some(self.current_term);
+        let first_request = self.next_request_id;
+        let RaftLeadershipState::Leader(state) = &mut self.leadership_state else {
+            return self.answer_read(reader, read_id, None);
+        };
+        if !committed_in_term {
+            return self.answer_read(reader, read_id, None);
+        }
+        state.reads.push(PendingRead {
+            reader,
+            read_id,
+            read_len,
+            first_request,
+            confirmed_by: BTreeSet::new(),
+        });
+        // without any peers we are a quorum on our own
+        self.confirm_reads(None)
+    }
+
+    /// Count `answer`, a follower's response to one of our requests, towards the reads
+    /// waiting on a quorum, and answer those that have one
+    pub(super) fn confirm_reads(
+        &mut self,
+        answer: Option<(&I, RequestId)>,
+    ) -> Vec<SendableMessage<T, I>> {
+        let quorum = self.quorum_size();
+        let RaftLeadershipState::Leader(state) = &mut self.leadership_state else {
+            return vec![];
+        };
+        if state.reads.is_empty() {
+            return vec![];
+        }
+        if let Some((follower, request_id)) = answer {
+            if !state.followers.contains_key(follower) {
+                return vec![];
+            }
+            for read in &mut state.reads {
+                if request_id >= read.first_request {
+                    read.confirmed_by.insert(follower.clone());
+                }
+            }
+        }
+        let (confirmed, waiting): (Vec<_>, Vec<_>) = mem::take(&mut state.reads)
+            .into_iter()
+            .partition(|read| read.confirmed_by.len() + 1 >= quorum);
+        state.reads = waiting;
+        confirmed
+            .into_iter()
+            .flat_map(|read| self.answer_read(read.reader, read.read_id, Some(read.read_len)))
+            .collect()
+    }
+}
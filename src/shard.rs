@@ -0,0 +1,93 @@
+use crate::error::{RaftError, Result};
+
+/// Which of a [`Router`]'s groups a key belongs to, counting from 0
+pub type ShardId = usize;
+
+/// How a [`Router`] spreads keys across its groups. Every client has to use the same
+/// placement, or they won't agree on where a key lives
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Placement {
+    /// Hash each key, so keys are spread evenly whatever they look like. The hash is fixed
+    /// and doesn't depend on the platform or the Rust version
+    Hash,
+    /// Split the key space into sorted ranges, so neighbouring keys stay together. Holds the
+    /// first key of every group but the first: group `i` gets the keys from split `i - 1`
+    /// up to, but not including, split `i`
+    Ranges(Vec<Vec<u8>>),
+}
+
+/// Maps keys to the Raft groups of a sharded store, each of which runs as a cluster of its
+/// own, and hands out the client for the group that owns a key. `C` is whatever talks to a
+/// group, e.g. a [`ThreadedClient`](crate::threaded::ThreadedClient) or
+/// [`RaftHandle`](crate::handle::RaftHandle) for one of its nodes
+#[derive(Clone, Debug)]
+pub struct Router<C> {
+    /// How keys are spread across `shards`
+    placement: Placement,
+    /// Client for each group, by [`ShardId`]
+    shards: Vec<C>,
+}
+
+impl<C> Router<C> {
+    /// Route keys to `shards` as `placement` says. Fails if there are no shards, or if
+    /// [range splits](Placement::Ranges) aren't strictly increasing or there isn't one
+    /// fewer of them than there are shards
+    pub fn new(placement: Placement, shards: Vec<C>) -> Result<Self> {
+        if shards.is_empty() {
+            return Err(RaftError::InvalidConfig(
+                "router needs at least one shard".into(),
+            ));
+        }
+        if let Placement::Ranges(splits) = &placement {
+            if splits.len() + 1 != shards.len() {
+                return Err(RaftError::InvalidConfig(format!(
+                    "{} range splits for {} shards, need {}",
+                    splits.len(),
+                    shards.len(),
+                    shards.len() - 1
+                )));
+            }
+            if splits.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(RaftError::InvalidConfig(
+                    "range splits must be strictly increasing".into(),
+                ));
+            }
+        }
+        Ok(Router { placement, shards })
+    }
+
+    /// Group that owns `key`
+    pub fn shard_of(&self, key: &[u8]) -> ShardId {
+        match &self.placement {
+            Placement::Hash => (fnv1a(key) % self.shards.len() as u64) as ShardId,
+            Placement::Ranges(splits) => splits.partition_point(|split| split.as_slice() <= key),
+        }
+    }
+
+    /// Client for the group that owns `key`
+    pub fn route(&self, key: &[u8]) -> &C {
+        &self.shards[self.shard_of(key)]
+    }
+
+    /// Client for group `shard`, if there is one
+    pub fn shard(&self, shard: ShardId) -> Option<&C> {
+        self.shards.get(shard)
+    }
+
+    /// Clients for every group, by [`ShardId`]
+    pub fn shards(&self) -> &[C] {
+        &self.shards
+    }
+
+    /// How keys are spread across the groups
+    pub fn placement(&self) -> &Placement {
+        &self.placement
+    }
+}
+
+/// 64-bit FNV-1a, which unlike std's hasher is guaranteed to stay the same everywhere
+fn fnv1a(key: &[u8]) -> u64 {
+    key.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
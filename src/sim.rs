@@ -0,0 +1,777 @@
+#[cfg(feature = "serde")]
+use crate::storage::{load_state, save_state};
+use crate::{
+    log::App,
+    rpc::{ReadId, Target, RPC},
+    server::{PersistentState, RaftConfig, RaftServer, ServerId, SlowPathConfig, Ticks, TieBreak},
+    topology::NetworkView,
+};
+use anyhow::{bail, Result};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use rand_core::{RngCore, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::{self, Debug},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A message on its way from one node to another
+#[derive(Clone)]
+pub struct Envelope<T> {
+    /// Who sent it
+    pub from: ServerId,
+    /// Who it is for. Broadcasts are split up into one envelope per recipient
+    pub to: ServerId,
+    /// The message itself
+    pub rpc: RPC<T>,
+}
+
+/// A message as it left the network, see [`Cluster::trace`]
+#[derive(Clone, Debug)]
+pub struct TracedMessage<T> {
+    /// Tick the message arrived (or got lost) in
+    pub at: Ticks,
+    /// Who sent it
+    pub from: ServerId,
+    /// Who it was for
+    pub to: ServerId,
+    /// The message itself
+    pub rpc: RPC<T>,
+    /// Whether it reached `to`, as opposed to being lost to a down node or a cut link
+    pub delivered: bool,
+}
+
+/// One line per message, e.g. `12 0->1 VoteRequest(..)`, with lost messages marked as
+/// such. Lines contain every field of the message, so two traces that render the same
+/// are the same protocol behavior
+impl<T: Debug> fmt::Display for TracedMessage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}->{} {:?}", self.at, self.from, self.to, self.rpc)?;
+        if !self.delivered {
+            write!(f, " (lost)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Ways the network misbehaves on top of [`cut`](Cluster::cut) links, applied to every
+/// message with the given probability. Everything is off by default
+#[derive(Clone, Debug, Default)]
+pub struct Faults {
+    /// Chance that a message gets delivered twice
+    pub duplicate: f64,
+    /// Chance that a message is held back for a while, letting later messages overtake it
+    pub delay: f64,
+    /// Most ticks a delayed message is held back for. Delays longer than an election
+    /// timeout let messages arrive after the term they were sent in is over
+    pub max_delay: Ticks,
+    /// Chance that a message gets corrupted on the wire. The receiving end's checksum
+    /// catches it and throws it away, so to Raft it is just lost
+    pub corrupt: f64,
+}
+
+/// What survives on a node's disk when it [`restart`](Cluster::restart)s
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disk {
+    /// Everything the node persisted
+    Intact,
+    /// Everything but the last `n` log entries, as if they were never synced
+    LostTail(usize),
+    /// Everything but the vote in the current term. Lets the node vote twice in one term,
+    /// which Raft's safety depends on never happening
+    LostVote,
+    /// Nothing, the node comes back with an empty log in term 0
+    Wiped,
+}
+
+/// Stream of the master seed's generator the network draws from
+const NETWORK_STREAM: u64 = 0;
+
+/// Stream of the master seed's generator handed out through [`Cluster::rng`]
+const TEST_STREAM: u64 = 1;
+
+/// Seed of a node in a [`Cluster`] created with seed `master`.
+/// `incarnation` counts how often the node was [`restart`](Cluster::restart)ed, starting at 0.
+///
+/// Every consumer of randomness in a cluster gets its own stream of a [`ChaCha8Rng`] seeded
+/// with `master`: the network uses stream 0, [`Cluster::rng`] stream 1, and node `id` in
+/// incarnation `n` takes the first `u64` of stream `(id + 2) << 32 | n`. Streams never
+/// overlap, so e.g. a test drawing more random numbers doesn't change the nodes' seeds.
+/// This also lets a single node from a failing run be rebuilt outside of the cluster
+pub fn node_seed(master: u64, id: ServerId, incarnation: u32) -> u64 {
+    stream(master, (id as u64 + 2) << 32 | incarnation as u64).next_u64()
+}
+
+/// Generator for one of the streams of the master seed, see [`node_seed`]
+fn stream(master: u64, stream: u64) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(master);
+    rng.set_stream(stream);
+    rng
+}
+
+/// A whole Raft cluster in a single process: N [`RaftServer`]s, a virtual clock and an
+/// in-memory message bus, all driven from a single master seed so every run with the same
+/// seed plays out exactly the same way. Printing [`seed`](Self::seed) is all it takes to
+/// reproduce a failing run, see [`node_seed`] for how it is split up.
+///
+/// Every [`tick`](Self::tick) advances the clock on every live node and then delivers
+/// messages until the network goes quiet, so a request and its response happen within
+/// the same tick. Nodes can be taken down and links cut to simulate crashes and
+/// partitions; messages to a down node or over a cut link are lost. Nodes can also be
+/// [`restart`](Self::restart)ed, losing everything but what is on their (simulated) disk,
+/// and the network can duplicate and reorder messages, see [`Faults`]. The disk is the
+/// crashed node itself unless the cluster [persists](Self::persist_to) to real files.
+pub struct Cluster<T, S> {
+    /// Every node in the cluster, up or down
+    nodes: BTreeMap<ServerId, RaftServer<T, S>>,
+    /// Config every node runs with
+    config: RaftConfig,
+    /// Creates a fresh app for a node that starts or restarts
+    new_app: NewApp<T, S>,
+    /// Ticks since the cluster was created
+    now: Ticks,
+    /// Seed everything else is derived from
+    seed: u64,
+    /// How often each node was restarted
+    incarnations: BTreeMap<ServerId, u32>,
+    /// Randomness of the network
+    rng: ChaCha8Rng,
+    /// Randomness handed out to tests
+    test_rng: ChaCha8Rng,
+    /// Messages sent but not delivered yet
+    bus: VecDeque<Envelope<T>>,
+    /// Messages held back by the network, with the tick they are let through at
+    delayed: Vec<(Ticks, Envelope<T>)>,
+    /// How the network misbehaves
+    faults: Faults,
+    /// Nodes that are down, they neither tick nor receive messages
+    down: BTreeSet<ServerId>,
+    /// Directed links `(from, to)` on which every message is lost
+    cut: BTreeSet<(ServerId, ServerId)>,
+    /// Number of messages delivered so far
+    delivered: u64,
+    /// Where nodes keep their persistent state, if on a real disk
+    storage: Option<DirStorage<T>>,
+    /// Nodes whose clock runs at a different speed than the cluster's, see
+    /// [`set_clock_rate`](Self::set_clock_rate)
+    clocks: BTreeMap<ServerId, SkewedClock>,
+    /// Nodes with a slow disk, with how many ticks a write takes
+    slow_disks: BTreeMap<ServerId, Ticks>,
+    /// Every message that left the network since tracing started, if it did
+    trace: Option<Vec<TracedMessage<T>>>,
+}
+
+/// Clock of a node that runs faster or slower than the cluster's
+struct SkewedClock {
+    /// Node ticks per cluster tick
+    rate: f64,
+    /// Fraction of a node tick carried over to the next cluster tick
+    carry: f64,
+}
+
+/// Storage directories a [`Cluster`] keeps its nodes' [`PersistentState`] in, see
+/// [`Cluster::persist_to`]. Holds on to the (de)serializing functions so the rest of the
+/// cluster doesn't need `T` to be serializable
+struct DirStorage<T> {
+    /// Parent of every node's storage directory
+    dir: PathBuf,
+    /// Writes a node's state into its directory
+    save: fn(&Path, &PersistentState<T>) -> io::Result<()>,
+    /// Reads a node's state back from its directory
+    load: fn(&Path) -> io::Result<Option<PersistentState<T>>>,
+}
+
+impl<T> DirStorage<T> {
+    /// Storage directory of node `id`
+    fn node_dir(&self, id: ServerId) -> PathBuf {
+        self.dir.join(format!("node-{}", id))
+    }
+}
+
+/// Creates the app of a node that starts or restarts
+type NewApp<T, S> = Box<dyn FnMut(ServerId) -> Box<dyn App<T, S>>>;
+
+/// The name downstream crates know [`Cluster`] by when writing integration tests, e.g.
+///
+/// ```
+/// # use miniraft::{log::{App, LogEntry}, sim::TestCluster};
+/// # struct Counter(u32);
+/// # impl App<u32, u32> for Counter {
+/// #     fn transition_fn(&mut self, entry: &LogEntry<u32>) { self.0 += entry.data }
+/// #     fn get_state(&self) -> u32 { self.0 }
+/// # }
+/// let mut cluster = TestCluster::builder().nodes(5).app(|| Counter(0)).build();
+/// assert!(cluster.run_until(100, |cluster| cluster.leader().is_some()));
+/// ```
+pub type TestCluster<T, S> = Cluster<T, S>;
+
+/// Config a [`ClusterBuilder`] gives its nodes unless told otherwise
+const DEFAULT_CONFIG: RaftConfig = RaftConfig {
+    election_timeout: 10,
+    election_timeout_jitter: 3,
+    heartbeat_interval: 5,
+    max_apply_lag: None,
+    slow_path: SlowPathConfig {
+        apply: None,
+        persist: None,
+        tick: None,
+    },
+    leaderless_alarm: None,
+    max_log_entries: None,
+    persist_in_background: false,
+    replication_batching: None,
+    broadcast_commits: false,
+    tie_break: TieBreak::Never,
+};
+
+/// Sets up a [`Cluster`], see [`Cluster::builder`]. Everything but the app has a default:
+/// 3 nodes, seed 0, a 10 tick election timeout with 3 ticks of jitter, a 5 tick heartbeat
+/// and a well-behaved network
+pub struct ClusterBuilder<T, S> {
+    /// Number of nodes
+    nodes: usize,
+    /// Master seed
+    seed: u64,
+    /// Config every node runs with
+    config: RaftConfig,
+    /// Creates the app of every node
+    new_app: Option<NewApp<T, S>>,
+    /// How the network misbehaves from the start
+    faults: Faults,
+}
+
+impl<T, S> ClusterBuilder<T, S>
+where
+    T: Clone + Debug + 'static,
+    S: 'static,
+{
+    /// Number of nodes, with ids `0..n`
+    pub fn nodes(mut self, n: usize) -> Self {
+        self.nodes = n;
+        self
+    }
+
+    /// Master seed, see [`node_seed`]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Config every node runs with
+    pub fn config(mut self, config: RaftConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// App every node runs, a fresh one from `new_app` per node and per restart
+    pub fn app<A>(mut self, mut new_app: impl FnMut() -> A + 'static) -> Self
+    where
+        A: App<T, S> + 'static,
+    {
+        self.new_app = Some(Box::new(move |_| Box::new(new_app())));
+        self
+    }
+
+    /// How the network misbehaves from the start, see [`Cluster::set_faults`]
+    pub fn faults(mut self, faults: Faults) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Create the cluster. Panics if no [`app`](Self::app) was given
+    pub fn build(self) -> Cluster<T, S> {
+        let new_app = self
+            .new_app
+            .expect("a cluster needs an app, see ClusterBuilder::app");
+        let mut cluster = Cluster::new(self.nodes, self.seed, self.config, new_app);
+        cluster.set_faults(self.faults);
+        cluster
+    }
+}
+
+impl<T, S> Cluster<T, S>
+where
+    T: Clone + Debug,
+{
+    /// Set up a cluster step by step, see [`ClusterBuilder`]
+    pub fn builder() -> ClusterBuilder<T, S> {
+        ClusterBuilder {
+            nodes: 3,
+            seed: 0,
+            config: DEFAULT_CONFIG,
+            new_app: None,
+            faults: Faults::default(),
+        }
+    }
+
+    /// Create a cluster of `n` nodes with ids `0..n`, each with its own app from `new_app`.
+    /// Every node's seed is derived from `seed`, see [`node_seed`]
+    pub fn new(
+        n: usize,
+        seed: u64,
+        config: RaftConfig,
+        mut new_app: impl FnMut(ServerId) -> Box<dyn App<T, S>> + 'static,
+    ) -> Self {
+        let ids: BTreeSet<ServerId> = (0..n).collect();
+        let nodes = ids
+            .iter()
+            .map(|&id| {
+                let mut peers = ids.clone();
+                peers.remove(&id);
+                let node_seed = Some(node_seed(seed, id, 0));
+                let server = RaftServer::new(id, peers, config.clone(), node_seed, new_app(id));
+                (id, server)
+            })
+            .collect();
+        Cluster {
+            nodes,
+            config,
+            new_app: Box::new(new_app),
+            now: 0,
+            seed,
+            incarnations: BTreeMap::new(),
+            rng: stream(seed, NETWORK_STREAM),
+            test_rng: stream(seed, TEST_STREAM),
+            bus: VecDeque::new(),
+            delayed: Vec::new(),
+            faults: Faults::default(),
+            down: BTreeSet::new(),
+            cut: BTreeSet::new(),
+            delivered: 0,
+            storage: None,
+            clocks: BTreeMap::new(),
+            slow_disks: BTreeMap::new(),
+            trace: None,
+        }
+    }
+
+    /// Advance the clock by one tick on every live node and deliver messages until the
+    /// network goes quiet. Nodes with a [skewed clock](Self::set_clock_rate) tick as
+    /// often as their clock says instead
+    pub fn tick(&mut self) {
+        self.now += 1;
+        let now = self.now;
+        let (due, held): (Vec<_>, Vec<_>) = self.delayed.drain(..).partition(|(at, _)| *at <= now);
+        self.delayed = held;
+        self.bus
+            .extend(due.into_iter().map(|(_, envelope)| envelope));
+        let ids: Vec<ServerId> = self.nodes.keys().cloned().collect();
+        for id in ids {
+            if self.down.contains(&id) {
+                continue;
+            }
+            for _ in 0..self.node_ticks(id) {
+                let msgs = self.nodes.get_mut(&id).expect("node exists").tick();
+                self.persist(id);
+                self.send(id, msgs);
+            }
+        }
+        while let Some(envelope) = self.bus.pop_front() {
+            self.deliver(envelope);
+        }
+    }
+
+    /// How many times node `id` ticks in the current cluster tick
+    fn node_ticks(&mut self, id: ServerId) -> u32 {
+        let Some(clock) = self.clocks.get_mut(&id) else {
+            return 1;
+        };
+        clock.carry += clock.rate;
+        let ticks = clock.carry.floor();
+        clock.carry -= ticks;
+        ticks as u32
+    }
+
+    /// Advance the clock by `n` ticks
+    pub fn tick_by(&mut self, n: Ticks) {
+        (0..n).for_each(|_| self.tick());
+    }
+
+    /// Tick until `done` holds, for at most `max_ticks` ticks.
+    /// Returns whether `done` ended up holding
+    pub fn run_until(&mut self, max_ticks: Ticks, done: impl Fn(&Self) -> bool) -> bool {
+        for _ in 0..max_ticks {
+            if done(self) {
+                return true;
+            }
+            self.tick();
+        }
+        done(self)
+    }
+
+    /// Put messages a node sent on the bus
+    fn send(&mut self, from: ServerId, msgs: Vec<(Target, RPC<T>)>) {
+        for (target, rpc) in msgs {
+            match target {
+                Target::Single(to) => self.post(Envelope { from, to, rpc }),
+                Target::Broadcast => {
+                    let peers: Vec<ServerId> = self
+                        .nodes
+                        .keys()
+                        .cloned()
+                        .filter(|&to| to != from)
+                        .collect();
+                    for to in peers {
+                        self.post(Envelope {
+                            from,
+                            to,
+                            rpc: rpc.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Put a single message on the bus, or hold it back, or both, as [`Faults`] has it
+    fn post(&mut self, envelope: Envelope<T>) {
+        // only roll the dice when a fault is on, so turning faults on in one test doesn't
+        // change how a seed plays out everywhere else
+        if self.faults.corrupt > 0.0 && self.rng.gen_bool(self.faults.corrupt) {
+            return;
+        }
+        if self.faults.duplicate > 0.0 && self.rng.gen_bool(self.faults.duplicate) {
+            self.hold_or_send(envelope.clone());
+        }
+        self.hold_or_send(envelope);
+    }
+
+    /// Hold a message back for a random number of ticks or put it straight on the bus.
+    /// Messages from a node with a [slow disk](Self::set_slow_disk) are always held back,
+    /// as the node only answers once its write is done
+    fn hold_or_send(&mut self, envelope: Envelope<T>) {
+        let mut at = self.now + self.slow_disks.get(&envelope.from).copied().unwrap_or(0);
+        if self.faults.delay > 0.0
+            && self.faults.max_delay > 0
+            && self.rng.gen_bool(self.faults.delay)
+        {
+            at += self.rng.gen_range(1..=self.faults.max_delay);
+        }
+        if at > self.now {
+            self.delayed.push((at, envelope));
+        } else {
+            self.bus.push_back(envelope);
+        }
+    }
+
+    /// Hand a message to its recipient unless it gets lost on the way
+    fn deliver(&mut self, envelope: Envelope<T>) {
+        let Envelope { from, to, rpc } = envelope;
+        let lost = self.down.contains(&to) || self.cut.contains(&(from, to));
+        if let Some(trace) = &mut self.trace {
+            trace.push(TracedMessage {
+                at: self.now,
+                from,
+                to,
+                rpc: rpc.clone(),
+                delivered: !lost,
+            });
+        }
+        if lost {
+            return;
+        }
+        let Some(node) = self.nodes.get_mut(&to) else {
+            return;
+        };
+        let replies = node.receive_rpc(&rpc);
+        self.delivered += 1;
+        self.persist(to);
+        self.send(to, replies);
+    }
+
+    /// Write a node's persistent state to its storage directory, if the cluster has any.
+    /// Called after every step of a node and before anything it sent goes out, as Raft
+    /// requires
+    fn persist(&self, id: ServerId) {
+        if let Some(storage) = &self.storage {
+            let state = self.nodes[&id].persistent_state();
+            (storage.save)(&storage.node_dir(id), &state).unwrap_or_else(|err| {
+                panic!("failed to persist the state of node {}: {}", id, err)
+            });
+        }
+    }
+
+    /// Ticks since the cluster was created
+    pub fn now(&self) -> Ticks {
+        self.now
+    }
+
+    /// Number of messages delivered so far
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Start recording every message that gets delivered or lost from now on, dropping
+    /// whatever was recorded before
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+
+    /// Messages recorded since [`start_trace`](Self::start_trace), in the order they left
+    /// the network. Empty if tracing never started
+    pub fn trace(&self) -> &[TracedMessage<T>] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    /// Master seed the cluster was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// RNG derived from the cluster's seed, for tests that need randomness of their own
+    /// without giving up on determinism
+    pub fn rng(&mut self) -> &mut ChaCha8Rng {
+        &mut self.test_rng
+    }
+
+    /// The node with id `id`
+    pub fn node(&self, id: ServerId) -> &RaftServer<T, S> {
+        &self.nodes[&id]
+    }
+
+    /// The node with id `id`
+    pub fn node_mut(&mut self, id: ServerId) -> &mut RaftServer<T, S> {
+        self.nodes.get_mut(&id).expect("no such node")
+    }
+
+    /// Every node, up or down
+    pub fn nodes(&self) -> impl Iterator<Item = &RaftServer<T, S>> {
+        self.nodes.values()
+    }
+
+    /// Every node that is up
+    pub fn live_nodes(&self) -> impl Iterator<Item = &RaftServer<T, S>> {
+        self.nodes
+            .values()
+            .filter(|node| !self.down.contains(&node.id))
+    }
+
+    /// The live leader in the highest term, if there is one. A node cut off from the rest
+    /// of the cluster can still believe it is leader of an older term
+    pub fn leader(&self) -> Option<&RaftServer<T, S>> {
+        self.live_nodes()
+            .filter(|node| node.is_leader())
+            .max_by_key(|node| node.current_term())
+    }
+
+    /// Propose `data` to the current [`leader`](Self::leader), returning who took it
+    pub fn client_request(&mut self, data: T) -> Result<ServerId> {
+        let Some(leader) = self.leader().map(|leader| leader.id) else {
+            bail!("no leader to take the request");
+        };
+        self.node_mut(leader).client_request(data)?;
+        self.persist(leader);
+        Ok(leader)
+    }
+
+    /// Make the current [`leader`](Self::leader) hand its leadership to `target`, or to
+    /// whoever is furthest along, returning who handed over. See
+    /// [`RaftServer::transfer_leadership`]
+    pub fn transfer_leadership(&mut self, target: Option<ServerId>) -> Result<ServerId> {
+        let Some(leader) = self.leader().map(|leader| leader.id) else {
+            bail!("no leader to transfer from");
+        };
+        let msgs = self.node_mut(leader).transfer_leadership(target)?;
+        self.send(leader, msgs);
+        Ok(leader)
+    }
+
+    /// Start a read barrier on node `id`, sending whatever it needs to. See
+    /// [`RaftServer::read_barrier`]
+    pub fn read_barrier(&mut self, id: ServerId) -> Result<ReadId> {
+        let Some(node) = self.nodes.get_mut(&id) else {
+            bail!("no node {}", id);
+        };
+        let (read, msgs) = node.read_barrier()?;
+        self.send(id, msgs);
+        Ok(read)
+    }
+
+    /// Make node `id` snapshot its app and compact its log right now, see
+    /// [`RaftServer::snapshot_now`]
+    pub fn snapshot(&mut self, id: ServerId) -> Result<()> {
+        let Some(node) = self.nodes.get_mut(&id) else {
+            bail!("no node {}", id);
+        };
+        node.snapshot_now()?;
+        self.persist(id);
+        Ok(())
+    }
+
+    /// Take a node down. It stops ticking and every message sent to it is lost, but it
+    /// keeps its state for when it comes back up
+    pub fn kill(&mut self, id: ServerId) {
+        self.down.insert(id);
+    }
+
+    /// Bring a node back up
+    pub fn revive(&mut self, id: ServerId) {
+        self.down.remove(&id);
+    }
+
+    /// [Pause](RaftServer::pause) node `id`. Unlike a [`kill`](Self::kill)ed node, it holds
+    /// on to the messages it gets and handles them once it is resumed
+    pub fn pause(&mut self, id: ServerId) {
+        self.node_mut(id).pause();
+    }
+
+    /// [Resume](RaftServer::resume) node `id`, sending whatever it answers the messages it
+    /// held on to with the next tick
+    pub fn resume(&mut self, id: ServerId) {
+        let msgs = self.node_mut(id).resume();
+        self.persist(id);
+        self.send(id, msgs);
+    }
+
+    /// Crash a node and start it again, as if its process died. Everything but what `disk`
+    /// leaves of its [persistent state](PersistentState) is lost, including its app which
+    /// is rebuilt from the snapshot. Messages still on the bus for it are lost too.
+    /// Works on nodes that are up as well as ones that were [`kill`](Self::kill)ed.
+    ///
+    /// With [`persist_to`](Self::persist_to) the state is read back from the node's storage
+    /// directory, otherwise it is taken straight from the crashed node
+    pub fn restart(&mut self, id: ServerId, disk: Disk) -> Result<()> {
+        let Some(node) = self.nodes.get(&id) else {
+            bail!("no node {}", id);
+        };
+        let mut state = match &self.storage {
+            Some(storage) => {
+                let dir = storage.node_dir(id);
+                match (storage.load)(&dir)? {
+                    Some(state) => state,
+                    None => bail!("no state for node {} in {}", id, dir.display()),
+                }
+            }
+            None => node.persistent_state(),
+        };
+        match disk {
+            Disk::Intact => {}
+            Disk::LostTail(n) => {
+                state
+                    .entries
+                    .truncate(state.entries.len().saturating_sub(n));
+            }
+            Disk::LostVote => state.voted_for = None,
+            Disk::Wiped => {
+                state = PersistentState {
+                    current_term: 0,
+                    voted_for: None,
+                    snapshot: Default::default(),
+                    entries: Vec::new(),
+                }
+            }
+        }
+        let peers = self
+            .nodes
+            .keys()
+            .cloned()
+            .filter(|&peer| peer != id)
+            .collect();
+        let incarnation = self.incarnations.entry(id).or_insert(0);
+        *incarnation += 1;
+        let seed = node_seed(self.seed, id, *incarnation);
+        let app = (self.new_app)(id);
+        let node = RaftServer::recover(id, peers, self.config.clone(), Some(seed), app, state)?;
+        self.nodes.insert(id, node);
+        self.persist(id);
+        self.bus.retain(|envelope| envelope.to != id);
+        self.delayed.retain(|(_, envelope)| envelope.to != id);
+        self.revive(id);
+        Ok(())
+    }
+
+    /// Lose every message from `from` to `to`
+    pub fn cut(&mut self, from: ServerId, to: ServerId) {
+        self.cut.insert((from, to));
+    }
+
+    /// Split the cluster in two: nodes in `side` can only talk among themselves, and so can
+    /// everyone else
+    pub fn partition(&mut self, side: &[ServerId]) {
+        let ids: Vec<ServerId> = self.nodes.keys().cloned().collect();
+        for &a in side {
+            for &b in ids.iter().filter(|b| !side.contains(b)) {
+                self.cut(a, b);
+                self.cut(b, a);
+            }
+        }
+    }
+
+    /// Make the network misbehave as described by `faults`, from now on
+    pub fn set_faults(&mut self, faults: Faults) {
+        self.faults = faults;
+    }
+
+    /// How the network currently misbehaves
+    pub fn faults(&self) -> &Faults {
+        &self.faults
+    }
+
+    /// Make node `id`'s clock run at `rate` times the speed of the cluster's, e.g. 2.0 for a
+    /// node whose timers fire twice as fast as everyone else's. 1.0 puts it back in sync
+    pub fn set_clock_rate(&mut self, id: ServerId, rate: f64) {
+        assert!(rate >= 0.0, "clock rate must not be negative");
+        if rate == 1.0 {
+            self.clocks.remove(&id);
+        } else {
+            self.clocks.insert(id, SkewedClock { rate, carry: 0.0 });
+        }
+    }
+
+    /// Give node `id` a disk that takes `ticks` ticks per write. Since a node has to persist
+    /// its state before answering, everything it sends goes out that much later.
+    /// 0 makes the disk fast again
+    pub fn set_slow_disk(&mut self, id: ServerId, ticks: Ticks) {
+        if ticks == 0 {
+            self.slow_disks.remove(&id);
+        } else {
+            self.slow_disks.insert(id, ticks);
+        }
+    }
+
+    /// Restore every cut link
+    pub fn heal(&mut self) {
+        self.cut.clear();
+    }
+
+    /// Which nodes are down and which links are cut, e.g. for
+    /// [`to_dot`](crate::topology::to_dot)
+    pub fn network(&self) -> NetworkView {
+        NetworkView {
+            down: self.down.clone(),
+            dropped: self.cut.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, S> Cluster<T, S>
+where
+    T: Clone + Debug + Serialize + DeserializeOwned,
+{
+    /// Keep every node's persistent state on disk, in a directory per node under `dir`.
+    /// From then on nodes write their state after every step, and
+    /// [`restart`](Self::restart) reads it back from there, so restarts go through the
+    /// same [`save_state`] and [`load_state`] a real deployment would use
+    pub fn persist_to(&mut self, dir: impl Into<PathBuf>) -> io::Result<()> {
+        let storage = DirStorage {
+            dir: dir.into(),
+            save: save_state::<T, ServerId>,
+            load: load_state::<T, ServerId>,
+        };
+        for (&id, node) in &self.nodes {
+            save_state(&storage.node_dir(id), &node.persistent_state())?;
+        }
+        self.storage = Some(storage);
+        Ok(())
+    }
+
+    /// Storage directory of node `id`, if the cluster [persists](Self::persist_to) to disk
+    pub fn storage_dir(&self, id: ServerId) -> Option<PathBuf> {
+        self.storage.as_ref().map(|storage| storage.node_dir(id))
+    }
+}
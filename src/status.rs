@@ -0,0 +1,176 @@
+use crate::{
+    log::LogIndex,
+    metrics::RaftMetrics,
+    server::{NodeReplicationState, RaftConfig, ServerId, Term, Ticks},
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Which role a Raft node currently has, without any of the role specific state.
+/// See [`RaftLeadershipState`](crate::server::RaftLeadershipState) for the full thing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Role {
+    /// Following a leader (or waiting for one to show up)
+    Follower,
+    /// Running an election
+    Candidate,
+    /// Leading the cluster
+    Leader,
+}
+
+/// Point in time view of a Raft node, as returned by
+/// [`RaftServer::status`](crate::server::RaftServer::status)
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "I: Deserialize<'de> + Ord"))
+)]
+pub struct RaftStatus<I = ServerId> {
+    /// ID of the node
+    pub id: I,
+    /// Current role of the node
+    pub role: Role,
+    /// Current term of the node
+    pub term: Term,
+    /// Who the node believes is leader, if anyone. Clients can be redirected here
+    pub leader_hint: Option<I>,
+    /// Who the node voted for in the current term
+    pub voted_for: Option<I>,
+    /// Votes received so far, only set while the node is a candidate
+    pub votes_received: Option<BTreeSet<I>>,
+    /// How much of the log is committed
+    pub committed_len: LogIndex,
+    /// How much of the log has been handed to the app
+    pub applied_len: LogIndex,
+    /// How much of the log the app has finished applying
+    pub last_applied: LogIndex,
+    /// Length of the full log, including entries compacted into the snapshot
+    pub log_len: LogIndex,
+    /// Number of entries covered by the snapshot
+    pub snapshot_len: LogIndex,
+    /// Replication progress of every follower, only set while the node is leader
+    pub followers: Option<BTreeMap<I, NodeReplicationState>>,
+    /// Ticks since we last heard from a leader, `None` if we are leader or never heard from one.
+    /// A number that keeps growing means the cluster is leaderless or we are cut off from it
+    pub ticks_since_leader_contact: Option<Ticks>,
+    /// Ticks since we became leader, only set while the node is leader
+    pub ticks_as_leader: Option<Ticks>,
+    /// Whether the node has gone longer than
+    /// [`leaderless_alarm`](crate::server::RaftConfig::leaderless_alarm) without a leader
+    pub leaderless_alarm: bool,
+    /// Progress of every follower that is behind the leader's log, only set while the node
+    /// is leader. Answers "how long until the new node is ready"
+    pub catch_up: BTreeMap<I, CatchUpProgress>,
+    /// Whether the node is [paused](crate::server::RaftServer::pause)
+    pub paused: bool,
+}
+
+impl<I: Clone + Ord> RaftStatus<I> {
+    /// How many entries each follower is missing compared to the leader's log, so the
+    /// follower holding back commits stands out. Empty if the node isn't leader
+    pub fn follower_lag(&self) -> BTreeMap<I, LogIndex> {
+        self.followers
+            .iter()
+            .flatten()
+            .map(|(id, state)| (id.clone(), self.log_len.saturating_sub(state.acked_up_to)))
+            .collect()
+    }
+
+    /// How many entries each follower has received but not applied yet. Unlike
+    /// [`follower_lag`](Self::follower_lag) this grows when the follower's app is slow,
+    /// not when the network is. Empty if the node isn't leader
+    pub fn follower_apply_lag(&self) -> BTreeMap<I, LogIndex> {
+        self.followers
+            .iter()
+            .flatten()
+            .map(|(id, state)| {
+                (
+                    id.clone(),
+                    state.acked_up_to.saturating_sub(state.applied_up_to),
+                )
+            })
+            .collect()
+    }
+}
+
+/// How far along a follower is in catching up with the leader's log, see
+/// [`RaftStatus::catch_up`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CatchUpProgress {
+    /// Entries the follower is still missing
+    pub remaining: LogIndex,
+    /// Entries the follower has acked since it fell behind
+    pub transferred: LogIndex,
+    /// Ticks since the follower fell behind
+    pub elapsed: Ticks,
+    /// Ticks until the follower has caught up if it keeps going at the rate it has so far.
+    /// `None` until it has made some progress
+    pub estimated_ticks_remaining: Option<Ticks>,
+    /// Snapshot on its way to the follower, if any
+    pub snapshot: Option<SnapshotTransfer>,
+}
+
+/// A snapshot sent to a follower that hasn't confirmed installing it yet.
+/// Snapshots go out as a single message, so there is no partial progress to report
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SnapshotTransfer {
+    /// Number of log entries the snapshot covers
+    pub entries: LogIndex,
+    /// Size of the snapshot's app data
+    pub bytes: usize,
+    /// Tick the snapshot was first sent at
+    pub sent_at: Ticks,
+    /// Tick the snapshot was last sent at. Unless the follower answers, it is only sent
+    /// again an [`election_timeout`](crate::server::RaftConfig::election_timeout) later
+    pub last_sent_at: Ticks,
+}
+
+/// A single log entry in a [`DebugDump`], with its data rendered through `Debug`
+/// so dumps don't depend on the entry type being serializable
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DumpedEntry {
+    /// Index of the entry in the full log
+    pub index: LogIndex,
+    /// Term the entry was created in
+    pub term: Term,
+    /// `Debug` rendering of the entry data
+    pub data: String,
+}
+
+/// Everything about a node worth attaching to a bug report, as returned by
+/// [`RaftServer::debug_dump`](crate::server::RaftServer::debug_dump)
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "I: Deserialize<'de> + Ord"))
+)]
+pub struct DebugDump<I = ServerId> {
+    /// Role, term, vote, log progress and follower progress
+    pub status: RaftStatus<I>,
+    /// Ticks left on the election timer (follower/candidate) or heartbeat timer (leader)
+    pub timer: Ticks,
+    /// Config the node runs with
+    pub config: RaftConfig,
+    /// Counters the node has kept since it started
+    pub metrics: RaftMetrics,
+    /// Term of the last entry covered by the snapshot
+    pub snapshot_term: Term,
+    /// The tail end of the log, oldest first
+    pub log_tail: Vec<DumpedEntry>,
+}
+
+#[cfg(feature = "serde")]
+impl<I: Serialize> DebugDump<I> {
+    /// Render the dump as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("debug dump is always serializable")
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    log::{LogEntry, LogIndex, Snapshot},
+    server::{PersistentState, ServerId},
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+#[cfg(feature = "serde")]
+use crate::{
+    error::{RaftError, Result},
+    server::{NodeId, RaftConfig},
+};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "serde")]
+use std::{collections::BTreeMap, fs::OpenOptions, io::BufRead, path::PathBuf};
+
+/// Name of the file in a node's storage directory holding its [`PersistentState`]
+#[cfg(feature = "serde")]
+const STATE_FILE: &str = "state.json";
+
+/// Name of the file in a node's storage directory holding the entries it
+/// [spilled](Storage::spill)
+#[cfg(feature = "serde")]
+const SPILL_FILE: &str = "spilled.jsonl";
+
+/// Write a [`Snapshot`] to `path`.
+/// The snapshot is written to a temporary file first and then moved into place, so a crash
+/// halfway through never leaves a torn snapshot behind.
+///
+/// Layout: `len` (u64 LE), `term` (u64 LE), then the app data until the end of the file
+pub fn save_snapshot(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    writer.write_all(&(snapshot.len as u64).to_le_bytes())?;
+    writer.write_all(&snapshot.term.to_le_bytes())?;
+    writer.write_all(&snapshot.data)?;
+    writer.into_inner()?.sync_all()?;
+    fs::rename(tmp_path, path)
+}
+
+/// Read a [`Snapshot`] previously written by [`save_snapshot`].
+/// Returns `None` if there is no snapshot at `path`
+pub fn load_snapshot(path: &Path) -> io::Result<Option<Snapshot>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut reader = BufReader::new(file);
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    let len = u64::from_le_bytes(buf) as usize;
+    reader.read_exact(&mut buf)?;
+    let term = u64::from_le_bytes(buf);
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(Some(Snapshot { len, term, data }))
+}
+
+/// Write a node's [`PersistentState`] into its storage directory `dir`, creating the
+/// directory if needed. Like [`save_snapshot`] the state goes through a temporary file, so
+/// a crash halfway through leaves the previous state in place.
+///
+/// Layout: a single JSON file holding term, vote, snapshot and log entries. Term and log
+/// are written together so they can never disagree after a crash
+#[cfg(feature = "serde")]
+pub fn save_state<T: Serialize, I: NodeId + Serialize>(
+    dir: &Path,
+    state: &PersistentState<T, I>,
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(STATE_FILE);
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    serde_json::to_writer(&mut writer, state)?;
+    writer.into_inner()?.sync_all()?;
+    fs::rename(tmp_path, path)
+}
+
+/// Read the [`PersistentState`] previously written by [`save_state`] into `dir`.
+/// Returns `None` if nothing was ever saved there
+#[cfg(feature = "serde")]
+pub fn load_state<T: DeserializeOwned, I: NodeId + DeserializeOwned>(
+    dir: &Path,
+) -> io::Result<Option<PersistentState<T, I>>> {
+    let file = match File::open(dir.join(STATE_FILE)) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+}
+
+/// Stable storage a node's [`PersistentState`] is kept in. Write the node's
+/// [`persistent_state`](crate::server::RaftServer::persistent_state) with
+/// [`save`](Self::save) before sending anything it returned, and hand what
+/// [`load`](Self::load) finds to [`RaftServer::recover`](crate::server::RaftServer::recover)
+/// after a restart
+pub trait Storage<T, I = ServerId> {
+    /// Replace whatever was saved before with `state`, returning once it is on disk
+    fn save(&mut self, state: &PersistentState<T, I>) -> io::Result<()>;
+
+    /// The state last [saved](Self::save), `None` if nothing was ever saved
+    fn load(&mut self) -> io::Result<Option<PersistentState<T, I>>>;
+
+    /// Keep committed `entries`, the first of which is at index `from`, after the node
+    /// compacts them out of memory. A node [spilling](crate::server::RaftServer::spill_to)
+    /// into this storage catches lagging followers up from them, read back through
+    /// [`fetch`](Self::fetch), rather than sending its whole snapshot. The same entries can
+    /// be spilled more than once. The default implementation keeps nothing
+    fn spill(&mut self, _from: LogIndex, _entries: &[LogEntry<T>]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Up to `max` [spilled](Self::spill) entries in a row, starting at index `from`. Empty
+    /// if the entry at `from` was never spilled
+    fn fetch(&mut self, _from: LogIndex, _max: usize) -> io::Result<Vec<LogEntry<T>>> {
+        Ok(Vec::new())
+    }
+}
+
+/// [`Storage`] in a directory of its own, through [`save_state`] and [`load_state`].
+/// [Spilled](Storage::spill) entries are appended to a file of their own, one JSON line
+/// each, which [`fetch`](Storage::fetch) reads through from the start
+#[cfg(feature = "serde")]
+pub struct StateDir {
+    /// Directory the state file is kept in
+    dir: PathBuf,
+}
+
+#[cfg(feature = "serde")]
+impl StateDir {
+    /// Keep state in `dir`, which is created on the first save if needed
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        StateDir { dir: dir.into() }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, I> Storage<T, I> for StateDir
+where
+    T: Serialize + DeserializeOwned,
+    I: NodeId + Serialize + DeserializeOwned,
+{
+    fn save(&mut self, state: &PersistentState<T, I>) -> io::Result<()> {
+        save_state(&self.dir, state)
+    }
+
+    fn load(&mut self) -> io::Result<Option<PersistentState<T, I>>> {
+        load_state(&self.dir)
+    }
+
+    fn spill(&mut self, from: LogIndex, entries: &[LogEntry<T>]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(SPILL_FILE))?;
+        let mut writer = BufWriter::new(file);
+        for (index, entry) in (from..).zip(entries) {
+            serde_json::to_writer(&mut writer, &(index, entry))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.into_inner()?.sync_all()
+    }
+
+    fn fetch(&mut self, from: LogIndex, max: usize) -> io::Result<Vec<LogEntry<T>>> {
+        let file = match File::open(self.dir.join(SPILL_FILE)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let end = from.saturating_add(max);
+        let mut found = BTreeMap::new();
+        for line in BufReader::new(file).lines() {
+            let (index, entry): (LogIndex, LogEntry<T>) = serde_json::from_str(&line?)?;
+            if (from..end).contains(&index) {
+                found.entry(index).or_insert(entry);
+            }
+        }
+        Ok((from..end)
+            .map_while(|index| found.remove(&index))
+            .collect())
+    }
+}
+
+/// Write a [`RaftConfig`] to `path` as pretty-printed JSON, so it can be kept next to a
+/// node's storage directory and edited by hand
+#[cfg(feature = "serde")]
+pub fn save_config(path: &Path, config: &RaftConfig) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, config)?;
+    writer.write_all(b"\n")?;
+    writer.into_inner()?.sync_all()
+}
+
+/// Read a [`RaftConfig`] from a JSON file like the ones [`save_config`] writes.
+/// Fields that have a sensible "off" value (`max_apply_lag`, `slow_path`, `leaderless_alarm`,
+/// `max_log_entries`) can be left out. Unlike the node's state a config file has to
+/// exist, and what is in it goes through [`RaftConfig::validate`]
+#[cfg(feature = "serde")]
+pub fn load_config(path: &Path) -> Result<RaftConfig> {
+    let file = File::open(path)?;
+    let config: RaftConfig = serde_json::from_reader(BufReader::new(file))
+        .map_err(|err| RaftError::InvalidConfig(format!("{}: {}", path.display(), err)))?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Keeps an eye on a config file like the ones [`save_config`] writes, so operators can
+/// tune a running node by editing the file instead of restarting it. Hand whatever
+/// [`poll`](Self::poll) finds to [`RaftServer::reconfigure`](crate::server::RaftServer::reconfigure)
+#[cfg(feature = "serde")]
+pub struct ConfigWatcher {
+    /// File being watched
+    path: PathBuf,
+    /// Contents of the file as of the last poll, `None` if it couldn't be read
+    seen: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "serde")]
+impl ConfigWatcher {
+    /// Watch the config file at `path`. Whatever is in it now counts as seen, as that is
+    /// presumably what the node was started with
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let seen = fs::read(&path).ok();
+        ConfigWatcher { path, seen }
+    }
+
+    /// The config in the file if the file changed since it was last polled, `None` if it
+    /// didn't. A changed file is checked the same way as by [`load_config`], and a broken
+    /// one is only reported once, until it changes again
+    pub fn poll(&mut self) -> Option<Result<RaftConfig>> {
+        let contents = fs::read(&self.path).ok();
+        if contents == self.seen {
+            return None;
+        }
+        self.seen = contents;
+        Some(load_config(&self.path))
+    }
+}
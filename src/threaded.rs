@@ -0,0 +1,252 @@
+use crate::{
+    clock::{Instant, WallClock},
+    error::{RaftError, Result},
+    log::LogIndex,
+    proposals::Proposals,
+    rpc::{SendableMessage, Transport, RPC},
+    server::{NodeId, RaftConfig, RaftServer, ServerId},
+    status::RaftStatus,
+};
+use std::{
+    fmt::Debug,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender},
+    time::Duration,
+};
+
+/// Requests from clients that can be queued up before [`ThreadedClient::propose`] blocks
+/// and [`ThreadedClient::receive`] starts dropping messages
+const COMMAND_QUEUE_LEN: usize = 1024;
+
+/// Something a [`ThreadedClient`] asks the node to do
+enum Command<T, I> {
+    /// Hand an RPC from a peer to the node
+    Receive(RPC<T, I>),
+    /// Propose an entry, answered once it is committed
+    Propose(T, Sender<Result<LogIndex, I>>),
+    /// Report the node's status
+    Status(Sender<RaftStatus<I>>),
+    /// [Switch](RaftServer::reconfigure) the node to a new config
+    Reconfigure(RaftConfig, Sender<Result<(), I>>),
+    /// [Pause](RaftServer::pause) the node, answered once it is
+    Pause(Sender<()>),
+    /// [Resume](RaftServer::resume) the node, answered once it is
+    Resume(Sender<()>),
+    /// Stop the node, handing over leadership first if asked to. Answered once it stopped
+    Shutdown(bool, Sender<()>),
+}
+
+/// Drives a [`RaftServer`] from a plain thread, for applications that don't want an async
+/// runtime. Does the same as `node::RaftNode` from the `tokio` feature: it ticks the server,
+/// feeds it the RPCs and proposals that come in through its [`ThreadedClient`]s, and
+/// hands everything it sends to a [`Transport`].
+///
+/// The node isn't `Send` (neither apps nor observer callbacks have to be), so build it on
+/// the thread that is going to [`run`](Self::run) it and hand a client back to the others
+pub struct ThreadedNode<T, S, I = ServerId> {
+    /// The node being driven
+    server: RaftServer<T, S, I>,
+    /// Wall time a single tick stands for
+    tick: Duration,
+    /// Where outgoing messages go
+    transport: Box<dyn Transport<T, I>>,
+    /// Requests from clients
+    commands: Receiver<Command<T, I>>,
+    /// Proposals that haven't been committed yet
+    proposals: Proposals<Sender<Result<LogIndex, I>>>,
+    /// Clients waiting for the node to stop, once one asked it to
+    stopping: Vec<Sender<()>>,
+}
+
+/// Handle for talking to a running [`ThreadedNode`]. Cheap to clone and `Send` as long as
+/// the entries and node ids are
+pub struct ThreadedClient<T, I = ServerId> {
+    /// Requests for the node
+    commands: SyncSender<Command<T, I>>,
+}
+
+impl<T, I> Clone for ThreadedClient<T, I> {
+    fn clone(&self) -> Self {
+        ThreadedClient {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<T, S, I> ThreadedNode<T, S, I>
+where
+    T: Clone + Debug,
+    I: NodeId,
+{
+    /// Drive `server`, ticking it every `tick` of wall time and sending its messages through
+    /// `transport`. Returns the node, which does nothing until it is [`run`](Self::run),
+    /// along with the first client for it.
+    /// Panics if `tick` is zero
+    pub fn new(
+        server: RaftServer<T, S, I>,
+        tick: Duration,
+        transport: impl Transport<T, I> + 'static,
+    ) -> (Self, ThreadedClient<T, I>) {
+        assert!(!tick.is_zero(), "tick length must be positive");
+        let (sender, commands) = mpsc::sync_channel(COMMAND_QUEUE_LEN);
+        let node = ThreadedNode {
+            server,
+            tick,
+            transport: Box::new(transport),
+            commands,
+            proposals: Proposals::new(),
+            stopping: Vec::new(),
+        };
+        (node, ThreadedClient { commands: sender })
+    }
+
+    /// Run the node on the current thread until it is [shut down](ThreadedClient::shutdown)
+    /// or every [`ThreadedClient`] for it has been dropped, then hand back the server, e.g.
+    /// to save its state
+    pub fn run(mut self) -> RaftServer<T, S, I> {
+        let mut clock = WallClock::new(self.tick);
+        let mut next_tick = Instant::now() + self.tick;
+        loop {
+            // wait for requests until the next tick is due, however busy clients keep us
+            let now = Instant::now();
+            let msgs = if now >= next_tick {
+                next_tick = now + self.tick;
+                self.server.advance(&mut clock)
+            } else {
+                match self.commands.recv_timeout(next_tick - now) {
+                    Ok(command) => self.handle(command),
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            };
+            for (target, rpc) in msgs {
+                self.transport.send(target, rpc);
+            }
+            for (reply, outcome) in self.proposals.settle(&self.server) {
+                let _ = reply.send(outcome);
+            }
+            // a node asked to stop only keeps going while it hands over leadership
+            if !self.stopping.is_empty() && self.server.leadership_transfer().is_none() {
+                break;
+            }
+        }
+        for reply in self.proposals.abandon() {
+            let _ = reply.send(Err(RaftError::Stopped));
+        }
+        for done in self.stopping.drain(..) {
+            let _ = done.send(());
+        }
+        self.server
+    }
+
+    /// Carry out a single request from a client
+    fn handle(&mut self, command: Command<T, I>) -> Vec<SendableMessage<T, I>> {
+        match command {
+            Command::Receive(rpc) => return self.server.receive_rpc(&rpc),
+            Command::Propose(_, reply) if !self.stopping.is_empty() => {
+                let _ = reply.send(Err(RaftError::Stopped));
+            }
+            Command::Propose(data, reply) => {
+                if let Some((reply, rejected)) =
+                    self.proposals.propose(&mut self.server, data, reply)
+                {
+                    let _ = reply.send(rejected);
+                }
+            }
+            Command::Status(reply) => {
+                let _ = reply.send(self.server.status());
+            }
+            Command::Reconfigure(config, reply) => {
+                let _ = reply.send(self.server.reconfigure(config));
+            }
+            Command::Pause(done) => {
+                self.server.pause();
+                let _ = done.send(());
+            }
+            Command::Resume(done) => {
+                let _ = done.send(());
+                return self.server.resume();
+            }
+            Command::Shutdown(transfer_leadership, done) => {
+                self.stopping.push(done);
+                if transfer_leadership
+                    && self.server.is_leader()
+                    && self.server.leadership_transfer().is_none()
+                {
+                    // a single node cluster has nobody to hand over to, and just stops
+                    return self.server.transfer_leadership(None).unwrap_or_default();
+                }
+            }
+        }
+        vec![]
+    }
+}
+
+impl<T, I> ThreadedClient<T, I> {
+    /// Hand an RPC that came in from a peer to the node. Like the network, this drops the
+    /// message if the node is too backed up to take it or no longer running
+    pub fn receive(&self, rpc: RPC<T, I>) {
+        let _ = self.commands.try_send(Command::Receive(rpc));
+    }
+
+    /// Propose `data` to the cluster, blocking until it is committed and returning its index
+    /// in the log. Fails right away on a node that isn't leader, and later if another leader
+    /// overwrote the entry before it got committed. Either way the error says who the leader
+    /// is, if the node knows
+    pub fn propose(&self, data: T) -> Result<LogIndex, I> {
+        let (reply, outcome) = mpsc::channel();
+        self.request(Command::Propose(data, reply))?;
+        outcome.recv().map_err(|_| RaftError::Stopped)?
+    }
+
+    /// Current status of the node
+    pub fn status(&self) -> Result<RaftStatus<I>, I> {
+        let (reply, status) = mpsc::channel();
+        self.request(Command::Status(reply))?;
+        status.recv().map_err(|_| RaftError::Stopped)
+    }
+
+    /// [Switch](RaftServer::reconfigure) the node to `config` without restarting it, e.g.
+    /// after a [`ConfigWatcher`](crate::storage::ConfigWatcher) saw the config file change
+    pub fn reconfigure(&self, config: RaftConfig) -> Result<(), I> {
+        let (reply, outcome) = mpsc::channel();
+        self.request(Command::Reconfigure(config, reply))?;
+        outcome.recv().map_err(|_| RaftError::Stopped)?
+    }
+
+    /// [Pause](RaftServer::pause) the node, e.g. to back up its state. Its timers stop,
+    /// RPCs from peers are held back until it is resumed, and proposals fail with
+    /// [`RaftError::Paused`]
+    pub fn pause(&self) -> Result<(), I> {
+        let (done, paused) = mpsc::channel();
+        self.request(Command::Pause(done))?;
+        paused.recv().map_err(|_| RaftError::Stopped)
+    }
+
+    /// [Resume](RaftServer::resume) a paused node, which catches up on the RPCs it held back
+    pub fn resume(&self) -> Result<(), I> {
+        let (done, resumed) = mpsc::channel();
+        self.request(Command::Resume(done))?;
+        resumed.recv().map_err(|_| RaftError::Stopped)
+    }
+
+    /// Stop the node, blocking until it has. With `transfer_leadership` a leader first hands
+    /// its leadership to the follower that is furthest along, which takes at most an
+    /// election timeout, so the cluster doesn't go without a leader until its next election.
+    /// Proposals that haven't been committed by then fail with [`RaftError::Stopped`], as
+    /// does anything sent to the node from then on. Returns right away if the node had
+    /// already stopped
+    pub fn shutdown(&self, transfer_leadership: bool) {
+        let (done, stopped) = mpsc::channel();
+        if self
+            .request(Command::Shutdown(transfer_leadership, done))
+            .is_ok()
+        {
+            let _ = stopped.recv();
+        }
+    }
+
+    /// Queue a request for the node, blocking until there is room if need be
+    fn request(&self, command: Command<T, I>) -> Result<(), I> {
+        self.commands.send(command).map_err(|_| RaftError::Stopped)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{
+    server::ServerId,
+    status::{RaftStatus, Role},
+};
+use std::{collections::BTreeSet, fmt::Write};
+
+/// What the network between the nodes looks like, for drawing partitions.
+/// Nodes and links not mentioned are assumed to be up
+#[derive(Clone, Debug, Default)]
+pub struct NetworkView {
+    /// Nodes that are crashed or otherwise unreachable
+    pub down: BTreeSet<ServerId>,
+    /// Directed links `(from, to)` on which messages are being dropped
+    pub dropped: BTreeSet<(ServerId, ServerId)>,
+}
+
+/// Render the cluster as a GraphViz DOT graph, one node per entry in `statuses`.
+///
+/// Leaders are drawn as double circles with an edge to each of their followers labelled
+/// with how far behind that follower is. Down nodes are greyed out and dropped links are
+/// drawn as dashed red edges. Pipe the output through `dot -Tsvg` to look at it
+pub fn to_dot(statuses: &[RaftStatus], network: &NetworkView) -> String {
+    let mut dot = String::from("digraph raft {\n    node [style=filled];\n");
+
+    for status in statuses {
+        let (shape, colour) = match status.role {
+            Role::Leader => ("doublecircle", "gold"),
+            Role::Candidate => ("circle", "lightblue"),
+            Role::Follower => ("circle", "white"),
+        };
+        let colour = if network.down.contains(&status.id) {
+            "grey"
+        } else {
+            colour
+        };
+        let _ = writeln!(
+            dot,
+            "    {} [label=\"{}\\n{:?} T{}\\nlog {} commit {}\", shape={}, fillcolor={}];",
+            status.id,
+            status.id,
+            status.role,
+            status.term,
+            status.log_len,
+            status.committed_len,
+            shape,
+            colour
+        );
+    }
+
+    for status in statuses {
+        for (follower, lag) in status.follower_lag() {
+            let style = if link_is_down(network, status.id, follower) {
+                ", style=dashed, color=red"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label=\"lag {}\"{}];",
+                status.id, follower, lag, style
+            );
+        }
+    }
+
+    // partitions that don't involve a leader's replication stream still matter
+    // (e.g. they decide who can win the next election)
+    for (from, to) in &network.dropped {
+        let drawn = statuses
+            .iter()
+            .any(|status| status.id == *from && status.role == Role::Leader);
+        if !drawn {
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label=\"dropped\", style=dashed, color=red];",
+                from, to
+            );
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// Whether messages from `from` can't currently reach `to`
+fn link_is_down(network: &NetworkView, from: ServerId, to: ServerId) -> bool {
+    network.down.contains(&from)
+        || network.down.contains(&to)
+        || network.dropped.contains(&(from, to))
+}
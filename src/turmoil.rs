@@ -0,0 +1,106 @@
+use crate::{
+    node::{RaftClient, RaftNode},
+    rpc::{Target, Transport, RPC},
+    server::{NodeId, RaftServer, ServerId},
+};
+use ::turmoil::net::UdpSocket;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::BTreeMap, fmt::Debug, io, net::Ipv4Addr};
+use tokio::sync::mpsc;
+
+/// Port nodes take RPCs on, on every host
+pub const PORT: u16 = 4000;
+
+/// Largest RPC that fits in a datagram. Anything bigger, e.g. a large snapshot, is dropped
+/// like a lost message, so it has to be split up with [`frames`](crate::frames) first
+pub const MAX_DATAGRAM: usize = 65_507;
+
+/// Sends a node's RPCs over turmoil's simulated network, JSON encoded in a UDP datagram
+/// each, to the hosts its peers run on. Latency, loss and partitions set up in the
+/// simulation (e.g. with `turmoil::partition`) then apply to them as they would to a real
+/// network. Messages are only queued here, the [`TurmoilNetwork`] made along with the
+/// transport sends them
+pub struct TurmoilTransport<I = ServerId> {
+    /// Host each peer runs on
+    hosts: BTreeMap<I, String>,
+    /// Encoded messages waiting to be sent, with the host they go to
+    outbox: mpsc::UnboundedSender<(String, Vec<u8>)>,
+}
+
+/// The socket side of a [`TurmoilTransport`], see [`network`]
+pub struct TurmoilNetwork {
+    /// Encoded messages the transport queued, with the host they go to
+    outbox: mpsc::UnboundedReceiver<(String, Vec<u8>)>,
+}
+
+/// Connect a node to turmoil's network, `peers` being the turmoil host each of its peers
+/// runs on. Drive the node with the returned transport and
+/// [`serve`](TurmoilNetwork::serve) it from the node's own host
+pub fn network<I: NodeId>(peers: BTreeMap<I, String>) -> (TurmoilTransport<I>, TurmoilNetwork) {
+    let (sender, outbox) = mpsc::unbounded_channel();
+    let transport = TurmoilTransport {
+        hosts: peers,
+        outbox: sender,
+    };
+    (transport, TurmoilNetwork { outbox })
+}
+
+impl<T, I> Transport<T, I> for TurmoilTransport<I>
+where
+    T: Serialize,
+    I: NodeId + Serialize,
+{
+    fn send(&mut self, target: Target<I>, rpc: RPC<T, I>) {
+        let Ok(encoded) = serde_json::to_vec(&rpc) else {
+            return;
+        };
+        if encoded.len() > MAX_DATAGRAM {
+            return;
+        }
+        let hosts: Vec<&String> = match &target {
+            Target::Single(peer) => self.hosts.get(peer).into_iter().collect(),
+            Target::Broadcast => self.hosts.values().collect(),
+        };
+        for host in hosts {
+            let _ = self.outbox.send((host.clone(), encoded.clone()));
+        }
+    }
+}
+
+impl TurmoilNetwork {
+    /// Run `node` on the turmoil host this is called from, taking RPCs for it on [`PORT`]
+    /// and handing them to it through `client`, and sending out whatever its transport
+    /// queued. Returns the server once the node is [shut down](RaftClient::shutdown), or
+    /// the error if the socket can't be bound
+    pub async fn serve<T, S, I>(
+        mut self,
+        node: RaftNode<T, S, I>,
+        client: RaftClient<T, I>,
+    ) -> io::Result<RaftServer<T, S, I>>
+    where
+        T: Clone + Debug + DeserializeOwned,
+        I: NodeId + DeserializeOwned,
+    {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT)).await?;
+        let running = node.run();
+        tokio::pin!(running);
+        let mut buf = vec![0; MAX_DATAGRAM];
+        loop {
+            tokio::select! {
+                server = &mut running => return Ok(server),
+                Some((host, encoded)) = self.outbox.recv() => {
+                    // raft copes with lost messages, so a failed send is just one of them
+                    let _ = socket.send_to(&encoded, (host.as_str(), PORT)).await;
+                }
+                received = socket.recv_from(&mut buf) => {
+                    let Ok((len, _)) = received else {
+                        continue;
+                    };
+                    if let Ok(rpc) = serde_json::from_slice(&buf[..len]) {
+                        client.receive(rpc);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "admin")]
+
+mod common;
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    time::Instant,
+};
+
+use common::*;
+use miniraft::admin::{AdminServer, CLIENT_TIMEOUT};
+
+fn get(addr: SocketAddr, path: &str) -> (String, serde_json::Value) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status_line = head.lines().next().unwrap().to_string();
+    (status_line, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn admin_serves_status_metrics_and_log() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+    for i in 0..5 {
+        cluster.get_by_id(leader_id).client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_TICKS);
+
+    let admin = AdminServer::bind(([127, 0, 0, 1], 0)).unwrap();
+    let addr = admin.local_addr();
+    let (status_line, _) = get(addr, "/status");
+    assert!(status_line.contains("503"));
+
+    admin.update(cluster.get_by_id(leader_id));
+    let (status_line, status) = get(addr, "/status");
+    assert!(status_line.contains("200"));
+    assert_eq!(status["role"], "Leader");
+    assert_eq!(status["committed_len"], 5);
+
+    let (_, metrics) = get(addr, "/metrics");
+    assert_eq!(metrics["entries_committed"], 5);
+
+    let (_, log) = get(addr, "/log?from=3");
+    let indices: Vec<_> = log
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["index"].as_u64().unwrap())
+        .collect();
+    assert_eq!(indices, [3, 4]);
+
+    let (status_line, _) = get(addr, "/log?from=nope");
+    assert!(status_line.contains("400"));
+    let (status_line, _) = get(addr, "/nope");
+    assert!(status_line.contains("404"));
+}
+
+#[test]
+fn idle_client_holds_up_neither_requests_nor_shutdown() {
+    let mut cluster = TestCluster::new(1, 0, DEFAULT_CFG);
+    let admin = AdminServer::bind(([127, 0, 0, 1], 0)).unwrap();
+    admin.update(cluster.get_by_id(0));
+
+    // connects and never sends a request
+    let _idle = TcpStream::connect(admin.local_addr()).unwrap();
+    let (status_line, _) = get(admin.local_addr(), "/status");
+    assert!(status_line.contains("200"));
+
+    let started = Instant::now();
+    drop(admin);
+    assert!(started.elapsed() < CLIENT_TIMEOUT);
+}
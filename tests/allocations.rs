@@ -0,0 +1,105 @@
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use common::*;
+use miniraft::{
+    rpc::{AppendResponse, RPC},
+    server::RaftServer,
+    sim::Cluster,
+};
+
+/// Counts the allocations made by each thread, so tests running alongside don't interfere
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of allocations `f` makes
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn quiet_ticks_do_not_allocate() {
+    let app = Box::new(CountingApp { state: 0 });
+    let mut node: RaftServer<u32, u32> =
+        RaftServer::new(0, [1, 2].into(), DEFAULT_CFG, Some(1), app);
+    let ticks = allocations(|| {
+        for _ in 1..DEFAULT_CFG.election_timeout {
+            assert!(node.tick().is_empty());
+        }
+    });
+    assert_eq!(ticks, 0);
+}
+
+#[test]
+fn heartbeat_acks_do_not_allocate() {
+    let mut cluster = Cluster::new(3, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    cluster.client_request(1).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    let leader = cluster.leader().unwrap().id;
+    let node = cluster.node_mut(leader);
+    let ack = RPC::AppendResponse(AppendResponse {
+        ok: true,
+        rejection: None,
+        term: node.current_term(),
+        ack_idx: 1,
+        follower_id: (leader + 1) % 3,
+        last_applied: 1,
+        request_id: 0,
+        trace: None,
+    });
+    let acks = allocations(|| {
+        for _ in 0..10 {
+            assert!(node.receive_rpc(&ack).is_empty());
+        }
+    });
+    assert_eq!(acks, 0);
+}
+
+#[test]
+fn heartbeats_cost_the_same_however_long_the_log() {
+    // allocations made by the leader sending out a heartbeat, once everything is replicated
+    let heartbeat = |entries| {
+        let mut cluster = Cluster::new(5, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+        assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+        for data in 0..entries {
+            cluster.client_request(data).unwrap();
+        }
+        cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+        let leader = cluster.leader().unwrap().id;
+        let node = cluster.node_mut(leader);
+        assert_eq!(node.log.committed_len, entries as usize);
+        let mut msgs = vec![];
+        let allocations = allocations(|| {
+            while msgs.is_empty() {
+                msgs = node.tick();
+            }
+        });
+        assert_eq!(msgs.len(), 4);
+        allocations
+    };
+    assert_eq!(heartbeat(1), heartbeat(1000));
+}
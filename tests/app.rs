@@ -1,6 +1,13 @@
 mod common;
 
+use std::{cell::RefCell, rc::Rc};
+
 use common::*;
+use miniraft::{
+    error::RaftError,
+    log::{App, ApplyContext, LogEntry},
+    server::ServerId,
+};
 
 #[test]
 fn appending_to_single_log_is_ok() {
@@ -57,7 +64,10 @@ fn cannot_append_to_non_leader() {
     cluster.kill(1);
     cluster.tick_by(MAX_WAIT);
     let node = cluster.get_by_id(2);
-    assert!(node.client_request(1).is_err());
+    assert!(matches!(
+        node.client_request(1),
+        Err(RaftError::NotLeader { leader: None })
+    ));
 }
 
 #[test]
@@ -122,8 +132,7 @@ fn leader_log_conflict_gets_resolved() {
     lead = cluster
         .peers
         .values_mut()
-        .filter(|peer| peer.is_leader() && peer.id != lead_id)
-        .nth(0)
+        .find(|peer| peer.is_leader() && peer.id != lead_id)
         .unwrap();
     let new_lead_id = lead.id;
 
@@ -161,8 +170,7 @@ fn dead_node_catches_up_after_reviving() {
     let follower_node_id = cluster
         .peers
         .values()
-        .filter(|peer| !peer.is_leader())
-        .nth(0)
+        .find(|peer| !peer.is_leader())
         .unwrap()
         .id;
     cluster.kill(follower_node_id);
@@ -184,3 +192,63 @@ fn dead_node_catches_up_after_reviving() {
     assert_eq!(cluster.get_leader().unwrap().log.app.get_state(), 10);
     assert!(cluster.state_consensus());
 }
+
+/// Counts up like [`CountingApp`], but also records side effects it emits as the leader
+struct SideEffectApp {
+    state: u32,
+    effects: Rc<RefCell<Vec<(ServerId, u32)>>>,
+    id: ServerId,
+}
+
+impl App<u32, u32> for SideEffectApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        self.state += entry.data;
+    }
+    fn apply(&mut self, entry: &LogEntry<u32>, ctx: &ApplyContext) {
+        self.transition_fn(entry);
+        if ctx.is_leader {
+            self.effects.borrow_mut().push((self.id, entry.data));
+        }
+    }
+    fn get_state(&self) -> u32 {
+        self.state
+    }
+}
+
+#[test]
+fn side_effects_only_emitted_by_proposing_leader() {
+    let effects = Rc::new(RefCell::new(Vec::new()));
+    let mut cluster = TestCluster::with_apps(3, 0, DEFAULT_CFG, |id| {
+        Box::new(SideEffectApp {
+            state: 0,
+            effects: effects.clone(),
+            id,
+        })
+    });
+    cluster.tick_by(MAX_WAIT);
+    let lead_id = cluster.get_leader().unwrap().id;
+    let lead = cluster.get_by_id(lead_id);
+    assert!(lead.client_request(1).is_ok());
+    assert!(lead.client_request(2).is_ok());
+    cluster.tick_by(MAX_WAIT);
+
+    // every node applied both entries, but only the leader emitted effects for them
+    assert!(cluster.state_consensus());
+    assert_eq!(*effects.borrow(), vec![(lead_id, 1), (lead_id, 2)]);
+
+    // new leader only emits effects for entries it proposed itself
+    cluster.kill(lead_id);
+    cluster.tick_by(MAX_WAIT);
+    let new_lead_id = cluster
+        .peers
+        .values()
+        .find(|peer| peer.is_leader() && peer.id != lead_id)
+        .unwrap()
+        .id;
+    assert!(cluster.get_by_id(new_lead_id).client_request(3).is_ok());
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(
+        *effects.borrow(),
+        vec![(lead_id, 1), (lead_id, 2), (new_lead_id, 3)]
+    );
+}
@@ -0,0 +1,279 @@
+mod common;
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    io,
+    sync::{
+        mpsc::{channel, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::*;
+use miniraft::{
+    apply::ApplyWorker,
+    debug::init_logger,
+    error::RaftError,
+    event::{RaftEvent, SlowOperation},
+    log::{App, ApplyContext, LogEntry},
+    server::{RaftConfig, RaftServer, SlowPathConfig},
+};
+
+/// App that refuses to apply an entry until it is handed a permit
+struct GatedApp {
+    state: u32,
+    permits: Receiver<()>,
+}
+
+impl App<u32, u32> for GatedApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        self.permits.recv().unwrap();
+        self.state += entry.data;
+    }
+    fn get_state(&self) -> u32 {
+        self.state
+    }
+}
+
+fn wait_for(cond: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !cond() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting on apply worker"
+        );
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn worker_applies_entries_in_order() {
+    let mut worker = ApplyWorker::new(Box::new(CountingApp { state: 0 }));
+    for data in [1, 2, 3] {
+        worker.transition_fn(&LogEntry { term: 1, data });
+    }
+    worker.flush().unwrap();
+    assert_eq!(worker.pending(), 0);
+    assert_eq!(worker.get_state(), 6);
+}
+
+/// App that panics on an entry holding 0
+struct FragileApp {
+    state: u32,
+}
+
+impl App<u32, u32> for FragileApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        assert_ne!(entry.data, 0, "can't apply 0");
+        self.state += entry.data;
+    }
+    fn get_state(&self) -> u32 {
+        self.state
+    }
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        writer.write_all(&self.state.to_le_bytes())
+    }
+}
+
+#[test]
+fn panicking_app_makes_the_worker_fail_rather_than_its_caller() {
+    let mut worker = ApplyWorker::new(Box::new(FragileApp { state: 0 }));
+    for data in [1, 0, 2] {
+        worker.transition_fn(&LogEntry { term: 1, data });
+    }
+    assert!(worker.flush().is_err());
+    assert!(worker.snapshot(&mut Vec::new()).is_err());
+    assert_eq!(worker.pending(), 2);
+    assert_eq!(worker.get_state(), 1);
+
+    // and it keeps taking entries without applying them
+    worker.transition_fn(&LogEntry { term: 1, data: 3 });
+    assert_eq!(worker.pending(), 3);
+}
+
+/// App that remembers the index of every entry it applied
+struct IndexApp {
+    indexes: Arc<Mutex<Vec<usize>>>,
+}
+
+impl App<u32, ()> for IndexApp {
+    fn transition_fn(&mut self, _entry: &LogEntry<u32>) {}
+    fn apply(&mut self, _entry: &LogEntry<u32>, ctx: &ApplyContext) {
+        self.indexes.lock().unwrap().push(ctx.index);
+    }
+    fn get_state(&self) {}
+    fn restore(&mut self, _reader: &mut dyn io::Read) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn worker_numbers_entries_on_from_a_restored_snapshot() {
+    let indexes = Arc::default();
+    let mut worker = ApplyWorker::new(Box::new(IndexApp {
+        indexes: Arc::clone(&indexes),
+    }));
+    worker.transition_fn(&LogEntry { term: 1, data: 1 });
+    worker.restore_at(&mut io::empty(), 5).unwrap();
+    worker.transition_fn(&LogEntry { term: 2, data: 2 });
+    worker.flush().unwrap();
+    assert_eq!(*indexes.lock().unwrap(), [0, 5]);
+}
+
+#[test]
+fn slow_apply_does_not_block_leader() {
+    init_logger();
+    let (permit, permits) = channel();
+    let app = ApplyWorker::new(Box::new(GatedApp { state: 0, permits }));
+    let mut node = RaftServer::new(0, BTreeSet::new(), DEFAULT_CFG, Some(0), Box::new(app));
+    (0..MAX_WAIT).for_each(|_| {
+        node.tick();
+    });
+    assert!(node.is_leader());
+
+    // entries commit straight away but the app is stuck on the first one
+    assert!(node.client_request(5).is_ok());
+    assert!(node.client_request(10).is_ok());
+    assert_eq!(node.log.committed_len, 2);
+    assert_eq!(node.log.applied_len, 2);
+    assert_eq!(node.log.last_applied(), 0);
+
+    // timers keep running while the app is blocked
+    (0..MAX_TICKS).for_each(|_| {
+        node.tick();
+    });
+    assert!(node.is_leader());
+    assert_eq!(node.log.last_applied(), 0);
+
+    // let the app catch up
+    permit.send(()).unwrap();
+    permit.send(()).unwrap();
+    wait_for(|| node.log.last_applied() == 2);
+    assert_eq!(node.log.app.get_state(), 15);
+}
+
+#[test]
+fn leader_rejects_requests_when_app_lags() {
+    init_logger();
+    let (permit, permits) = channel();
+    let app = ApplyWorker::new(Box::new(GatedApp { state: 0, permits }));
+    let config = RaftConfig {
+        max_apply_lag: Some(2),
+        ..DEFAULT_CFG
+    };
+    let mut node = RaftServer::new(0, BTreeSet::new(), config, Some(0), Box::new(app));
+    (0..MAX_WAIT).for_each(|_| {
+        node.tick();
+    });
+    assert!(node.is_leader());
+
+    // app is stuck, so only max_apply_lag entries are accepted
+    assert!(node.client_request(1).is_ok());
+    assert!(node.client_request(2).is_ok());
+    assert!(matches!(
+        node.client_request(3),
+        Err(RaftError::Busy { lag: 2, max: 2 })
+    ));
+    assert_eq!(node.log.entries.len(), 2);
+
+    // once the app catches up we accept requests again
+    permit.send(()).unwrap();
+    wait_for(|| node.log.last_applied() == 1);
+    assert!(node.client_request(3).is_ok());
+    assert_eq!(node.log.entries.len(), 3);
+
+    (0..2).for_each(|_| permit.send(()).unwrap());
+}
+
+/// App that takes a while to apply every entry
+#[test]
+fn leader_sees_follower_apply_lag() {
+    let permits = RefCell::new(BTreeMap::new());
+    let mut cluster = TestCluster::with_apps(3, 0, DEFAULT_CFG, |id| {
+        let (permit, gate) = channel();
+        permits.borrow_mut().insert(id, permit);
+        Box::new(ApplyWorker::new(Box::new(GatedApp {
+            state: 0,
+            permits: gate,
+        })))
+    });
+    let permits = permits.into_inner();
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+    let slow = (0..3).find(|&id| id != leader_id).unwrap();
+    let fast = (0..3).find(|&id| id != leader_id && id != slow).unwrap();
+
+    cluster.get_by_id(leader_id).client_request(1).unwrap();
+    cluster.get_by_id(leader_id).client_request(2).unwrap();
+    cluster.tick_by(MAX_WAIT);
+
+    // everyone but the slow follower gets to apply both entries
+    for id in [leader_id, fast] {
+        permits[&id].send(()).unwrap();
+        permits[&id].send(()).unwrap();
+        wait_for(|| cluster.peers[&id].log.last_applied() == 2);
+    }
+    cluster.tick_by(MAX_WAIT);
+
+    let status = cluster.get_by_id(leader_id).status();
+    assert_eq!(status.follower_lag()[&slow], 0);
+    assert_eq!(status.follower_apply_lag()[&slow], 2);
+    assert_eq!(status.follower_apply_lag()[&fast], 0);
+
+    (0..2).for_each(|_| permits[&slow].send(()).unwrap());
+}
+
+struct SlowApp {
+    state: u32,
+}
+
+impl App<u32, u32> for SlowApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        thread::sleep(Duration::from_millis(5));
+        self.state += entry.data;
+    }
+    fn get_state(&self) -> u32 {
+        self.state
+    }
+}
+
+#[test]
+fn slow_apply_is_reported() {
+    init_logger();
+    let config = RaftConfig {
+        slow_path: SlowPathConfig {
+            apply: Some(Duration::from_millis(1)),
+            tick: Some(Duration::from_secs(60)),
+            ..Default::default()
+        },
+        ..DEFAULT_CFG
+    };
+    let mut node = RaftServer::new(
+        0,
+        BTreeSet::new(),
+        config,
+        Some(0),
+        Box::new(SlowApp { state: 0 }),
+    );
+    let events = node.observers.subscribe();
+    (0..MAX_WAIT).for_each(|_| {
+        node.tick();
+    });
+    node.client_request(1).unwrap();
+
+    let slow: Vec<SlowOperation> = events
+        .try_iter()
+        .filter_map(|event| match event {
+            RaftEvent::SlowPath { operation, elapsed } => {
+                assert!(elapsed >= Duration::from_millis(5));
+                Some(operation)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(slow, [SlowOperation::Apply { entries: 1 }]);
+    assert_eq!(node.metrics().slow_operations, 1);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::*;
+use miniraft::{
+    error::RaftError,
+    server::{RaftConfig, RaftServer},
+};
+
+fn builder() -> miniraft::server::RaftServerBuilder<u32, u32> {
+    RaftServer::builder()
+        .id(0)
+        .config(DEFAULT_CFG)
+        .seed(1)
+        .app(CountingApp { state: 0 })
+}
+
+#[test]
+fn built_node_runs() {
+    let mut node = builder().build().unwrap();
+    // no peers is a single node cluster
+    node.tick();
+    assert!(node.is_leader());
+    node.client_request(2).unwrap();
+    assert_eq!(node.log.app.get_state(), 2);
+
+    let node = builder().peers([1, 2]).build().unwrap();
+    assert_eq!(node.quorum_size(), 2);
+}
+
+#[test]
+fn same_seed_same_node() {
+    let first = builder().peers([1, 2]).build().unwrap();
+    let second = builder().peers([1, 2]).build().unwrap();
+    assert_eq!(first.debug_dump(0).timer, second.debug_dump(0).timer);
+}
+
+#[test]
+fn nodes_recover_from_storage() {
+    let mut node = builder().peers([1, 2]).build().unwrap();
+    while !node.is_candidate() {
+        node.tick();
+    }
+    let state = node.persistent_state();
+    assert!(state.current_term > 0);
+
+    let recovered = builder().peers([1, 2]).storage(state).build().unwrap();
+    assert_eq!(recovered.current_term(), node.current_term());
+    assert_eq!(recovered.voted_for(), Some(0));
+}
+
+#[test]
+fn bad_setups_are_rejected() {
+    let app = || CountingApp { state: 0 };
+    let no_id = RaftServer::<u32, u32>::builder().app(app()).build();
+    assert!(matches!(no_id, Err(RaftError::InvalidRequest(_))));
+    let no_app = RaftServer::<u32, u32>::builder().id(0).build();
+    assert!(matches!(no_app, Err(RaftError::InvalidRequest(_))));
+    let own_peer = builder().peers([0, 1]).build();
+    assert!(matches!(own_peer, Err(RaftError::InvalidRequest(_))));
+    let bad_config = builder()
+        .config(RaftConfig {
+            heartbeat_interval: 0,
+            ..DEFAULT_CFG
+        })
+        .build();
+    assert!(matches!(bad_config, Err(RaftError::InvalidConfig(_))));
+}
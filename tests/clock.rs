@@ -0,0 +1,86 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use common::*;
+use miniraft::{
+    clock::{Clock, ManualClock, WallClock},
+    rpc::RPC,
+    server::{RaftConfig, RaftServer},
+    sim::Cluster,
+};
+
+#[test]
+fn manual_clock_drives_many_ticks_at_once() {
+    let mut cluster = TestCluster::new(1, 0, DEFAULT_CFG);
+    let node = cluster.get_by_id(0);
+    let mut clock = ManualClock::new();
+
+    assert!(node.advance(&mut clock).is_empty());
+    assert!(!node.is_leader());
+
+    clock.advance(MAX_WAIT);
+    node.advance(&mut clock);
+    assert!(node.is_leader());
+    assert_eq!(clock.elapsed(), 0);
+}
+
+#[test]
+fn tick_n_ends_up_where_single_ticks_do() {
+    let cfg = RaftConfig {
+        leaderless_alarm: Some(3),
+        ..DEFAULT_CFG
+    };
+    let new = || {
+        let app = Box::new(CountingApp { state: 0 });
+        RaftServer::<u32, u32>::new(0, [1, 2].into(), cfg.clone(), Some(7), app)
+    };
+    let (mut one_by_one, mut at_once) = (new(), new());
+    let (events, events_at_once) = (
+        one_by_one.observers.subscribe(),
+        at_once.observers.subscribe(),
+    );
+
+    // a candidate nobody answers keeps starting elections, and the alarm goes off midway
+    let msgs: Vec<_> = (0..1_000).flat_map(|_| one_by_one.tick()).collect();
+    assert_eq!(at_once.tick_n(1_000), msgs);
+    assert_eq!(at_once.current_term(), one_by_one.current_term());
+    assert_eq!(at_once.debug_dump(0).timer, one_by_one.debug_dump(0).timer);
+    assert_eq!(
+        events_at_once.try_iter().collect::<Vec<_>>(),
+        events.try_iter().collect::<Vec<_>>()
+    );
+
+    at_once.pause();
+    assert!(at_once.tick_n(1_000).is_empty());
+    assert_eq!(at_once.current_term(), one_by_one.current_term());
+}
+
+#[test]
+fn late_leader_sends_what_it_would_have() {
+    let elected = || {
+        let mut cluster = Cluster::new(3, 1, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+        assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+        cluster.client_request(1).unwrap();
+        cluster
+    };
+    let (mut one_by_one, mut at_once) = (elected(), elected());
+    let leader = one_by_one.leader().unwrap().id;
+
+    let node = one_by_one.node_mut(leader);
+    let msgs: Vec<_> = (0..100).flat_map(|_| node.tick()).collect();
+    assert!(msgs
+        .iter()
+        .any(|(_, rpc)| matches!(rpc, RPC::AppendRequest(_))));
+    assert_eq!(at_once.node_mut(leader).tick_n(100), msgs);
+    assert!(at_once.node(leader).is_leader());
+}
+
+#[test]
+fn wall_clock_counts_whole_ticks() {
+    let mut clock = WallClock::new(Duration::from_millis(10));
+    assert_eq!(clock.elapsed(), 0);
+    thread::sleep(Duration::from_millis(35));
+    let ticks = clock.elapsed();
+    assert!(ticks >= 3, "only {} ticks after 35ms", ticks);
+}
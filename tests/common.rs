@@ -1,13 +1,18 @@
 #![allow(dead_code)]
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::PathBuf,
+};
 
 use anyhow::Result;
 use miniraft::{
     debug::{assertion, colour_server, colour_term, init_logger},
     log::{App, Log, LogEntry},
     rpc::{SendableMessage, Target},
-    server::{RaftConfig, RaftServer, ServerId, Term},
+    server::{RaftConfig, RaftServer, ServerId, SlowPathConfig, Term, TieBreak},
+    topology::{to_dot, NetworkView},
 };
 
 use rand::{RngCore, SeedableRng};
@@ -17,13 +22,25 @@ pub const DEFAULT_CFG: RaftConfig = RaftConfig {
     election_timeout: 10,
     election_timeout_jitter: 3,
     heartbeat_interval: 5,
+    max_apply_lag: None,
+    slow_path: SlowPathConfig {
+        apply: None,
+        persist: None,
+        tick: None,
+    },
+    leaderless_alarm: None,
+    max_log_entries: None,
+    persist_in_background: false,
+    replication_batching: None,
+    broadcast_commits: false,
+    tie_break: TieBreak::Never,
 };
 
 pub const MAX_WAIT: u32 = DEFAULT_CFG.election_timeout + DEFAULT_CFG.election_timeout_jitter;
 pub const MAX_TICKS: u32 = 1_000;
 
 pub struct CountingApp {
-    state: u32,
+    pub state: u32,
 }
 
 impl App<u32, u32> for CountingApp {
@@ -33,6 +50,23 @@ impl App<u32, u32> for CountingApp {
     fn get_state(&self) -> u32 {
         self.state
     }
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        writer.write_all(&self.state.to_le_bytes())
+    }
+    fn restore(&mut self, reader: &mut dyn io::Read) -> io::Result<()> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        self.state = u32::from_le_bytes(buf);
+        Ok(())
+    }
+}
+
+/// Fresh, empty directory for a test to write files into
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("miniraft-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
 }
 
 pub fn setup_log() -> Log<u32, u32> {
@@ -85,8 +119,8 @@ impl TestCluster {
             (Target::Single(to), rpc) => {
                 if !self.should_drop(from.to_owned(), to.to_owned()) {
                     // get target peer, return an error if its not found
-                    let peer = self.peers.get_mut(&to).expect("peer not found");
-                    let new_msgs = wrap_with_sender(peer.id, peer.receive_rpc(&rpc));
+                    let peer = self.peers.get_mut(to).expect("peer not found");
+                    let new_msgs = wrap_with_sender(peer.id, peer.receive_rpc(rpc));
                     self.msg_queue.extend(new_msgs);
                 }
             }
@@ -99,7 +133,7 @@ impl TestCluster {
                             || self.drop_connections.contains(&(from.to_owned(), to));
                         !should_drop
                     })
-                    .map(|peer| wrap_with_sender(peer.id, peer.receive_rpc(&rpc)))
+                    .map(|peer| wrap_with_sender(peer.id, peer.receive_rpc(rpc)))
                     .for_each(|new_msgs| self.msg_queue.extend(new_msgs));
             }
         });
@@ -113,6 +147,15 @@ impl TestCluster {
     }
 
     pub fn new(n: usize, seed: u64, config: RaftConfig) -> Self {
+        Self::with_apps(n, seed, config, |_| Box::new(CountingApp { state: 0 }))
+    }
+
+    pub fn with_apps(
+        n: usize,
+        seed: u64,
+        config: RaftConfig,
+        new_app: impl Fn(ServerId) -> Box<dyn App<u32, u32>>,
+    ) -> Self {
         init_logger();
 
         let mut cluster = TestCluster {
@@ -137,7 +180,7 @@ impl TestCluster {
                     peers_without_this,
                     config.clone(),
                     Some(rng.next_u64()),
-                    Box::new(CountingApp { state: 0 }),
+                    new_app(this_id),
                 ),
             );
         }
@@ -170,7 +213,7 @@ impl TestCluster {
     }
 
     pub fn get_leader(&self) -> Option<&RaftServer<u32, u32>> {
-        self.peers.values().filter(|peer| peer.is_leader()).last()
+        self.peers.values().rfind(|peer| peer.is_leader())
     }
 
     pub fn get_leader_mut(&mut self) -> Option<&mut RaftServer<u32, u32>> {
@@ -196,17 +239,15 @@ impl TestCluster {
         self.peers
             .values()
             .filter(|peer| peer.current_term != l_term)
-            .map(|peer| {
+            .inspect(|peer| {
                 assertion(format!(
                     "mismatched term: {} is at current {}",
                     colour_server(&peer.id),
                     colour_term(peer.current_term)
                 ));
-                peer
             })
             .collect::<Vec<_>>()
-            .len()
-            == 0
+            .is_empty()
     }
 
     pub fn state_consensus(&self) -> bool {
@@ -222,17 +263,15 @@ impl TestCluster {
         self.peers
             .values()
             .filter(|peer| peer.log.app.get_state() != l_state)
-            .map(|peer| {
+            .inspect(|peer| {
                 assertion(format!(
                     "mismatched state: {} has state {}",
                     colour_server(&peer.id),
                     peer.log.app.get_state()
                 ));
-                peer
             })
             .collect::<Vec<_>>()
-            .len()
-            == 0
+            .is_empty()
     }
 }
 
@@ -242,3 +281,15 @@ fn wrap_with_sender(
 ) -> Vec<(ServerId, SendableMessage<u32>)> {
     msgs.into_iter().map(|msg| (from, msg)).collect()
 }
+
+impl TestCluster {
+    /// Current view of the cluster as a GraphViz graph, partitions included
+    pub fn to_dot(&self) -> String {
+        let statuses: Vec<_> = self.peers.values().map(|peer| peer.status()).collect();
+        let network = NetworkView {
+            down: self.down.clone(),
+            dropped: self.drop_connections.clone(),
+        };
+        to_dot(&statuses, &network)
+    }
+}
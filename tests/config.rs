@@ -0,0 +1,255 @@
+mod common;
+
+use std::{collections::BTreeSet, time::Duration};
+
+use common::*;
+use miniraft::{
+    error::RaftError,
+    server::{RaftConfig, RaftConfigBuilder, RaftServer, ReplicationBatching, TieBreak},
+};
+
+fn invalid(builder: RaftConfigBuilder) -> String {
+    match builder.build() {
+        Err(RaftError::InvalidConfig(msg)) => msg,
+        other => panic!("expected an invalid config, got {:?}", other),
+    }
+}
+
+#[test]
+fn builder_defaults_are_valid() {
+    let config = RaftConfig::builder().build().unwrap();
+    assert_eq!(config.election_timeout, DEFAULT_CFG.election_timeout);
+    assert_eq!(config.heartbeat_interval, DEFAULT_CFG.heartbeat_interval);
+    assert!(DEFAULT_CFG.validate().is_ok());
+}
+
+#[test]
+fn builder_rejects_configs_that_cannot_work() {
+    let msg = invalid(RaftConfig::builder().election_timeout(0));
+    assert!(msg.contains("election_timeout"), "{}", msg);
+    let msg = invalid(RaftConfig::builder().heartbeat_interval(0));
+    assert!(msg.contains("heartbeat_interval"), "{}", msg);
+    let msg = invalid(RaftConfig::builder().election_timeout_jitter(10));
+    assert!(msg.contains("election_timeout_jitter (10)"), "{}", msg);
+    let msg = invalid(RaftConfig::builder().heartbeat_interval(7));
+    assert!(msg.contains("shortest election timeout (7)"), "{}", msg);
+    invalid(RaftConfig::builder().max_apply_lag(0));
+    invalid(RaftConfig::builder().leaderless_alarm(0));
+    invalid(RaftConfig::builder().max_log_entries(0));
+    invalid(RaftConfig::builder().replication_batching(0, 4));
+    invalid(RaftConfig::builder().replication_batching(2, 0));
+
+    let config = RaftConfig::builder()
+        .election_timeout(100)
+        .election_timeout_jitter(20)
+        .heartbeat_interval(10)
+        .max_apply_lag(50)
+        .build()
+        .unwrap();
+    assert_eq!(config.max_apply_lag, Some(50));
+}
+
+#[test]
+fn jitter_larger_than_timeout_does_not_underflow() {
+    let config = RaftConfig {
+        election_timeout: 2,
+        election_timeout_jitter: 5,
+        ..DEFAULT_CFG
+    };
+    assert!(config.validate().is_err());
+    let mut node = RaftServer::new(
+        0,
+        BTreeSet::from([1, 2]),
+        config,
+        Some(0),
+        Box::new(CountingApp { state: 0 }),
+    );
+    for _ in 0..MAX_TICKS {
+        node.tick();
+    }
+    assert!(node.is_candidate());
+}
+
+#[test]
+fn durations_are_converted_to_ticks() {
+    let ms = Duration::from_millis;
+    let config = RaftConfig::from_durations(ms(150), ms(50), ms(10)).unwrap();
+    assert_eq!(config.election_timeout, 15);
+    assert_eq!(config.election_timeout_jitter, 5);
+    assert_eq!(config.heartbeat_interval, 5);
+
+    // election timeout rounds up, heartbeat rounds down
+    let config = RaftConfig::from_durations(ms(301), ms(99), ms(10)).unwrap();
+    assert_eq!(config.election_timeout, 31);
+    assert_eq!(config.heartbeat_interval, 9);
+
+    assert!(matches!(
+        RaftConfig::from_durations(ms(150), ms(50), Duration::ZERO),
+        Err(RaftError::InvalidConfig(_))
+    ));
+    // a heartbeat shorter than a tick would never go out
+    assert!(RaftConfig::from_durations(ms(150), ms(5), ms(10)).is_err());
+    // and one as long as the election timeout is too late
+    assert!(RaftConfig::from_durations(ms(150), ms(150), ms(10)).is_err());
+    assert!(RaftConfig::from_durations(Duration::MAX, ms(50), Duration::from_nanos(1)).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn config_round_trips_through_a_file() {
+    use miniraft::{
+        server::SlowPathConfig,
+        storage::{load_config, save_config},
+    };
+
+    let path = test_dir("config_round_trip").join("raft.json");
+    let config = RaftConfig::builder()
+        .max_apply_lag(100)
+        .leaderless_alarm(3)
+        .slow_path(SlowPathConfig {
+            apply: Some(Duration::from_millis(5)),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    save_config(&path, &config).unwrap();
+    assert_eq!(load_config(&path).unwrap(), config);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn config_files_are_checked() {
+    use miniraft::storage::load_config;
+
+    let dir = test_dir("config_files");
+    let path = dir.join("raft.json");
+    // optional settings can be left out
+    std::fs::write(
+        &path,
+        r#"{ "election_timeout": 10, "election_timeout_jitter": 3, "heartbeat_interval": 5 }"#,
+    )
+    .unwrap();
+    assert_eq!(load_config(&path).unwrap(), DEFAULT_CFG);
+
+    std::fs::write(
+        &path,
+        r#"{ "election_timeout": 10, "election_timeout_jitter": 3, "heartbeat_interval": 10 }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        load_config(&path),
+        Err(RaftError::InvalidConfig(_))
+    ));
+
+    std::fs::write(&path, r#"{ "election_timeout": "soon" }"#).unwrap();
+    assert!(matches!(
+        load_config(&path),
+        Err(RaftError::InvalidConfig(_))
+    ));
+
+    assert!(matches!(
+        load_config(&dir.join("missing.json")),
+        Err(RaftError::Storage(_))
+    ));
+}
+
+#[test]
+fn running_node_takes_new_timeouts() {
+    let slow = RaftConfig::builder()
+        .election_timeout(1_000)
+        .heartbeat_interval(100)
+        .build()
+        .unwrap();
+    let app = Box::new(CountingApp { state: 0 });
+    let mut node: RaftServer<u32, u32> = RaftServer::new(0, [1, 2].into(), slow, Some(1), app);
+    assert!(node.debug_dump(0).timer > MAX_WAIT);
+
+    // a timer that was set with the old timeouts is cut short
+    node.reconfigure(DEFAULT_CFG).unwrap();
+    assert_eq!(node.config(), &DEFAULT_CFG);
+    assert!(node.debug_dump(0).timer <= MAX_WAIT);
+    node.tick_n(MAX_WAIT);
+    assert!(node.is_candidate());
+
+    let broken = RaftConfig {
+        heartbeat_interval: 0,
+        ..DEFAULT_CFG
+    };
+    assert!(matches!(
+        node.reconfigure(broken),
+        Err(RaftError::InvalidConfig(_))
+    ));
+    assert_eq!(node.config(), &DEFAULT_CFG);
+}
+
+#[test]
+fn reconfigure_changes_tuning_but_not_how_the_log_is_persisted() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap().id;
+
+    // everything but persistence can change on a running node
+    let tuned = RaftConfig {
+        max_apply_lag: Some(100),
+        leaderless_alarm: Some(3),
+        max_log_entries: Some(2),
+        replication_batching: Some(ReplicationBatching {
+            delay: 1,
+            max_entries: 1,
+        }),
+        broadcast_commits: true,
+        tie_break: TieBreak::LowerId,
+        ..DEFAULT_CFG
+    };
+    for id in 0..3 {
+        cluster.get_by_id(id).reconfigure(tuned.clone()).unwrap();
+    }
+    for i in 0..5 {
+        cluster.get_by_id(leader).client_request(i).unwrap();
+        cluster.tick_by(2);
+    }
+    cluster.tick_by(MAX_WAIT);
+    for id in 0..3 {
+        let node = cluster.get_by_id(id);
+        assert_eq!(node.log.committed_len, 5);
+        // the new log limit made them compact
+        assert!(node.log.snapshot.len > 0);
+    }
+
+    let background = RaftConfig {
+        persist_in_background: true,
+        ..tuned.clone()
+    };
+    let node = cluster.get_by_id(leader);
+    assert!(matches!(
+        node.reconfigure(background),
+        Err(RaftError::InvalidConfig(_))
+    ));
+    assert_eq!(node.config(), &tuned);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn watcher_reports_each_change_once() {
+    use miniraft::storage::{save_config, ConfigWatcher};
+
+    let path = test_dir("config_watcher").join("raft.json");
+    save_config(&path, &DEFAULT_CFG).unwrap();
+    let mut watcher = ConfigWatcher::new(&path);
+    assert!(watcher.poll().is_none());
+
+    let tuned = RaftConfig {
+        election_timeout: 20,
+        ..DEFAULT_CFG
+    };
+    save_config(&path, &tuned).unwrap();
+    assert_eq!(watcher.poll().unwrap().unwrap(), tuned);
+    assert!(watcher.poll().is_none());
+
+    std::fs::write(&path, "{").unwrap();
+    assert!(matches!(
+        watcher.poll(),
+        Some(Err(RaftError::InvalidConfig(_)))
+    ));
+    assert!(watcher.poll().is_none());
+}
@@ -0,0 +1,173 @@
+//! Crash-recovery runs against real storage directories: nodes write their persistent state
+//! to disk after every step, get killed at random points and come back from nothing but
+//! what is in their directory
+
+#![cfg(feature = "serde")]
+
+mod common;
+
+use std::{collections::BTreeMap, path::Path};
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    log::{LogEntry, LogIndex},
+    server::ServerId,
+    sim::{Cluster, Disk},
+    storage::load_state,
+};
+use rand::Rng;
+
+const NODES: usize = 5;
+const MAJORITY: usize = NODES / 2 + 1;
+
+/// Every entry any node has ever reported as committed, by index
+#[derive(Default)]
+struct Committed(BTreeMap<LogIndex, LogEntry<u32>>);
+
+impl Committed {
+    /// Record what every live node has committed, panicking if it contradicts what was
+    /// committed before, i.e. if a committed entry was lost, replaced or moved
+    fn observe(&mut self, cluster: &Cluster<u32, u32>) {
+        for node in cluster.live_nodes() {
+            let log = &node.log;
+            for idx in log.snapshot.len..log.committed_len {
+                let entry = &log.entries[idx - log.snapshot.len];
+                let seen = self.0.entry(idx).or_insert_with(|| entry.clone());
+                assert_eq!(
+                    seen,
+                    entry,
+                    "seed {}: node {} committed a different entry at {}",
+                    cluster.seed(),
+                    node.id,
+                    idx
+                );
+            }
+        }
+    }
+
+    /// Check every committed entry is on the disk of a majority of nodes, so no single
+    /// crash can lose it
+    fn check_on_disk(&self, cluster: &Cluster<u32, u32>, dirs: &[impl AsRef<Path>]) {
+        let states: Vec<_> = dirs
+            .iter()
+            .map(|dir| {
+                load_state::<u32, ServerId>(dir.as_ref())
+                    .unwrap()
+                    .expect("state on disk")
+            })
+            .collect();
+        for (&idx, entry) in &self.0 {
+            let copies = states
+                .iter()
+                .filter(|state| {
+                    idx.checked_sub(state.snapshot.len)
+                        .and_then(|i| state.entries.get(i))
+                        == Some(entry)
+                })
+                .count();
+            assert!(
+                copies >= MAJORITY,
+                "seed {}: committed entry {} is only on {} disks",
+                cluster.seed(),
+                idx,
+                copies
+            );
+        }
+    }
+}
+
+/// Run a workload on a cluster persisting to `name`'s test directory, killing nodes at
+/// random points and restarting them from their storage directories. Checks no committed
+/// entry is ever lost or reordered, and returns the cluster once every node has caught up
+fn run_with_crashes(name: &str, seed: u64, rounds: u32) -> Cluster<u32, u32> {
+    init_logger();
+    let mut cluster = Cluster::new(NODES, seed, DEFAULT_CFG, |_| {
+        Box::new(CountingApp { state: 0 })
+    });
+    cluster.persist_to(test_dir(name)).unwrap();
+    let dirs: Vec<_> = (0..NODES)
+        .map(|id| cluster.storage_dir(id).unwrap())
+        .collect();
+    let mut committed = Committed::default();
+    // nodes that are down, with the tick they come back at
+    let mut down: BTreeMap<ServerId, u32> = BTreeMap::new();
+    let mut next = 1;
+
+    for _ in 0..rounds {
+        let rng = cluster.rng();
+        let propose = rng.gen_bool(0.3);
+        let crash = rng.gen_bool(0.05).then(|| rng.gen_range(0..NODES));
+        let downtime = rng.gen_range(0..2 * MAX_WAIT);
+
+        if propose && cluster.client_request(next).is_ok() {
+            next += 1;
+        }
+        if let Some(id) = crash {
+            // keep a majority up so the cluster can make progress
+            if !down.contains_key(&id) && down.len() + 1 < MAJORITY {
+                cluster.kill(id);
+                down.insert(id, cluster.now() + downtime);
+                committed.check_on_disk(&cluster, &dirs);
+            }
+        }
+        let now = cluster.now();
+        let back: Vec<ServerId> = down
+            .iter()
+            .filter(|(_, &at)| at <= now)
+            .map(|(&id, _)| id)
+            .collect();
+        for id in back {
+            down.remove(&id);
+            cluster.restart(id, Disk::Intact).unwrap();
+        }
+        cluster.tick();
+        committed.observe(&cluster);
+    }
+
+    // bring everyone back and make sure every committed entry made it onto every node
+    for id in down.into_keys() {
+        cluster.restart(id, Disk::Intact).unwrap();
+    }
+    cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some());
+    cluster.client_request(next).unwrap();
+    assert!(cluster.run_until(MAX_TICKS, |cluster| {
+        cluster.nodes().all(|node| {
+            node.log.committed_len == node.log.len()
+                && node.log.entries.last().map(|entry| entry.data) == Some(next)
+        })
+    }));
+    committed.observe(&cluster);
+    committed.check_on_disk(&cluster, &dirs);
+    for node in cluster.nodes() {
+        assert_eq!(node.log.committed_len, committed.0.len());
+    }
+    cluster
+}
+
+#[test]
+fn committed_entries_survive_crashes() {
+    for seed in 0..3 {
+        run_with_crashes(&format!("crash-recovery-{}", seed), seed, 300);
+    }
+}
+
+#[test]
+fn every_node_restarted_from_disk_ends_up_with_the_same_state() {
+    let mut cluster = run_with_crashes("crash-recovery-all", 42, 200);
+    for id in 0..NODES {
+        cluster.restart(id, Disk::Intact).unwrap();
+    }
+    cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some());
+    cluster.client_request(0).unwrap();
+    assert!(cluster.run_until(MAX_TICKS, |cluster| {
+        let states: Vec<u32> = cluster
+            .nodes()
+            .map(|node| node.log.app.get_state())
+            .collect();
+        cluster
+            .nodes()
+            .all(|node| node.log.committed_len == node.log.len())
+            && states.windows(2).all(|pair| pair[0] == pair[1])
+    }));
+}
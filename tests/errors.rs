@@ -0,0 +1,99 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::*;
+use miniraft::{
+    error::RaftError,
+    log::{LogEntry, Snapshot},
+    rpc::{AppendRejection, AppendResponse, SnapshotResponse, RPC},
+    server::{PersistentState, RaftServer},
+};
+
+fn recover(state: PersistentState<u32>) -> Result<RaftServer<u32, u32>, RaftError> {
+    RaftServer::recover(
+        0,
+        BTreeSet::from([1, 2]),
+        DEFAULT_CFG,
+        Some(0),
+        Box::new(CountingApp { state: 0 }),
+        state,
+    )
+}
+
+fn entries(terms: &[u64]) -> Vec<LogEntry<u32>> {
+    terms
+        .iter()
+        .map(|&term| LogEntry { term, data: 1 })
+        .collect()
+}
+
+#[test]
+fn followers_point_clients_at_the_leader() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_WAIT);
+    let leader = cluster.get_leader().unwrap().id;
+    let follower = cluster.get_by_id((leader + 1) % 3);
+    match follower.client_request(1) {
+        Err(RaftError::NotLeader { leader: hint }) => assert_eq!(hint, Some(leader)),
+        other => panic!("expected NotLeader, got {:?}", other),
+    }
+}
+
+#[test]
+fn recover_rejects_state_no_node_could_have_persisted() {
+    let state = |current_term, voted_for, terms: &[u64]| PersistentState {
+        current_term,
+        voted_for,
+        snapshot: Snapshot::default(),
+        entries: entries(terms),
+    };
+    assert!(recover(state(2, Some(1), &[1, 2])).is_ok());
+    assert!(matches!(
+        recover(state(2, Some(7), &[1, 2])),
+        Err(RaftError::UnknownPeer(7))
+    ));
+    assert!(matches!(
+        recover(state(2, None, &[2, 1])),
+        Err(RaftError::InvalidRequest(_))
+    ));
+    assert!(matches!(
+        recover(state(1, None, &[1, 3])),
+        Err(RaftError::StaleTerm { term: 1, seen: 3 })
+    ));
+}
+
+#[test]
+fn leader_drops_responses_from_unknown_followers() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_WAIT);
+    let leader = cluster.get_leader_mut().unwrap();
+    let term = leader.current_term();
+    let responses = [
+        RPC::AppendResponse(AppendResponse {
+            ok: false,
+            rejection: Some(AppendRejection::LogMismatch),
+            term,
+            ack_idx: 5,
+            last_applied: 0,
+            follower_id: 42,
+            request_id: 0,
+            trace: None,
+        }),
+        RPC::SnapshotResponse(SnapshotResponse {
+            term,
+            ack_idx: 5,
+            follower_id: 42,
+        }),
+    ];
+    for res in &responses {
+        assert!(leader.receive_rpc(res).is_empty());
+    }
+    assert!(leader.is_leader());
+    assert_eq!(leader.metrics().responses_from_unknown_followers, 2);
+
+    // the cluster carries on as if nothing happened
+    leader.client_request(1).unwrap();
+    cluster.tick_by(3);
+    assert!(cluster.state_consensus());
+}
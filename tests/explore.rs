@@ -0,0 +1,60 @@
+mod common;
+
+use common::*;
+use miniraft::explore::{Choice, Explorer};
+
+fn explorer(depth: usize) -> Explorer<u32, u32> {
+    Explorer::new(3, DEFAULT_CFG, depth, |_| {
+        Box::new(CountingApp { state: 0 })
+    })
+}
+
+/// First schedule the explorer finds that gets a leader elected
+fn elect_leader() -> Vec<Choice> {
+    explorer(4)
+        .invariant("nobody leads", |nodes| {
+            nodes.iter().all(|node| !node.is_leader())
+        })
+        .run()
+        .unwrap_err()
+        .schedule
+}
+
+#[test]
+fn explorer_reports_schedule_breaking_an_invariant() {
+    let schedule = elect_leader();
+    // a node times out and then hears back from a peer
+    assert!(matches!(schedule[0], Choice::Timeout(_)));
+    assert!(schedule.len() >= 3);
+}
+
+#[test]
+fn elections_are_safe_under_every_interleaving() {
+    let states = explorer(6).run().unwrap();
+    assert!(states > 1000);
+}
+
+#[test]
+fn replication_is_safe_under_every_interleaving() {
+    let leader_elected = elect_leader();
+    let states = explorer(5)
+        .after(leader_elected.clone())
+        .proposals(vec![1])
+        .run()
+        .unwrap();
+    assert!(states > 1000);
+
+    // and the bound is deep enough for proposals to get committed
+    let counterexample = explorer(5)
+        .after(leader_elected)
+        .proposals(vec![1])
+        .invariant("nothing commits", |nodes| {
+            nodes.iter().all(|node| node.log.committed_len == 0)
+        })
+        .run()
+        .unwrap_err();
+    assert_eq!(
+        counterexample.violation,
+        "invariant 'nothing commits' violated"
+    );
+}
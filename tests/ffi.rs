@@ -0,0 +1,155 @@
+#![cfg(feature = "ffi")]
+
+mod common;
+
+use std::{ffi::c_void, ptr, slice};
+
+use common::*;
+use miniraft::ffi::*;
+
+const CONFIG: MiniraftConfig = MiniraftConfig {
+    election_timeout: DEFAULT_CFG.election_timeout,
+    election_timeout_jitter: DEFAULT_CFG.election_timeout_jitter,
+    heartbeat_interval: DEFAULT_CFG.heartbeat_interval,
+};
+
+/// What a host keeps per node
+#[derive(Default)]
+struct Host {
+    /// Messages the node sent, waiting to be delivered
+    outbox: Vec<(u64, Vec<u8>)>,
+    /// Entries the node applied
+    applied: Vec<(u64, Vec<u8>)>,
+}
+
+extern "C" fn send(ctx: *mut c_void, to: u64, data: *const u8, len: usize) {
+    let host = unsafe { &mut *(ctx as *mut Host) };
+    let data = unsafe { slice::from_raw_parts(data, len) };
+    host.outbox.push((to, data.to_vec()));
+}
+
+extern "C" fn apply(ctx: *mut c_void, index: u64, data: *const u8, len: usize) {
+    let host = unsafe { &mut *(ctx as *mut Host) };
+    let data = unsafe { slice::from_raw_parts(data, len) };
+    host.applied.push((index, data.to_vec()));
+}
+
+struct Cluster {
+    /// Never grows, so the nodes' pointers into it stay valid
+    hosts: Vec<Host>,
+    nodes: Vec<*mut MiniraftNode>,
+}
+
+impl Cluster {
+    fn new(n: u64) -> Self {
+        let mut hosts: Vec<Host> = (0..n).map(|_| Host::default()).collect();
+        let nodes = (0..n)
+            .map(|id| {
+                let peers: Vec<u64> = (0..n).filter(|peer| *peer != id).collect();
+                let callbacks = MiniraftCallbacks {
+                    ctx: &mut hosts[id as usize] as *mut Host as *mut c_void,
+                    send,
+                    apply,
+                };
+                let node =
+                    unsafe { miniraft_new(id, peers.as_ptr(), peers.len(), CONFIG, id, callbacks) };
+                assert!(!node.is_null());
+                node
+            })
+            .collect();
+        Cluster { hosts, nodes }
+    }
+
+    /// Tick every node, then deliver messages until there are none left
+    fn tick(&mut self) {
+        for node in &self.nodes {
+            assert_eq!(unsafe { miniraft_tick(*node) }, MiniraftResult::Ok);
+        }
+        loop {
+            let mut sent = Vec::new();
+            for (from, host) in self.hosts.iter_mut().enumerate() {
+                sent.extend(
+                    host.outbox
+                        .drain(..)
+                        .map(|(to, data)| (from as u64, to, data)),
+                );
+            }
+            if sent.is_empty() {
+                break;
+            }
+            for (from, to, data) in sent {
+                for (id, node) in self.nodes.iter().enumerate() {
+                    let id = id as u64;
+                    if id == to || (to == MINIRAFT_BROADCAST && id != from) {
+                        let result = unsafe { miniraft_receive(*node, data.as_ptr(), data.len()) };
+                        assert_eq!(result, MiniraftResult::Ok);
+                    }
+                }
+            }
+        }
+    }
+
+    fn leader(&self) -> Option<u64> {
+        let mut leader = 0;
+        let knows = unsafe { miniraft_leader(self.nodes[0], &mut leader) };
+        knows.then_some(leader)
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        for node in self.nodes.drain(..) {
+            unsafe { miniraft_free(node) };
+        }
+    }
+}
+
+#[test]
+fn nodes_elect_and_apply_through_the_c_abi() {
+    let mut cluster = Cluster::new(3);
+    for _ in 0..MAX_TICKS {
+        cluster.tick();
+        if cluster.leader().is_some() {
+            break;
+        }
+    }
+    let leader = cluster.leader().expect("no leader elected") as usize;
+    let follower = (leader + 1) % 3;
+    assert!(unsafe { miniraft_term(cluster.nodes[leader]) } >= 1);
+
+    let entry = b"hello";
+    let propose = |node| unsafe { miniraft_propose(node, entry.as_ptr(), entry.len()) };
+    assert_eq!(propose(cluster.nodes[follower]), MiniraftResult::NotLeader);
+    assert_eq!(propose(cluster.nodes[leader]), MiniraftResult::Ok);
+    for _ in 0..DEFAULT_CFG.heartbeat_interval * 2 {
+        cluster.tick();
+    }
+    for (node, host) in cluster.nodes.iter().zip(&cluster.hosts) {
+        assert_eq!(unsafe { miniraft_committed_len(*node) }, 1);
+        assert_eq!(host.applied, vec![(0, entry.to_vec())]);
+    }
+}
+
+#[test]
+fn bad_arguments_are_rejected() {
+    let callbacks = MiniraftCallbacks {
+        ctx: ptr::null_mut(),
+        send,
+        apply,
+    };
+    let config = MiniraftConfig {
+        heartbeat_interval: 0,
+        ..CONFIG
+    };
+    assert!(unsafe { miniraft_new(0, ptr::null(), 0, config, 0, callbacks) }.is_null());
+
+    let cluster = Cluster::new(1);
+    let garbage = b"not a message";
+    let result = unsafe { miniraft_receive(cluster.nodes[0], garbage.as_ptr(), garbage.len()) };
+    assert_eq!(result, MiniraftResult::InvalidArgument);
+    assert_eq!(
+        unsafe { miniraft_tick(ptr::null_mut()) },
+        MiniraftResult::InvalidArgument
+    );
+    unsafe { miniraft_free(ptr::null_mut()) };
+}
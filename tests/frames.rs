@@ -0,0 +1,145 @@
+use std::num::NonZeroUsize;
+
+use miniraft::frames::{FrameError, Framer, Reassembler};
+#[cfg(feature = "serde")]
+use miniraft::{
+    log::LogEntry,
+    rpc::{AppendRequest, RPC},
+};
+
+fn framer(max_frame_len: usize) -> Framer {
+    Framer::new(NonZeroUsize::new(max_frame_len).unwrap())
+}
+
+fn reassembler(max_partial: usize) -> Reassembler {
+    Reassembler::new(NonZeroUsize::new(max_partial).unwrap(), 1 << 20)
+}
+
+#[test]
+fn frames_come_back_together_in_any_order() {
+    let message: Vec<u8> = (0..=255).collect();
+    let mut frames = framer(100).split(&message);
+    assert_eq!(frames.len(), 3);
+    assert!(frames.iter().all(|frame| frame.data.len() <= 100));
+
+    let mut reassembler = reassembler(4);
+    frames.reverse();
+    let duplicate = frames[0].clone();
+    assert_eq!(reassembler.receive(frames.remove(0)), Ok(None));
+    assert_eq!(reassembler.receive(duplicate), Ok(None));
+    assert_eq!(reassembler.receive(frames.remove(0)), Ok(None));
+    assert_eq!(reassembler.pending(), 1);
+    assert_eq!(reassembler.receive(frames.remove(0)), Ok(Some(message)));
+    assert_eq!(reassembler.pending(), 0);
+
+    let mut framer = framer(100);
+    let empty = framer.split(&[]);
+    assert_eq!(empty.len(), 1);
+    assert_eq!(reassembler.receive(empty[0].clone()), Ok(Some(vec![])));
+}
+
+#[test]
+fn messages_missing_frames_are_given_up_on() {
+    let mut framer = framer(1);
+    let mut reassembler = reassembler(2);
+    let lost: Vec<_> = (0..3).map(|_| framer.split(b"ab")).collect();
+    for frames in &lost {
+        reassembler.receive(frames[0].clone()).unwrap();
+    }
+    assert_eq!(reassembler.pending(), 2);
+    // the oldest was dropped, the newer ones can still complete
+    assert_eq!(reassembler.receive(lost[0][1].clone()), Ok(None));
+    assert_eq!(
+        reassembler.receive(lost[2][1].clone()),
+        Ok(Some(b"ab".to_vec()))
+    );
+
+    // frames that don't belong with the rest of their message are turned away, and so is
+    // the rest of the message
+    let mut bad = lost[1][1].clone();
+    bad.count = 3;
+    assert_eq!(
+        reassembler.receive(bad.clone()),
+        Err(FrameError::CountMismatch {
+            count: 3,
+            expected: 2
+        })
+    );
+    assert_eq!(reassembler.pending(), 0);
+    bad.index = 7;
+    assert_eq!(
+        reassembler.receive(bad),
+        Err(FrameError::IndexOutOfRange { index: 7, count: 3 })
+    );
+}
+
+#[test]
+fn oversized_messages_are_turned_away_before_taking_memory() {
+    let mut reassembler = Reassembler::new(NonZeroUsize::new(4).unwrap(), 10);
+
+    // a header claiming more frames than bytes we take can't be right
+    let mut huge = framer(1).split(b"ab").remove(0);
+    huge.count = u32::MAX;
+    assert_eq!(
+        reassembler.receive(huge),
+        Err(FrameError::TooLong {
+            len: u32::MAX as usize,
+            max: 10
+        })
+    );
+    assert_eq!(reassembler.pending(), 0);
+
+    // frames that each look fine but add up to too much
+    let mut frames = framer(4).split(&[7; 12]);
+    assert_eq!(reassembler.receive(frames.remove(0)), Ok(None));
+    assert_eq!(reassembler.receive(frames.remove(0)), Ok(None));
+    assert_eq!(
+        reassembler.receive(frames.remove(0)),
+        Err(FrameError::TooLong { len: 12, max: 10 })
+    );
+    assert_eq!(reassembler.pending(), 0);
+
+    let single = framer(100).split(&[7; 11]).remove(0);
+    assert!(reassembler.receive(single).is_err());
+    let fits = framer(4).split(&[7; 10]);
+    let message = fits
+        .into_iter()
+        .map(|frame| reassembler.receive(frame).unwrap())
+        .last()
+        .unwrap();
+    assert_eq!(message, Some(vec![7; 10]));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn large_entries_get_through_a_small_transport() {
+    let rpc: RPC<Vec<u8>> = RPC::AppendRequest(AppendRequest {
+        leader_term: 1,
+        leader_id: 0,
+        leader_last_log_idx: 0,
+        leader_last_log_term: 0,
+        leader_commit: 0,
+        transfer_target: None,
+        transfer_ticks_left: 0,
+        entries: vec![LogEntry {
+            term: 1,
+            data: vec![7; 10_000],
+        }]
+        .into(),
+        request_id: 0,
+        trace: None,
+    });
+    let encoded = serde_json::to_vec(&rpc).unwrap();
+    let frames = framer(1024).split(&encoded);
+    assert!(frames.len() > 10);
+
+    let mut reassembler = reassembler(1);
+    let decoded = frames
+        .into_iter()
+        .find_map(|frame| reassembler.receive(frame).unwrap())
+        .unwrap();
+    assert_eq!(
+        serde_json::from_slice::<RPC<Vec<u8>>>(&decoded).unwrap(),
+        rpc
+    );
+}
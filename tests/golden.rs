@@ -0,0 +1,109 @@
+//! Golden-trace regression tests: canonical scenarios are played out on a seeded cluster
+//! and every message they produce is compared line by line against a trace checked in
+//! under `tests/golden`. A diff means the protocol behaves differently than it used to.
+//! If that is intended, rerun with `UPDATE_GOLDEN=1` (`make golden`) and review the new traces
+
+mod common;
+
+use std::{env, fs, path::PathBuf};
+
+use common::*;
+use miniraft::{debug::init_logger, sim::Cluster};
+
+fn cluster() -> Cluster<u32, u32> {
+    init_logger();
+    Cluster::new(3, 11, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }))
+}
+
+/// Compare the cluster's trace against the golden trace `name`, or overwrite the golden
+/// trace with it if `UPDATE_GOLDEN` is set
+fn assert_golden(name: &str, cluster: &Cluster<u32, u32>) {
+    let actual: String = cluster
+        .trace()
+        .iter()
+        .map(|msg| format!("{}\n", msg))
+        .collect();
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("trace");
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("no golden trace at {}: {}", path.display(), err));
+    for (i, (expected, actual)) in expected.lines().zip(actual.lines()).enumerate() {
+        assert_eq!(
+            expected,
+            actual,
+            "{} differs from the golden trace at line {}",
+            name,
+            i + 1
+        );
+    }
+    assert_eq!(
+        expected.lines().count(),
+        actual.lines().count(),
+        "{} has a different number of messages than the golden trace",
+        name
+    );
+}
+
+/// Run until there is a leader, plus a heartbeat round
+fn elect(cluster: &mut Cluster<u32, u32>) -> usize {
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    cluster.tick();
+    cluster.leader().unwrap().id
+}
+
+/// Run until every live node has committed everything in its log
+fn settle(cluster: &mut Cluster<u32, u32>) {
+    assert!(cluster.run_until(MAX_TICKS, |cluster| {
+        cluster
+            .live_nodes()
+            .all(|node| node.log.committed_len == node.log.len())
+    }));
+}
+
+#[test]
+fn clean_election() {
+    let mut cluster = cluster();
+    cluster.start_trace();
+    elect(&mut cluster);
+    assert_golden("clean_election", &cluster);
+}
+
+#[test]
+fn follower_catch_up() {
+    let mut cluster = cluster();
+    let leader = elect(&mut cluster);
+    let follower = (leader + 1) % 3;
+    cluster.kill(follower);
+    for data in 1..=3 {
+        cluster.client_request(data).unwrap();
+    }
+    settle(&mut cluster);
+    cluster.start_trace();
+    cluster.revive(follower);
+    assert!(cluster.run_until(MAX_TICKS, |cluster| {
+        cluster.node(follower).log.committed_len == 3
+    }));
+    assert_golden("follower_catch_up", &cluster);
+}
+
+#[test]
+fn leader_failover() {
+    let mut cluster = cluster();
+    let old_leader = elect(&mut cluster);
+    cluster.client_request(1).unwrap();
+    settle(&mut cluster);
+    cluster.start_trace();
+    cluster.kill(old_leader);
+    let new_leader = elect(&mut cluster);
+    assert_ne!(new_leader, old_leader);
+    cluster.client_request(2).unwrap();
+    settle(&mut cluster);
+    assert_golden("leader_failover", &cluster);
+}
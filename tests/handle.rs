@@ -0,0 +1,119 @@
+mod common;
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    handle::RaftHandle,
+    rpc::{SendableMessage, Target},
+    server::{RaftServer, ServerId},
+    status::Role,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn spawn(id: ServerId, nodes: usize) -> RaftHandle<u32> {
+    RaftHandle::spawn(move || {
+        let peers = (0..nodes).filter(|peer| *peer != id).collect();
+        let app = Box::new(CountingApp { state: 0 });
+        RaftServer::new(id, peers, DEFAULT_CFG, Some(id as u64), app)
+    })
+}
+
+/// Hand what `from` sent to its peers, and whatever they answer back, until it dies down
+fn deliver(handles: &[RaftHandle<u32>], from: ServerId, msgs: Vec<SendableMessage<u32>>) {
+    let mut queue: Vec<_> = msgs.into_iter().map(|msg| (from, msg)).collect();
+    while let Some((from, (target, rpc))) = queue.pop() {
+        for (to, handle) in handles.iter().enumerate() {
+            let to_peer = match target {
+                Target::Single(id) => id == to,
+                Target::Broadcast => from != to,
+            };
+            if to_peer {
+                let replies = handle.receive(rpc.clone()).unwrap();
+                queue.extend(replies.into_iter().map(|msg| (to, msg)));
+            }
+        }
+    }
+}
+
+/// Tick every node once, in lockstep
+fn tick(handles: &[RaftHandle<u32>]) {
+    for (id, handle) in handles.iter().enumerate() {
+        deliver(handles, id, handle.tick().unwrap());
+    }
+}
+
+#[test]
+fn threads_share_a_cluster() {
+    init_logger();
+    let handles: Vec<_> = (0..3).map(|id| spawn(id, 3)).collect();
+    let leaders = |handles: &[RaftHandle<u32>]| {
+        let statuses = handles.iter().map(|handle| handle.status().unwrap());
+        statuses
+            .filter(|status| status.role == Role::Leader)
+            .map(|status| status.id)
+            .collect::<Vec<_>>()
+    };
+    for _ in 0..MAX_TICKS {
+        tick(&handles);
+        if !leaders(&handles).is_empty() {
+            break;
+        }
+    }
+    let leader = leaders(&handles)[0];
+
+    // one thread keeps the cluster ticking while others propose to the leader
+    let deadline = Instant::now() + TIMEOUT;
+    thread::scope(|scope| {
+        let handles = &handles;
+        scope.spawn(move || {
+            while handles
+                .iter()
+                .any(|h| h.status().unwrap().committed_len < 8)
+            {
+                assert!(Instant::now() < deadline, "entries never got committed");
+                tick(handles);
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        for _ in 0..4 {
+            let leader = handles[leader].clone();
+            scope.spawn(move || {
+                for _ in 0..2 {
+                    leader.propose(1).unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(leaders(&handles), [leader]);
+    assert_eq!(handles[leader].status().unwrap().log_len, 8);
+}
+
+#[test]
+fn followers_turn_proposals_away() {
+    init_logger();
+    let handles: Vec<_> = (0..3).map(|id| spawn(id, 3)).collect();
+    assert!(matches!(
+        handles[0].propose(1),
+        Err(RaftError::NotLeader { leader: None })
+    ));
+
+    let single = spawn(0, 1);
+    single.tick().unwrap();
+    assert_eq!(single.propose(1).unwrap(), 0);
+    assert_eq!(single.propose(2).unwrap(), 1);
+    assert_eq!(single.status().unwrap().committed_len, 2);
+}
+
+#[test]
+fn gone_node_reports_stopped() {
+    let handle = RaftHandle::<u32>::spawn(|| -> RaftServer<u32, u32> { panic!("no disk") });
+    assert!(matches!(handle.status(), Err(RaftError::Stopped)));
+    assert!(matches!(handle.tick(), Err(RaftError::Stopped)));
+}
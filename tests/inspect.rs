@@ -0,0 +1,41 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use std::{fs, process::Command};
+
+use common::*;
+use miniraft::{log::Snapshot, storage::save_snapshot};
+
+fn inspect(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_miniraft-inspect"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn inspect_prints_snapshot_metadata() {
+    let dir = test_dir("inspect");
+    let snapshot = Snapshot {
+        len: 12,
+        term: 3,
+        data: vec![0xab, 0xcd],
+    };
+    save_snapshot(&dir.join("snapshot"), &snapshot).unwrap();
+    fs::write(dir.join("garbage"), b"nope").unwrap();
+
+    let out = inspect(&[dir.to_str().unwrap()]);
+    assert!(out.contains("snapshot covering 12 entries, last term 3"));
+    assert!(out.contains("app data: 2 bytes [abcd]"));
+    assert!(out.contains("not a snapshot"));
+
+    let out = inspect(&["--json", dir.join("snapshot").to_str().unwrap()]);
+    let reports: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(reports[0]["kind"], "snapshot");
+    assert_eq!(reports[0]["len"], 12);
+    assert_eq!(reports[0]["term"], 3);
+    assert_eq!(reports[0]["data"], "abcd");
+}
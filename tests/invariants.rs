@@ -0,0 +1,30 @@
+#![cfg(debug_assertions)]
+
+mod common;
+
+use common::*;
+
+#[test]
+#[should_panic(expected = "term went backwards")]
+fn term_going_backwards_panics() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader_mut().unwrap();
+    leader.current_term -= 1;
+    leader.tick();
+}
+
+#[test]
+#[should_panic(expected = "committed entry 0 changed from term")]
+fn rewriting_committed_entries_panics() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+    cluster.get_by_id(leader_id).client_request(1).unwrap();
+    cluster.tick_by(MAX_WAIT);
+
+    let leader = cluster.get_by_id(leader_id);
+    assert_eq!(leader.log.committed_len, 1);
+    leader.log.entries[0].term += 1;
+    leader.tick();
+}
@@ -0,0 +1,256 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    linearizability::{is_linearizable, Model, Operation},
+    log::{App, LogEntry},
+    server::ServerId,
+    sim::Cluster,
+};
+use rand::Rng;
+
+type Key = u8;
+type ClientId = usize;
+type Seq = u64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum KvOp {
+    Put(u32),
+    Get,
+}
+
+/// A command as it goes through the log
+#[derive(Clone, Debug)]
+struct Command {
+    client: ClientId,
+    seq: Seq,
+    key: Key,
+    op: KvOp,
+}
+
+/// Replicated key-value store that remembers what every command returned, so clients can
+/// look up their response once it is applied
+#[derive(Clone, Default, PartialEq)]
+struct KvState {
+    values: BTreeMap<Key, u32>,
+    responses: BTreeMap<(ClientId, Seq), Option<u32>>,
+}
+
+struct KvApp {
+    state: KvState,
+}
+
+impl App<Command, KvState> for KvApp {
+    fn transition_fn(&mut self, entry: &LogEntry<Command>) {
+        let Command {
+            client,
+            seq,
+            key,
+            op,
+        } = &entry.data;
+        let response = match op {
+            KvOp::Put(value) => {
+                self.state.values.insert(*key, *value);
+                None
+            }
+            KvOp::Get => self.state.values.get(key).cloned(),
+        };
+        self.state.responses.insert((*client, *seq), response);
+    }
+
+    fn get_state(&self) -> KvState {
+        self.state.clone()
+    }
+}
+
+/// Sequential spec of a single key
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Register(Option<u32>);
+
+impl Model for Register {
+    type Op = KvOp;
+    type Output = Option<u32>;
+
+    fn step(&self, op: &KvOp) -> (Self, Option<u32>) {
+        match op {
+            KvOp::Put(value) => (Register(Some(*value)), None),
+            KvOp::Get => (self.clone(), self.0),
+        }
+    }
+}
+
+type History = Vec<Operation<KvOp, Option<u32>>>;
+
+#[test]
+fn checker_accepts_concurrent_reorderings() {
+    // the get overlaps with the put, so it may see either value
+    let history: History = vec![
+        Operation {
+            op: KvOp::Put(1),
+            invoked_at: 0,
+            returned: Some((5, None)),
+        },
+        Operation {
+            op: KvOp::Get,
+            invoked_at: 1,
+            returned: Some((2, None)),
+        },
+    ];
+    assert!(is_linearizable(Register(None), &history));
+}
+
+#[test]
+fn checker_rejects_stale_reads() {
+    // the put finished before the get started, so the get has to see it
+    let history: History = vec![
+        Operation {
+            op: KvOp::Put(1),
+            invoked_at: 0,
+            returned: Some((1, None)),
+        },
+        Operation {
+            op: KvOp::Get,
+            invoked_at: 2,
+            returned: Some((3, None)),
+        },
+    ];
+    assert!(!is_linearizable(Register(None), &history));
+}
+
+#[test]
+fn checker_lets_pending_operations_take_effect_or_not() {
+    let put = |value, returned| Operation {
+        op: KvOp::Put(value),
+        invoked_at: 0,
+        returned,
+    };
+    let get = |seen| Operation {
+        op: KvOp::Get,
+        invoked_at: 10,
+        returned: Some((11, seen)),
+    };
+    assert!(is_linearizable(
+        Register(None),
+        &[put(1, None), get(Some(1))]
+    ));
+    assert!(is_linearizable(Register(None), &[put(1, None), get(None)]));
+    assert!(!is_linearizable(
+        Register(None),
+        &[put(1, None), get(Some(2))]
+    ));
+}
+
+/// An operation a client is waiting on
+struct Pending {
+    seq: Seq,
+    key: Key,
+    history_idx: usize,
+    deadline: u64,
+}
+
+/// Run clients against a cluster that keeps crashing and partitioning, and return the
+/// history of every key
+fn run_clients(seed: u64) -> BTreeMap<Key, History> {
+    const CLIENTS: usize = 3;
+    const KEYS: Key = 4;
+    const TIMEOUT: u64 = 20;
+
+    let mut cluster: Cluster<Command, KvState> = Cluster::new(5, seed, DEFAULT_CFG, |_| {
+        Box::new(KvApp {
+            state: KvState::default(),
+        })
+    });
+    let mut histories: BTreeMap<Key, History> = BTreeMap::new();
+    let mut pending: Vec<Option<Pending>> = (0..CLIENTS).map(|_| None).collect();
+    let mut next_seq: Seq = 0;
+
+    for _ in 0..300 {
+        cluster.tick();
+        let now = cluster.now() as u64;
+
+        // faults
+        let roll = cluster.rng().gen_range(0..100);
+        let victim: ServerId = cluster.rng().gen_range(0..5);
+        match roll {
+            0..=1 => cluster.kill(victim),
+            2..=4 => cluster.revive(victim),
+            5 => cluster.partition(&[victim, (victim + 1) % 5]),
+            6..=8 => cluster.heal(),
+            _ => {}
+        }
+
+        for (client, slot) in pending.iter_mut().enumerate() {
+            // collect responses, from any node that has applied the command
+            if let Some(op) = slot {
+                let response = cluster.nodes().find_map(|node| {
+                    node.log
+                        .app
+                        .get_state()
+                        .responses
+                        .get(&(client, op.seq))
+                        .cloned()
+                });
+                if let Some(response) = response {
+                    histories.get_mut(&op.key).unwrap()[op.history_idx].returned =
+                        Some((now, response));
+                    *slot = None;
+                } else if now > op.deadline {
+                    // give up, the outcome stays unknown
+                    *slot = None;
+                }
+            }
+
+            // issue a new operation now and then
+            if slot.is_none() && cluster.rng().gen_bool(0.3) {
+                next_seq += 1;
+                let key = cluster.rng().gen_range(0..KEYS);
+                let op = if cluster.rng().gen_bool(0.5) {
+                    KvOp::Put(cluster.rng().gen_range(0..10))
+                } else {
+                    KvOp::Get
+                };
+                let command = Command {
+                    client,
+                    seq: next_seq,
+                    key,
+                    op: op.clone(),
+                };
+                if cluster.client_request(command).is_ok() {
+                    let history = histories.entry(key).or_default();
+                    history.push(Operation {
+                        op,
+                        invoked_at: now,
+                        returned: None,
+                    });
+                    *slot = Some(Pending {
+                        seq: next_seq,
+                        key,
+                        history_idx: history.len() - 1,
+                        deadline: now + TIMEOUT,
+                    });
+                }
+            }
+        }
+    }
+    histories
+}
+
+#[test]
+fn kv_histories_under_faults_are_linearizable() {
+    init_logger();
+    for seed in 0..20 {
+        for (key, history) in run_clients(seed) {
+            assert!(history.iter().any(|op| op.returned.is_some()));
+            assert!(
+                is_linearizable(Register(None), &history),
+                "history of key {} with seed {} is not linearizable: {:#?}",
+                key,
+                seed,
+                history
+            );
+        }
+    }
+}
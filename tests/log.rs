@@ -1,7 +1,7 @@
 mod common;
 use common::*;
 
-use miniraft::log::LogEntry;
+use miniraft::log::{LogEntry, SharedEntries, Snapshot};
 
 #[test]
 fn last_term_and_index_of_empty() {
@@ -24,6 +24,41 @@ fn last_term_and_index_of_non_empty() {
     assert_eq!(l.last_idx(), 2);
 }
 
+#[test]
+fn term_at_the_start_of_the_log_is_the_snapshot_term() {
+    let mut l = setup_log();
+    assert_eq!(l.term_at(0), Some(0));
+    assert_eq!(l.term_at(1), None);
+
+    l.entries.push(LogEntry { term: 2, data: 1 });
+    assert_eq!(l.term_at(0), Some(0));
+    assert_eq!(l.term_at(1), Some(2));
+
+    l.snapshot = Snapshot {
+        len: 1,
+        term: 2,
+        data: vec![],
+    };
+    l.entries.clear();
+    assert_eq!(l.term_at(0), None);
+    assert_eq!(l.term_at(1), Some(2));
+    assert_eq!(l.term_at(2), None);
+}
+
+#[test]
+fn shared_entries_skip_without_copying() {
+    let entries: SharedEntries<u32> = (1..=3)
+        .map(|data| LogEntry { term: 1, data })
+        .collect::<Vec<_>>()
+        .into();
+    let rest = entries.skip(1);
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest[0].data, 2);
+    assert!(std::ptr::eq(&rest[0], &entries[1]));
+    assert!(entries.skip(5).is_empty());
+    assert_eq!(rest, SharedEntries::from(entries[1..].to_vec()));
+}
+
 #[test]
 fn apply_to_state() {
     let mut l = setup_log();
@@ -130,10 +165,10 @@ fn append_entries_non_empty_conflict_append() {
         ],
     );
 
-    let entries = vec![LogEntry { term: 1, data: 4 }, LogEntry { term: 2, data: 5 }];
+    let entries = vec![LogEntry { term: 1, data: 2 }, LogEntry { term: 2, data: 5 }];
     l.append_entries(1, 3, entries);
     assert_eq!(l.applied_len, 3);
-    assert_eq!(l.app.get_state(), 10);
+    assert_eq!(l.app.get_state(), 8);
     assert_eq!(l.last_idx(), 2);
     assert_eq!(l.last_term(), 2);
 }
@@ -156,3 +191,27 @@ fn append_entries_idempotency() {
     assert_eq!(l.last_idx(), 1);
     assert_eq!(l.last_term(), 1);
 }
+
+#[test]
+fn append_entries_only_truncates_from_the_first_conflict() {
+    let mut l = setup_log();
+    let terms = [1, 1, 2, 2];
+    let entries = terms.map(|term| LogEntry { term, data: 1 }).to_vec();
+    l.append_entries(0, 1, entries.clone());
+    l.persisted_len = 4;
+
+    // a late retransmission of entries we already have changes nothing
+    l.append_entries(1, 1, entries[1..3].to_vec());
+    assert_eq!((l.len(), l.persisted_len), (4, 4));
+
+    // a new leader replaced the last entry, the ones before it stay written
+    let replaced = vec![
+        LogEntry { term: 1, data: 1 },
+        LogEntry { term: 2, data: 1 },
+        LogEntry { term: 3, data: 5 },
+    ];
+    l.append_entries(1, 1, replaced);
+    assert_eq!((l.len(), l.persisted_len), (4, 3));
+    assert_eq!(l.last_term(), 3);
+    assert_eq!(l.app.get_state(), 1);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+use miniraft::metrics::RaftMetrics;
+
+#[test]
+fn metrics_start_at_zero() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    assert_eq!(cluster.get_by_id(0).metrics(), RaftMetrics::default());
+}
+
+#[test]
+fn metrics_count_elections_and_replication() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader_mut().unwrap();
+    let leader_id = leader.id;
+    for i in 0..3 {
+        leader.client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_TICKS);
+
+    let metrics = cluster.get_by_id(leader_id).metrics();
+    assert!(metrics.elections_started >= 1);
+    assert_eq!(metrics.elections_won, 1);
+    assert!(metrics.heartbeats_sent > 0);
+    assert!(metrics.append_requests_sent >= metrics.heartbeats_sent);
+    assert_eq!(metrics.entries_committed, 3);
+    assert_eq!(metrics.entries_applied, 3);
+
+    let follower_id = (0..3).find(|&id| id != leader_id).unwrap();
+    let metrics = cluster.get_by_id(follower_id).metrics();
+    assert_eq!(metrics.elections_won, 0);
+    assert!(metrics.votes_granted >= 1);
+    assert!(metrics.append_requests_received > 0);
+    assert_eq!(metrics.entries_committed, 3);
+    assert_eq!(metrics.entries_applied, 3);
+}
+
+#[test]
+fn metrics_count_rejected_appends() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let old_leader = cluster.get_leader().unwrap().id;
+
+    // the rest of the cluster moves on to a new term while the old leader is away,
+    // its heartbeats from the old term get rejected once it comes back
+    cluster.kill(old_leader);
+    cluster.tick_by(MAX_TICKS);
+    cluster.revive(old_leader);
+    cluster.tick_by(MAX_TICKS);
+
+    let rejected: u64 = (0..3)
+        .filter(|&id| id != old_leader)
+        .map(|id| cluster.get_by_id(id).metrics().append_requests_rejected)
+        .sum();
+    assert!(rejected > 0);
+    assert_eq!(cluster.num_leaders(), 1);
+}
+
+#[test]
+fn rpc_latencies_are_tracked_per_peer() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+
+    let latencies = cluster.get_by_id(leader_id).rpc_latencies().clone();
+    let followers: Vec<_> = (0..3).filter(|&id| id != leader_id).collect();
+    assert_eq!(
+        latencies.append.keys().cloned().collect::<Vec<_>>(),
+        followers
+    );
+    for histogram in latencies.append.values() {
+        assert!(histogram.count > 0);
+        assert_eq!(histogram.buckets.iter().sum::<u64>(), histogram.count);
+        assert!(histogram.mean().is_some());
+    }
+    // whoever answered the leader's vote request got timed too
+    assert!(!latencies.vote.is_empty());
+    assert!(latencies.vote.keys().all(|id| followers.contains(id)));
+
+    // followers never send requests, so they have nothing to time
+    let follower = cluster.get_by_id(followers[0]).rpc_latencies();
+    assert!(follower.append.is_empty());
+}
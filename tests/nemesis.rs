@@ -0,0 +1,132 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    log::LogIndex,
+    nemesis::{ClockSkewer, MessageCorrupter, Nemeses, Partitioner, SlowDisk},
+    server::{ServerId, Term},
+    sim::Cluster,
+};
+
+fn cluster(seed: u64) -> Cluster<u32, u32> {
+    init_logger();
+    Cluster::new(5, seed, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }))
+}
+
+fn all_nemeses() -> Nemeses<u32, u32> {
+    Nemeses::new()
+        .with(Partitioner::new(0.02, 4 * MAX_WAIT))
+        .with(ClockSkewer::new(0.02, 3.0, 4 * MAX_WAIT))
+        .with(SlowDisk::new(0.02, MAX_WAIT, 4 * MAX_WAIT))
+        .with(MessageCorrupter::new(0.02, 0.3, 4 * MAX_WAIT))
+}
+
+/// Leaders seen per term and the committed entry terms, checked against every tick
+#[derive(Default)]
+struct Safety {
+    leaders: BTreeMap<Term, ServerId>,
+    committed: BTreeMap<LogIndex, (Term, u32)>,
+}
+
+impl Safety {
+    fn check(&mut self, cluster: &Cluster<u32, u32>) -> Result<()> {
+        for node in cluster.nodes() {
+            if node.is_leader() {
+                let leader = *self.leaders.entry(node.current_term()).or_insert(node.id);
+                if leader != node.id {
+                    bail!(
+                        "{} and {} both lead term {}",
+                        leader,
+                        node.id,
+                        node.current_term()
+                    );
+                }
+            }
+            let log = &node.log;
+            for idx in log.snapshot.len..log.committed_len {
+                let entry = &log.entries[idx - log.snapshot.len];
+                let seen = *self
+                    .committed
+                    .entry(idx)
+                    .or_insert((entry.term, entry.data));
+                if seen != (entry.term, entry.data) {
+                    bail!("node {} committed a different entry at {}", node.id, idx);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn cluster_stays_safe_and_recovers_under_every_nemesis() {
+    for seed in 0..5 {
+        let mut cluster = cluster(seed);
+        let mut nemeses = all_nemeses();
+        let mut safety = Safety::default();
+        let mut next = 0;
+        nemeses
+            .run(&mut cluster, 2_000, |cluster| {
+                if cluster.now() % 10 == 0 && cluster.client_request(next).is_ok() {
+                    next += 1;
+                }
+                safety.check(cluster)
+            })
+            .unwrap();
+        assert!(
+            !nemeses.history().is_empty(),
+            "seed {}: nemeses never struck",
+            seed
+        );
+
+        nemeses.heal(&mut cluster);
+        assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+        cluster.client_request(next).unwrap();
+        assert!(cluster.run_until(MAX_TICKS, |cluster| {
+            cluster.nodes().all(|node| {
+                node.log.committed_len == node.log.len()
+                    && node.log.entries.last().map(|entry| entry.data) == Some(next)
+            })
+        }));
+        safety.check(&cluster).unwrap();
+    }
+}
+
+#[test]
+fn same_seed_same_schedule() {
+    let history = |seed| {
+        let mut cluster = cluster(seed);
+        let mut nemeses = all_nemeses();
+        nemeses.run(&mut cluster, 500, |_| Ok(())).unwrap();
+        nemeses.history().to_vec()
+    };
+    assert_eq!(history(3), history(3));
+    assert_ne!(history(3), history(4));
+}
+
+#[test]
+fn failed_checks_name_the_tick_and_seed() {
+    let mut cluster = cluster(9);
+    let err = Nemeses::new()
+        .with(Partitioner::new(1.0, MAX_WAIT))
+        .run(&mut cluster, 100, |cluster| {
+            if cluster.now() == 20 {
+                bail!("boom");
+            }
+            Ok(())
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "check failed at tick 20 with seed 9: boom");
+}
+
+#[test]
+fn fast_clock_times_out_first() {
+    let mut cluster = cluster(1);
+    cluster.set_clock_rate(3, 4.0);
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    assert_eq!(cluster.leader().unwrap().id, 3);
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+    time::Duration,
+};
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    log::Snapshot,
+    node::{RaftClient, RaftNode},
+    rpc::{SnapshotRequest, Target, VoteResponse, RPC},
+    server::{RaftServer, ServerId},
+    status::Role,
+};
+use tokio::{sync::mpsc, task::LocalSet, time};
+
+const TICK: Duration = Duration::from_millis(1);
+
+fn server(id: ServerId, nodes: usize) -> RaftServer<u32, u32> {
+    let peers = (0..nodes).filter(|peer| *peer != id).collect();
+    let app = Box::new(CountingApp { state: 0 });
+    RaftServer::new(id, peers, DEFAULT_CFG, Some(id as u64), app)
+}
+
+#[tokio::test]
+async fn single_node_commits_proposals() {
+    init_logger();
+    LocalSet::new()
+        .run_until(async {
+            let (node, client) = RaftNode::new(server(0, 1), TICK, |_, _| {});
+            let running = tokio::task::spawn_local(node.run());
+
+            // elects itself on its first tick
+            let mut leadership = client.watch_leadership().await.unwrap();
+            leadership
+                .wait_for(|(_, leader)| *leader == Some(0))
+                .await
+                .unwrap();
+            assert_eq!(leadership.borrow().0, 1);
+            assert_eq!(client.propose(3).await.unwrap(), 0);
+            assert_eq!(client.propose(4).await.unwrap(), 1);
+            assert_eq!(client.status().await.unwrap().committed_len, 2);
+
+            // the node stops once nobody can talk to it anymore
+            drop(client);
+            let server = running.await.unwrap();
+            assert_eq!(server.log.app.get_state(), 7);
+        })
+        .await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn node_keeps_time_with_tokio() {
+    init_logger();
+    LocalSet::new()
+        .run_until(async {
+            // a day's wait for its first tick, which goes by in no time with the clock paused
+            let (node, client) =
+                RaftNode::new(server(0, 1), Duration::from_secs(86_400), |_, _| {});
+            tokio::task::spawn_local(node.run());
+            let mut leadership = client.watch_leadership().await.unwrap();
+            let elected = leadership.wait_for(|(_, leader)| *leader == Some(0));
+            time::timeout(Duration::from_secs(2 * 86_400), elected)
+                .await
+                .expect("node never ticked")
+                .unwrap();
+            assert_eq!(client.propose(3).await.unwrap(), 0);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn paused_node_turns_proposals_away() {
+    init_logger();
+    LocalSet::new()
+        .run_until(async {
+            let (node, client) = RaftNode::new(server(0, 1), TICK, |_, _| {});
+            tokio::task::spawn_local(node.run());
+            while client.status().await.unwrap().role != Role::Leader {
+                time::sleep(TICK).await;
+            }
+
+            client.pause().await.unwrap();
+            assert!(client.status().await.unwrap().paused);
+            assert!(matches!(client.propose(1).await, Err(RaftError::Paused)));
+            client.resume().await.unwrap();
+            assert_eq!(client.propose(1).await.unwrap(), 0);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn shut_down_node_fails_requests() {
+    init_logger();
+    LocalSet::new()
+        .run_until(async {
+            let (node, client) = RaftNode::new(server(0, 1), TICK, |_, _| {});
+            let running = tokio::task::spawn_local(node.run());
+            while client.status().await.unwrap().role != Role::Leader {
+                time::sleep(TICK).await;
+            }
+            assert_eq!(client.propose(3).await.unwrap(), 0);
+
+            // nobody to hand over to, so the node just stops
+            client.shutdown(true).await;
+            let server = running.await.unwrap();
+            assert_eq!(server.log.app.get_state(), 3);
+            assert!(matches!(client.propose(4).await, Err(RaftError::Stopped)));
+            assert!(matches!(client.status().await, Err(RaftError::Stopped)));
+            client.shutdown(false).await;
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn cluster_elects_and_commits() {
+    init_logger();
+    LocalSet::new()
+        .run_until(async {
+            let clients: Rc<RefCell<BTreeMap<ServerId, RaftClient<u32>>>> = Rc::default();
+            for id in 0..3 {
+                let routes = clients.clone();
+                let transport = move |target: Target, rpc: RPC<u32>| {
+                    for (peer, client) in routes.borrow().iter() {
+                        let to_peer = match target {
+                            Target::Single(to) => to == *peer,
+                            Target::Broadcast => id != *peer,
+                        };
+                        if to_peer {
+                            client.receive(rpc.clone());
+                        }
+                    }
+                };
+                let (node, client) = RaftNode::new(server(id, 3), TICK, transport);
+                clients.borrow_mut().insert(id, client);
+                tokio::task::spawn_local(node.run());
+            }
+            let client = |id: ServerId| clients.borrow()[&id].clone();
+
+            let committed = time::timeout(Duration::from_secs(10), async {
+                let mut target = 0;
+                loop {
+                    // an entry whose leader got deposed can stay uncommitted until the next
+                    // leader commits one of its own, so give up on it and propose again
+                    let client = client(target);
+                    match time::timeout(TICK * 100, client.propose(5)).await {
+                        Ok(Ok(index)) => break index,
+                        Ok(Err(RaftError::NotLeader { leader })) => {
+                            target = leader.unwrap_or((target + 1) % 3);
+                            time::sleep(TICK * 5).await;
+                        }
+                        Ok(Err(err)) => panic!("proposal failed: {}", err),
+                        Err(_) => target = (target + 1) % 3,
+                    }
+                }
+            });
+            let committed_len = committed.await.expect("nothing got committed") + 1;
+
+            // followers learn about the commit with the next heartbeat
+            let replicated = time::timeout(Duration::from_secs(10), async {
+                let mut pending: BTreeSet<ServerId> = (0..3).collect();
+                while !pending.is_empty() {
+                    for id in pending.clone() {
+                        if client(id).status().await.unwrap().committed_len >= committed_len {
+                            pending.remove(&id);
+                        }
+                    }
+                    time::sleep(TICK).await;
+                }
+            });
+            replicated.await.expect("entry never made it to every node");
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn deposed_leader_fails_proposals_a_newer_snapshot_covers() {
+    init_logger();
+    LocalSet::new()
+        .run_until(async {
+            let (sent, mut outbox) = mpsc::unbounded_channel();
+            let transport = move |target, rpc| {
+                let _ = sent.send((target, rpc));
+            };
+            let (node, client) = RaftNode::new(server(0, 3), TICK, transport);
+            tokio::task::spawn_local(node.run());
+
+            // node 1 votes for us, node 2 never answers
+            let term = loop {
+                if let (_, RPC::VoteRequest(req)) = outbox.recv().await.unwrap() {
+                    client.receive(RPC::VoteResponse(VoteResponse {
+                        term: req.candidate_term,
+                        vote_granted: true,
+                        votee_id: 1,
+                        request_id: req.request_id,
+                    }));
+                    break req.candidate_term;
+                }
+            };
+            while client.status().await.unwrap().role != Role::Leader {
+                time::sleep(TICK).await;
+            }
+            let proposer = client.clone();
+            let proposal = tokio::task::spawn_local(async move { proposer.propose(7).await });
+            while client.status().await.unwrap().log_len < 1 {
+                time::sleep(TICK).await;
+            }
+
+            // node 1 took over and compacted a log that doesn't have our entry
+            client.receive(RPC::SnapshotRequest(SnapshotRequest {
+                leader_term: term + 1,
+                leader_id: 1,
+                snapshot: Snapshot {
+                    len: 2,
+                    term: term + 1,
+                    data: 5u32.to_le_bytes().to_vec(),
+                },
+            }));
+            assert!(matches!(
+                proposal.await.unwrap(),
+                Err(RaftError::NotLeader { leader: Some(1) })
+            ));
+            assert_eq!(client.status().await.unwrap().committed_len, 2);
+        })
+        .await;
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use std::collections::{BTreeSet, VecDeque};
+
+use common::*;
+#[cfg(feature = "serde")]
+use miniraft::storage::{load_state, save_state};
+use miniraft::{
+    error::RaftError,
+    rpc::{SendableMessage, Target},
+    server::RaftServer,
+};
+
+type Node = RaftServer<u32, u32, String>;
+
+const HOSTS: [&str; 3] = ["raft-a.local", "raft-b.local", "raft-c.local"];
+
+fn cluster() -> Vec<Node> {
+    HOSTS
+        .iter()
+        .enumerate()
+        .map(|(i, host)| {
+            let peers: BTreeSet<String> = HOSTS
+                .iter()
+                .filter(|peer| *peer != host)
+                .map(|peer| peer.to_string())
+                .collect();
+            let app = Box::new(CountingApp { state: 0 });
+            RaftServer::new(host.to_string(), peers, DEFAULT_CFG, Some(i as u64), app)
+        })
+        .collect()
+}
+
+/// Deliver messages until nobody has anything left to say
+fn deliver(nodes: &mut [Node], mut queue: VecDeque<(String, SendableMessage<u32, String>)>) {
+    while let Some((from, (target, rpc))) = queue.pop_front() {
+        for node in nodes.iter_mut() {
+            let to_node = match &target {
+                Target::Single(id) => *id == node.id,
+                Target::Broadcast => node.id != from,
+            };
+            if to_node {
+                let id = node.id.clone();
+                queue.extend(
+                    node.receive_rpc(&rpc)
+                        .into_iter()
+                        .map(|msg| (id.clone(), msg)),
+                );
+            }
+        }
+    }
+}
+
+fn tick(nodes: &mut [Node]) {
+    let queue = nodes
+        .iter_mut()
+        .flat_map(|node| {
+            let id = node.id.clone();
+            node.tick().into_iter().map(move |msg| (id.clone(), msg))
+        })
+        .collect();
+    deliver(nodes, queue);
+}
+
+#[test]
+fn elects_and_commits_with_string_ids() {
+    let mut nodes = cluster();
+    for _ in 0..MAX_TICKS {
+        tick(&mut nodes);
+        if nodes.iter().any(|node| node.is_leader()) {
+            break;
+        }
+    }
+    let leader = nodes
+        .iter()
+        .position(|node| node.is_leader())
+        .expect("no leader elected");
+    let leader_id = nodes[leader].id.clone();
+    assert!(HOSTS.contains(&leader_id.as_str()));
+
+    let follower = (leader + 1) % nodes.len();
+    match nodes[follower].client_request(1) {
+        Err(RaftError::NotLeader { leader }) => assert_eq!(leader, Some(leader_id.clone())),
+        other => panic!("expected a redirect to the leader, got {:?}", other),
+    }
+
+    nodes[leader].client_request(5).unwrap();
+    for _ in 0..DEFAULT_CFG.heartbeat_interval * 2 {
+        tick(&mut nodes);
+    }
+    for node in &nodes {
+        assert_eq!(node.log.committed_len, 1);
+        assert_eq!(node.leader_id(), Some(leader_id.clone()));
+        assert_eq!(node.status().id, node.id);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn state_with_string_ids_survives_a_round_trip_to_disk() {
+    let mut nodes = cluster();
+    while !nodes.iter().any(|node| node.is_leader()) {
+        tick(&mut nodes);
+    }
+    let leader = nodes.iter().position(|node| node.is_leader()).unwrap();
+    nodes[leader].client_request(5).unwrap();
+    tick(&mut nodes);
+
+    let dir = test_dir("string-ids");
+    for node in &nodes {
+        let state = node.persistent_state();
+        assert!(state.voted_for.is_some());
+        let node_dir = dir.join(&node.id);
+        save_state(&node_dir, &state).unwrap();
+        assert_eq!(load_state::<u32, String>(&node_dir).unwrap(), Some(state));
+    }
+}
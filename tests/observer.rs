@@ -0,0 +1,224 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use common::*;
+use miniraft::{
+    event::RaftEvent,
+    log::LogIndex,
+    server::{RaftConfig, Term},
+    status::Role,
+};
+
+/// Role changes seen by a callback, shared with the test
+type RoleChanges = Rc<RefCell<Vec<(Role, Role, Term)>>>;
+
+/// Commit notifications seen by a callback, shared with the test
+type Commits = Rc<RefCell<Vec<(LogIndex, LogIndex)>>>;
+
+#[test]
+fn role_changes_fire_callbacks() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    let changes: Vec<RoleChanges> = (0..3).map(|_| Rc::default()).collect();
+    for (id, seen) in changes.iter().enumerate() {
+        let seen = seen.clone();
+        cluster
+            .get_by_id(id)
+            .observers
+            .on_role_change(move |from, to, term| seen.borrow_mut().push((from, to, term)));
+    }
+    cluster.tick_by(MAX_TICKS);
+
+    let leader = cluster.get_leader().unwrap().id;
+    let term = cluster.leader_term();
+    let leader_changes = changes[leader].borrow();
+    assert_eq!(
+        leader_changes.last(),
+        Some(&(Role::Candidate, Role::Leader, term))
+    );
+    // followers that never stood for election never changed role
+    for (id, seen) in changes.iter().enumerate() {
+        assert!(seen
+            .borrow()
+            .iter()
+            .all(|(from, to, _)| from != to && (id == leader || *to != Role::Leader)));
+    }
+}
+
+#[test]
+fn term_changes_fire_callbacks() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    let terms: Rc<RefCell<Vec<(Term, Term)>>> = Rc::default();
+    let seen = terms.clone();
+    cluster
+        .get_by_id(0)
+        .observers
+        .on_term_change(move |from, to| seen.borrow_mut().push((from, to)));
+    cluster.tick_by(MAX_TICKS);
+
+    let terms = terms.borrow();
+    assert!(!terms.is_empty());
+    assert!(terms.iter().all(|(from, to)| from < to));
+    assert_eq!(terms.last().unwrap().1, cluster.leader_term());
+}
+
+#[test]
+fn commit_advancement_fires_callbacks() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap().id;
+    let follower = (0..3).find(|&id| id != leader).unwrap();
+
+    let commits: Vec<Commits> = [leader, follower].iter().map(|_| Rc::default()).collect();
+    for (id, seen) in [leader, follower].iter().zip(&commits) {
+        let seen = seen.clone();
+        cluster
+            .get_by_id(*id)
+            .observers
+            .on_commit(move |committed_len, count| seen.borrow_mut().push((committed_len, count)));
+    }
+
+    for i in 0..3 {
+        cluster.get_by_id(leader).client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_TICKS);
+
+    for seen in commits {
+        let seen = seen.borrow();
+        assert_eq!(seen.last().unwrap().0, 3);
+        assert_eq!(seen.iter().map(|(_, count)| count).sum::<LogIndex>(), 3);
+    }
+}
+
+#[test]
+fn events_are_published_to_subscribers() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    let receivers: Vec<_> = (0..3)
+        .map(|id| cluster.get_by_id(id).observers.subscribe())
+        .collect();
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap().id;
+    let term = cluster.leader_term();
+    cluster.get_by_id(leader).client_request(1).unwrap();
+    cluster.tick_by(MAX_TICKS);
+    cluster.get_by_id(leader).snapshot_now().unwrap();
+
+    let events: Vec<Vec<RaftEvent>> = receivers
+        .iter()
+        .map(|receiver| receiver.try_iter().collect())
+        .collect();
+    for node_events in &events {
+        assert!(node_events.contains(&RaftEvent::EntriesCommitted {
+            committed_len: 1,
+            count: 1
+        }));
+    }
+    assert!(events[leader].contains(&RaftEvent::BecameLeader { term }));
+    assert!(events[leader].contains(&RaftEvent::SnapshotTaken { len: 1, term }));
+    // the leader needed at least one vote besides its own to win
+    assert!(events
+        .iter()
+        .any(|node_events| node_events.contains(&RaftEvent::VoteGranted {
+            candidate: leader,
+            term
+        })));
+}
+
+#[test]
+fn dropped_subscribers_are_forgotten() {
+    let mut cluster = TestCluster::new(1, 0, DEFAULT_CFG);
+    let events: Rc<RefCell<Vec<RaftEvent>>> = Rc::default();
+    let seen = events.clone();
+    let node = cluster.get_by_id(0);
+    node.observers
+        .on_event(move |event| seen.borrow_mut().push(event.clone()));
+    drop(node.observers.subscribe());
+    cluster.tick_by(MAX_TICKS);
+
+    assert_eq!(
+        events.borrow()[..3],
+        [
+            RaftEvent::TermChanged { from: 0, to: 1 },
+            RaftEvent::BecameCandidate { term: 1 },
+            RaftEvent::BecameLeader { term: 1 },
+        ]
+    );
+}
+
+#[test]
+fn leaderless_alarm_is_raised_and_cleared() {
+    let config = RaftConfig {
+        leaderless_alarm: Some(3),
+        ..DEFAULT_CFG
+    };
+    let mut cluster = TestCluster::new(3, 0, config);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap().id;
+    let follower = (0..3).find(|&id| id != leader).unwrap();
+    let events = cluster.get_by_id(follower).observers.subscribe();
+    assert!(!cluster.get_by_id(follower).status().leaderless_alarm);
+
+    // with everyone else gone elections can never succeed
+    for id in (0..3).filter(|&id| id != follower) {
+        cluster.kill(id);
+    }
+    cluster.tick_by(MAX_TICKS);
+    assert!(cluster.get_by_id(follower).status().leaderless_alarm);
+
+    for id in 0..3 {
+        cluster.revive(id);
+    }
+    cluster.tick_by(MAX_TICKS);
+    assert!(!cluster.get_by_id(follower).status().leaderless_alarm);
+
+    let alarms: Vec<RaftEvent> = events
+        .try_iter()
+        .filter(|event| {
+            matches!(
+                event,
+                RaftEvent::LeaderlessAlarm { .. } | RaftEvent::LeaderFound { .. }
+            )
+        })
+        .collect();
+    assert_eq!(alarms.len(), 2);
+    assert!(
+        matches!(alarms[0], RaftEvent::LeaderlessAlarm { ticks, .. } if ticks > 3 * DEFAULT_CFG.election_timeout)
+    );
+    assert!(matches!(alarms[1], RaftEvent::LeaderFound { .. }));
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn leadership_watch_follows_elections() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    let mut watches: Vec<_> = (0..3)
+        .map(|id| cluster.get_by_id(id).watch_leadership())
+        .collect();
+    assert!(watches.iter().all(|watch| *watch.borrow() == (0, None)));
+
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap().id;
+    let term = cluster.leader_term();
+    for watch in &mut watches {
+        assert!(watch.has_changed().unwrap());
+        assert_eq!(*watch.borrow_and_update(), (term, Some(leader)));
+    }
+    // heartbeats change nothing
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    assert!(!watches[leader].has_changed().unwrap());
+
+    cluster.kill(leader);
+    cluster.tick_by(MAX_TICKS);
+    let new_leader = cluster.get_leader().unwrap().id;
+    let follower = (0..3)
+        .find(|id| ![leader, new_leader].contains(id))
+        .unwrap();
+    assert!(cluster.leader_term() > term);
+    assert_eq!(
+        *watches[follower].borrow(),
+        (cluster.leader_term(), Some(new_leader))
+    );
+    // a late watcher starts from where the node is now
+    let late = cluster.get_by_id(follower).watch_leadership();
+    assert_eq!(*late.borrow(), *watches[follower].borrow());
+}
@@ -0,0 +1,218 @@
+#![cfg(feature = "openraft")]
+
+use std::{collections::BTreeMap, fmt::Debug, io::Cursor, ops::RangeBounds};
+
+use miniraft::{
+    log::{LogEntry, Snapshot},
+    openraft::OpenraftStorage,
+    server::PersistentState,
+    storage::Storage,
+};
+use openraft::{
+    storage::{LogState, RaftLogReader, RaftSnapshotBuilder, RaftStorage, SnapshotMeta},
+    BasicNode, Entry, LogId, StorageError, StoredMembership, Vote,
+};
+
+openraft::declare_raft_types!(Config: D = u32, R = ());
+
+/// Just enough of an openraft backend to keep everything in memory
+#[derive(Default)]
+struct MemStore {
+    vote: Option<Vote<u64>>,
+    log: BTreeMap<u64, Entry<Config>>,
+    last_purged: Option<LogId<u64>>,
+    snapshot: Option<(SnapshotMeta<u64, BasicNode>, Vec<u8>)>,
+}
+
+/// Log reader and snapshot builder, which the adapter never asks for
+struct Unused;
+
+impl RaftLogReader<Config> for Unused {
+    async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send>(
+        &mut self,
+        _range: RB,
+    ) -> Result<Vec<Entry<Config>>, StorageError<u64>> {
+        unreachable!()
+    }
+}
+
+impl RaftSnapshotBuilder<Config> for Unused {
+    async fn build_snapshot(&mut self) -> Result<openraft::Snapshot<Config>, StorageError<u64>> {
+        unreachable!()
+    }
+}
+
+impl RaftLogReader<Config> for MemStore {
+    async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send>(
+        &mut self,
+        range: RB,
+    ) -> Result<Vec<Entry<Config>>, StorageError<u64>> {
+        Ok(self
+            .log
+            .range(range)
+            .map(|(_, entry)| entry.clone())
+            .collect())
+    }
+}
+
+impl RaftStorage<Config> for MemStore {
+    type LogReader = Unused;
+    type SnapshotBuilder = Unused;
+
+    async fn save_vote(&mut self, vote: &Vote<u64>) -> Result<(), StorageError<u64>> {
+        self.vote = Some(*vote);
+        Ok(())
+    }
+
+    async fn read_vote(&mut self) -> Result<Option<Vote<u64>>, StorageError<u64>> {
+        Ok(self.vote)
+    }
+
+    async fn get_log_state(&mut self) -> Result<LogState<Config>, StorageError<u64>> {
+        let last = self.log.values().last().map(|entry| entry.log_id);
+        Ok(LogState {
+            last_purged_log_id: self.last_purged,
+            last_log_id: last.or(self.last_purged),
+        })
+    }
+
+    async fn get_log_reader(&mut self) -> Unused {
+        Unused
+    }
+
+    async fn append_to_log<I>(&mut self, entries: I) -> Result<(), StorageError<u64>>
+    where
+        I: IntoIterator<Item = Entry<Config>> + Send,
+    {
+        for entry in entries {
+            self.log.insert(entry.log_id.index, entry);
+        }
+        Ok(())
+    }
+
+    async fn delete_conflict_logs_since(
+        &mut self,
+        log_id: LogId<u64>,
+    ) -> Result<(), StorageError<u64>> {
+        self.log.split_off(&log_id.index);
+        Ok(())
+    }
+
+    async fn purge_logs_upto(&mut self, log_id: LogId<u64>) -> Result<(), StorageError<u64>> {
+        self.log = self.log.split_off(&(log_id.index + 1));
+        self.last_purged = Some(log_id);
+        Ok(())
+    }
+
+    async fn last_applied_state(
+        &mut self,
+    ) -> Result<(Option<LogId<u64>>, StoredMembership<u64, BasicNode>), StorageError<u64>> {
+        unreachable!()
+    }
+
+    async fn apply_to_state_machine(
+        &mut self,
+        _entries: &[Entry<Config>],
+    ) -> Result<Vec<()>, StorageError<u64>> {
+        unreachable!("entries are applied by the node's app")
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Unused {
+        Unused
+    }
+
+    async fn begin_receiving_snapshot(
+        &mut self,
+    ) -> Result<Box<Cursor<Vec<u8>>>, StorageError<u64>> {
+        Ok(Box::new(Cursor::new(Vec::new())))
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<u64, BasicNode>,
+        snapshot: Box<Cursor<Vec<u8>>>,
+    ) -> Result<(), StorageError<u64>> {
+        self.snapshot = Some((meta.clone(), snapshot.into_inner()));
+        Ok(())
+    }
+
+    async fn get_current_snapshot(
+        &mut self,
+    ) -> Result<Option<openraft::Snapshot<Config>>, StorageError<u64>> {
+        Ok(self
+            .snapshot
+            .clone()
+            .map(|(meta, data)| openraft::Snapshot {
+                meta,
+                snapshot: Box::new(Cursor::new(data)),
+            }))
+    }
+}
+
+fn entries(terms: &[u64]) -> Vec<LogEntry<u32>> {
+    terms
+        .iter()
+        .zip(1..)
+        .map(|(&term, data)| LogEntry { term, data })
+        .collect()
+}
+
+#[test]
+fn state_survives_a_round_trip_through_an_openraft_backend() {
+    let mut storage = OpenraftStorage::new(1, MemStore::default());
+    assert_eq!(storage.load().unwrap(), None);
+
+    let state = PersistentState {
+        current_term: 3,
+        voted_for: Some(2),
+        snapshot: Snapshot {
+            len: 2,
+            term: 1,
+            data: vec![4, 2],
+        },
+        entries: entries(&[2, 3]),
+    };
+    storage.save(&state).unwrap();
+    assert_eq!(storage.load().unwrap(), Some(state));
+
+    let store = storage.into_inner();
+    assert_eq!(store.snapshot.unwrap().1, [4, 2]);
+    assert_eq!(store.log.keys().copied().collect::<Vec<_>>(), [3, 4]);
+}
+
+#[test]
+fn saving_again_replaces_conflicting_entries_and_compacts() {
+    let mut storage = OpenraftStorage::new(1, MemStore::default());
+    let mut state = PersistentState {
+        current_term: 2,
+        voted_for: Some(3),
+        snapshot: Snapshot::default(),
+        entries: entries(&[1, 1, 2]),
+    };
+    storage.save(&state).unwrap();
+
+    // a new leader overwrote our entry from term 2, and we haven't voted in its term
+    state.current_term = 3;
+    state.voted_for = None;
+    state.entries = entries(&[1, 1, 3, 3]);
+    storage.save(&state).unwrap();
+    let loaded = storage.load().unwrap().unwrap();
+    assert_eq!(loaded.entries, state.entries);
+    // openraft can't tell apart not having voted from having voted for ourselves
+    assert_eq!(loaded.voted_for, Some(1));
+
+    state.snapshot = Snapshot {
+        len: 3,
+        term: 3,
+        data: vec![7],
+    };
+    state.entries.drain(..3);
+    storage.save(&state).unwrap();
+    let loaded = storage.load().unwrap().unwrap();
+    assert_eq!(loaded.snapshot, state.snapshot);
+    assert_eq!(loaded.entries, state.entries);
+    assert_eq!(
+        storage.into_inner().log.keys().copied().collect::<Vec<_>>(),
+        [4]
+    );
+}
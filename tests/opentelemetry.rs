@@ -0,0 +1,50 @@
+#![cfg(feature = "opentelemetry")]
+mod common;
+
+use common::*;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+use tracing_subscriber::layer::SubscriberExt;
+
+#[test]
+fn proposal_is_traced_across_nodes() {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("miniraft")));
+
+    let leader_id = tracing::subscriber::with_default(subscriber, || {
+        let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+        cluster.tick_by(MAX_TICKS);
+        let leader_id = cluster.get_leader().unwrap().id;
+        tracing::info_span!("proposal").in_scope(|| {
+            cluster.get_by_id(leader_id).client_request(7).unwrap();
+        });
+        cluster.tick_by(MAX_TICKS);
+        leader_id
+    });
+    provider.force_flush().unwrap();
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let proposal = spans.iter().find(|span| span.name == "proposal").unwrap();
+    let trace_id = proposal.span_context.trace_id();
+    let in_trace: Vec<_> = spans
+        .iter()
+        .filter(|span| span.span_context.trace_id() == trace_id)
+        .collect();
+
+    // leader append -> follower handling it -> leader handling the ack -> apply
+    let followers_handling = in_trace
+        .iter()
+        .filter(|span| span.name == "receive_rpc")
+        .filter(|span| {
+            span.attributes
+                .iter()
+                .any(|kv| kv.key.as_str() == "id" && kv.value.as_str() != leader_id.to_string())
+        })
+        .count();
+    assert!(followers_handling >= 2);
+    assert!(in_trace.iter().any(|span| span.name == "apply"));
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    rpc::{VoteRequest, RPC},
+    server::{RaftServer, MAX_PAUSED_RPCS},
+    sim::Cluster,
+};
+
+fn cluster() -> Cluster<u32, u32> {
+    init_logger();
+    let mut cluster = Cluster::new(3, 5, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    cluster
+}
+
+#[test]
+fn paused_follower_catches_up_on_resume() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    let follower = (leader + 1) % 3;
+    cluster.pause(follower);
+    assert!(cluster.node(follower).status().paused);
+
+    cluster.client_request(4).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    // the rest of the cluster commits without it, while it stays exactly as it was
+    assert_eq!(cluster.node(leader).log.committed_len, 1);
+    assert_eq!(cluster.node(follower).log.len(), 0);
+
+    cluster.resume(follower);
+    cluster.tick();
+    assert!(!cluster.node(follower).is_paused());
+    assert_eq!(cluster.node(follower).log.len(), 1);
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+    assert_eq!(cluster.node(follower).log.app.get_state(), 4);
+}
+
+#[test]
+fn paused_node_timers_are_frozen() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    let term = cluster.leader().unwrap().current_term();
+    let follower = (leader + 1) % 3;
+    cluster.pause(follower);
+    cluster.kill(leader);
+
+    // the other follower times out and starts elections, the paused one never does
+    cluster.tick_by(MAX_WAIT * 3);
+    assert!(cluster.node(follower).is_follower());
+    assert_eq!(cluster.node(follower).current_term(), term);
+
+    // once back, it votes in whatever election is going on, and a leader gets elected
+    cluster.resume(follower);
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    assert!(cluster.node(follower).current_term() > term);
+}
+
+#[test]
+fn paused_leader_takes_no_proposals_and_gets_replaced() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    cluster.pause(leader);
+    assert!(matches!(
+        cluster.node_mut(leader).client_request(1),
+        Err(RaftError::Paused)
+    ));
+
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster
+        .live_nodes()
+        .any(|node| node.is_leader() && node.id != leader)));
+    cluster.resume(leader);
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+    assert!(cluster.node(leader).is_follower());
+}
+
+#[test]
+fn paused_node_drops_rpcs_beyond_its_buffer() {
+    init_logger();
+    let app = Box::new(CountingApp { state: 0 });
+    let mut server: RaftServer<u32, u32> = RaftServer::new(0, [1].into(), DEFAULT_CFG, None, app);
+    server.pause();
+    server.pause();
+    let rpc = RPC::VoteRequest(VoteRequest {
+        candidate_term: 1,
+        candidate_id: 1,
+        candidate_last_log_idx: 0,
+        candidate_last_log_term: 0,
+        request_id: 0,
+    });
+    for _ in 0..MAX_PAUSED_RPCS + 10 {
+        assert!(server.receive_rpc(&rpc).is_empty());
+    }
+    assert!(server.tick().is_empty());
+    // every request it held on to gets answered
+    assert_eq!(server.resume().len(), MAX_PAUSED_RPCS);
+    assert!(server.resume().is_empty());
+}
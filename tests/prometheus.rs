@@ -0,0 +1,71 @@
+#![cfg(feature = "prometheus")]
+mod common;
+
+use common::*;
+use miniraft::prometheus::PrometheusExporter;
+use prometheus::{
+    proto::{MetricFamily, MetricType},
+    Registry,
+};
+
+/// Value of `name` for node `id`, whatever type of metric it is
+fn value(families: &[MetricFamily], name: &str, id: usize) -> f64 {
+    let family = families
+        .iter()
+        .find(|family| family.get_name() == name)
+        .unwrap_or_else(|| panic!("{} not registered", name));
+    let metric = family
+        .get_metric()
+        .iter()
+        .find(|metric| metric.get_label()[0].get_value() == id.to_string())
+        .unwrap_or_else(|| panic!("{} has no series for node {}", name, id));
+    match family.get_field_type() {
+        MetricType::HISTOGRAM => metric.get_histogram().get_sample_count() as f64,
+        MetricType::COUNTER => metric.get_counter().get_value(),
+        _ => metric.get_gauge().get_value(),
+    }
+}
+
+#[test]
+fn exporter_tracks_cluster() {
+    let registry = Registry::new();
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    let mut exporters: Vec<PrometheusExporter> = (0..3)
+        .map(|id| PrometheusExporter::new(id, &registry).unwrap())
+        .collect();
+
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+    for i in 0..3 {
+        cluster.get_by_id(leader_id).client_request(i).unwrap();
+        exporters[leader_id].update(cluster.get_by_id(leader_id));
+    }
+    cluster.tick_by(MAX_TICKS);
+    for (id, exporter) in exporters.iter_mut().enumerate() {
+        exporter.update(cluster.get_by_id(id));
+    }
+
+    let families = registry.gather();
+    let term = cluster.leader_term() as f64;
+    for id in 0..3 {
+        assert_eq!(value(&families, "raft_term", id), term);
+        assert_eq!(value(&families, "raft_commit_index", id), 3.0);
+        assert_eq!(value(&families, "raft_entries_committed_total", id), 3.0);
+        assert_eq!(
+            value(&families, "raft_is_leader", id),
+            (id == leader_id) as u8 as f64
+        );
+    }
+    assert_eq!(value(&families, "raft_elections_won_total", leader_id), 1.0);
+    assert_eq!(
+        value(&families, "raft_commit_latency_seconds", leader_id),
+        3.0
+    );
+}
+
+#[test]
+fn exporter_rejects_duplicate_node() {
+    let registry = Registry::new();
+    PrometheusExporter::new(0, &registry).unwrap();
+    assert!(PrometheusExporter::new(0, &registry).is_err());
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "python")]
+
+use miniraft::python::miniraft;
+use pyo3::{prelude::*, types::PyDict, wrap_pymodule};
+use std::ffi::CStr;
+
+/// Run a Python script with the `miniraft` module in scope, as if it was imported
+fn run(script: &CStr) {
+    Python::initialize();
+    Python::attach(|py| {
+        let globals = PyDict::new(py);
+        globals.set_item("miniraft", wrap_pymodule!(miniraft)(py))?;
+        py.run(script, Some(&globals), None)
+    })
+    .unwrap_or_else(|err| panic!("script failed: {err}"));
+}
+
+#[test]
+fn cluster_replicates_entries() {
+    run(c"
+cluster = miniraft.Cluster(nodes=3, seed=7)
+while cluster.leader is None:
+    cluster.tick()
+leader = cluster.leader
+assert cluster.propose('x = 1') == leader
+cluster.tick(10)
+assert all(cluster.applied(id) == ['x = 1'] for id in range(3))
+
+cluster.partition([leader])
+cluster.tick(50)
+assert cluster.leader != leader
+statuses = cluster.status()
+assert [status['id'] for status in statuses] == [0, 1, 2]
+assert statuses[leader]['role'] == 'Leader'
+
+try:
+    cluster.applied(3)
+    raise AssertionError('unknown node accepted')
+except ValueError:
+    pass
+");
+}
+
+#[test]
+fn servers_talk_through_the_script() {
+    run(c"
+servers = [miniraft.Server(id, [peer for peer in range(3) if peer != id], seed=id) for id in range(3)]
+
+def deliver(sender, msgs):
+    while msgs:
+        frm, (to, rpc) = sender, msgs.pop(0)
+        for server in servers:
+            if server.id == to or (to is None and server.id != frm):
+                msgs.extend(server.receive(rpc))
+
+def tick():
+    for server in servers:
+        deliver(server.id, server.tick())
+
+for _ in range(100):
+    tick()
+    if any(server.role == 'Leader' for server in servers):
+        break
+leader = next(server for server in servers if server.role == 'Leader')
+follower = servers[(leader.id + 1) % 3]
+assert follower.leader == leader.id
+assert follower.term == leader.term
+
+try:
+    follower.propose('nope')
+    raise AssertionError('follower took a proposal')
+except RuntimeError as err:
+    assert 'leader' in str(err)
+
+leader.propose('hello')
+for _ in range(10):
+    tick()
+assert all(server.applied == ['hello'] for server in servers)
+assert leader.status()['committed_len'] == 1
+
+try:
+    miniraft.Server(0, [1], heartbeat_interval=0)
+    raise AssertionError('bad config accepted')
+except ValueError:
+    pass
+");
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    rpc::{Target, VoteRequest, RPC},
+    server::{RaftServer, ServerId},
+    sim::Cluster,
+};
+
+/// Leader of a 3 node cluster, and one of its followers
+fn leader_and_follower() -> (Cluster<u32, u32>, ServerId, ServerId) {
+    init_logger();
+    let mut cluster = Cluster::new(3, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    let leader = cluster.leader().unwrap().id;
+    (cluster, leader, (leader + 1) % 3)
+}
+
+/// Whether anything `msgs` sends goes to `peer`
+fn sends_to(msgs: &[(Target, RPC<u32>)], peer: ServerId) -> bool {
+    msgs.iter()
+        .any(|(target, _)| matches!(target, Target::Single(to) if *to == peer))
+}
+
+/// Whether `node` sends anything to `peer` within a heartbeat interval
+fn heartbeats(node: &mut RaftServer<u32, u32>, peer: ServerId) -> bool {
+    (0..DEFAULT_CFG.heartbeat_interval).any(|_| sends_to(&node.tick(), peer))
+}
+
+/// Vote request from `candidate` for the term after `node`'s
+fn campaign(node: &RaftServer<u32, u32>, candidate: ServerId) -> RPC<u32> {
+    RPC::VoteRequest(VoteRequest {
+        candidate_term: node.current_term() + 1,
+        candidate_id: candidate,
+        candidate_last_log_idx: 100,
+        candidate_last_log_term: node.current_term(),
+        request_id: 0,
+    })
+}
+
+#[test]
+fn quarantined_peers_are_cut_off_until_it_runs_out() {
+    let (mut cluster, leader, follower) = leader_and_follower();
+    let node = cluster.node_mut(leader);
+    assert!(matches!(
+        node.quarantine(7, 10),
+        Err(RaftError::UnknownPeer(7))
+    ));
+    node.quarantine(follower, 10).unwrap();
+
+    for _ in 1..10 {
+        assert!(!sends_to(&node.tick(), follower));
+    }
+    // not even a newer term from it gets through
+    let term = node.current_term();
+    assert!(node.receive_rpc(&campaign(node, follower)).is_empty());
+    assert!(node.is_leader());
+    assert_eq!(node.current_term(), term);
+
+    // the quarantine is over on the 10th tick
+    node.tick();
+    assert!(node.quarantined().is_empty());
+    assert!(heartbeats(node, follower));
+}
+
+#[test]
+fn quarantines_end_on_time_when_ticks_are_skipped() {
+    let (mut cluster, leader, follower) = leader_and_follower();
+    let node = cluster.node_mut(leader);
+    node.quarantine(follower, 3).unwrap();
+    node.tick_n(2);
+    assert!(node.quarantined().contains_key(&follower));
+    node.tick_n(1);
+    assert!(node.quarantined().is_empty());
+}
+
+#[test]
+fn released_peers_are_let_back_in_right_away() {
+    let (mut cluster, leader, follower) = leader_and_follower();
+    let node = cluster.node_mut(leader);
+    node.quarantine(follower, MAX_TICKS).unwrap();
+    node.release(&follower);
+    assert!(node.quarantined().is_empty());
+    assert!(heartbeats(node, follower));
+    node.receive_rpc(&campaign(node, follower));
+    assert!(node.is_follower());
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    rpc::{ReadId, VoteRequest, RPC},
+    server::ServerId,
+    sim::Cluster,
+};
+
+fn cluster(n: usize) -> Cluster<u32, u32> {
+    init_logger();
+    let mut cluster = Cluster::new(n, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    cluster
+}
+
+/// Tick until `read` on node `id` is ready, failing on the way if it gets refused
+fn wait_for_read(cluster: &mut Cluster<u32, u32>, id: ServerId, read: ReadId) {
+    for _ in 0..MAX_WAIT {
+        if cluster.node_mut(id).read_ready(read).unwrap() {
+            return;
+        }
+        cluster.tick();
+    }
+    panic!("read {} on node {} never became ready", read, id);
+}
+
+#[test]
+fn followers_read_everything_committed_before_the_read() {
+    let mut cluster = cluster(5);
+    let leader = cluster.leader().unwrap().id;
+    for data in 1..=3 {
+        cluster.client_request(data).unwrap();
+    }
+    assert!(
+        cluster.run_until(MAX_WAIT, |cluster| cluster.node(leader).log.committed_len
+            == 3)
+    );
+
+    let follower = (leader + 1) % 5;
+    let read = cluster.read_barrier(follower).unwrap();
+    wait_for_read(&mut cluster, follower, read);
+    assert_eq!(cluster.node(follower).log.app.get_state(), 6);
+    // picking up a ready read forgets it
+    assert!(matches!(
+        cluster.node_mut(follower).read_ready(read),
+        Err(RaftError::ReadUnconfirmed)
+    ));
+}
+
+#[test]
+fn new_leaders_refuse_reads_until_they_commit_in_their_term() {
+    let mut cluster = cluster(5);
+    let leader = cluster.leader().unwrap().id;
+    let read = cluster.read_barrier(leader).unwrap();
+    assert!(matches!(
+        cluster.node_mut(leader).read_ready(read),
+        Err(RaftError::ReadUnconfirmed)
+    ));
+
+    cluster.client_request(1).unwrap();
+    assert!(
+        cluster.run_until(MAX_WAIT, |cluster| cluster.node(leader).log.committed_len
+            == 1)
+    );
+    let read = cluster.read_barrier(leader).unwrap();
+    wait_for_read(&mut cluster, leader, read);
+}
+
+#[test]
+fn reads_are_refused_once_the_term_moves_on() {
+    let mut cluster = cluster(5);
+    let leader = cluster.leader().unwrap().id;
+    cluster.client_request(1).unwrap();
+    assert!(
+        cluster.run_until(MAX_WAIT, |cluster| cluster.node(leader).log.committed_len
+            == 1)
+    );
+
+    let follower = (leader + 1) % 5;
+    let read = cluster.read_barrier(follower).unwrap();
+    let node = cluster.node_mut(follower);
+    let term = node.current_term();
+    node.receive_rpc(&RPC::VoteRequest(VoteRequest {
+        candidate_term: term + 1,
+        candidate_id: (leader + 2) % 5,
+        candidate_last_log_idx: 0,
+        candidate_last_log_term: 0,
+        request_id: 0,
+    }));
+    assert!(matches!(
+        node.read_ready(read),
+        Err(RaftError::ReadUnconfirmed)
+    ));
+    // nobody to ask until a new leader is known
+    assert!(matches!(
+        node.read_barrier(),
+        Err(RaftError::NotLeader { leader: None })
+    ));
+}
+
+#[test]
+fn single_nodes_read_right_away() {
+    let mut cluster = cluster(1);
+    cluster.client_request(1).unwrap();
+    let (read, msgs) = cluster.node_mut(0).read_barrier().unwrap();
+    assert!(msgs.is_empty());
+    assert!(cluster.node_mut(0).read_ready(read).unwrap());
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    record::{read_recording, replay, RecordedEvent, Recorder},
+    rpc::{SendableMessage, Target},
+    server::{RaftServer, ServerId},
+};
+
+/// Run a three node cluster with node 0 recorded to `name`, proposing a single entry halfway
+/// through, and return where the recording went along with node 0's final state
+fn record_cluster(name: &str) -> (PathBuf, u32) {
+    init_logger();
+    let path = test_dir(name).join("node0.jsonl");
+    let mut recorded = Recorder::new(
+        0,
+        BTreeSet::from([1, 2]),
+        DEFAULT_CFG,
+        42,
+        Box::new(CountingApp { state: 0 }),
+        Box::new(File::create(&path).unwrap()),
+    );
+    let mut others: BTreeMap<ServerId, RaftServer<u32, u32>> = [1, 2]
+        .into_iter()
+        .map(|id| {
+            let peers = BTreeSet::from([0, 1, 2])
+                .difference(&[id].into())
+                .cloned()
+                .collect();
+            let app = Box::new(CountingApp { state: 0 });
+            (
+                id,
+                RaftServer::new(id, peers, DEFAULT_CFG, Some(id as u64), app),
+            )
+        })
+        .collect();
+
+    for tick in 0..MAX_TICKS {
+        let mut queue: Vec<(ServerId, SendableMessage<u32>)> =
+            recorded.tick().into_iter().map(|msg| (0, msg)).collect();
+        for (id, node) in others.iter_mut() {
+            queue.extend(node.tick().into_iter().map(|msg| (*id, msg)));
+        }
+        while !queue.is_empty() {
+            let mut next = Vec::new();
+            for (from, (target, rpc)) in queue {
+                let to: Vec<ServerId> = match target {
+                    Target::Single(to) => vec![to],
+                    Target::Broadcast => (0..3).filter(|&id| id != from).collect(),
+                };
+                for id in to {
+                    let replies = match id {
+                        0 => recorded.receive_rpc(&rpc),
+                        _ => others.get_mut(&id).unwrap().receive_rpc(&rpc),
+                    };
+                    next.extend(replies.into_iter().map(|msg| (id, msg)));
+                }
+            }
+            queue = next;
+        }
+        if tick == MAX_TICKS / 2 {
+            if recorded.server().is_leader() {
+                recorded.client_request(5).unwrap();
+            }
+            for node in others.values_mut().filter(|node| node.is_leader()) {
+                node.client_request(5).unwrap();
+            }
+        }
+    }
+    recorded.flush().unwrap();
+    (path, recorded.server().log.app.get_state())
+}
+
+fn load(path: &Path) -> Vec<RecordedEvent<u32>> {
+    read_recording(BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+#[test]
+fn recorder_captures_inputs_and_outputs() {
+    let (path, state) = record_cluster("record");
+    assert_eq!(state, 5);
+
+    let events = load(&path);
+    assert!(matches!(
+        events[0],
+        RecordedEvent::Start {
+            id: 0,
+            seed: 42,
+            ..
+        }
+    ));
+    let ticks = events
+        .iter()
+        .filter(|event| matches!(event, RecordedEvent::Tick { .. }))
+        .count();
+    assert_eq!(ticks, MAX_TICKS as usize);
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, RecordedEvent::Receive { .. })));
+    let last_tick = events.iter().rev().find_map(|event| match event {
+        RecordedEvent::Tick { tick, .. } => Some(*tick),
+        _ => None,
+    });
+    assert_eq!(last_tick, Some(MAX_TICKS));
+}
+
+#[test]
+fn replay_reproduces_recording() {
+    let (path, state) = record_cluster("replay");
+    let server = replay(load(&path), Box::new(CountingApp { state: 0 })).unwrap();
+    assert_eq!(server.log.app.get_state(), state);
+}
+
+#[test]
+fn replay_reports_divergence() {
+    let (path, _) = record_cluster("replay-diverge");
+    let mut events = load(&path);
+    if let RecordedEvent::Start { seed, .. } = &mut events[0] {
+        *seed += 1;
+    }
+    let err = replay(events, Box::new(CountingApp { state: 0 }))
+        .err()
+        .expect("a different seed changes election timing");
+    assert!(err.to_string().contains("replay diverged"), "{}", err);
+}
@@ -0,0 +1,316 @@
+mod common;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    log::LogEntry,
+    rpc::{AppendRejection, AppendRequest, Target, RPC},
+    server::{RaftConfig, RaftServer, ReplicationBatching, Term},
+    sim::Cluster,
+};
+
+#[test]
+fn caught_up_followers_get_empty_heartbeats() {
+    init_logger();
+    let mut cluster = Cluster::new(3, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    let leader = cluster.leader().unwrap().id;
+    let lagging = (leader + 1) % 3;
+    cluster.client_request(1).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    assert!(cluster.nodes().all(|node| node.log.committed_len == 1));
+
+    // the lagging follower misses an entry the other one acks
+    cluster.kill(lagging);
+    cluster.client_request(2).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    cluster.revive(lagging);
+
+    let msgs = (0..DEFAULT_CFG.heartbeat_interval)
+        .map(|_| cluster.node_mut(leader).tick())
+        .find(|msgs| !msgs.is_empty())
+        .unwrap();
+    let mut sent: Vec<_> = msgs
+        .into_iter()
+        .map(|(_, rpc)| match rpc {
+            RPC::AppendRequest(req) => {
+                assert_eq!(req.leader_commit, 2);
+                (req.leader_last_log_idx, req.entries.len())
+            }
+            rpc => panic!("expected an AppendRequest, got {rpc:?}"),
+        })
+        .collect();
+    sent.sort();
+    // only the lagging follower is sent an entry
+    assert_eq!(sent, [(1, 1), (2, 0)]);
+}
+
+#[test]
+fn entries_commit_once_a_quorum_has_them() {
+    init_logger();
+    let mut cluster = Cluster::new(5, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    let leader = cluster.leader().unwrap().id;
+    let others: Vec<_> = (1..5).map(|offset| (leader + offset) % 5).collect();
+
+    // with only one follower around the leader can't commit
+    cluster.kill(others[1]);
+    cluster.kill(others[2]);
+    cluster.kill(others[3]);
+    cluster.client_request(1).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    assert_eq!(cluster.node(leader).log.committed_len, 0);
+
+    // the third copy is a quorum
+    cluster.revive(others[1]);
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    assert_eq!(cluster.node(leader).log.committed_len, 1);
+    assert_eq!(cluster.node(leader).log.app.get_state(), 1);
+}
+
+#[test]
+fn followers_share_one_copy_of_the_entries() {
+    init_logger();
+    let mut cluster = Cluster::new(5, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    let leader = cluster.leader().unwrap().id;
+    let lagging = [(leader + 1) % 5, (leader + 2) % 5];
+    cluster.kill(lagging[0]);
+    cluster.client_request(1).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    cluster.kill(lagging[1]);
+    cluster.client_request(2).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+
+    let msgs = (0..DEFAULT_CFG.heartbeat_interval)
+        .map(|_| cluster.node_mut(leader).tick())
+        .find(|msgs| !msgs.is_empty())
+        .unwrap();
+    let last_entries: Vec<_> = msgs
+        .iter()
+        .filter_map(|(_, rpc)| match rpc {
+            RPC::AppendRequest(req) => req.entries.last(),
+            _ => None,
+        })
+        .collect();
+    // both lagging followers are sent the last entry, out of the same copy
+    assert_eq!(last_entries.len(), 2);
+    assert!(std::ptr::eq(last_entries[0], last_entries[1]));
+}
+
+#[test]
+fn leader_counts_itself_once_its_write_is_done() {
+    init_logger();
+    let config = RaftConfig {
+        persist_in_background: true,
+        ..DEFAULT_CFG
+    };
+    let mut cluster = Cluster::new(3, 3, config, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    let leader = cluster.leader().unwrap().id;
+    cluster.kill((leader + 1) % 3);
+
+    // the entry goes out and the one follower left acks it, but the leader's own
+    // write hasn't finished so there is no quorum yet
+    cluster.client_request(1).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    let node = cluster.node_mut(leader);
+    assert_eq!(node.log.committed_len, 0);
+
+    node.log_persisted(1);
+    assert_eq!(node.log.committed_len, 1);
+    assert_eq!(node.log.app.get_state(), 1);
+}
+
+/// Ticks it takes `leader` to send anything, and how many entries that carries
+fn next_send(leader: &mut RaftServer<u32, u32>) -> (u32, usize) {
+    for ticks in 1..=MAX_TICKS {
+        if let Some((_, RPC::AppendRequest(req))) = leader.tick().first() {
+            return (ticks, req.entries.len());
+        }
+    }
+    panic!("leader never sent anything");
+}
+
+#[test]
+fn proposals_go_out_in_batches() {
+    init_logger();
+    let config = RaftConfig {
+        replication_batching: Some(ReplicationBatching {
+            delay: 3,
+            max_entries: 4,
+        }),
+        ..DEFAULT_CFG
+    };
+    let mut cluster = Cluster::new(3, 3, config, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    let leader = cluster.leader().unwrap().id;
+    let node = cluster.node_mut(leader);
+    // heartbeats come every interval, not every tick
+    next_send(node);
+    assert_eq!(next_send(node), (DEFAULT_CFG.heartbeat_interval, 0));
+
+    // a proposal waits for the delay, picking up others on the way
+    node.client_request(1).unwrap();
+    node.tick();
+    node.client_request(2).unwrap();
+    assert_eq!(next_send(node), (2, 2));
+
+    // a full batch goes out right away. Nothing was delivered, so the followers are
+    // sent the first batch again along with it
+    (0..4).for_each(|data| node.client_request(data).unwrap());
+    assert_eq!(next_send(node), (1, 6));
+}
+
+#[test]
+fn commits_can_be_broadcast_right_away() {
+    init_logger();
+    for broadcast_commits in [false, true] {
+        let config = RaftConfig {
+            replication_batching: Some(ReplicationBatching {
+                delay: 1,
+                max_entries: 1,
+            }),
+            broadcast_commits,
+            ..DEFAULT_CFG
+        };
+        let mut cluster = Cluster::new(3, 3, config, |_| Box::new(CountingApp { state: 0 }));
+        assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+        let leader = cluster.leader().unwrap().id;
+        let follower = (leader + 1) % 3;
+        cluster.node_mut(leader).client_request(1).unwrap();
+
+        // the entry reaches one follower, whose ack commits it
+        let append = (0..MAX_TICKS)
+            .flat_map(|_| cluster.node_mut(leader).tick())
+            .find_map(|(target, rpc)| match &rpc {
+                RPC::AppendRequest(req)
+                    if target == Target::Single(follower) && !req.entries.is_empty() =>
+                {
+                    Some(rpc)
+                }
+                _ => None,
+            })
+            .unwrap();
+        let ack = cluster.node_mut(follower).receive_rpc(&append).remove(0).1;
+        let msgs = cluster.node_mut(leader).receive_rpc(&ack);
+        assert_eq!(cluster.node(leader).log.committed_len, 1);
+        assert_eq!(cluster.node(follower).log.committed_len, 0);
+
+        // and only goes out to everyone before the next heartbeat if asked to
+        let commit = msgs.into_iter().find(|(target, rpc)| {
+            *target == Target::Single(follower)
+                && matches!(rpc, RPC::AppendRequest(req) if req.leader_commit == 1)
+        });
+        assert_eq!(commit.is_some(), broadcast_commits);
+        if let Some((_, rpc)) = commit {
+            cluster.node_mut(follower).receive_rpc(&rpc);
+            assert_eq!(cluster.node(follower).log.app.get_state(), 1);
+        }
+    }
+}
+
+#[test]
+fn broadcast_commits_leave_out_followers_already_sent_a_request() {
+    init_logger();
+    let config = RaftConfig {
+        replication_batching: Some(ReplicationBatching {
+            delay: 1,
+            max_entries: 1,
+        }),
+        broadcast_commits: true,
+        ..DEFAULT_CFG
+    };
+    let mut cluster = Cluster::new(3, 3, config, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    let leader = cluster.leader().unwrap().id;
+    let follower = (leader + 1) % 3;
+    cluster.node_mut(leader).client_request(1).unwrap();
+    let append = (0..MAX_TICKS)
+        .flat_map(|_| cluster.node_mut(leader).tick())
+        .find_map(|(target, rpc)| match &rpc {
+            RPC::AppendRequest(req)
+                if target == Target::Single(follower) && !req.entries.is_empty() =>
+            {
+                Some(rpc)
+            }
+            _ => None,
+        })
+        .unwrap();
+
+    // while we hand leadership to the follower, its ack makes us send it what it is still
+    // missing, which carries the new commit index too
+    cluster.node_mut(leader).client_request(2).unwrap();
+    cluster
+        .node_mut(leader)
+        .transfer_leadership(Some(follower))
+        .unwrap();
+    let ack = cluster.node_mut(follower).receive_rpc(&append).remove(0).1;
+    let msgs = cluster.node_mut(leader).receive_rpc(&ack);
+    assert_eq!(cluster.node(leader).log.committed_len, 1);
+    let appends_to = |to| {
+        msgs.iter()
+            .filter(|(target, rpc)| {
+                *target == Target::Single(to)
+                    && matches!(rpc, RPC::AppendRequest(req) if req.leader_commit == 1)
+            })
+            .count()
+    };
+    assert_eq!(appends_to(follower), 1);
+    assert_eq!(appends_to((leader + 2) % 3), 1);
+}
+
+#[test]
+fn followers_reject_requests_no_leader_would_send() {
+    init_logger();
+    let app = Box::new(CountingApp { state: 0 });
+    let mut follower = RaftServer::<u32, u32>::new(0, [1, 2].into(), DEFAULT_CFG, Some(0), app);
+    let mut append = |term, prefix_len, prefix_term, leader_commit, terms: &[Term]| {
+        let req = RPC::AppendRequest(AppendRequest {
+            leader_term: term,
+            leader_id: 1,
+            leader_last_log_idx: prefix_len,
+            leader_last_log_term: prefix_term,
+            leader_commit,
+            transfer_target: None,
+            transfer_ticks_left: 0,
+            entries: terms
+                .iter()
+                .map(|&term| LogEntry { term, data: 1 })
+                .collect::<Vec<_>>()
+                .into(),
+            request_id: 0,
+            trace: None,
+        });
+        match &follower.receive_rpc(&req)[..] {
+            [(_, RPC::AppendResponse(res))] => {
+                assert_eq!(res.ok, res.rejection.is_none());
+                (
+                    res.rejection,
+                    follower.log.len(),
+                    follower.log.committed_len,
+                )
+            }
+            msgs => panic!("expected a single AppendResponse, got {msgs:?}"),
+        }
+    };
+
+    // a commit index past the end of the request only commits what the request carries
+    assert_eq!(append(2, 0, 0, 5, &[1, 2]), (None, 2, 2));
+    // entries from a later term than the leader's, or going back in terms
+    let malformed = Some(AppendRejection::Malformed);
+    assert_eq!(append(2, 2, 2, 3, &[3]), (malformed, 2, 2));
+    assert_eq!(append(2, 2, 2, 3, &[1]), (malformed, 2, 2));
+    assert_eq!(append(3, 2, 2, 3, &[3, 2]), (malformed, 2, 2));
+    // a gap in the log, and an old leader
+    assert_eq!(
+        append(3, 5, 2, 6, &[3]),
+        (Some(AppendRejection::LogMismatch), 2, 2)
+    );
+    assert_eq!(
+        append(2, 2, 2, 3, &[2]),
+        (Some(AppendRejection::StaleTerm), 2, 2)
+    );
+    assert_eq!(follower.metrics().append_requests_rejected, 5);
+    assert_eq!(follower.log.app.get_state(), 2);
+}
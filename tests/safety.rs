@@ -0,0 +1,169 @@
+//! Property based checks of Raft's safety properties (figure 3 of the Raft paper) over
+//! randomly generated schedules of client requests, crashes and partitions
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::*;
+use miniraft::{
+    log::{LogEntry, LogIndex},
+    server::{ServerId, Term},
+    sim::Cluster,
+};
+use proptest::prelude::*;
+
+/// Something that happens to the cluster
+#[derive(Clone, Debug)]
+enum Op {
+    /// Let the cluster run for a while
+    Tick(u32),
+    /// Propose an entry to whoever is leader
+    Propose(u32),
+    /// Crash a node
+    Kill(ServerId),
+    /// Bring a node back up
+    Revive(ServerId),
+    /// Cut the given nodes off from the rest
+    Partition(Vec<ServerId>),
+    /// Restore every link
+    Heal,
+}
+
+fn op(nodes: usize) -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (1..30u32).prop_map(Op::Tick),
+        3 => any::<u32>().prop_map(|data| Op::Propose(data % 100)),
+        1 => (0..nodes).prop_map(Op::Kill),
+        1 => (0..nodes).prop_map(Op::Revive),
+        1 => proptest::sample::subsequence((0..nodes).collect::<Vec<_>>(), 1..nodes)
+            .prop_map(Op::Partition),
+        1 => Just(Op::Heal),
+    ]
+}
+
+/// What we've learned about the cluster so far, to check properties that span time
+#[derive(Default)]
+struct History {
+    /// Who led each term
+    leaders: BTreeMap<Term, ServerId>,
+    /// Every entry known to be committed by index, along with the term it was first seen
+    /// committed in
+    committed: BTreeMap<LogIndex, (LogEntry<u32>, Term)>,
+}
+
+/// Full log of a node, snapshots aside (this test never takes any)
+fn entries(cluster: &Cluster<u32, u32>, id: ServerId) -> &[LogEntry<u32>] {
+    &cluster.node(id).log.entries
+}
+
+fn check(cluster: &Cluster<u32, u32>, history: &mut History) -> Result<(), TestCaseError> {
+    // Election Safety: at most one leader can be elected in a given term
+    for node in cluster.nodes().filter(|node| node.is_leader()) {
+        let leader = *history
+            .leaders
+            .entry(node.current_term())
+            .or_insert(node.id);
+        prop_assert_eq!(
+            leader,
+            node.id,
+            "two leaders in term {}",
+            node.current_term()
+        );
+    }
+
+    // Log Matching: if two logs contain an entry with the same index and term, the logs are
+    // identical in all entries up through that index
+    let nodes = cluster.nodes().count();
+    for a in 0..nodes {
+        for b in a + 1..nodes {
+            let (log_a, log_b) = (entries(cluster, a), entries(cluster, b));
+            let last_match = (0..log_a.len().min(log_b.len()))
+                .rev()
+                .find(|&i| log_a[i].term == log_b[i].term);
+            if let Some(i) = last_match {
+                prop_assert_eq!(
+                    &log_a[..=i],
+                    &log_b[..=i],
+                    "logs of {} and {} diverge",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    // State Machine Safety: no two nodes ever commit (and so apply) a different entry at the
+    // same index
+    for node in cluster.nodes() {
+        for (index, entry) in node.log.entries[..node.log.committed_len]
+            .iter()
+            .enumerate()
+        {
+            let (known, _) = history
+                .committed
+                .entry(index)
+                .or_insert_with(|| (entry.clone(), node.current_term()));
+            prop_assert_eq!(
+                &*known,
+                entry,
+                "node {} committed a different entry {}",
+                node.id,
+                index
+            );
+        }
+    }
+
+    // Leader Completeness: a committed entry is present in the logs of the leaders of all
+    // later terms
+    for node in cluster.nodes().filter(|node| node.is_leader()) {
+        for (index, (entry, committed_in)) in &history.committed {
+            if *committed_in < node.current_term() {
+                prop_assert_eq!(
+                    node.log.entries.get(*index),
+                    Some(entry),
+                    "leader {} of term {} is missing committed entry {}",
+                    node.id,
+                    node.current_term(),
+                    index
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1_000))]
+
+    // even sizes too, where half the nodes must not make a quorum
+    #[test]
+    fn raft_is_safe(
+        (nodes, seed, ops) in (2..=5usize).prop_flat_map(|nodes| {
+            (Just(nodes), any::<u64>(), proptest::collection::vec(op(nodes), 1..40))
+        })
+    ) {
+        let mut cluster: Cluster<u32, u32> =
+            Cluster::new(nodes, seed, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+        let mut history = History::default();
+        for op in ops {
+            match op {
+                Op::Tick(n) => {
+                    for _ in 0..n {
+                        cluster.tick();
+                        check(&cluster, &mut history)?;
+                    }
+                }
+                Op::Propose(data) => {
+                    // no leader around is fine, the request is just dropped
+                    let _ = cluster.client_request(data);
+                }
+                Op::Kill(id) => cluster.kill(id),
+                Op::Revive(id) => cluster.revive(id),
+                Op::Partition(side) => cluster.partition(&side),
+                Op::Heal => cluster.heal(),
+            }
+            check(&cluster, &mut history)?;
+        }
+    }
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::*;
+use miniraft::{scenario::Scenario, sim::Disk};
+
+fn scenario() -> Scenario<u32, u32> {
+    Scenario::new(5, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }))
+}
+
+#[test]
+fn isolated_leader_is_replaced_and_catches_up() {
+    let cluster = scenario()
+        .wait_for_leader()
+        .propose(1)
+        .tick(MAX_WAIT)
+        .isolate_leader()
+        .wait_for_new_leader()
+        .propose(2)
+        .heal()
+        .assert_logs_converge()
+        .run()
+        .unwrap();
+    assert!(cluster.nodes().all(|node| node.log.app.get_state() == 3));
+}
+
+#[test]
+fn wiped_node_catches_up() {
+    scenario()
+        .seed(3)
+        .wait_for_leader()
+        .propose(1)
+        .propose(2)
+        .tick(MAX_WAIT)
+        .restart(0, Disk::Wiped)
+        .assert_logs_converge()
+        .check("every node applied both entries", |cluster| {
+            cluster.nodes().all(|node| node.log.app.get_state() == 3)
+        })
+        .run()
+        .unwrap();
+}
+
+#[test]
+fn failed_step_is_reported_with_seed() {
+    let err = scenario()
+        .seed(9)
+        .wait_for_leader()
+        .check("nothing", |_| false)
+        .run()
+        .err()
+        .unwrap();
+    assert!(
+        err.to_string()
+            .starts_with("step 2 (check nothing) failed with seed 9"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "schema")]
+
+use miniraft::{
+    log::LogEntry,
+    rpc::{self, AppendRequest, TimeoutNow, RPC},
+    server::ServerId,
+};
+use serde_json::{json, Value};
+
+fn schema() -> Value {
+    serde_json::to_value(rpc::schema::<u32, ServerId>()).unwrap()
+}
+
+#[test]
+fn every_rpc_has_a_variant_in_the_schema() {
+    let schema = schema();
+    let variants: Vec<&str> = schema["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variant| variant["required"][0].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        variants,
+        [
+            "VoteRequest",
+            "VoteResponse",
+            "AppendRequest",
+            "AppendResponse",
+            "SnapshotRequest",
+            "SnapshotResponse",
+            "TimeoutNow",
+            "ReadIndexRequest",
+            "ReadIndexResponse",
+        ]
+    );
+}
+
+#[test]
+fn serialized_rpcs_have_what_the_schema_requires() {
+    let schema = schema();
+    let rpcs = [
+        RPC::AppendRequest(AppendRequest {
+            leader_term: 2,
+            leader_id: 1,
+            leader_last_log_idx: 0,
+            leader_last_log_term: 0,
+            leader_commit: 0,
+            transfer_target: None,
+            transfer_ticks_left: 0,
+            entries: vec![LogEntry { term: 2, data: 7 }].into(),
+            request_id: 3,
+            trace: None,
+        }),
+        RPC::TimeoutNow(TimeoutNow {
+            leader_term: 2,
+            leader_id: 1,
+        }),
+    ];
+    for rpc in rpcs {
+        let value = serde_json::to_value(&rpc).unwrap();
+        let (variant, fields) = value.as_object().unwrap().iter().next().unwrap();
+        let definition = &schema["$defs"][variant];
+        for required in definition["required"].as_array().unwrap() {
+            let field = required.as_str().unwrap();
+            assert!(fields.get(field).is_some(), "{variant} is missing {field}");
+        }
+        for field in fields.as_object().unwrap().keys() {
+            assert!(
+                definition["properties"].get(field).is_some(),
+                "{variant} has {field}, which the schema doesn't know"
+            );
+        }
+    }
+    // entries are typed by the app's data type
+    let entry = &schema["$defs"]["LogEntry"];
+    assert_eq!(entry["properties"]["data"]["type"], json!("integer"));
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::*;
+use miniraft::{
+    error::RaftError,
+    rpc::{Target, RPC},
+    server::RaftServer,
+    shard::{Placement, Router},
+    threaded::ThreadedNode,
+};
+
+const TICK: Duration = Duration::from_millis(1);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn keys_go_to_the_shard_that_owns_them() {
+    let splits = vec![b"g".to_vec(), b"p".to_vec()];
+    let router = Router::new(Placement::Ranges(splits), vec!["a-f", "g-o", "p-z"]).unwrap();
+    assert_eq!(router.route(b""), &"a-f");
+    assert_eq!(router.route(b"fzz"), &"a-f");
+    assert_eq!(router.route(b"g"), &"g-o");
+    assert_eq!(router.route(b"orange"), &"g-o");
+    assert_eq!(router.route(b"pear"), &"p-z");
+    assert_eq!(router.shard(3), None);
+
+    // hashing is stable, and spreads keys over every shard
+    let router = Router::new(Placement::Hash, vec![(); 4]).unwrap();
+    let mut used = [0; 4];
+    for key in 0..100u32 {
+        let shard = router.shard_of(&key.to_le_bytes());
+        assert_eq!(router.shard_of(&key.to_le_bytes()), shard);
+        used[shard] += 1;
+    }
+    assert!(used.iter().all(|keys| *keys > 10), "{:?}", used);
+    assert_eq!(router.shard_of(b"key"), 0);
+}
+
+#[test]
+fn placements_have_to_fit_the_shards() {
+    let invalid = |placement, shards: usize| {
+        matches!(
+            Router::new(placement, vec![(); shards]),
+            Err(RaftError::InvalidConfig(_))
+        )
+    };
+    assert!(invalid(Placement::Hash, 0));
+    assert!(invalid(Placement::Ranges(vec![]), 2));
+    assert!(invalid(
+        Placement::Ranges(vec![b"b".to_vec(), b"a".to_vec()]),
+        3
+    ));
+    assert!(invalid(
+        Placement::Ranges(vec![b"a".to_vec(), b"a".to_vec()]),
+        3
+    ));
+    assert!(!invalid(Placement::Ranges(vec![]), 1));
+}
+
+#[test]
+fn proposals_land_in_their_own_group() {
+    // a single node group per shard, each elects itself
+    let (clients, handles): (Vec<_>, Vec<_>) = (0..2)
+        .map(|_| {
+            let (client_sender, client) = std::sync::mpsc::channel();
+            let handle = thread::spawn(move || {
+                let app = Box::new(CountingApp { state: 0 });
+                let server = RaftServer::new(0, [].into(), DEFAULT_CFG, Some(0), app);
+                let transport = |_: Target, _: RPC<u32>| {};
+                let (node, client) = ThreadedNode::new(server, TICK, transport);
+                client_sender.send(client).unwrap();
+                node.run().log.app.get_state()
+            });
+            (client.recv().unwrap(), handle)
+        })
+        .unzip();
+    let router = Router::new(Placement::Ranges(vec![b"m".to_vec()]), clients).unwrap();
+
+    let started = Instant::now();
+    for (key, amount) in [(&b"apple"[..], 1), (b"zebra", 10), (b"banana", 2)] {
+        loop {
+            assert!(started.elapsed() < TIMEOUT, "nothing got committed");
+            match router.route(key).propose(amount) {
+                Ok(_) => break,
+                Err(RaftError::NotLeader { .. }) => thread::sleep(TICK * 5),
+                Err(err) => panic!("proposal failed: {}", err),
+            }
+        }
+    }
+
+    for client in router.shards() {
+        client.shutdown(false);
+    }
+    let states: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(states, [3, 10]);
+}
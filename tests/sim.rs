@@ -0,0 +1,225 @@
+mod common;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    server::{RaftServer, Term},
+    sim::{node_seed, Cluster, Disk, Faults},
+};
+use rand_core::RngCore;
+
+fn cluster(seed: u64) -> Cluster<u32, u32> {
+    init_logger();
+    Cluster::new(5, seed, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }))
+}
+
+/// Who leads which term, as seen at every tick
+fn leadership_timeline(seed: u64) -> Vec<Option<(usize, Term)>> {
+    let mut cluster = cluster(seed);
+    (0..200)
+        .map(|_| {
+            cluster.tick();
+            cluster
+                .leader()
+                .map(|leader| (leader.id, leader.current_term()))
+        })
+        .collect()
+}
+
+#[test]
+fn same_seed_same_run() {
+    assert_eq!(leadership_timeline(7), leadership_timeline(7));
+}
+
+#[test]
+fn nodes_can_be_rebuilt_from_the_master_seed() {
+    let mut cluster = cluster(7);
+    // drawing from the test RNG doesn't change how the nodes play out
+    cluster.rng().next_u64();
+    let peers = (0..5).filter(|&id| id != 2).collect();
+    let node = RaftServer::new(
+        2,
+        peers,
+        DEFAULT_CFG,
+        Some(node_seed(cluster.seed(), 2, 0)),
+        Box::new(CountingApp { state: 0 }),
+    );
+    assert_eq!(
+        node.debug_dump(0).timer,
+        cluster.node(2).debug_dump(0).timer
+    );
+}
+
+#[test]
+fn cluster_replicates_requests() {
+    let mut cluster = cluster(0);
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    for i in 1..=3 {
+        cluster.client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_WAIT);
+    assert!(cluster.nodes().all(|node| node.log.app.get_state() == 6));
+    assert!(cluster.delivered() > 0);
+}
+
+#[test]
+fn majority_side_of_partition_elects_new_leader() {
+    let mut cluster = cluster(0);
+    cluster.tick_by(MAX_TICKS);
+    let old_leader = cluster.leader().unwrap().id;
+    let minority: Vec<usize> = (0..5).filter(|&id| id != old_leader).take(1).collect();
+
+    cluster.partition(&[old_leader, minority[0]]);
+    cluster.tick_by(MAX_TICKS);
+    let new_leader = cluster.leader().unwrap();
+    assert_ne!(new_leader.id, old_leader);
+    assert!(!minority.contains(&new_leader.id));
+    assert!(cluster.client_request(1).is_ok());
+
+    cluster.heal();
+    cluster.tick_by(MAX_TICKS);
+    assert_eq!(cluster.nodes().filter(|node| node.is_leader()).count(), 1);
+    assert!(cluster.nodes().all(|node| node.log.app.get_state() == 1));
+}
+
+#[test]
+fn client_requests_fail_without_leader() {
+    let mut cluster = cluster(0);
+    assert!(cluster.client_request(1).is_err());
+}
+
+#[test]
+fn restarted_leader_recovers_from_disk() {
+    let mut cluster = cluster(0);
+    cluster.tick_by(MAX_TICKS);
+    for i in 1..=3 {
+        cluster.client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_WAIT);
+    let leader = cluster.leader().unwrap();
+    let (id, term, log_len) = (leader.id, leader.current_term(), leader.status().log_len);
+
+    cluster.restart(id, Disk::Intact).unwrap();
+    let restarted = cluster.node(id);
+    assert!(!restarted.is_leader());
+    assert_eq!(restarted.current_term(), term);
+    assert_eq!(restarted.status().log_len, log_len);
+    assert_eq!(restarted.log.app.get_state(), 0);
+
+    cluster.tick_by(MAX_TICKS);
+    cluster.client_request(4).unwrap();
+    cluster.tick_by(MAX_WAIT);
+    assert!(cluster.nodes().all(|node| node.log.app.get_state() == 10));
+}
+
+#[test]
+fn restarted_followers_catch_up_after_losing_disk() {
+    for disk in [Disk::LostTail(2), Disk::LostVote, Disk::Wiped] {
+        let mut cluster = cluster(0);
+        cluster.tick_by(MAX_TICKS);
+        for i in 1..=3 {
+            cluster.client_request(i).unwrap();
+        }
+        cluster.tick_by(MAX_WAIT);
+        let leader = cluster.leader().unwrap().id;
+        let follower = (0..5).find(|&id| id != leader).unwrap();
+
+        cluster.restart(follower, disk).unwrap();
+        cluster.tick_by(MAX_WAIT);
+        assert_eq!(cluster.leader().unwrap().id, leader, "{:?}", disk);
+        assert_eq!(cluster.node(follower).log.app.get_state(), 6, "{:?}", disk);
+    }
+}
+
+#[test]
+fn wiped_follower_is_replicated_to_from_the_start_of_the_log() {
+    let mut cluster = cluster(1);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.leader().unwrap().id;
+    let follower = (leader + 1) % 5;
+    for i in 1..=50 {
+        cluster.client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(cluster.node(leader).log.snapshot.len, 0);
+
+    cluster.restart(follower, Disk::Wiped).unwrap();
+    assert!(cluster.node(follower).log.is_empty());
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(
+        cluster.node(follower).log.entries,
+        cluster.node(leader).log.entries
+    );
+    assert_eq!(cluster.node(follower).log.app.get_state(), 50 * 51 / 2);
+}
+
+#[test]
+fn cluster_survives_duplicated_and_late_messages() {
+    for seed in 0..10 {
+        let mut cluster = cluster(seed);
+        cluster.set_faults(Faults {
+            duplicate: 0.2,
+            delay: 0.2,
+            max_delay: 3 * MAX_WAIT,
+            ..Default::default()
+        });
+        for round in 0..10 {
+            cluster.tick_by(MAX_WAIT);
+            let _ = cluster.client_request(round);
+            // force a few elections so late messages arrive in a newer term
+            if round % 3 == 0 {
+                if let Some(leader) = cluster.leader().map(|leader| leader.id) {
+                    cluster.restart(leader, Disk::Intact).unwrap();
+                }
+            }
+        }
+
+        // a leader can only commit entries from earlier terms along with one of its own
+        cluster.set_faults(Faults::default());
+        cluster.tick_by(10 * MAX_WAIT);
+        cluster.client_request(0).unwrap();
+        cluster.tick_by(MAX_WAIT);
+        let states: Vec<u32> = cluster
+            .nodes()
+            .map(|node| node.log.app.get_state())
+            .collect();
+        assert!(
+            states.windows(2).all(|pair| pair[0] == pair[1]),
+            "seed {}: {:?}",
+            seed,
+            states
+        );
+    }
+}
+
+#[test]
+fn builder_wires_up_a_cluster() {
+    init_logger();
+    let mut tested = miniraft::sim::TestCluster::builder()
+        .nodes(5)
+        .seed(3)
+        .config(DEFAULT_CFG)
+        .app(|| CountingApp { state: 0 })
+        .build();
+    assert_eq!(tested.nodes().count(), 5);
+    assert_eq!(tested.seed(), 3);
+    assert!(tested.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    tested.client_request(7).unwrap();
+    tested.tick_by(MAX_WAIT);
+    assert!(tested.nodes().all(|node| node.log.app.get_state() == 7));
+
+    // the same seed gives the same cluster as the plain constructor, the
+    // default config being the one the tests use
+    let mut built = Cluster::builder()
+        .seed(7)
+        .nodes(5)
+        .app(|| CountingApp { state: 0 })
+        .build();
+    built.tick_by(MAX_WAIT);
+    let mut plain = cluster(7);
+    plain.tick_by(MAX_WAIT);
+    assert_eq!(
+        built.leader().map(|leader| leader.id),
+        plain.leader().map(|leader| leader.id)
+    );
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    server::RaftServer,
+    sim::{Cluster, Disk},
+};
+
+fn single_node() -> RaftServer<u32, u32> {
+    init_logger();
+    let app = Box::new(CountingApp { state: 0 });
+    RaftServer::new(0, BTreeSet::new(), DEFAULT_CFG, Some(0), app)
+}
+
+#[test]
+fn elects_itself_on_first_tick() {
+    let mut node = single_node();
+    assert_eq!(node.quorum_size(), 1);
+    assert!(node.tick().is_empty());
+    assert!(node.is_leader());
+    assert_eq!(node.current_term(), 1);
+}
+
+#[test]
+fn commits_on_proposal() {
+    let mut node = single_node();
+    node.tick();
+    for data in 1..=3 {
+        node.client_request(data).unwrap();
+        assert_eq!(node.log.committed_len, data as usize);
+    }
+    assert_eq!(node.log.app.get_state(), 6);
+    // heartbeats go to nobody
+    for _ in 0..MAX_TICKS {
+        assert!(node.tick().is_empty());
+    }
+    assert!(node.is_leader());
+    assert_eq!(node.current_term(), 1);
+}
+
+#[test]
+fn restarted_single_node_leads_again() {
+    init_logger();
+    let mut cluster = Cluster::new(1, 0, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    cluster.tick();
+    cluster.client_request(2).unwrap();
+    cluster.client_request(3).unwrap();
+    cluster.restart(0, Disk::Intact).unwrap();
+    assert!(cluster.leader().is_none());
+    cluster.tick();
+    let leader = cluster.leader().unwrap();
+    assert_eq!(leader.current_term(), 2);
+    // entries from the old term commit along with the first one of the new term
+    cluster.client_request(4).unwrap();
+    assert_eq!(cluster.node(0).log.committed_len, 3);
+    assert_eq!(cluster.node(0).log.app.get_state(), 9);
+}
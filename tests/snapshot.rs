@@ -0,0 +1,636 @@
+mod common;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    io,
+    rc::Rc,
+};
+
+use common::*;
+use miniraft::{
+    apply::ApplyWorker,
+    debug::init_logger,
+    log::{App, LogEntry, LogIndex, Snapshot},
+    rpc::{AppendRequest, Target, VoteRequest, RPC},
+    scenario::Scenario,
+    server::{PersistentState, RaftConfig, RaftServer, ServerId},
+    sim::Disk,
+    storage::{load_snapshot, save_snapshot, Storage},
+};
+
+#[test]
+fn app_snapshot_round_trip() {
+    let mut app = CountingApp { state: 0 };
+    app.transition_fn(&LogEntry { term: 1, data: 42 });
+
+    let mut buf = Vec::new();
+    app.snapshot(&mut buf).unwrap();
+
+    let mut restored = CountingApp { state: 7 };
+    restored.restore(&mut buf.as_slice()).unwrap();
+    assert_eq!(restored.get_state(), 42);
+}
+
+/// App that leaves snapshots unsupported
+struct NoSnapshots;
+
+impl App<u32, ()> for NoSnapshots {
+    fn transition_fn(&mut self, _entry: &LogEntry<u32>) {}
+    fn get_state(&self) {}
+}
+
+#[test]
+fn apps_without_snapshot_support_report_unsupported() {
+    let err = NoSnapshots.snapshot(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn apply_worker_snapshot_includes_queued_entries() {
+    let mut worker = ApplyWorker::new(Box::new(CountingApp { state: 0 }));
+    for data in [1, 2, 3] {
+        worker.transition_fn(&LogEntry { term: 1, data });
+    }
+
+    let mut buf = Vec::new();
+    worker.snapshot(&mut buf).unwrap();
+    assert_eq!(buf, 6u32.to_le_bytes());
+
+    let mut restored = ApplyWorker::new(Box::new(CountingApp { state: 0 }));
+    restored.restore(&mut buf.as_slice()).unwrap();
+    assert_eq!(restored.get_state(), 6);
+}
+
+#[test]
+fn compact_replaces_applied_prefix() {
+    let mut l = setup_log();
+    l.append_entries(
+        0,
+        2,
+        vec![
+            LogEntry { term: 1, data: 1 },
+            LogEntry { term: 2, data: 2 },
+            LogEntry { term: 2, data: 3 },
+        ],
+    );
+    l.compact().unwrap();
+
+    assert_eq!(l.snapshot.len, 2);
+    assert_eq!(l.snapshot.term, 2);
+    assert_eq!(l.snapshot.data, 3u32.to_le_bytes());
+    assert_eq!(l.entries.len(), 1);
+    assert_eq!(l.len(), 3);
+    assert_eq!(l.last_idx(), 2);
+    assert_eq!(l.term_at(1), None);
+    assert_eq!(l.term_at(2), Some(2));
+    assert_eq!(l.term_at(3), Some(2));
+
+    // appending and committing keeps working past the snapshot
+    l.append_entries(3, 4, vec![LogEntry { term: 3, data: 4 }]);
+    assert_eq!(l.len(), 4);
+    assert_eq!(l.last_term(), 3);
+    assert_eq!(l.app.get_state(), 10);
+}
+
+#[test]
+fn install_snapshot_keeps_matching_suffix() {
+    let mut l = setup_log();
+    l.append_entries(
+        0,
+        0,
+        vec![
+            LogEntry { term: 1, data: 1 },
+            LogEntry { term: 1, data: 2 },
+            LogEntry { term: 2, data: 3 },
+        ],
+    );
+    l.install_snapshot(Snapshot {
+        len: 2,
+        term: 1,
+        data: 3u32.to_le_bytes().to_vec(),
+    })
+    .unwrap();
+
+    assert_eq!(l.committed_len, 2);
+    assert_eq!(l.app.get_state(), 3);
+    assert_eq!(l.len(), 3);
+    assert_eq!(l.last_term(), 2);
+}
+
+#[test]
+fn install_snapshot_discards_conflicting_log() {
+    let mut l = setup_log();
+    l.append_entries(
+        0,
+        0,
+        vec![LogEntry { term: 1, data: 1 }, LogEntry { term: 1, data: 2 }],
+    );
+    l.install_snapshot(Snapshot {
+        len: 2,
+        term: 2,
+        data: 5u32.to_le_bytes().to_vec(),
+    })
+    .unwrap();
+
+    assert!(l.entries.is_empty());
+    assert_eq!(l.len(), 2);
+    assert_eq!(l.last_term(), 2);
+    assert_eq!(l.app.get_state(), 5);
+}
+
+#[test]
+fn leader_keeps_replicating_after_compaction() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_WAIT);
+    let lead = cluster.get_leader_mut().unwrap();
+    assert!(lead.client_request(1).is_ok());
+    assert!(lead.client_request(2).is_ok());
+    cluster.tick_by(MAX_WAIT);
+
+    let lead = cluster.get_leader_mut().unwrap();
+    assert!(lead.snapshot_now().is_ok());
+    assert_eq!(lead.log.snapshot.len, 2);
+    assert!(lead.log.entries.is_empty());
+
+    assert!(lead.client_request(3).is_ok());
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(cluster.get_leader().unwrap().log.app.get_state(), 6);
+    assert!(cluster.state_consensus());
+}
+
+#[test]
+fn lagging_follower_catches_up_from_snapshot() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_WAIT);
+    let lead_id = cluster.get_leader().unwrap().id;
+    let follower_id = (0..3).find(|id| *id != lead_id).unwrap();
+    cluster.kill(follower_id);
+
+    // follower misses entries which then get compacted away on the leader
+    let lead = cluster.get_by_id(lead_id);
+    assert!(lead.client_request(1).is_ok());
+    assert!(lead.client_request(2).is_ok());
+    cluster.tick_by(MAX_WAIT);
+    let lead = cluster.get_by_id(lead_id);
+    assert!(lead.snapshot_now().is_ok());
+    assert!(lead.client_request(3).is_ok());
+    cluster.tick_by(MAX_WAIT);
+
+    cluster.revive(follower_id);
+    cluster.tick_by(MAX_WAIT);
+    let follower = cluster.get_by_id(follower_id);
+    assert_eq!(follower.log.snapshot.len, 2);
+    assert_eq!(follower.log.len(), 3);
+    assert_eq!(follower.log.app.get_state(), 6);
+    assert!(cluster.state_consensus());
+}
+
+#[test]
+fn snapshots_are_only_resent_once_the_follower_had_time_to_answer() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_WAIT);
+    let lead_id = cluster.get_leader().unwrap().id;
+    let follower_id = (0..3).find(|id| *id != lead_id).unwrap();
+    cluster.kill(follower_id);
+
+    let lead = cluster.get_by_id(lead_id);
+    assert!(lead.client_request(1).is_ok());
+    cluster.tick_by(MAX_WAIT);
+    let lead = cluster.get_by_id(lead_id);
+    assert!(lead.snapshot_now().is_ok());
+
+    // the follower keeps getting heartbeats, but the snapshot only once per election timeout
+    let (mut snapshots, mut heartbeats) = (0, 0);
+    for _ in 0..2 * DEFAULT_CFG.election_timeout {
+        for (target, rpc) in lead.tick() {
+            if target == Target::Single(follower_id) {
+                match rpc {
+                    RPC::SnapshotRequest(_) => snapshots += 1,
+                    RPC::AppendRequest(req) if req.entries.is_empty() => heartbeats += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    assert_eq!(snapshots, 2);
+    assert_eq!(snapshots + heartbeats, 2 * DEFAULT_CFG.election_timeout);
+    let status = lead.status();
+    let transfer = status.catch_up[&follower_id].snapshot.as_ref().unwrap();
+    assert_eq!(
+        transfer.last_sent_at,
+        transfer.sent_at + DEFAULT_CFG.election_timeout
+    );
+}
+
+#[test]
+fn long_logs_are_compacted_during_follower_outages() {
+    let config = RaftConfig {
+        max_log_entries: Some(5),
+        ..DEFAULT_CFG
+    };
+    let mut cluster = TestCluster::new(3, 0, config);
+    cluster.tick_by(MAX_WAIT);
+    let lead_id = cluster.get_leader().unwrap().id;
+    let follower_id = (0..3).find(|id| *id != lead_id).unwrap();
+    cluster.kill(follower_id);
+
+    // the leader never holds much more than its limit while the follower is gone
+    for data in 1..=20 {
+        assert!(cluster.get_by_id(lead_id).client_request(data).is_ok());
+        cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+        assert!(cluster.get_by_id(lead_id).log.entries.len() <= 5);
+    }
+    assert!(cluster.get_by_id(lead_id).log.snapshot.len >= 15);
+
+    cluster.revive(follower_id);
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(cluster.get_by_id(follower_id).log.app.get_state(), 210);
+    assert!(cluster.state_consensus());
+}
+
+/// Entries spilled by any node, kept in memory
+#[derive(Clone, Default)]
+struct MemSpill(Rc<RefCell<BTreeMap<LogIndex, LogEntry<u32>>>>);
+
+impl Storage<u32> for MemSpill {
+    fn save(&mut self, _state: &PersistentState<u32, ServerId>) -> io::Result<()> {
+        Ok(())
+    }
+    fn load(&mut self) -> io::Result<Option<PersistentState<u32, ServerId>>> {
+        Ok(None)
+    }
+    fn spill(&mut self, from: LogIndex, entries: &[LogEntry<u32>]) -> io::Result<()> {
+        let mut spilled = self.0.borrow_mut();
+        spilled.extend((from..).zip(entries.iter().cloned()));
+        Ok(())
+    }
+    fn fetch(&mut self, from: LogIndex, max: usize) -> io::Result<Vec<LogEntry<u32>>> {
+        let spilled = self.0.borrow();
+        Ok((from..from + max)
+            .map_while(|index| spilled.get(&index).cloned())
+            .collect())
+    }
+}
+
+/// [`CountingApp`] that counts snapshot restores across every node
+struct RestoreCountingApp {
+    app: CountingApp,
+    restores: Rc<Cell<u32>>,
+}
+
+impl App<u32, u32> for RestoreCountingApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        self.app.transition_fn(entry);
+    }
+    fn get_state(&self) -> u32 {
+        self.app.get_state()
+    }
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        self.app.snapshot(writer)
+    }
+    fn restore(&mut self, reader: &mut dyn io::Read) -> io::Result<()> {
+        self.restores.set(self.restores.get() + 1);
+        self.app.restore(reader)
+    }
+}
+
+#[test]
+fn lagging_follower_catches_up_from_spilled_entries() {
+    let config = RaftConfig {
+        max_log_entries: Some(5),
+        ..DEFAULT_CFG
+    };
+    let restores = Rc::new(Cell::new(0));
+    let mut cluster = TestCluster::with_apps(3, 0, config, |_| {
+        Box::new(RestoreCountingApp {
+            app: CountingApp { state: 0 },
+            restores: restores.clone(),
+        })
+    });
+    let spill = MemSpill::default();
+    for id in 0..3 {
+        cluster.get_by_id(id).spill_to(spill.clone());
+    }
+    cluster.tick_by(MAX_WAIT);
+    let lead_id = cluster.get_leader().unwrap().id;
+    let follower_id = (0..3).find(|id| *id != lead_id).unwrap();
+    cluster.kill(follower_id);
+
+    // the leader still keeps only a few entries in memory
+    for data in 1..=20 {
+        assert!(cluster.get_by_id(lead_id).client_request(data).is_ok());
+        cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+        assert!(cluster.get_by_id(lead_id).log.entries.len() <= 5);
+    }
+    assert!(cluster.get_by_id(lead_id).log.snapshot.len >= 15);
+    assert!(spill.0.borrow().len() >= 15);
+
+    // but the follower gets every entry it missed rather than the snapshot
+    cluster.revive(follower_id);
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(cluster.get_by_id(follower_id).log.app.get_state(), 210);
+    assert_eq!(restores.get(), 0);
+    assert!(cluster.state_consensus());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn state_dir_hands_back_spilled_entries_in_a_row() {
+    use miniraft::{server::Term, storage::StateDir};
+
+    let entries = |terms: &[Term]| -> Vec<LogEntry<u32>> {
+        terms
+            .iter()
+            .map(|term| LogEntry {
+                term: *term,
+                data: *term as u32,
+            })
+            .collect()
+    };
+    let storage: &mut dyn Storage<u32> = &mut StateDir::new(test_dir("spill"));
+    assert!(storage.fetch(0, 10).unwrap().is_empty());
+
+    storage.spill(0, &entries(&[1, 1, 2])).unwrap();
+    // spilled again, e.g. after a snapshot failed
+    storage.spill(2, &entries(&[2, 3])).unwrap();
+    // after a gap, e.g. after installing a snapshot from the leader
+    storage.spill(7, &entries(&[4])).unwrap();
+
+    assert_eq!(storage.fetch(1, 10).unwrap(), entries(&[1, 2, 3]));
+    assert_eq!(storage.fetch(0, 2).unwrap(), entries(&[1, 1]));
+    assert!(storage.fetch(5, 10).unwrap().is_empty());
+    assert_eq!(storage.fetch(7, 10).unwrap(), entries(&[4]));
+}
+
+#[test]
+fn long_logs_of_apps_without_snapshots_fail_once_until_they_grow() {
+    init_logger();
+    let config = RaftConfig {
+        max_log_entries: Some(5),
+        ..DEFAULT_CFG
+    };
+    let mut node = RaftServer::new(0, BTreeSet::new(), config, Some(0), Box::new(NoSnapshots));
+    node.tick_n(MAX_WAIT);
+    (1..=6).for_each(|data| assert!(node.client_request(data).is_ok()));
+    for _ in 0..MAX_WAIT {
+        node.tick();
+    }
+    node.tick_n(MAX_WAIT);
+    assert_eq!(node.metrics().snapshots_failed, 1);
+    assert_eq!(node.log.entries.len(), 6);
+
+    // tried again once there are another 5 entries
+    (1..=4).for_each(|data| assert!(node.client_request(data).is_ok()));
+    node.tick_n(MAX_WAIT);
+    assert_eq!(node.metrics().snapshots_failed, 1);
+    assert!(node.client_request(5).is_ok());
+    node.tick_n(MAX_WAIT);
+    assert_eq!(node.metrics().snapshots_failed, 2);
+}
+
+/// App that asks for a snapshot once it has seen a bulk load marker (`0`)
+struct BulkLoadApp {
+    state: u32,
+    snapshot_requested: bool,
+}
+
+impl App<u32, u32> for BulkLoadApp {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        self.state += entry.data;
+        self.snapshot_requested |= entry.data == 0;
+    }
+    fn get_state(&self) -> u32 {
+        self.state
+    }
+    fn wants_snapshot(&mut self) -> bool {
+        std::mem::take(&mut self.snapshot_requested)
+    }
+    fn snapshot(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        writer.write_all(&self.state.to_le_bytes())
+    }
+}
+
+#[test]
+fn app_can_trigger_snapshot() {
+    init_logger();
+    let app = BulkLoadApp {
+        state: 0,
+        snapshot_requested: false,
+    };
+    let mut node = RaftServer::new(0, BTreeSet::new(), DEFAULT_CFG, Some(0), Box::new(app));
+    (0..MAX_WAIT).for_each(|_| {
+        node.tick();
+    });
+    (1..=10).for_each(|data| assert!(node.client_request(data).is_ok()));
+    node.tick();
+    assert_eq!(node.log.snapshot.len, 0);
+
+    // bulk load done, app asks for a snapshot which happens on the next tick
+    assert!(node.client_request(0).is_ok());
+    node.tick();
+    assert_eq!(node.log.snapshot.len, 11);
+    assert_eq!(node.log.snapshot.data, 55u32.to_le_bytes());
+    assert!(node.log.entries.is_empty());
+
+    // only once per request
+    assert!(node.client_request(1).is_ok());
+    node.tick();
+    assert_eq!(node.log.snapshot.len, 11);
+}
+
+#[test]
+fn snapshot_requests_survive_skipped_ticks() {
+    let app = BulkLoadApp {
+        state: 0,
+        snapshot_requested: false,
+    };
+    let mut node = RaftServer::new(0, BTreeSet::new(), DEFAULT_CFG, Some(0), Box::new(app));
+    node.tick_n(MAX_WAIT);
+    (0..=10).for_each(|data| assert!(node.client_request(data).is_ok()));
+    node.tick_n(DEFAULT_CFG.heartbeat_interval * 3);
+    assert_eq!(node.log.snapshot.len, 11);
+}
+
+#[test]
+fn snapshot_file_round_trip() {
+    let path = test_dir("snapshot_file_round_trip").join("snapshot");
+    assert!(load_snapshot(&path).unwrap().is_none());
+
+    let snapshot = Snapshot {
+        len: 12,
+        term: 3,
+        data: vec![1, 2, 3],
+    };
+    save_snapshot(&path, &snapshot).unwrap();
+    let loaded = load_snapshot(&path).unwrap().unwrap();
+    assert_eq!(loaded.len, 12);
+    assert_eq!(loaded.term, 3);
+    assert_eq!(loaded.data, vec![1, 2, 3]);
+}
+
+#[test]
+fn cold_start_restores_snapshot_as_log_baseline() {
+    init_logger();
+    let snapshot = Snapshot {
+        len: 5,
+        term: 2,
+        data: 15u32.to_le_bytes().to_vec(),
+    };
+    let node = RaftServer::from_snapshot(
+        0,
+        BTreeSet::from([1, 2]),
+        DEFAULT_CFG,
+        Some(0),
+        Box::new(CountingApp { state: 0 }),
+        snapshot,
+    )
+    .unwrap();
+
+    assert_eq!(node.log.app.get_state(), 15);
+    assert_eq!(node.log.len(), 5);
+    assert_eq!(node.log.last_term(), 2);
+    assert_eq!(node.log.committed_len, 5);
+    assert_eq!(node.current_term, 2);
+}
+
+/// Node 0 with a log of 5 entries up to term 2, all of them compacted into its snapshot
+fn compacted_node() -> RaftServer<u32, u32> {
+    init_logger();
+    let snapshot = Snapshot {
+        len: 5,
+        term: 2,
+        data: 15u32.to_le_bytes().to_vec(),
+    };
+    let app = Box::new(CountingApp { state: 0 });
+    RaftServer::from_snapshot(
+        0,
+        BTreeSet::from([1, 2]),
+        DEFAULT_CFG,
+        Some(0),
+        app,
+        snapshot,
+    )
+    .unwrap()
+}
+
+#[test]
+fn compacted_nodes_vote_on_the_log_behind_their_snapshot() {
+    let vote = |candidate_last_log_idx| {
+        let mut node = compacted_node();
+        let rpc = RPC::VoteRequest(VoteRequest {
+            candidate_term: 3,
+            candidate_id: 1,
+            candidate_last_log_idx,
+            candidate_last_log_term: 2,
+            request_id: 0,
+        });
+        match &node.receive_rpc(&rpc)[..] {
+            [(_, RPC::VoteResponse(res))] => res.vote_granted,
+            msgs => panic!("expected a single VoteResponse, got {msgs:?}"),
+        }
+    };
+    assert!(!vote(3));
+    assert!(vote(4));
+}
+
+#[test]
+fn compacted_followers_take_late_appends_from_inside_their_snapshot() {
+    let mut node = compacted_node();
+    // a retransmitted request from the start of the log, all of it long since compacted
+    let rpc = RPC::AppendRequest(AppendRequest {
+        leader_term: 2,
+        leader_id: 1,
+        leader_last_log_idx: 1,
+        leader_last_log_term: 1,
+        leader_commit: 3,
+        transfer_target: None,
+        transfer_ticks_left: 0,
+        entries: vec![LogEntry { term: 2, data: 2 }].into(),
+        request_id: 0,
+        trace: None,
+    });
+    match &node.receive_rpc(&rpc)[..] {
+        // everything in the snapshot is committed, so it matches the leader's log too
+        [(_, RPC::AppendResponse(res))] => assert!(res.ok && res.ack_idx == 5),
+        msgs => panic!("expected a single AppendResponse, got {msgs:?}"),
+    }
+    assert_eq!(node.log.len(), 5);
+    assert_eq!(node.log.committed_len, 5);
+    assert_eq!(node.log.app.get_state(), 15);
+}
+
+#[test]
+fn restarted_node_rejoins_from_snapshot_on_disk() {
+    let path = test_dir("restarted_node_rejoins_from_snapshot_on_disk").join("snapshot");
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_WAIT);
+    let lead_id = cluster.get_leader().unwrap().id;
+    let lead = cluster.get_by_id(lead_id);
+    assert!(lead.client_request(1).is_ok());
+    assert!(lead.client_request(2).is_ok());
+    cluster.tick_by(MAX_WAIT);
+
+    // a follower snapshots to disk, then restarts having lost everything else
+    let follower_id = (0..3).find(|id| *id != lead_id).unwrap();
+    let follower = cluster.get_by_id(follower_id);
+    assert!(follower.snapshot_now().is_ok());
+    save_snapshot(&path, &follower.log.snapshot).unwrap();
+
+    let snapshot = load_snapshot(&path).unwrap().unwrap();
+    let peers = (0..3).filter(|id| *id != follower_id).collect();
+    let restarted = RaftServer::from_snapshot(
+        follower_id,
+        peers,
+        DEFAULT_CFG,
+        Some(1),
+        Box::new(CountingApp { state: 0 }),
+        snapshot,
+    )
+    .unwrap();
+    assert_eq!(restarted.log.app.get_state(), 3);
+    cluster.peers.insert(follower_id, restarted);
+
+    // rejoins and keeps up with new entries
+    let lead = cluster.get_by_id(lead_id);
+    assert!(lead.client_request(3).is_ok());
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(cluster.get_by_id(follower_id).log.app.get_state(), 6);
+    assert!(cluster.state_consensus());
+}
+
+/// Snapshot every node after `before` entries, propose `after` more, then wipe node `wiped`
+/// and check it comes back identical to the leader from InstallSnapshot plus the entries
+/// trailing the snapshot
+fn wiped_node_round_trip(before: u32, after: u32, wiped: ServerId) {
+    init_logger();
+    let mut scenario = Scenario::new(3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }))
+        .seed(before as u64)
+        .wait_for_leader();
+    for data in 1..=before {
+        scenario = scenario.propose(data);
+    }
+    scenario = scenario.tick(MAX_WAIT).snapshot(0).snapshot(1).snapshot(2);
+    for data in 1..=after {
+        scenario = scenario.propose(10 * data);
+    }
+    scenario
+        .tick(MAX_WAIT)
+        .restart(wiped, Disk::Wiped)
+        .wait_for_leader()
+        .propose(100)
+        .assert_rebuilt_from_snapshot(wiped)
+        .run()
+        .unwrap_or_else(|err| panic!("{} before, {} after: {}", before, after, err));
+}
+
+#[test]
+fn wiped_node_is_rebuilt_from_snapshot_at_any_point() {
+    for before in 1..=4 {
+        for after in 0..=2 {
+            for wiped in 0..3 {
+                wiped_node_round_trip(before, after, wiped);
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use common::*;
+use miniraft::status::Role;
+
+#[test]
+fn status_reports_leader_and_followers() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader_mut().unwrap();
+    let leader_id = leader.id;
+    leader.client_request(1).unwrap();
+    cluster.tick_by(MAX_TICKS);
+
+    let status = cluster.get_by_id(leader_id).status();
+    assert_eq!(status.role, Role::Leader);
+    assert_eq!(status.leader_hint, Some(leader_id));
+    assert_eq!(status.voted_for, Some(leader_id));
+    assert_eq!(status.log_len, 1);
+    assert_eq!(status.committed_len, 1);
+    assert_eq!(status.last_applied, 1);
+    let followers = status.followers.unwrap();
+    assert_eq!(followers.len(), 2);
+    assert!(followers.values().all(|f| f.acked_up_to == 1));
+
+    let follower_id = (0..3).find(|&id| id != leader_id).unwrap();
+    let status = cluster.get_by_id(follower_id).status();
+    assert_eq!(status.role, Role::Follower);
+    assert_eq!(status.leader_hint, Some(leader_id));
+    assert_eq!(status.term, cluster.leader_term());
+    assert_eq!(status.committed_len, 1);
+    assert!(status.followers.is_none());
+    assert!(status.votes_received.is_none());
+}
+
+#[test]
+fn status_reports_candidate_votes() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.kill(1);
+    cluster.kill(2);
+    cluster.tick_by(MAX_TICKS);
+
+    let status = cluster.get_by_id(0).status();
+    assert_eq!(status.role, Role::Candidate);
+    assert_eq!(status.leader_hint, None);
+    assert_eq!(status.voted_for, Some(0));
+    assert_eq!(
+        status
+            .votes_received
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [0]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn status_serializes() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let status = cluster.get_leader().unwrap().status();
+
+    let json = serde_json::to_string(&status).unwrap();
+    let parsed: miniraft::status::RaftStatus = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.role, Role::Leader);
+    assert_eq!(parsed.term, status.term);
+    assert_eq!(parsed.followers.unwrap().len(), 2);
+}
+
+#[test]
+fn status_shows_lagging_follower() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+    let (healthy, lagging) = {
+        let mut followers = (0..3).filter(|&id| id != leader_id);
+        (followers.next().unwrap(), followers.next().unwrap())
+    };
+
+    cluster.kill(lagging);
+    for i in 0..3 {
+        cluster.get_by_id(leader_id).client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_TICKS);
+
+    let status = cluster.get_by_id(leader_id).status();
+    let lag = status.follower_lag();
+    assert_eq!(lag[&healthy], 0);
+    assert_eq!(lag[&lagging], 3);
+
+    let followers = status.followers.unwrap();
+    assert!(followers[&healthy].inflight < followers[&lagging].inflight);
+    assert!(followers[&healthy].last_response_tick > followers[&lagging].last_response_tick);
+}
+
+#[test]
+fn debug_dump_includes_bounded_log_tail() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+    for i in 0..5 {
+        cluster.get_by_id(leader_id).client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_TICKS);
+
+    let dump = cluster.get_by_id(leader_id).debug_dump(3);
+    assert_eq!(dump.status.role, Role::Leader);
+    assert_eq!(dump.config.election_timeout, DEFAULT_CFG.election_timeout);
+    assert_eq!(
+        dump.log_tail
+            .iter()
+            .map(|entry| (entry.index, entry.data.as_str()))
+            .collect::<Vec<_>>(),
+        [(2, "2"), (3, "3"), (4, "4")]
+    );
+
+    // entries compacted away can't be dumped
+    cluster.get_by_id(leader_id).snapshot_now().unwrap();
+    cluster.get_by_id(leader_id).client_request(5).unwrap();
+    let dump = cluster.get_by_id(leader_id).debug_dump(3);
+    assert_eq!(dump.log_tail.len(), 1);
+    assert_eq!(dump.log_tail[0].index, 5);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn debug_dump_renders_json() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let json = cluster.get_leader().unwrap().debug_dump(10).to_json();
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["status"]["role"], "Leader");
+    assert_eq!(value["config"]["heartbeat_interval"], 5);
+    assert!(value["metrics"]["elections_won"].as_u64().unwrap() >= 1);
+}
+
+#[test]
+fn status_tracks_leader_stability() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let old_leader = cluster.get_leader().unwrap().id;
+    let status = cluster.get_by_id(old_leader).status();
+    assert!(status.ticks_as_leader.unwrap() > 0);
+    assert_eq!(status.ticks_since_leader_contact, None);
+
+    let follower = (0..3).find(|&id| id != old_leader).unwrap();
+    let status = cluster.get_by_id(follower).status();
+    assert_eq!(status.ticks_as_leader, None);
+    assert!(status.ticks_since_leader_contact.unwrap() <= DEFAULT_CFG.heartbeat_interval);
+    assert_eq!(cluster.get_by_id(follower).metrics().leader_changes, 1);
+
+    // followers notice the silence, then move on to a new leader
+    cluster.kill(old_leader);
+    cluster.tick_by(MAX_WAIT / 2);
+    let silent_for = cluster
+        .get_by_id(follower)
+        .status()
+        .ticks_since_leader_contact
+        .unwrap();
+    assert!(silent_for >= MAX_WAIT / 2);
+
+    cluster.tick_by(MAX_TICKS);
+    // the old leader is dead but still thinks it leads, so look among the others
+    let new_leader = (0..3)
+        .filter(|&id| id != old_leader)
+        .find(|&id| cluster.get_by_id(id).is_leader())
+        .unwrap();
+    assert_eq!(cluster.get_by_id(new_leader).metrics().leader_changes, 2);
+}
+
+#[test]
+fn accessors_expose_term_vote_and_leader() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap();
+    let (leader_id, term) = (leader.id, leader.current_term());
+    assert_eq!(leader.leader_id(), Some(leader_id));
+    assert_eq!(leader.voted_for(), Some(leader_id));
+
+    for id in (0..3).filter(|&id| id != leader_id) {
+        let follower = cluster.get_by_id(id);
+        assert_eq!(follower.current_term(), term);
+        assert_eq!(follower.leader_id(), Some(leader_id));
+    }
+}
+
+#[test]
+fn status_reports_catch_up_progress() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader_id = cluster.get_leader().unwrap().id;
+    let lagging = (0..3).find(|&id| id != leader_id).unwrap();
+
+    cluster.kill(lagging);
+    for i in 0..4 {
+        cluster.get_by_id(leader_id).client_request(i).unwrap();
+    }
+    cluster.tick_by(MAX_WAIT);
+    cluster.get_by_id(leader_id).snapshot_now().unwrap();
+
+    // the follower gets the snapshot but its answer never makes it back
+    cluster.revive(lagging);
+    cluster.drop_between(lagging, leader_id);
+    cluster.tick_by(MAX_WAIT);
+    let status = cluster.get_by_id(leader_id).status();
+    let progress = &status.catch_up[&lagging];
+    assert_eq!(progress.remaining, 4);
+    assert_eq!(progress.transferred, 0);
+    assert!(progress.elapsed > MAX_WAIT);
+    assert_eq!(progress.estimated_ticks_remaining, None);
+    let snapshot = progress.snapshot.as_ref().unwrap();
+    assert_eq!(snapshot.entries, 4);
+    assert_eq!(snapshot.bytes, 4);
+
+    cluster.drop_connections.clear();
+    cluster.tick_by(MAX_WAIT);
+    assert!(cluster.get_by_id(leader_id).status().catch_up.is_empty());
+}
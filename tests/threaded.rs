@@ -0,0 +1,207 @@
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Arc, RwLock},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    log::Snapshot,
+    rpc::{SnapshotRequest, Target, VoteResponse, RPC},
+    server::{RaftServer, ServerId},
+    status::Role,
+    threaded::{ThreadedClient, ThreadedNode},
+};
+
+const TICK: Duration = Duration::from_millis(1);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+type Routes = Arc<RwLock<BTreeMap<ServerId, ThreadedClient<u32>>>>;
+
+/// Start a node on its own thread, sending to whoever is in `routes`. The thread returns
+/// the state of the node's app once the node stops
+fn spawn(id: ServerId, nodes: usize, routes: Routes) -> (ThreadedClient<u32>, JoinHandle<u32>) {
+    let (client_sender, client) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        let peers = (0..nodes).filter(|peer| *peer != id).collect();
+        let app = Box::new(CountingApp { state: 0 });
+        let server = RaftServer::new(id, peers, DEFAULT_CFG, Some(id as u64), app);
+        let transport = move |target: Target, rpc: RPC<u32>| {
+            for (peer, client) in routes.read().unwrap().iter() {
+                let to_peer = match target {
+                    Target::Single(to) => to == *peer,
+                    Target::Broadcast => id != *peer,
+                };
+                if to_peer {
+                    client.receive(rpc.clone());
+                }
+            }
+        };
+        let (node, client) = ThreadedNode::new(server, TICK, transport);
+        client_sender.send(client).unwrap();
+        node.run().log.app.get_state()
+    });
+    (client.recv().unwrap(), handle)
+}
+
+#[test]
+fn cluster_elects_commits_and_stops() {
+    init_logger();
+    let routes = Routes::default();
+    let (clients, handles): (Vec<_>, Vec<_>) =
+        (0..3).map(|id| spawn(id, 3, routes.clone())).unzip();
+    routes
+        .write()
+        .unwrap()
+        .extend(clients.iter().cloned().enumerate());
+
+    let started = Instant::now();
+    let mut target = 0;
+    let index = loop {
+        assert!(started.elapsed() < TIMEOUT, "nothing got committed");
+        match clients[target].propose(5) {
+            Ok(index) => break index,
+            Err(RaftError::NotLeader { leader }) => {
+                target = leader.unwrap_or((target + 1) % 3);
+                thread::sleep(TICK * 5);
+            }
+            Err(err) => panic!("proposal failed: {}", err),
+        }
+    };
+    assert_eq!(index, 0);
+
+    // followers learn about the commit with the next heartbeat
+    while clients
+        .iter()
+        .any(|client| client.status().unwrap().committed_len < 1)
+    {
+        assert!(
+            started.elapsed() < TIMEOUT,
+            "entry never made it to every node"
+        );
+        thread::sleep(TICK);
+    }
+
+    // nodes stop once nobody can talk to them anymore
+    routes.write().unwrap().clear();
+    let last = clients[0].clone();
+    drop(clients);
+    assert_eq!(last.status().unwrap().committed_len, 1);
+    drop(last);
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 5);
+    }
+}
+
+#[test]
+fn leader_hands_over_when_shut_down() {
+    init_logger();
+    let routes = Routes::default();
+    let (clients, mut handles): (Vec<_>, Vec<_>) =
+        (0..3).map(|id| spawn(id, 3, routes.clone())).unzip();
+    routes
+        .write()
+        .unwrap()
+        .extend(clients.iter().cloned().enumerate());
+
+    let started = Instant::now();
+    let (leader, term) = loop {
+        assert!(started.elapsed() < TIMEOUT, "no leader elected");
+        let status = clients[0].status().unwrap();
+        if let Some(leader) = status.leader_hint {
+            break (leader, status.term);
+        }
+        thread::sleep(TICK);
+    };
+    clients[leader].shutdown(true);
+    handles.remove(leader).join().unwrap();
+    assert!(matches!(
+        clients[leader].propose(1),
+        Err(RaftError::Stopped)
+    ));
+    // stopping again is a no-op
+    clients[leader].shutdown(true);
+
+    // another node took over in the very next term
+    let follower = (leader + 1) % 3;
+    loop {
+        assert!(started.elapsed() < TIMEOUT, "nobody took over");
+        let status = clients[follower].status().unwrap();
+        if status.leader_hint.is_some() {
+            assert_ne!(status.leader_hint, Some(leader));
+            assert_eq!(status.term, term + 1);
+            break;
+        }
+        thread::sleep(TICK);
+    }
+
+    for client in &clients {
+        client.shutdown(false);
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn deposed_leader_fails_proposals_a_newer_snapshot_covers() {
+    init_logger();
+    let (sent, outbox) = mpsc::channel();
+    let (client_sender, client) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        let app = Box::new(CountingApp { state: 0 });
+        let server = RaftServer::new(0, [1, 2].into(), DEFAULT_CFG, Some(0), app);
+        let transport = move |target, rpc| {
+            let _ = sent.send((target, rpc));
+        };
+        let (node, client) = ThreadedNode::new(server, TICK, transport);
+        client_sender.send(client).unwrap();
+        node.run();
+    });
+    let client: ThreadedClient<u32> = client.recv().unwrap();
+
+    // node 1 votes for us, node 2 never answers
+    let term = loop {
+        if let (_, RPC::VoteRequest(req)) = outbox.recv_timeout(TIMEOUT).unwrap() {
+            client.receive(RPC::VoteResponse(VoteResponse {
+                term: req.candidate_term,
+                vote_granted: true,
+                votee_id: 1,
+                request_id: req.request_id,
+            }));
+            break req.candidate_term;
+        }
+    };
+    while client.status().unwrap().role != Role::Leader {
+        thread::sleep(TICK);
+    }
+    let proposer = client.clone();
+    let proposal = thread::spawn(move || proposer.propose(7));
+    while client.status().unwrap().log_len < 1 {
+        thread::sleep(TICK);
+    }
+
+    // node 1 took over and compacted a log that doesn't have our entry
+    client.receive(RPC::SnapshotRequest(SnapshotRequest {
+        leader_term: term + 1,
+        leader_id: 1,
+        snapshot: Snapshot {
+            len: 2,
+            term: term + 1,
+            data: 5u32.to_le_bytes().to_vec(),
+        },
+    }));
+    assert!(matches!(
+        proposal.join().unwrap(),
+        Err(RaftError::NotLeader { leader: Some(1) })
+    ));
+    assert_eq!(client.status().unwrap().committed_len, 2);
+
+    client.shutdown(false);
+    handle.join().unwrap();
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn dot_shows_leader_and_replication() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap().id;
+
+    let dot = cluster.to_dot();
+    assert!(dot.starts_with("digraph raft {"));
+    assert_eq!(dot.matches("doublecircle").count(), 1);
+    for follower in (0..3).filter(|&id| id != leader) {
+        assert!(dot.contains(&format!("{} -> {} [label=\"lag 0\"];", leader, follower)));
+    }
+}
+
+#[test]
+fn dot_shows_partitions() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_TICKS);
+    let leader = cluster.get_leader().unwrap().id;
+    let follower = (0..3).find(|&id| id != leader).unwrap();
+    let other = (0..3).find(|&id| id != leader && id != follower).unwrap();
+
+    cluster.drop_between(leader, follower);
+    cluster.drop_between(follower, other);
+    cluster.kill(other);
+    let dot = cluster.to_dot();
+    assert!(dot.contains(&format!(
+        "{} -> {} [label=\"lag 0\", style=dashed, color=red];",
+        leader, follower
+    )));
+    assert!(dot.contains(&format!(
+        "{} -> {} [label=\"dropped\", style=dashed, color=red];",
+        follower, other
+    )));
+    assert!(dot.contains("fillcolor=grey"));
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "tracing")]
+mod common;
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use common::*;
+
+/// Collects everything the fmt subscriber writes so tests can look through it
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn election_and_commit_emit_events() {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    tracing::subscriber::with_default(subscriber, || {
+        let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+        cluster.tick_by(MAX_TICKS);
+        cluster.get_leader_mut().unwrap().client_request(1).unwrap();
+        cluster.tick_by(MAX_TICKS);
+    });
+
+    let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    assert!(output.contains("election timer expired"));
+    assert!(output.contains("won election"));
+    assert!(output.contains("role changed") && output.contains("role=Leader"));
+    assert!(output.contains("commit index advanced"));
+    // rpc handling happens inside a span carrying the node's id and term
+    assert!(output.contains("receive_rpc{id="));
+}
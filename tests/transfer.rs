@@ -0,0 +1,200 @@
+mod common;
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    rpc::{VoteRequest, RPC},
+    sim::Cluster,
+};
+
+fn cluster() -> Cluster<u32, u32> {
+    init_logger();
+    let mut cluster = Cluster::new(5, 3, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    assert!(cluster.run_until(MAX_TICKS, |cluster| cluster.leader().is_some()));
+    cluster
+}
+
+#[test]
+fn leadership_moves_to_the_target() {
+    let mut cluster = cluster();
+    let old = cluster.leader().unwrap().id;
+    let term = cluster.leader().unwrap().current_term();
+    let target = (old + 1) % 5;
+
+    assert_eq!(cluster.transfer_leadership(Some(target)).unwrap(), old);
+    // proposals go to the new leader while the old one hands over
+    assert_eq!(cluster.node(old).leadership_transfer(), Some(target));
+    assert!(matches!(
+        cluster.node_mut(old).client_request(1),
+        Err(RaftError::NotLeader { leader: Some(leader) }) if leader == target
+    ));
+
+    cluster.tick();
+    let leader = cluster.leader().unwrap();
+    assert_eq!(leader.id, target);
+    // taken over in a single election, well before any election timer ran out
+    assert_eq!(leader.current_term(), term + 1);
+    assert!(cluster.node(old).is_follower());
+    assert_eq!(cluster.node(old).leadership_transfer(), None);
+}
+
+#[test]
+fn lagging_target_catches_up_first() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    let target = (leader + 1) % 5;
+    cluster.kill(target);
+    for data in 1..=3 {
+        cluster.client_request(data).unwrap();
+    }
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+    cluster.revive(target);
+
+    cluster.transfer_leadership(Some(target)).unwrap();
+    assert!(cluster.run_until(MAX_WAIT, |cluster| cluster
+        .leader()
+        .is_some_and(|leader| leader.id == target)));
+    cluster.client_request(4).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval * 2);
+    assert!(cluster.nodes().all(|node| node.log.app.get_state() == 10));
+}
+
+#[test]
+fn transfer_to_an_unreachable_target_is_given_up() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    let target = (leader + 1) % 5;
+    cluster.kill(target);
+
+    cluster.transfer_leadership(Some(target)).unwrap();
+    assert!(cluster.node_mut(leader).client_request(1).is_err());
+    cluster.tick_by(DEFAULT_CFG.election_timeout);
+    assert_eq!(cluster.leader().unwrap().id, leader);
+    assert_eq!(cluster.node(leader).leadership_transfer(), None);
+    assert!(cluster.node_mut(leader).client_request(1).is_ok());
+}
+
+#[test]
+fn without_a_target_the_most_up_to_date_follower_takes_over() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    // only one follower keeps up with the log
+    let caught_up = (leader + 2) % 5;
+    for id in (0..5).filter(|id| ![leader, caught_up].contains(id)) {
+        cluster.kill(id);
+    }
+    cluster.client_request(1).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+    for id in 0..5 {
+        cluster.revive(id);
+    }
+
+    cluster.transfer_leadership(None).unwrap();
+    assert_eq!(cluster.node(leader).leadership_transfer(), Some(caught_up));
+    cluster.tick();
+    assert_eq!(cluster.leader().unwrap().id, caught_up);
+}
+
+#[test]
+fn only_leaders_transfer_to_their_followers() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    let follower = (leader + 1) % 5;
+    assert!(matches!(
+        cluster.node_mut(follower).transfer_leadership(None),
+        Err(RaftError::NotLeader { leader: Some(id) }) if id == leader
+    ));
+    assert!(matches!(
+        cluster.node_mut(leader).transfer_leadership(Some(7)),
+        Err(RaftError::UnknownPeer(7))
+    ));
+    assert!(matches!(
+        cluster.node_mut(leader).transfer_leadership(Some(leader)),
+        Err(RaftError::UnknownPeer(_))
+    ));
+
+    let mut single = Cluster::new(1, 0, DEFAULT_CFG, |_| Box::new(CountingApp { state: 0 }));
+    single.tick();
+    assert!(matches!(
+        single.node_mut(0).transfer_leadership(None),
+        Err(RaftError::InvalidRequest(_))
+    ));
+}
+
+#[test]
+fn nobody_else_gets_votes_during_a_transfer() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    let term = cluster.node(leader).current_term();
+    let (target, contender) = ((leader + 1) % 5, (leader + 2) % 5);
+    cluster.kill(target);
+    cluster.transfer_leadership(Some(target)).unwrap();
+    // followers learn about the transfer with the next heartbeat
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+
+    let contender_log = &cluster.node(contender).log;
+    let campaign = RPC::VoteRequest(VoteRequest {
+        candidate_term: term + 1,
+        candidate_id: contender,
+        candidate_last_log_idx: contender_log.last_idx(),
+        candidate_last_log_term: contender_log.last_term(),
+        request_id: 0,
+    });
+    let others: Vec<_> = (0..5)
+        .filter(|id| ![target, contender].contains(id))
+        .collect();
+    for &id in &others {
+        let node = cluster.node_mut(id);
+        let msgs = node.receive_rpc(&campaign);
+        assert!(matches!(
+            &msgs[..],
+            [(_, RPC::VoteResponse(res))] if !res.vote_granted && res.term == term
+        ));
+        assert_eq!(node.current_term(), term);
+    }
+    assert_eq!(cluster.leader().unwrap().id, leader);
+
+    // once the transfer is given up on, everyone is free to vote again
+    cluster.tick_by(DEFAULT_CFG.election_timeout);
+    assert_eq!(cluster.node(leader).leadership_transfer(), None);
+    let follower = cluster.node_mut((leader + 3) % 5);
+    assert!(matches!(
+        &follower.receive_rpc(&campaign)[..],
+        [(_, RPC::VoteResponse(res))] if res.vote_granted
+    ));
+}
+
+#[test]
+fn followers_vote_again_when_a_transfer_runs_out_without_its_leader() {
+    let mut cluster = cluster();
+    let leader = cluster.leader().unwrap().id;
+    let term = cluster.node(leader).current_term();
+    let (target, contender) = ((leader + 1) % 5, (leader + 2) % 5);
+    cluster.kill(target);
+    cluster.transfer_leadership(Some(target)).unwrap();
+    cluster.tick_by(DEFAULT_CFG.heartbeat_interval);
+    // nobody is left to say the transfer was given up on
+    cluster.kill(leader);
+
+    let campaign = RPC::VoteRequest(VoteRequest {
+        candidate_term: term + 1,
+        candidate_id: contender,
+        candidate_last_log_idx: 100,
+        candidate_last_log_term: term,
+        request_id: 0,
+    });
+    let follower = cluster.node_mut((leader + 3) % 5);
+    for _ in 0..DEFAULT_CFG.election_timeout {
+        // the transfer runs out before the follower would start an election of its own
+        assert!(follower.is_follower());
+        assert_eq!(follower.current_term(), term);
+        if let [(_, RPC::VoteResponse(res))] = &follower.receive_rpc(&campaign)[..] {
+            if res.vote_granted {
+                return;
+            }
+        }
+        follower.tick();
+    }
+    panic!("follower kept withholding its vote");
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "turmoil")]
+
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::*;
+use miniraft::{
+    debug::init_logger,
+    error::RaftError,
+    node::{RaftClient, RaftNode},
+    server::{RaftServer, ServerId},
+    turmoil::network,
+};
+
+const TICK: Duration = Duration::from_millis(10);
+const PROPOSAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Clients = Arc<Mutex<BTreeMap<ServerId, RaftClient<u32>>>>;
+
+fn host(id: ServerId) -> String {
+    format!("node{}", id)
+}
+
+/// Client for `id`, once its host is up
+fn client(clients: &Clients, id: ServerId) -> RaftClient<u32> {
+    clients.lock().unwrap()[&id].clone()
+}
+
+/// Propose `data` through whoever `clients` say is leader, once there is one among `nodes`.
+/// A proposal a leader took just before it was voted out can wait until a later leader
+/// commits something after it, so one that takes too long is made again elsewhere
+async fn propose(clients: &Clients, nodes: &[ServerId], data: u32) -> (ServerId, usize) {
+    let mut target = nodes[0];
+    loop {
+        let next = nodes[(nodes.iter().position(|id| *id == target).unwrap() + 1) % nodes.len()];
+        match tokio::time::timeout(PROPOSAL_TIMEOUT, client(clients, target).propose(data)).await {
+            Ok(Ok(index)) => return (target, index),
+            Ok(Err(RaftError::NotLeader { leader })) => {
+                target = leader
+                    .filter(|leader| nodes.contains(leader))
+                    .unwrap_or(next);
+                tokio::time::sleep(TICK * 5).await;
+            }
+            Ok(Err(err)) => panic!("proposal failed: {}", err),
+            Err(_) => target = next,
+        }
+    }
+}
+
+#[test]
+fn cluster_gets_past_a_partitioned_leader() -> ::turmoil::Result {
+    init_logger();
+    let mut sim = ::turmoil::Builder::new()
+        .simulation_duration(Duration::from_secs(60))
+        .rng_seed(0)
+        .build();
+    let clients = Clients::default();
+    for id in 0..3 {
+        let clients = clients.clone();
+        sim.host(host(id), move || {
+            let clients = clients.clone();
+            async move {
+                let peers = (0..3).filter(|peer| *peer != id);
+                let (transport, network) =
+                    network(peers.clone().map(|peer| (peer, host(peer))).collect());
+                let app = Box::new(CountingApp { state: 0 });
+                let server =
+                    RaftServer::new(id, peers.collect(), DEFAULT_CFG, Some(id as u64), app);
+                let (node, client) = RaftNode::new(server, TICK, transport);
+                clients.lock().unwrap().insert(id, client.clone());
+                network.serve(node, client).await?;
+                Ok(())
+            }
+        });
+    }
+
+    sim.client("test", async move {
+        while clients.lock().unwrap().len() < 3 {
+            tokio::time::sleep(TICK).await;
+        }
+        let (leader, first) = propose(&clients, &[0, 1, 2], 1).await;
+
+        // the leader is cut off, the other two carry on without it
+        let rest: Vec<ServerId> = (0..3).filter(|id| *id != leader).collect();
+        for id in &rest {
+            ::turmoil::partition(host(leader), host(*id));
+        }
+        let (new_leader, index) = propose(&clients, &rest, 2).await;
+        assert_ne!(new_leader, leader);
+        assert!(index > first);
+
+        // and the old leader follows one of them once it can hear from them again
+        for id in &rest {
+            ::turmoil::repair(host(leader), host(*id));
+        }
+        loop {
+            let status = client(&clients, leader).status().await?;
+            if status.leader_hint.is_some_and(|id| rest.contains(&id))
+                && status.committed_len > index
+            {
+                break;
+            }
+            tokio::time::sleep(TICK).await;
+        }
+        for id in 0..3 {
+            client(&clients, id).shutdown(false).await;
+        }
+        Ok(())
+    });
+
+    sim.run()
+}
@@ -3,7 +3,11 @@ mod common;
 use std::collections::BTreeMap;
 
 use common::*;
-use miniraft::server::{NodeReplicationState, RaftConfig, ServerId};
+use miniraft::{
+    history::{ElectionOutcome, ELECTION_HISTORY_LEN},
+    rpc::{VoteRequest, VoteResponse, RPC},
+    server::{NodeReplicationState, RaftConfig, RaftServer, ServerId, TieBreak},
+};
 
 #[test]
 fn trivial_case_one_server_remains_leader() {
@@ -50,12 +54,13 @@ fn no_jitter_never_has_leader() {
 }
 
 #[test]
-fn two_cluster_partition_has_two_leaders() {
+fn two_cluster_partition_has_no_leader() {
+    // neither node is a majority on its own
     let mut cluster = TestCluster::new(2, 0, DEFAULT_CFG);
     cluster.drop_between(0, 1);
     cluster.tick_by(MAX_WAIT);
-    assert_eq!(cluster.num_leaders(), 2);
-    assert!(!cluster.has_candidate());
+    assert_eq!(cluster.num_leaders(), 0);
+    assert!(cluster.has_candidate());
 }
 
 #[test]
@@ -69,6 +74,72 @@ fn three_cluster_partition_has_one_leader() {
     assert!(cluster.term_consensus());
 }
 
+#[test]
+fn tie_breaks_settle_split_votes_without_jitter() {
+    let mut cluster = TestCluster::new(
+        3,
+        0,
+        RaftConfig {
+            election_timeout_jitter: 0,
+            tie_break: TieBreak::LowerId,
+            ..DEFAULT_CFG
+        },
+    );
+    cluster.tick_by(MAX_WAIT * 2);
+    assert_eq!(cluster.num_leaders(), 1);
+    assert_eq!(cluster.get_leader().unwrap().id, 0);
+}
+
+#[test]
+fn candidates_step_aside_for_whoever_the_tie_break_favours() {
+    // node 1 standing in an election, with an empty log
+    let yields = |tie_break, candidate_id, candidate_last_log_term| {
+        let config = RaftConfig {
+            tie_break,
+            ..DEFAULT_CFG
+        };
+        let app = Box::new(CountingApp { state: 0 });
+        let mut node = RaftServer::<u32, u32>::new(1, [0, 2].into(), config, Some(0), app);
+        while !node.is_candidate() {
+            node.tick();
+        }
+        let term = node.current_term();
+        let msgs = node.receive_rpc(&RPC::VoteRequest(VoteRequest {
+            candidate_term: term,
+            candidate_id,
+            candidate_last_log_idx: 0,
+            candidate_last_log_term,
+            request_id: 0,
+        }));
+        // our vote went to ourselves either way
+        assert!(matches!(
+            &msgs[..],
+            [(
+                _,
+                RPC::VoteResponse(VoteResponse {
+                    vote_granted: false,
+                    ..
+                })
+            )]
+        ));
+        assert_eq!(node.voted_for(), Some(1));
+        assert_eq!(node.current_term(), term);
+        if node.is_candidate() {
+            return false;
+        }
+        // and sat out the other candidate's election
+        node.tick_n(DEFAULT_CFG.election_timeout + DEFAULT_CFG.election_timeout_jitter);
+        assert!(node.is_follower());
+        true
+    };
+    assert!(!yields(TieBreak::Never, 0, 0));
+    assert!(yields(TieBreak::LowerId, 0, 0));
+    assert!(!yields(TieBreak::LowerId, 2, 1));
+    assert!(yields(TieBreak::LongerLog, 0, 0));
+    assert!(yields(TieBreak::LongerLog, 2, 1));
+    assert!(!yields(TieBreak::LongerLog, 2, 0));
+}
+
 #[test]
 fn candidate_mismatched_terms() {
     let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
@@ -122,3 +193,93 @@ fn two_down_of_three_does_not_achieve_quorum() {
     cluster.tick_by(MAX_TICKS);
     assert_eq!(cluster.num_leaders(), 1);
 }
+
+#[test]
+fn election_history_records_outcomes() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.kill(1);
+    cluster.kill(2);
+    cluster.tick_by(MAX_WAIT);
+    cluster.revive(1);
+    cluster.revive(2);
+    cluster.tick_by(MAX_TICKS);
+
+    let leader = cluster.get_leader().unwrap().id;
+    let leader_term = cluster.leader_term();
+    let node = cluster.get_by_id(0);
+    let history = node.election_history();
+    assert!(history.len() > 1);
+    assert!(history.len() <= ELECTION_HISTORY_LEN);
+
+    // stuck alone, node 0 kept timing out and starting new elections
+    let first = history.iter().next().unwrap();
+    assert_eq!(first.candidate, 0);
+    assert_eq!(first.outcome, ElectionOutcome::TimedOut);
+    assert_eq!(first.votes_received, 1);
+    assert!(first.duration > 0);
+    assert!(history
+        .iter()
+        .zip(history.iter().skip(1))
+        .all(|(a, b)| a.term < b.term && a.started_at + a.duration <= b.started_at));
+
+    // whoever ended up leading has the winning election as its latest
+    let last = cluster
+        .get_by_id(leader)
+        .election_history()
+        .last()
+        .unwrap()
+        .clone();
+    assert_eq!(last.outcome, ElectionOutcome::Won);
+    assert_eq!(last.term, leader_term);
+    assert!(last.votes_received >= 2);
+}
+
+#[test]
+fn nodes_outside_the_cluster_get_no_votes() {
+    let mut cluster = TestCluster::new(3, 0, DEFAULT_CFG);
+    cluster.tick_by(MAX_WAIT);
+    let term = cluster.leader_term();
+
+    // a node that was taken out of the cluster keeps campaigning with ever higher terms
+    let campaign = RPC::VoteRequest(VoteRequest {
+        candidate_term: term + 5,
+        candidate_id: 7,
+        candidate_last_log_idx: 100,
+        candidate_last_log_term: term + 4,
+        request_id: 0,
+    });
+    for node in cluster.peers.values_mut() {
+        let denied = node.metrics().votes_denied;
+        assert!(node.receive_rpc(&campaign).is_empty());
+        assert_eq!(node.current_term(), term);
+        assert_eq!(node.metrics().votes_denied, denied + 1);
+    }
+    assert_eq!(cluster.num_leaders(), 1);
+    cluster.tick_by(MAX_WAIT);
+    assert_eq!(cluster.leader_term(), term);
+}
+
+#[test]
+fn votes_from_outside_the_cluster_are_not_counted() {
+    let app = Box::new(CountingApp { state: 0 });
+    let mut candidate = RaftServer::<u32, u32>::new(0, [1, 2].into(), DEFAULT_CFG, Some(0), app);
+    while !candidate.is_candidate() {
+        candidate.tick();
+    }
+    let vote = |votee_id| {
+        RPC::VoteResponse(VoteResponse {
+            votee_id,
+            term: candidate.current_term(),
+            vote_granted: true,
+            request_id: 0,
+        })
+    };
+    let (stray, peer) = (vote(7), vote(1));
+
+    // our own vote and a stray one would make a quorum of two, if it counted
+    candidate.receive_rpc(&stray);
+    assert!(candidate.is_candidate());
+    assert_eq!(candidate.metrics().responses_from_unknown_followers, 1);
+    candidate.receive_rpc(&peer);
+    assert!(candidate.is_leader());
+}
@@ -0,0 +1,139 @@
+//! Runs a simulated miniraft cluster in the browser, for watching Raft at work.
+//!
+//! The page (`index.html`) owns the clock: it calls [`Demo::tick`] on a timer and redraws
+//! the cluster from the JSON [`Demo::state`] hands back. Everything else is the same
+//! deterministic [`Cluster`] the tests run on.
+
+use miniraft::{
+    log::{App, LogEntry},
+    server::{ServerId, Ticks},
+    sim::Cluster,
+    status::RaftStatus,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Adds up every committed entry, so it is easy to see nodes agree
+struct Counter(u32);
+
+impl App<u32, u32> for Counter {
+    fn transition_fn(&mut self, entry: &LogEntry<u32>) {
+        self.0 += entry.data;
+    }
+
+    fn get_state(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A message that arrived or got lost during the last tick
+#[derive(Serialize)]
+struct Message {
+    from: ServerId,
+    to: ServerId,
+    kind: String,
+    delivered: bool,
+}
+
+/// A single node as drawn on the page
+#[derive(Serialize)]
+struct Node {
+    up: bool,
+    state: u32,
+    #[serde(flatten)]
+    status: RaftStatus,
+}
+
+/// Everything the page needs to draw a frame
+#[derive(Serialize)]
+struct Frame<'a> {
+    now: Ticks,
+    nodes: Vec<Node>,
+    messages: &'a [Message],
+}
+
+/// A simulated cluster, driven one tick at a time from JS
+#[wasm_bindgen]
+pub struct Demo {
+    cluster: Cluster<u32, u32>,
+    /// Messages that arrived or got lost during the last tick
+    messages: Vec<Message>,
+}
+
+#[wasm_bindgen]
+impl Demo {
+    /// Start a cluster of `nodes` nodes. The same seed always plays out the same way
+    #[wasm_bindgen(constructor)]
+    pub fn new(nodes: usize, seed: u64) -> Demo {
+        let cluster = Cluster::builder()
+            .nodes(nodes)
+            .seed(seed)
+            .app(|| Counter(0))
+            .build();
+        Demo {
+            cluster,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Move the whole cluster forward by a tick
+    pub fn tick(&mut self) {
+        // only keep what happened during this tick
+        self.cluster.start_trace();
+        self.cluster.tick();
+        self.messages = self
+            .cluster
+            .trace()
+            .iter()
+            .map(|msg| Message {
+                from: msg.from,
+                to: msg.to,
+                kind: msg.rpc.to_string(),
+                delivered: msg.delivered,
+            })
+            .collect();
+    }
+
+    /// Propose `data` to the leader, returning whether there was one to take it
+    pub fn propose(&mut self, data: u32) -> bool {
+        self.cluster.client_request(data).is_ok()
+    }
+
+    /// Crash node `id` if it is up, bring it back if it is down
+    pub fn toggle(&mut self, id: ServerId) {
+        if self.cluster.network().down.contains(&id) {
+            self.cluster.revive(id);
+        } else {
+            self.cluster.kill(id);
+        }
+    }
+
+    /// Cut `side` off from the rest of the cluster
+    pub fn partition(&mut self, side: &[usize]) {
+        self.cluster.partition(side);
+    }
+
+    /// Undo every partition and cut link
+    pub fn heal(&mut self) {
+        self.cluster.heal();
+    }
+
+    /// JSON describing the cluster as of the last tick
+    pub fn state(&self) -> String {
+        let down = self.cluster.network().down;
+        let frame = Frame {
+            now: self.cluster.now(),
+            nodes: self
+                .cluster
+                .nodes()
+                .map(|node| Node {
+                    up: !down.contains(&node.id),
+                    state: node.log.app.get_state(),
+                    status: node.status(),
+                })
+                .collect(),
+            messages: &self.messages,
+        };
+        serde_json::to_string(&frame).expect("frames are always serializable")
+    }
+}